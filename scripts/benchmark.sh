@@ -4,10 +4,11 @@ set -euo pipefail
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 # Create temporary large test file
 TEMP_FILE="$(mktemp)"
+OVERFLOW_FILE="$(mktemp)"
 
 cleanup() {
     # Cleanup
-    rm "$TEMP_FILE"
+    rm "$TEMP_FILE" "$OVERFLOW_FILE"
     echo "Cleanup completed"
 }
 
@@ -51,5 +52,26 @@ for max_lines in "${MAX_LINES_VALUES[@]}"; do
     fi
 done
 
+# Pathological input: every line opens an object that is never closed, so
+# the buffer stays full and overflows on each line. Processing time should
+# grow linearly with the input, regardless of the buffer size.
+echo ""
+echo "Creating overflow test file: $OVERFLOW_FILE"
+yes '{ "unterminated": "record",' | head -n 20000 >"$OVERFLOW_FILE" || true
+
+OVERFLOW_MAX_LINES_VALUES=(10 1024 4096)
+
+echo "Running overflow benchmarks with different max-lines values..."
+for max_lines in "${OVERFLOW_MAX_LINES_VALUES[@]}"; do
+    echo ""
+    echo "=== Benchmarking overflow with --max-lines $max_lines ==="
+
+    hyperfine \
+        --runs 5 \
+        --warmup 1 \
+        --export-json "benchmark_results_overflow_${max_lines}.json" \
+        "./target/release/jlif --max-lines $max_lines '$OVERFLOW_FILE'"
+done
+
 echo ""
 echo "=== All benchmarks completed ==="
@@ -0,0 +1,794 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::field_path::FieldPath;
+use crate::level::{Level, LevelMap};
+use crate::schema::SchemaAggregator;
+use enum_dispatch::enum_dispatch;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
+
+// Width of the longest bar in rendered charts
+const BAR_WIDTH: usize = 40;
+// Default number of equal-width buckets used for numeric histograms
+const NUMERIC_BUCKETS: usize = 10;
+// Categories beyond this are summarized in a single line
+const MAX_CATEGORIES: usize = 20;
+// Number of most frequent messages shown in the summary view
+const SUMMARY_TOP_MESSAGES: usize = 5;
+// Longer messages are shortened in the summary view
+const SUMMARY_MESSAGE_WIDTH: usize = 60;
+
+/// Trait for aggregations that consume records instead of printing them
+///
+/// Aggregators observe every JSON record that passed the filter and render a
+/// report once the input is exhausted.
+#[enum_dispatch]
+pub trait Aggregator {
+    /// Records a single filtered JSON value
+    fn observe(&mut self, value: &Value);
+
+    /// Writes the final report
+    fn render(&self, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Enum dispatch for different aggregation implementations
+#[enum_dispatch(Aggregator)]
+#[derive(Debug)]
+pub enum Aggregation {
+    Count(CountAggregator),
+    Histogram(HistogramAggregator),
+    Numeric(NumericAggregator),
+    Schema(SchemaAggregator),
+    Summary(SummaryAggregator),
+    Top(TopAggregator),
+}
+
+/// Occurrence counts of values, remembering first-seen order
+#[derive(Debug)]
+struct Counter<K = String> {
+    counts: Vec<(K, u64)>,
+    index: HashMap<K, usize>,
+}
+
+impl<K> Default for Counter<K> {
+    fn default() -> Self {
+        Self {
+            counts: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash> Counter<K> {
+    fn count(&mut self, label: K) {
+        match self.index.get(&label) {
+            Some(&index) => self.counts[index].1 += 1,
+            None => {
+                self.index.insert(label.clone(), self.counts.len());
+                self.counts.push((label, 1));
+            }
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.counts.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Returns all values, most frequent first (ties keep first-seen order)
+    fn sorted(&self) -> Vec<(K, u64)> {
+        let mut rows = self.counts.clone();
+        rows.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        rows
+    }
+}
+
+/// Number of records per distinct value of one or more fields, like
+/// `GROUP BY` with `COUNT(*)` in SQL or `sort | uniq -c | sort -rn`.
+///
+/// Every combination of values is a row, most frequent first. Records
+/// without a field count towards `-` in its column.
+#[derive(Debug)]
+pub struct CountAggregator {
+    fields: Vec<FieldPath>,
+    groups: Counter<Vec<String>>,
+}
+
+impl CountAggregator {
+    pub fn new(fields: Vec<FieldPath>) -> Self {
+        Self {
+            fields,
+            groups: Counter::default(),
+        }
+    }
+}
+
+impl Aggregator for CountAggregator {
+    fn observe(&mut self, value: &Value) {
+        let group = self
+            .fields
+            .iter()
+            .map(|field| match field.get(value) {
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+                None => "-".to_string(),
+            })
+            .collect();
+        self.groups.count(group);
+    }
+
+    fn render(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let fields: Vec<String> = self.fields.iter().map(ToString::to_string).collect();
+        writeln!(
+            writer,
+            "{} ({} records)",
+            fields.join(", "),
+            self.groups.total()
+        )?;
+
+        let rows = self.groups.sorted();
+        let count_width = rows
+            .iter()
+            .map(|(_, count)| count.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let mut widths = vec![0; self.fields.len()];
+        for (group, _) in &rows {
+            for (width, value) in widths.iter_mut().zip(group) {
+                *width = (*width).max(value.width());
+            }
+        }
+
+        for (group, count) in &rows {
+            let mut line = format!("  {:>count_width$}", count);
+            for (index, (value, width)) in group.iter().zip(&widths).enumerate() {
+                line.push_str("  ");
+                line.push_str(value);
+                // The last column needs no padding
+                if index + 1 < group.len() {
+                    line.push_str(&" ".repeat(width - value.width()));
+                }
+            }
+            writeln!(writer, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The most frequent values of a single field, with their counts and share
+/// of all values
+#[derive(Debug)]
+pub struct TopAggregator {
+    field: FieldPath,
+    limit: usize,
+    values: Counter,
+    missing: u64,
+}
+
+impl TopAggregator {
+    pub fn new(field: FieldPath, limit: usize) -> Self {
+        Self {
+            field,
+            limit,
+            values: Counter::default(),
+            missing: 0,
+        }
+    }
+}
+
+impl Aggregator for TopAggregator {
+    fn observe(&mut self, value: &Value) {
+        match self.field.get(value) {
+            None => self.missing += 1,
+            Some(Value::String(s)) => self.values.count(s.clone()),
+            Some(other) => self.values.count(other.to_string()),
+        }
+    }
+
+    fn render(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let total = self.values.total();
+        write!(writer, "{} ({} values", self.field, total)?;
+        if self.missing > 0 {
+            write!(writer, ", {} records without field", self.missing)?;
+        }
+        writeln!(writer, ")")?;
+
+        let mut rows = self.values.sorted();
+        let hidden = rows.split_off(rows.len().min(self.limit));
+        let share = |count: u64| count as f64 * 100.0 / total as f64;
+        let label_width = rows.iter().map(|(label, _)| label.width()).max();
+        let count_width = rows.iter().map(|(_, count)| count.to_string().len()).max();
+
+        for (label, count) in &rows {
+            writeln!(
+                writer,
+                "  {}{}  {:>count_width$}  {:>5.1}%",
+                label,
+                " ".repeat(label_width.unwrap_or(0) - label.width()),
+                count,
+                share(*count),
+                count_width = count_width.unwrap_or(0),
+            )?;
+        }
+        if !hidden.is_empty() {
+            let hidden_total: u64 = hidden.iter().map(|(_, count)| count).sum();
+            writeln!(
+                writer,
+                "  … {} more values ({} records, {:.1}%)",
+                hidden.len(),
+                hidden_total,
+                share(hidden_total)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Bar-chart histogram of a single field's value distribution.
+///
+/// If every observed value is a number, values are grouped into equal-width
+/// numeric buckets, ten unless set with [`HistogramAggregator::with_buckets`].
+/// Otherwise each distinct value is its own category.
+#[derive(Debug)]
+pub struct HistogramAggregator {
+    field: FieldPath,
+    buckets: usize,
+    numbers: Vec<f64>,
+    categories: Counter,
+    missing: u64,
+}
+
+impl HistogramAggregator {
+    pub fn new(field: FieldPath) -> Self {
+        Self {
+            field,
+            buckets: NUMERIC_BUCKETS,
+            numbers: Vec::new(),
+            categories: Counter::default(),
+            missing: 0,
+        }
+    }
+
+    /// Groups numeric values into this many buckets, at least one
+    pub fn with_buckets(mut self, buckets: usize) -> Self {
+        self.buckets = buckets.max(1);
+        self
+    }
+
+    fn numeric_rows(&self) -> Vec<(String, u64)> {
+        let min = self.numbers.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self
+            .numbers
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        if min == max {
+            return vec![(format_number(min), self.numbers.len() as u64)];
+        }
+
+        let width = (max - min) / self.buckets as f64;
+        let mut counts = vec![0u64; self.buckets];
+        for number in &self.numbers {
+            // The maximum belongs to the last (closed) bucket
+            let index = (((number - min) / width) as usize).min(self.buckets - 1);
+            counts[index] += 1;
+        }
+
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(index, count)| {
+                let low = min + width * index as f64;
+                let high = min + width * (index + 1) as f64;
+                let close = if index == self.buckets - 1 { ']' } else { ')' };
+                let label = format!("[{}, {}{}", format_number(low), format_number(high), close);
+                (label, count)
+            })
+            .collect()
+    }
+}
+
+impl Aggregator for HistogramAggregator {
+    fn observe(&mut self, value: &Value) {
+        match self.field.get(value) {
+            None => self.missing += 1,
+            Some(field_value) => {
+                if let Some(number) = field_value.as_f64() {
+                    self.numbers.push(number);
+                }
+                let label = match field_value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                self.categories.count(label);
+            }
+        }
+    }
+
+    fn render(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let total = self.categories.total();
+        write!(writer, "{} ({} values", self.field, total)?;
+        if self.missing > 0 {
+            write!(writer, ", {} records without field", self.missing)?;
+        }
+        writeln!(writer, ")")?;
+
+        if total == 0 {
+            return Ok(());
+        }
+
+        // Numeric bucketing only applies if no value was anything but a number.
+        // All buckets are shown, as asked for, while categories are cut short.
+        let (mut rows, shown) = if self.numbers.len() as u64 == total {
+            (self.numeric_rows(), self.buckets)
+        } else {
+            (self.categories.sorted(), MAX_CATEGORIES)
+        };
+
+        let hidden: Vec<(String, u64)> = rows.split_off(rows.len().min(shown));
+        render_bars(writer, &rows)?;
+        if !hidden.is_empty() {
+            let hidden_total: u64 = hidden.iter().map(|(_, count)| count).sum();
+            writeln!(
+                writer,
+                "  … {} more values ({} records)",
+                hidden.len(),
+                hidden_total
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Minimum, maximum, mean and percentiles of a numeric field, e.g. a latency.
+///
+/// Numbers in strings like `"12.5"` count as well. All values are kept in
+/// memory until the report, as percentiles need them sorted.
+#[derive(Debug)]
+pub struct NumericAggregator {
+    field: FieldPath,
+    values: Vec<f64>,
+    // Records without the field or with a value that isn't a number
+    skipped: u64,
+}
+
+impl NumericAggregator {
+    pub fn new(field: FieldPath) -> Self {
+        Self {
+            field,
+            values: Vec::new(),
+            skipped: 0,
+        }
+    }
+}
+
+impl Aggregator for NumericAggregator {
+    fn observe(&mut self, value: &Value) {
+        let number = match self.field.get(value) {
+            Some(Value::Number(number)) => number.as_f64(),
+            Some(Value::String(text)) => text.trim().parse().ok(),
+            _ => None,
+        };
+        match number.filter(|number: &f64| number.is_finite()) {
+            Some(number) => self.values.push(number),
+            None => self.skipped += 1,
+        }
+    }
+
+    fn render(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write!(writer, "{} ({} values", self.field, self.values.len())?;
+        if self.skipped > 0 {
+            write!(writer, ", {} records without a number", self.skipped)?;
+        }
+        writeln!(writer, ")")?;
+
+        if self.values.is_empty() {
+            return Ok(());
+        }
+
+        let mut sorted = self.values.clone();
+        sorted.sort_by(f64::total_cmp);
+        // Nearest-rank percentiles, always one of the observed values
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        let mean = sorted.iter().sum::<f64>() / sorted.len() as f64;
+
+        let rows = [
+            ("min", sorted[0]),
+            ("max", sorted[sorted.len() - 1]),
+            ("mean", mean),
+            ("p50", percentile(50.0)),
+            ("p95", percentile(95.0)),
+            ("p99", percentile(99.0)),
+        ];
+        for (label, number) in rows {
+            writeln!(writer, "  {:<4}  {}", label, format_number(number))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compact live overview of a stream: record counts by level, the most
+/// frequent messages and overall throughput
+#[derive(Debug)]
+pub struct SummaryAggregator {
+    started: Instant,
+    records: u64,
+    levels: HashMap<Level, u64>,
+    messages: Counter,
+    level_map: LevelMap,
+}
+
+impl SummaryAggregator {
+    pub fn new(level_map: LevelMap) -> Self {
+        Self {
+            level_map,
+            started: Instant::now(),
+            records: 0,
+            levels: HashMap::new(),
+            messages: Counter::default(),
+        }
+    }
+}
+
+impl Aggregator for SummaryAggregator {
+    fn observe(&mut self, value: &Value) {
+        self.records += 1;
+
+        if let Some(level) = self.level_map.detect(value) {
+            *self.levels.entry(level).or_default() += 1;
+        }
+
+        let message = ["msg", "message"]
+            .iter()
+            .find_map(|key| value.get(key))
+            .and_then(Value::as_str);
+        if let Some(message) = message {
+            self.messages.count(shorten(message, SUMMARY_MESSAGE_WIDTH));
+        }
+    }
+
+    fn render(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let elapsed = self.started.elapsed();
+        let rate = self.records as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        writeln!(
+            writer,
+            "{} records in {} ({:.1}/s)",
+            self.records,
+            humantime::format_duration(Duration::from_secs(elapsed.as_secs())),
+            rate
+        )?;
+
+        // Most severe levels first, as those are what one keeps an eye on
+        let level_rows: Vec<(String, u64)> = Level::ALL
+            .iter()
+            .rev()
+            .filter_map(|level| {
+                self.levels
+                    .get(level)
+                    .map(|count| (level.to_string(), *count))
+            })
+            .collect();
+        if !level_rows.is_empty() {
+            writeln!(writer, "\nLevels")?;
+            render_bars(writer, &level_rows)?;
+        }
+
+        let mut message_rows = self.messages.sorted();
+        message_rows.truncate(SUMMARY_TOP_MESSAGES);
+        if !message_rows.is_empty() {
+            writeln!(writer, "\nTop messages")?;
+            render_bars(writer, &message_rows)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Shortens text to at most `width` characters, marking the cut with an ellipsis
+pub(crate) fn shorten(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut shortened: String = text.chars().take(width - 1).collect();
+    shortened.push('…');
+    shortened
+}
+
+/// Renders labeled horizontal bars scaled to the largest count
+fn render_bars(writer: &mut dyn Write, rows: &[(String, u64)]) -> io::Result<()> {
+    let label_width = rows.iter().map(|(label, _)| label.chars().count()).max();
+    let count_width = rows.iter().map(|(_, count)| count.to_string().len()).max();
+    let max_count = rows.iter().map(|(_, count)| *count).max().unwrap_or(0);
+
+    for (label, count) in rows {
+        let bar_length = if max_count == 0 {
+            0
+        } else {
+            // Round up so that any non-zero count remains visible
+            (*count as usize * BAR_WIDTH).div_ceil(max_count as usize)
+        };
+        writeln!(
+            writer,
+            "  {:<label_width$} │{:<BAR_WIDTH$} {:>count_width$}",
+            label,
+            "█".repeat(bar_length),
+            count,
+            label_width = label_width.unwrap_or(0),
+            count_width = count_width.unwrap_or(0),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Formats bucket boundaries with at most two decimals and no trailing zeros
+fn format_number(number: f64) -> String {
+    let formatted = format!("{:.2}", number);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn render(aggregation: &Aggregation) -> String {
+        let mut output = Vec::new();
+        aggregation.render(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_count_groups_sorted_by_count() {
+        let mut count = Aggregation::Count(CountAggregator::new(vec![FieldPath::parse("species")]));
+        for species in ["Bajoran", "Ferengi", "Bajoran", "Changeling", "Bajoran"] {
+            count.observe(&json!({"species": species}));
+        }
+        count.observe(&json!({"name": "Garak"}));
+
+        assert_eq!(
+            render(&count),
+            "species (6 records)\n  3  Bajoran\n  1  Ferengi\n  1  Changeling\n  1  -\n"
+        );
+    }
+
+    #[test]
+    fn test_count_combinations_of_fields() {
+        let mut count = Aggregation::Count(CountAggregator::new(vec![
+            FieldPath::parse("level"),
+            FieldPath::parse("station.name"),
+        ]));
+        for _ in 0..12 {
+            count.observe(&json!({"level": "info", "station": {"name": "DS9"}}));
+        }
+        count.observe(&json!({"level": "error", "station": {"name": "Empok Nor"}}));
+        count.observe(&json!({"level": "info", "station": {"name": "Empok Nor"}}));
+
+        assert_eq!(
+            render(&count),
+            "level, station.name (14 records)\n\
+             \x20 12  info   DS9\n\
+             \x20  1  error  Empok Nor\n\
+             \x20  1  info   Empok Nor\n"
+        );
+    }
+
+    #[test]
+    fn test_top_values_with_shares() {
+        let mut top = Aggregation::Top(TopAggregator::new(FieldPath::parse("http.path"), 2));
+        for path in [
+            "/airlock",
+            "/promenade",
+            "/airlock",
+            "/ops",
+            "/airlock",
+            "/promenade",
+        ] {
+            top.observe(&json!({"http": {"path": path}}));
+        }
+        top.observe(&json!({"msg": "Docking request"}));
+
+        assert_eq!(
+            render(&top),
+            "http.path (6 values, 1 records without field)\n\
+             \x20 /airlock    3   50.0%\n\
+             \x20 /promenade  2   33.3%\n\
+             \x20 … 1 more values (1 records, 16.7%)\n"
+        );
+    }
+
+    #[test]
+    fn test_top_without_values() {
+        let top = Aggregation::Top(TopAggregator::new(FieldPath::parse("path"), 10));
+
+        assert_eq!(render(&top), "path (0 values)\n");
+    }
+
+    #[test]
+    fn test_numeric_statistics() {
+        let mut numeric = Aggregation::Numeric(NumericAggregator::new(FieldPath::parse("ms")));
+        for ms in 1..=100 {
+            numeric.observe(&json!({"ms": ms}));
+        }
+        numeric.observe(&json!({"ms": "250.5"}));
+        numeric.observe(&json!({"ms": "slow"}));
+        numeric.observe(&json!({"msg": "Docked"}));
+
+        assert_eq!(
+            render(&numeric),
+            "ms (101 values, 2 records without a number)\n\
+             \x20 min   1\n\
+             \x20 max   250.5\n\
+             \x20 mean  52.48\n\
+             \x20 p50   51\n\
+             \x20 p95   96\n\
+             \x20 p99   100\n"
+        );
+    }
+
+    #[test]
+    fn test_numeric_single_value() {
+        let mut numeric = Aggregation::Numeric(NumericAggregator::new(FieldPath::parse("ms")));
+        numeric.observe(&json!({"ms": 7}));
+
+        let output = render(&numeric);
+
+        assert!(output.ends_with("  p50   7\n  p95   7\n  p99   7\n"));
+    }
+
+    #[test]
+    fn test_histogram_categories_sorted_by_count() {
+        let mut histogram =
+            Aggregation::Histogram(HistogramAggregator::new(FieldPath::parse("species")));
+        for species in [
+            "Bajoran",
+            "Ferengi",
+            "Bajoran",
+            "Changeling",
+            "Bajoran",
+            "Ferengi",
+        ] {
+            histogram.observe(&json!({"species": species}));
+        }
+        histogram.observe(&json!({"name": "Garak"}));
+
+        let output = render(&histogram);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "species (6 values, 1 records without field)");
+        assert!(lines[1].starts_with("  Bajoran    │████████████████████████████████████████ 3"));
+        assert!(lines[2].starts_with("  Ferengi    │"));
+        assert!(lines[2].ends_with(" 2"));
+        assert!(lines[3].starts_with("  Changeling │"));
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn test_histogram_numeric_buckets() {
+        let mut histogram =
+            Aggregation::Histogram(HistogramAggregator::new(FieldPath::parse("latency")));
+        for latency in 0..=100 {
+            histogram.observe(&json!({"latency": latency}));
+        }
+
+        let output = render(&histogram);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "latency (101 values)");
+        assert_eq!(lines.len(), 1 + NUMERIC_BUCKETS);
+        assert!(lines[1].starts_with("  [0, 10)   │"));
+        assert!(lines[1].ends_with(" 10"));
+        // The maximum is included in the last bucket
+        assert!(lines[10].starts_with("  [90, 100] │"));
+        assert!(lines[10].ends_with(" 11"));
+    }
+
+    #[rstest]
+    #[case(4, "  [0, 25)   │", "  [75, 100] │")]
+    #[case(25, "  [0, 4)    │", "  [96, 100] │")]
+    fn test_histogram_bucket_count(
+        #[case] buckets: usize,
+        #[case] first: &str,
+        #[case] last: &str,
+    ) {
+        let mut histogram = Aggregation::Histogram(
+            HistogramAggregator::new(FieldPath::parse("latency")).with_buckets(buckets),
+        );
+        for latency in 0..=100 {
+            histogram.observe(&json!({"latency": latency}));
+        }
+
+        let output = render(&histogram);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 1 + buckets);
+        assert!(lines[1].starts_with(first));
+        assert!(lines[buckets].starts_with(last));
+    }
+
+    #[test]
+    fn test_histogram_mixed_types_fall_back_to_categories() {
+        let mut histogram =
+            Aggregation::Histogram(HistogramAggregator::new(FieldPath::parse("code")));
+        histogram.observe(&json!({"code": 404}));
+        histogram.observe(&json!({"code": "timeout"}));
+        histogram.observe(&json!({"code": 404}));
+
+        let output = render(&histogram);
+
+        assert!(output.contains("  404     │"));
+        assert!(output.contains("  timeout │"));
+    }
+
+    #[test]
+    fn test_histogram_single_numeric_value() {
+        let mut histogram =
+            Aggregation::Histogram(HistogramAggregator::new(FieldPath::parse("deck")));
+        histogram.observe(&json!({"deck": 4.5}));
+        histogram.observe(&json!({"deck": 4.5}));
+
+        let output = render(&histogram);
+
+        assert_eq!(
+            output.lines().nth(1).unwrap().trim_end(),
+            format!("  4.5 │{} 2", "█".repeat(BAR_WIDTH))
+        );
+    }
+
+    #[test]
+    fn test_histogram_without_values() {
+        let histogram =
+            Aggregation::Histogram(HistogramAggregator::new(FieldPath::parse("missing")));
+
+        assert_eq!(render(&histogram), "missing (0 values)\n");
+    }
+
+    #[test]
+    fn test_summary_counts_levels_and_messages() {
+        let mut summary = Aggregation::Summary(SummaryAggregator::new(LevelMap::default()));
+        summary.observe(&json!({"level": "info", "msg": "Request handled"}));
+        summary.observe(&json!({"level": 50, "msg": "Warp core breach"}));
+        summary.observe(&json!({"level": "info", "msg": "Request handled"}));
+        summary.observe(&json!({"message": "No level"}));
+
+        let output = render(&summary);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines[0].starts_with("4 records in "));
+        assert!(lines[0].ends_with("/s)"));
+        assert_eq!(lines[2], "Levels");
+        assert!(lines[3].starts_with("  error │"));
+        assert!(lines[4].starts_with("  info  │"));
+        assert_eq!(lines[6], "Top messages");
+        assert!(lines[7].starts_with("  Request handled  │"));
+        assert!(lines[7].ends_with(" 2"));
+        assert!(lines[8].starts_with("  Warp core breach │"));
+        assert!(lines[9].starts_with("  No level         │"));
+    }
+
+    #[test]
+    fn test_shorten() {
+        assert_eq!(shorten("Quark", 10), "Quark");
+        assert_eq!(
+            shorten("Quark's Bar, Grill, Gaming House", 10),
+            "Quark's B…"
+        );
+    }
+}
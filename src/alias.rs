@@ -0,0 +1,115 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::field_path::FieldPath;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+/// Display-only key renames, e.g. `ts` rendered as `timestamp`.
+///
+/// Aliases are applied to a copy of the record right before formatting, so
+/// filters always see the original keys produced by the application.
+#[derive(Debug, Default)]
+pub struct KeyAliases {
+    aliases: Vec<(FieldPath, String)>,
+}
+
+impl KeyAliases {
+    /// Creates aliases from `(source path, display name)` pairs
+    pub fn new(aliases: Vec<(String, String)>) -> Self {
+        Self {
+            aliases: aliases
+                .into_iter()
+                .map(|(source, display)| (FieldPath::parse(&source), display))
+                .collect(),
+        }
+    }
+
+    /// Returns the record with aliased keys renamed, keeping key order.
+    ///
+    /// The record is only cloned if at least one alias applies. A key is left
+    /// untouched if its display name would clash with an existing key.
+    pub fn apply<'a>(&self, value: &'a Value) -> Cow<'a, Value> {
+        let mut renamed = Cow::Borrowed(value);
+
+        for (source, display) in &self.aliases {
+            if source.get(&renamed).is_none() {
+                continue;
+            }
+
+            let (parent_path, key) = source.split_last();
+            if let Some(Value::Object(parent)) = parent_path.get_mut(renamed.to_mut())
+                && !parent.contains_key(display)
+            {
+                *parent = std::mem::take(parent)
+                    .into_iter()
+                    .map(|(k, v)| {
+                        if k == key {
+                            (display.clone(), v)
+                        } else {
+                            (k, v)
+                        }
+                    })
+                    .collect::<Map<String, Value>>();
+            }
+        }
+
+        renamed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn aliases(pairs: &[(&str, &str)]) -> KeyAliases {
+        KeyAliases::new(
+            pairs
+                .iter()
+                .map(|(source, display)| (source.to_string(), display.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_renames_keys_in_place() {
+        let aliases = aliases(&[("ts", "timestamp"), ("lvl", "level")]);
+        let value = json!({"ts": 1715594400, "lvl": "warn", "msg": "Cloaked ship detected"});
+
+        let renamed = aliases.apply(&value);
+
+        assert_eq!(
+            serde_json::to_string(&renamed).unwrap(),
+            r#"{"timestamp":1715594400,"level":"warn","msg":"Cloaked ship detected"}"#
+        );
+    }
+
+    #[test]
+    fn test_renames_nested_keys() {
+        let aliases = aliases(&[("http.sc", "status_code")]);
+        let value = json!({"http": {"sc": 404, "m": "GET"}});
+
+        assert_eq!(
+            *aliases.apply(&value),
+            json!({"http": {"status_code": 404, "m": "GET"}})
+        );
+    }
+
+    #[test]
+    fn test_leaves_record_borrowed_without_matches() {
+        let aliases = aliases(&[("ts", "timestamp")]);
+        let value = json!({"time": 1715594400});
+
+        assert!(matches!(aliases.apply(&value), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_does_not_overwrite_existing_keys() {
+        let aliases = aliases(&[("lvl", "level")]);
+        let value = json!({"lvl": 30, "level": "info"});
+
+        assert_eq!(*aliases.apply(&value), json!({"lvl": 30, "level": "info"}));
+    }
+}
@@ -0,0 +1,71 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::borrow::Cow;
+
+const ESCAPE: char = '\x1b';
+const BELL: char = '\x07';
+
+/// Removes ANSI escape sequences from a line.
+///
+/// Covers CSI sequences like the SGR colors `ESC [ 31 m`, OSC sequences like
+/// hyperlinks terminated by BEL or `ESC \`, and short escapes like `ESC 7`. An
+/// unterminated sequence at the end of the line is removed as well. Lines
+/// without an escape character are returned as they are.
+pub fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if !line.contains(ESCAPE) {
+        return Cow::Borrowed(line);
+    }
+
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != ESCAPE {
+            stripped.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameter and intermediate bytes up to a final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: anything up to BEL or the string terminator `ESC \`
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == BELL || (c == ESCAPE && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // Intermediate bytes like the `(` of `ESC ( B` up to a final byte
+            Some(c) if ('\x20'..='\x2f').contains(&c) => {
+                while chars.next_if(|c| ('\x20'..='\x2f').contains(c)).is_some() {}
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Quark's bar", "Quark's bar")]
+    #[case("\x1b[32m{\"ship\": \"Defiant\"}\x1b[0m", "{\"ship\": \"Defiant\"}")]
+    #[case("\x1b[1;38;5;208mINFO\x1b[m docked", "INFO docked")]
+    #[case("\x1b]8;;https://ds9.example\x07Ops\x1b]8;;\x1b\\ log", "Ops log")]
+    #[case("\x1b(Bplain\x1b7", "plain")]
+    #[case("Odo \x1b[31", "Odo ")]
+    fn test_strip_ansi(#[case] line: &str, #[case] expected: &str) {
+        assert_eq!(strip_ansi(line), expected);
+    }
+}
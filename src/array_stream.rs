@@ -0,0 +1,323 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::buffer::BufferResult;
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+enum SplitterState {
+    Searching, // Waiting for the opening bracket of the array
+    InArray,   // Collecting elements
+    Done,      // Closing bracket seen, remaining input is not ours
+}
+
+/// Incremental parser emitting the elements of one top-level JSON array.
+///
+/// API dumps often arrive as a single pretty-printed array spanning far more
+/// lines than the `LineBuffer` is willing to hold. Instead of parsing the whole
+/// document, the splitter tracks nesting and string state character by
+/// character and hands out each element as soon as its closing delimiter is
+/// seen, so memory is bounded by the largest element rather than the input.
+///
+/// Elements that fail to parse are passed through as text.
+#[derive(Debug)]
+pub struct ArraySplitter {
+    state: SplitterState,
+    current: String,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl Default for ArraySplitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArraySplitter {
+    pub fn new() -> Self {
+        Self {
+            state: SplitterState::Searching,
+            current: String::new(),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    /// Returns true if the line belongs to the array.
+    ///
+    /// Input that doesn't start with an array, and everything after its closing
+    /// bracket, is left to regular line processing. So is text that merely
+    /// starts with a bracket, like `[INFO] Server started`.
+    pub fn accepts(&self, line: &str) -> bool {
+        match self.state {
+            SplitterState::Searching => {
+                let trimmed = line.trim_start();
+                trimmed.is_empty() || trimmed.strip_prefix('[').is_some_and(starts_elements)
+            }
+            SplitterState::InArray => true,
+            SplitterState::Done => false,
+        }
+    }
+
+    /// Consumes the next line, returning every element completed by it
+    pub fn add_line(&mut self, line: String) -> Vec<BufferResult> {
+        let mut results = Vec::new();
+
+        for (index, c) in line.char_indices() {
+            match self.state {
+                SplitterState::Searching => {
+                    if c == '[' {
+                        self.state = SplitterState::InArray;
+                    }
+                }
+                SplitterState::InArray => self.consume(c, &mut results),
+                SplitterState::Done => {
+                    // Trailing content on the line of the closing bracket
+                    let rest = line[index..].trim();
+                    if !rest.is_empty() {
+                        results.push(BufferResult::Text(rest.to_string()));
+                    }
+                    break;
+                }
+            }
+        }
+
+        if !self.current.is_empty() {
+            self.current.push('\n');
+        }
+
+        results
+    }
+
+    /// Flushes an unterminated element at EOF as text
+    pub fn drain(&mut self) -> Vec<BufferResult> {
+        let rest = self.current.trim();
+        let results = if rest.is_empty() {
+            vec![]
+        } else {
+            vec![BufferResult::Text(rest.to_string())]
+        };
+        self.current.clear();
+        results
+    }
+
+    fn consume(&mut self, c: char, results: &mut Vec<BufferResult>) {
+        if self.in_string {
+            self.current.push(c);
+            if self.escaped {
+                self.escaped = false;
+            } else if c == '\\' {
+                self.escaped = true;
+            } else if c == '"' {
+                self.in_string = false;
+            }
+            return;
+        }
+
+        match c {
+            '"' => {
+                self.in_string = true;
+                self.current.push(c);
+            }
+            '[' | '{' => {
+                self.depth += 1;
+                self.current.push(c);
+            }
+            ']' if self.depth == 0 => {
+                self.finish_element(results);
+                self.state = SplitterState::Done;
+            }
+            ']' | '}' => {
+                // Stray closers in malformed elements must not underflow
+                self.depth = self.depth.saturating_sub(1);
+                self.current.push(c);
+            }
+            ',' if self.depth == 0 => self.finish_element(results),
+            _ => self.current.push(c),
+        }
+    }
+
+    fn finish_element(&mut self, results: &mut Vec<BufferResult>) {
+        let element = self.current.trim();
+        if !element.is_empty() {
+            results.push(match serde_json::from_str::<Value>(element) {
+                Ok(value) => BufferResult::Json(value, element.len()),
+                Err(_) => BufferResult::Text(element.to_string()),
+            });
+        }
+        self.current.clear();
+    }
+}
+
+/// Whether the text after an opening bracket continues a JSON array: nothing
+/// or the closing bracket, or a first element that is followed by `,` or `]`
+/// or cut off by the end of the line
+fn starts_elements(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with(']') {
+        return true;
+    }
+    let mut elements = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+    match elements.next() {
+        Some(Ok(_)) => {
+            let after = rest[elements.byte_offset()..].trim_start();
+            after.is_empty() || after.starts_with([',', ']'])
+        }
+        Some(Err(error)) => error.is_eof(),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn split(input: &str) -> Vec<BufferResult> {
+        let mut splitter = ArraySplitter::new();
+        let mut results = Vec::new();
+        for line in input.lines() {
+            assert!(splitter.accepts(line));
+            results.extend(splitter.add_line(line.to_string()));
+        }
+        results.extend(splitter.drain());
+        results
+    }
+
+    #[test]
+    fn test_emits_elements_of_pretty_printed_array() {
+        let input = r#"[
+  {
+    "name": "Sisko",
+    "rank": "Captain"
+  },
+  {
+    "name": "Kira",
+    "aliases": ["Nerys", "]"]
+  },
+  47,
+  "Odo, the \"changeling\""
+]"#;
+
+        let results = split(input);
+        let values: Vec<&Value> = results
+            .iter()
+            .map(|result| match result {
+                BufferResult::Json(value, _) => value,
+                other => panic!("Expected JSON, got {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![
+                &json!({"name": "Sisko", "rank": "Captain"}),
+                &json!({"name": "Kira", "aliases": ["Nerys", "]"]}),
+                &json!(47),
+                &json!("Odo, the \"changeling\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emits_elements_as_soon_as_they_complete() {
+        let mut splitter = ArraySplitter::new();
+
+        assert_eq!(splitter.add_line("[".to_string()), vec![]);
+        assert_eq!(
+            splitter.add_line(r#"  {"ship": "Defiant"}"#.to_string()),
+            vec![]
+        );
+        assert_eq!(
+            splitter.add_line(r#"  , {"ship": "Rio Grande"}"#.to_string()),
+            vec![BufferResult::Json(json!({"ship": "Defiant"}), 19)]
+        );
+        assert_eq!(
+            splitter.add_line("]".to_string()),
+            vec![BufferResult::Json(json!({"ship": "Rio Grande"}), 22)]
+        );
+    }
+
+    #[test]
+    fn test_single_line_array() {
+        let results = split(r#"[{"a": 1}, [2, 3], null]"#);
+
+        assert_eq!(
+            results,
+            vec![
+                BufferResult::Json(json!({"a": 1}), 8),
+                BufferResult::Json(json!([2, 3]), 6),
+                BufferResult::Json(json!(null), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_array() {
+        assert_eq!(split("[\n]"), vec![]);
+    }
+
+    #[test]
+    fn test_malformed_element_passes_through_as_text() {
+        let results = split("[\n  {\"ok\": true},\n  {broken}},\n  3\n]");
+
+        assert_eq!(
+            results,
+            vec![
+                BufferResult::Json(json!({"ok": true}), 12),
+                BufferResult::Text("{broken}}".to_string()),
+                BufferResult::Json(json!(3), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_array_flushes_rest_at_drain() {
+        let results = split("[\n  {\"complete\": 1},\n  {\"trunc");
+
+        assert_eq!(
+            results,
+            vec![
+                BufferResult::Json(json!({"complete": 1}), 15),
+                BufferResult::Text("{\"trunc".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bracketed_text_is_not_an_array() {
+        let splitter = ArraySplitter::new();
+
+        assert!(!splitter.accepts("[INFO] Server started"));
+        assert!(!splitter.accepts("[2024-05-01 12:00:00] Docking clamps released"));
+        assert!(!splitter.accepts("[ds9] {\"ship\": \"Defiant\"}"));
+        assert!(splitter.accepts("[]"));
+        assert!(splitter.accepts("[{\"ship\": \"Defiant\"},"));
+        assert!(splitter.accepts("  [{\"ship\":"));
+        assert!(splitter.accepts("[\"Sisko\""));
+    }
+
+    #[test]
+    fn test_releases_input_after_closing_bracket() {
+        let mut splitter = ArraySplitter::new();
+
+        assert!(!splitter.accepts("Quark's bar is open"));
+        assert!(splitter.accepts("   "));
+        assert!(splitter.accepts("["));
+
+        assert_eq!(
+            splitter.add_line("[1, 2]  trailing".to_string()),
+            vec![
+                BufferResult::Json(json!(1), 1),
+                BufferResult::Json(json!(2), 1),
+                BufferResult::Text("trailing".to_string()),
+            ]
+        );
+
+        assert!(!splitter.accepts(r#"{"after": "array"}"#));
+    }
+}
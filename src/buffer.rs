@@ -3,27 +3,74 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use serde_json::Value;
+use std::collections::VecDeque;
 
 #[derive(Debug, PartialEq)]
 pub enum BufferResult {
-    Json(Value),             // Parsed JSON object ready for formatting
+    Json(Value, usize),      // Parsed JSON and its original size in bytes
     Text(String),            // Non-JSON line for pass-through
     Incomplete(Vec<String>), // Buffered lines, need more input
 }
 
+impl BufferResult {
+    /// Size of the original input in bytes, excluding the final line ending
+    pub fn size(&self) -> usize {
+        match self {
+            BufferResult::Json(_, size) => *size,
+            BufferResult::Text(text) => text.len(),
+            BufferResult::Incomplete(lines) => joined_size(lines),
+        }
+    }
+}
+
+/// Handling of text following a JSON document on the same line, like the
+/// ` took 34ms` of `{"a":1} took 34ms`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrailingText {
+    /// Output the text as a line of its own after the record
+    Separate,
+    /// Add the text to the record under this key
+    Field(String),
+}
+
 pub struct LineBuffer {
-    buffer: Vec<String>,
+    buffer: VecDeque<BufferedLine>,
     max_lines: usize,
+    max_bytes: Option<usize>,
+    trailing: Option<TrailingText>,
+    // Totals over all buffered lines, kept up to date on every push and pop
+    depth: isize,
+    open_strings: usize,
+    bytes: usize,
 }
 
 impl LineBuffer {
     pub fn new(max_lines: usize) -> Self {
         Self {
-            buffer: Vec::new(),
+            buffer: VecDeque::new(),
             max_lines,
+            max_bytes: None,
+            trailing: None,
+            depth: 0,
+            open_strings: 0,
+            bytes: 0,
         }
     }
 
+    /// Also overflows once the buffered lines hold more than `max_bytes`,
+    /// so huge lines can't pile up regardless of their count
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Accepts lines holding a JSON object or array followed by other text,
+    /// which otherwise aren't JSON at all
+    pub fn with_trailing_text(mut self, trailing: TrailingText) -> Self {
+        self.trailing = Some(trailing);
+        self
+    }
+
     /// Processes a new line and returns parsing results.
     ///
     /// ## Processing Logic Overview
@@ -36,6 +83,7 @@ impl LineBuffer {
     ///   - If successful: extract JSON, clear buffer, continue in Accumulating state
     ///
     /// - **Overflow Handling**: When buffer exceeds max_lines (e.g., max_lines=3, buffer has 3+ lines)
+    ///   or holds more than max_bytes
     ///   - Example: buffer `["garbage", "{\"a\":1}", "more text", "fourth line"]` with max_lines=3
     ///   - Action: remove first line ("garbage") as Text, switch to Draining state
     ///   - Rationale: We can't wait forever, must make progress by removing oldest line
@@ -74,7 +122,7 @@ impl LineBuffer {
             return vec![BufferResult::Text(line)];
         }
 
-        self.buffer.push(line);
+        self.push_line(line);
         let mut results = Vec::new();
 
         #[derive(Debug)]
@@ -91,41 +139,44 @@ impl LineBuffer {
 
             match state {
                 BufferState::Accumulating => {
-                    if let Some((json_value, _)) = self.try_parse_buffer_segments() {
+                    if let Some((documents, _)) = self.try_parse_buffer_segments() {
                         // Full buffer is JSON - no text before it
-                        results.push(BufferResult::Json(json_value));
-                        self.buffer.clear();
+                        results.extend(documents);
+                        self.clear();
                         is_stable = false;
-                    } else if self.buffer.len() >= self.max_lines {
+                    } else if self.is_full() {
                         // Overflow: remove first line and transition to Draining
-                        results.push(BufferResult::Text(self.buffer.remove(0)));
+                        results.push(BufferResult::Text(self.remove_first_line()));
                         state = BufferState::Draining;
                         is_stable = false;
-                    } else if self.buffer.len() == 1 && !Self::could_be_json_start(&self.buffer[0])
+                    } else if self.buffer.len() == 1
+                        && !Self::could_be_json_start(&self.buffer[0].text)
                     {
                         // Single non-JSON line - flush it
-                        results.push(BufferResult::Text(self.buffer.remove(0)));
+                        results.push(BufferResult::Text(self.remove_first_line()));
                         is_stable = false;
                     }
                 }
                 BufferState::Draining => {
-                    if let Some((json_value, end_idx)) = self.try_parse_forward_segments() {
+                    if let Some((documents, end_idx)) = self.try_parse_forward_segments() {
                         // Found JSON via forward scanning
-                        results.push(BufferResult::Json(json_value));
+                        results.extend(documents);
                         for _ in 0..end_idx {
-                            self.buffer.remove(0);
+                            self.remove_first_line();
                         }
                         state = BufferState::Draining; // Stay in draining - buffer structure changed
                         is_stable = false;
-                    } else if !Self::could_be_json_start(&self.buffer[0]) {
+                    } else if !Self::could_be_json_start(&self.buffer[0].text) {
                         // First line not JSON-like, flush as text
-                        results.push(BufferResult::Text(self.buffer.remove(0)));
+                        results.push(BufferResult::Text(self.remove_first_line()));
                         state = BufferState::Draining; // Stay in draining - buffer structure changed
                         is_stable = false;
                     } else {
                         // First line could be JSON but forward scan found nothing
                         // Buffer structure unchanged - back to accumulating
                         state = BufferState::Accumulating;
+                        // Unless a huge remaining line keeps the buffer over its byte limit
+                        is_stable = !self.is_full();
                     }
                 }
             }
@@ -133,7 +184,8 @@ impl LineBuffer {
             // If buffer is stable, we're done
             if is_stable {
                 if !self.buffer.is_empty() {
-                    results.push(BufferResult::Incomplete(self.buffer.clone()));
+                    let lines = self.buffer.iter().map(|line| line.text.clone()).collect();
+                    results.push(BufferResult::Incomplete(lines));
                 }
                 break;
             }
@@ -147,6 +199,39 @@ impl LineBuffer {
         results
     }
 
+    fn push_line(&mut self, text: String) {
+        let shape = LineShape::of(&text);
+        self.depth += shape.depth;
+        self.open_strings += usize::from(shape.open_string);
+        self.bytes += text.len();
+        self.buffer.push_back(BufferedLine { text, shape });
+    }
+
+    fn remove_first_line(&mut self) -> String {
+        let line = self
+            .buffer
+            .pop_front()
+            .expect("only called on a non-empty buffer");
+        self.depth -= line.shape.depth;
+        self.open_strings -= usize::from(line.shape.open_string);
+        self.bytes -= line.text.len();
+        line.text
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.depth = 0;
+        self.open_strings = 0;
+        self.bytes = 0;
+    }
+
+    fn is_full(&self) -> bool {
+        self.buffer.len() >= self.max_lines
+            || self
+                .max_bytes
+                .is_some_and(|max_bytes| self.bytes > max_bytes)
+    }
+
     fn could_be_json_start(line: &str) -> bool {
         let trimmed = line.trim();
         if trimmed.is_empty() {
@@ -161,60 +246,73 @@ impl LineBuffer {
         }
     }
 
-    fn try_parse_buffer_segments(&self) -> Option<(Value, usize)> {
+    fn try_parse_buffer_segments(&self) -> Option<(Vec<BufferResult>, usize)> {
+        // Unbalanced brackets or a line break within a string can't be valid
+        // JSON, so serde doesn't have to re-parse the buffer for every line
+        if self.depth != 0 || self.open_strings > 0 {
+            return None;
+        }
+
         // Only try full buffer parsing
-        let full_combined = self.buffer.join("\n");
-        if let Ok(json_value) = serde_json::from_str::<Value>(&full_combined) {
-            return Some((json_value, 0));
+        let mut full_combined = String::with_capacity(self.bytes + self.buffer.len());
+        for (index, line) in self.buffer.iter().enumerate() {
+            if index > 0 {
+                full_combined.push('\n');
+            }
+            full_combined.push_str(&line.text);
+        }
+        if let Some(documents) = parse_documents(&full_combined, self.trailing.as_ref()) {
+            return Some((documents, 0));
         }
 
         None
     }
 
-    fn try_parse_forward_segments(&self) -> Option<(Value, usize)> {
-        // Incremental forward scan with optimized string building
+    fn try_parse_forward_segments(&self) -> Option<(Vec<BufferResult>, usize)> {
+        // Forward scan guided by the line shapes
         //
-        // PERFORMANCE OPTIMIZATION RATIONALE:
-        // Previous naive approach: buffer[0..n].join("\n") for each segment
-        // - For buffer size 128: creates 128 separate string allocations
-        // - Each join() scans all lines again: O(n²) string operations
-        // - Total memory allocations: ~128 * average_line_length * buffer_size
-        // - Example: 128 lines * 50 chars * 128 segments = ~819KB of allocations per scan
-        //
-        // This optimized approach: single pre-allocated string, incremental building
-        // - Creates exactly 1 string allocation for entire buffer content
-        // - Incrementally appends each line: O(n) string operations
-        // - Total memory allocation: 1 * total_buffer_content (~6KB for same example)
-        // - Result: ~3x performance improvement by eliminating allocation overhead
-
-        if self.buffer.is_empty() {
-            return None;
-        }
-
-        // Pre-calculate total capacity needed (all lines + newlines between them)
-        let total_capacity: usize =
-            self.buffer.iter().map(|line| line.len()).sum::<usize>() + (self.buffer.len() - 1); // newlines between lines
-        let mut combined = String::with_capacity(total_capacity);
-
-        // Try parsing single line first
-        combined.push_str(&self.buffer[0]);
-        if let Ok(json_value) = serde_json::from_str::<Value>(&combined) {
-            return Some((json_value, 1));
-        }
+        // Only segments with balanced brackets can hold complete documents, so
+        // the combined string is built and parsed only up to those. The scan
+        // stops at the first segment no amount of further lines could turn
+        // into valid JSON: one closing a bracket it never opened, or breaking
+        // a line within a string. Overflowing a buffer of unfinished lines,
+        // e.g. thousands of lines starting with `{`, is then a walk over
+        // integers instead of a serde parse per buffered line.
+        let mut combined = String::new();
+        let mut combined_lines = 0;
+        let mut depth = 0;
+
+        for (index, line) in self.buffer.iter().enumerate() {
+            if line.shape.open_string || depth + line.shape.min_depth < 0 {
+                return None;
+            }
+            depth += line.shape.depth;
+            if depth != 0 {
+                continue;
+            }
 
-        // Incrementally build combined string for multi-line segments
-        for end_idx in 2..=self.buffer.len() {
-            combined.push('\n');
-            combined.push_str(&self.buffer[end_idx - 1]);
+            // Incrementally extend the combined string up to this line
+            for line in self.buffer.range(combined_lines..=index) {
+                if combined_lines > 0 {
+                    combined.push('\n');
+                }
+                combined.push_str(&line.text);
+                combined_lines += 1;
+            }
 
-            if let Ok(json_value) = serde_json::from_str::<Value>(&combined) {
-                return Some((json_value, end_idx));
+            if let Some(documents) = parse_documents(&combined, self.trailing.as_ref()) {
+                return Some((documents, index + 1));
             }
         }
 
         None
     }
 
+    /// Returns true if no lines are waiting for a JSON document to complete
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
     /// Drains all remaining buffer contents, extracting any valid JSON.
     ///
     /// This method should be called when input ends (EOF) to flush any remaining
@@ -226,15 +324,15 @@ impl LineBuffer {
 
         // Keep processing until buffer is empty (like Draining state)
         while !self.buffer.is_empty() {
-            if let Some((json_value, end_idx)) = self.try_parse_forward_segments() {
+            if let Some((documents, end_idx)) = self.try_parse_forward_segments() {
                 // Found valid JSON, extract it
-                results.push(BufferResult::Json(json_value));
+                results.extend(documents);
                 for _ in 0..end_idx {
-                    self.buffer.remove(0);
+                    self.remove_first_line();
                 }
             } else {
                 // No valid JSON found, flush first line as text (don't wait)
-                results.push(BufferResult::Text(self.buffer.remove(0)));
+                results.push(BufferResult::Text(self.remove_first_line()));
             }
         }
 
@@ -242,6 +340,108 @@ impl LineBuffer {
     }
 }
 
+struct BufferedLine {
+    text: String,
+    shape: LineShape,
+}
+
+/// Bracket nesting and string state of a single line.
+///
+/// JSON strings can't contain raw line breaks, so every line of a valid
+/// document starts outside of a string and lines can be scanned on their own.
+/// Only lines with balanced brackets in total can hold complete JSON. The
+/// shape doesn't validate anything else, serde still has the final word.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct LineShape {
+    // Opened minus closed brackets
+    depth: isize,
+    // Lowest nesting reached relative to the start of the line
+    min_depth: isize,
+    // Whether the line ends within a string
+    open_string: bool,
+}
+
+impl LineShape {
+    fn of(line: &str) -> Self {
+        let mut shape = LineShape::default();
+        let mut escaped = false;
+
+        for byte in line.bytes() {
+            if shape.open_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => shape.open_string = false,
+                    _ => {}
+                }
+            } else {
+                match byte {
+                    b'"' => shape.open_string = true,
+                    b'{' | b'[' => shape.depth += 1,
+                    b'}' | b']' => {
+                        shape.depth -= 1;
+                        shape.min_depth = shape.min_depth.min(shape.depth);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        shape
+    }
+}
+
+/// Parses text holding a single JSON value or several concatenated JSON
+/// documents, like `{"a":1}{"b":2}` or `{"a":1} {"b":2}`.
+///
+/// Concatenation is only accepted for objects and arrays, so text such as
+/// `1 2 3` isn't mistaken for a sequence of numbers. The same goes for text
+/// trailing the documents on their last line, if accepted at all.
+fn parse_documents(text: &str, trailing: Option<&TrailingText>) -> Option<Vec<BufferResult>> {
+    if let Ok(value) = serde_json::from_str::<Value>(text) {
+        return Some(vec![BufferResult::Json(value, text.len())]);
+    }
+
+    let mut documents = Vec::new();
+    let mut stream = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+    let mut start = 0;
+    while let Some(value) = stream.next() {
+        let Some(value) = value.ok().filter(|v| v.is_object() || v.is_array()) else {
+            let rest = text[start..].trim();
+            return match trailing {
+                Some(trailing) if !documents.is_empty() && !rest.contains('\n') => {
+                    attach_trailing(&mut documents, trailing, rest);
+                    Some(documents)
+                }
+                _ => None,
+            };
+        };
+        let end = stream.byte_offset();
+        documents.push(BufferResult::Json(value, text[start..end].trim().len()));
+        start = end;
+    }
+
+    // A single document with trailing whitespace was handled above already
+    (documents.len() > 1).then_some(documents)
+}
+
+/// Adds text trailing the last document as configured, as a line of its own
+/// where it can't be added to a record
+fn attach_trailing(documents: &mut Vec<BufferResult>, trailing: &TrailingText, text: &str) {
+    if let (TrailingText::Field(key), Some(BufferResult::Json(Value::Object(record), _))) =
+        (trailing, documents.last_mut())
+    {
+        record.insert(key.clone(), Value::String(text.to_string()));
+    } else {
+        documents.push(BufferResult::Text(text.to_string()));
+    }
+}
+
+/// Byte size of lines joined by newlines, as they appeared in the input
+fn joined_size(lines: &[String]) -> usize {
+    lines.iter().map(String::len).sum::<usize>() + lines.len().saturating_sub(1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,7 +472,8 @@ mod tests {
         assert_eq!(
             results,
             vec![BufferResult::Json(
-                json!({"vedek": "Bareil Antos", "occupation": "Former resistance fighter"})
+                json!({"vedek": "Bareil Antos", "occupation": "Former resistance fighter"}),
+                68
             )]
         );
         assert!(buffer.buffer.is_empty());
@@ -301,7 +502,8 @@ mod tests {
         assert_eq!(
             results3,
             vec![BufferResult::Json(
-                json!({"holosuite_program": "Vic Fontaine"})
+                json!({"holosuite_program": "Vic Fontaine"}),
+                41
             )]
         );
         assert!(buffer.buffer.is_empty());
@@ -360,9 +562,12 @@ mod tests {
             results5,
             vec![
                 BufferResult::Text("{Morn speaks for the first time}".to_string()),
-                BufferResult::Json(json!({"patron": "Morn", "beverage_tab": "astronomical"})),
+                BufferResult::Json(
+                    json!({"patron": "Morn", "beverage_tab": "astronomical"}),
+                    50
+                ),
                 BufferResult::Text("Quark closes the bar".to_string()),
-                BufferResult::Json(json!({"barkeeper": "Quark"}))
+                BufferResult::Json(json!({"barkeeper": "Quark"}), 25)
             ]
         );
         assert!(buffer.buffer.is_empty());
@@ -384,12 +589,113 @@ mod tests {
         assert_eq!(
             results2,
             vec![BufferResult::Json(
-                json!({"suspect": "Quark", "evidence": "none"})
+                json!({"suspect": "Quark", "evidence": "none"}),
+                40
             )]
         );
         assert!(buffer.buffer.is_empty());
     }
 
+    #[rstest]
+    #[case("{", 1, 0, false)]
+    #[case("\"crew\": [\"Sisko\", \"Kira\"]", 0, 0, false)]
+    #[case("}", -1, -1, false)]
+    #[case("{\"quote\": \"unbalanced } and ] in a string\"}", 0, 0, false)]
+    #[case("{\"quote\": \"escaped \\\" quote }", 1, 0, true)]
+    #[case("\"escaped backslash \\\\\" {", 1, 0, false)]
+    #[case("{\"a\": 1}{\"b\": 2}", 0, 0, false)]
+    #[case("}, {", 0, -1, false)]
+    fn test_line_shape(
+        #[case] line: &str,
+        #[case] depth: isize,
+        #[case] min_depth: isize,
+        #[case] open_string: bool,
+    ) {
+        assert_eq!(
+            LineShape::of(line),
+            LineShape {
+                depth,
+                min_depth,
+                open_string
+            }
+        );
+    }
+
+    #[test]
+    fn test_overflow_extracts_json_after_unbalanced_line() {
+        let mut buffer = LineBuffer::new(3);
+
+        buffer.add_line("{ \"station\": \"Terok Nor\",".to_string());
+        buffer.add_line("{\"a\":".to_string());
+        // Overflow drops the first line, the rest stays unbalanced
+        let results = buffer.add_line("1}".to_string());
+        assert_eq!(
+            results,
+            vec![
+                BufferResult::Text("{ \"station\": \"Terok Nor\",".to_string()),
+                BufferResult::Json(json!({"a": 1}), 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_depth_is_updated_after_overflow() {
+        let mut buffer = LineBuffer::new(2);
+
+        buffer.add_line("[".to_string());
+        // Overflows the unbalanced `[`, leaving the buffer balanced on its own
+        let results = buffer.add_line("{".to_string());
+        assert_eq!(
+            results,
+            vec![
+                BufferResult::Text("[".to_string()),
+                BufferResult::Incomplete(vec!["{".to_string()])
+            ]
+        );
+
+        let results = buffer.add_line("}".to_string());
+        assert_eq!(results, vec![BufferResult::Json(json!({}), 3)]);
+    }
+
+    #[test]
+    fn test_buffer_overflow_by_bytes() {
+        let mut buffer = LineBuffer::new(10).with_max_bytes(16);
+
+        let results1 = buffer.add_line("{".to_string());
+        assert_eq!(
+            results1,
+            vec![BufferResult::Incomplete(vec!["{".to_string()])]
+        );
+
+        // Far below max_lines, but the buffered lines exceed 16 bytes
+        let results2 = buffer.add_line("\"log\": \"Captain's log, stardate 47457.1\"".to_string());
+        assert_eq!(
+            results2,
+            vec![
+                BufferResult::Text("{".to_string()),
+                BufferResult::Text("\"log\": \"Captain's log, stardate 47457.1\"".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oversized_line_is_not_buffered() {
+        let mut buffer = LineBuffer::new(10).with_max_bytes(8);
+
+        let results = buffer.add_line("[\"Kira\", \"Odo\",".to_string());
+        assert_eq!(
+            results,
+            vec![BufferResult::Text("[\"Kira\", \"Odo\",".to_string())]
+        );
+
+        // Complete documents are still parsed, however large
+        let results = buffer.add_line("[\"Kira\", \"Odo\"]".to_string());
+        assert_eq!(
+            results,
+            vec![BufferResult::Json(json!(["Kira", "Odo"]), 15)]
+        );
+    }
+
     #[test]
     fn test_buffer_overflow() {
         let mut buffer = LineBuffer::new(2);
@@ -447,11 +753,12 @@ mod tests {
     #[case("true", json!(true))]
     #[case("false", json!(false))]
     #[case("null", json!(null))]
+    #[allow(clippy::approx_constant)]
     fn test_single_line_json_types(#[case] json_str: &str, #[case] expected: serde_json::Value) {
         let mut buffer = LineBuffer::new(10);
         let results = buffer.add_line(json_str.to_string());
 
-        assert_eq!(results, vec![BufferResult::Json(expected)]);
+        assert_eq!(results, vec![BufferResult::Json(expected, json_str.len())]);
         assert!(buffer.buffer.is_empty());
     }
 
@@ -532,7 +839,8 @@ mod tests {
         );
 
         let results4 = buffer.add_line(line4.to_string());
-        assert_eq!(results4, vec![BufferResult::Json(expected)]);
+        let size = [line1, line2, line3, line4].join("\n").len();
+        assert_eq!(results4, vec![BufferResult::Json(expected, size)]);
         assert!(buffer.buffer.is_empty());
     }
 
@@ -565,7 +873,8 @@ mod tests {
         );
 
         let results3 = buffer.add_line(close.to_string());
-        assert_eq!(results3, vec![BufferResult::Json(expected)]);
+        let size = [open, valid_json_content, close].join("\n").len();
+        assert_eq!(results3, vec![BufferResult::Json(expected, size)]);
         assert!(buffer.buffer.is_empty());
     }
 
@@ -607,7 +916,8 @@ mod tests {
         );
 
         let results4 = buffer.add_line(close.to_string());
-        assert_eq!(results4, vec![BufferResult::Json(expected)]);
+        let size = [open, valid_json1, valid_json2, close].join("\n").len();
+        assert_eq!(results4, vec![BufferResult::Json(expected, size)]);
         assert!(buffer.buffer.is_empty());
     }
 
@@ -639,7 +949,7 @@ mod tests {
             results3,
             vec![
                 BufferResult::Text("{Kai Winn plots against Sisko}".to_string()),
-                BufferResult::Json(json!("Benjamin Sisko is the Emissary")),
+                BufferResult::Json(json!("Benjamin Sisko is the Emissary"), 32),
                 BufferResult::Incomplete(vec!["[Prophets communicate through orbs".to_string()])
             ]
         );
@@ -660,7 +970,7 @@ mod tests {
             results5,
             vec![
                 BufferResult::Text("[Prophets communicate through orbs".to_string()),
-                BufferResult::Json(json!([1, 2, 3])),
+                BufferResult::Json(json!([1, 2, 3]), 9),
                 BufferResult::Text("Garak tailors clothes on the promenade".to_string())
             ]
         );
@@ -687,7 +997,7 @@ mod tests {
             results2,
             vec![
                 BufferResult::Text("{invalid json syntax".to_string()),
-                BufferResult::Json(json!({"valid": "json"}))
+                BufferResult::Json(json!({"valid": "json"}), 17)
             ]
         );
         assert!(buffer.buffer.is_empty());
@@ -739,7 +1049,7 @@ mod tests {
             drain_results,
             vec![
                 BufferResult::Text("{incomplete json".to_string()),
-                BufferResult::Json(json!({"valid": "json"})),
+                BufferResult::Json(json!({"valid": "json"}), 17),
                 BufferResult::Text("more text".to_string())
             ]
         );
@@ -828,7 +1138,7 @@ mod tests {
             drain_results,
             vec![
                 BufferResult::Text("{Worf's honor code".to_string()),
-                BufferResult::Json(json!({"captain": "Sisko"}))
+                BufferResult::Json(json!({"captain": "Sisko"}), 24)
             ]
         );
         assert!(buffer.buffer.is_empty());
@@ -843,4 +1153,98 @@ mod tests {
         assert_eq!(drain_results, vec![]);
         assert!(buffer.buffer.is_empty());
     }
+
+    #[rstest]
+    #[case(r#"{"a":1}{"b":2}"#, vec![(json!({"a": 1}), 7), (json!({"b": 2}), 7)])]
+    #[case(r#"{"a": 1} [2, 3]  {"c": {}}"#, vec![(json!({"a": 1}), 8), (json!([2, 3]), 6), (json!({"c": {}}), 9)])]
+    fn test_concatenated_documents_on_one_line(
+        #[case] line: &str,
+        #[case] expected: Vec<(Value, usize)>,
+    ) {
+        let mut buffer = LineBuffer::new(10);
+        let results = buffer.add_line(line.to_string());
+
+        let expected: Vec<BufferResult> = expected
+            .into_iter()
+            .map(|(value, size)| BufferResult::Json(value, size))
+            .collect();
+        assert_eq!(results, expected);
+        assert!(buffer.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_concatenated_documents_across_lines() {
+        let mut buffer = LineBuffer::new(10);
+
+        buffer.add_line(r#"{"ship": "Defiant"}{"#.to_string());
+        let results = buffer.add_line(r#"  "ship": "Rio Grande"}"#.to_string());
+
+        assert_eq!(
+            results,
+            vec![
+                BufferResult::Json(json!({"ship": "Defiant"}), 19),
+                BufferResult::Json(json!({"ship": "Rio Grande"}), 25),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        TrailingText::Separate,
+        vec![
+            BufferResult::Json(json!({"ship": "Defiant"}), 19),
+            BufferResult::Text("took 34ms".to_string()),
+        ]
+    )]
+    #[case(
+        TrailingText::Field("trailing".to_string()),
+        vec![BufferResult::Json(json!({"ship": "Defiant", "trailing": "took 34ms"}), 19)]
+    )]
+    fn test_trailing_text(#[case] trailing: TrailingText, #[case] expected: Vec<BufferResult>) {
+        let mut buffer = LineBuffer::new(10).with_trailing_text(trailing);
+
+        let results = buffer.add_line(r#"{"ship": "Defiant"} took 34ms"#.to_string());
+
+        assert_eq!(results, expected);
+        assert!(buffer.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_trailing_text_after_multi_line_document() {
+        let mut buffer = LineBuffer::new(10).with_trailing_text(TrailingText::Separate);
+
+        buffer.add_line("{".to_string());
+        let results = buffer.add_line(r#"  "ship": "Rio Grande"} docked"#.to_string());
+
+        assert_eq!(
+            results,
+            vec![
+                BufferResult::Json(json!({"ship": "Rio Grande"}), 25),
+                BufferResult::Text("docked".to_string()),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("2024-05-01 12:00:03 Docked")]
+    #[case(r#"{"ship": Defiant} docked"#)]
+    #[case(r#""Quark" took 34ms"#)]
+    #[case("[INFO] Docked")]
+    fn test_trailing_text_needs_leading_document(#[case] line: &str) {
+        let mut buffer = LineBuffer::new(1).with_trailing_text(TrailingText::Separate);
+        let results = buffer.add_line(line.to_string());
+
+        assert_eq!(results, vec![BufferResult::Text(line.to_string())]);
+    }
+
+    #[rstest]
+    #[case("1 2 3")]
+    #[case(r#"{"a":1} 2"#)]
+    #[case(r#""Quark" "Rom""#)]
+    fn test_concatenated_scalars_are_not_split(#[case] line: &str) {
+        let mut buffer = LineBuffer::new(1);
+        let results = buffer.add_line(line.to_string());
+
+        assert_eq!(results, vec![BufferResult::Text(line.to_string())]);
+    }
 }
@@ -2,16 +2,118 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use clap::Parser;
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use jiff::Timestamp;
+use jlif::formatter::{OutputFormat, ThemeEngine};
+use jlif::generator::GenFormat;
+use jlif::input::InputFormat;
+use jlif::level::Level;
+use jlif::listen::ListenAddress;
+use jlif::pipeline::DEFAULT_MAX_LINES;
+use jlif::processor::RateLimit;
+use jlif::source::LineSeparator;
+use jlif::stage::Stage;
+use jlif::timestamp;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// JSON Line Formatter - Process and format JSON data from streaming input
 #[derive(Parser, Debug)]
 #[command(version)]
+#[command(group(ArgGroup::new("report").args(["histogram", "count_by", "top", "stats_field", "infer_schema"])))]
+#[command(group(
+    ArgGroup::new("line_prefix")
+        .args(["strip_prefix", "connection_prefix", "exec", "merge_by", "label"])
+        .multiple(true)
+))]
 pub struct JlifArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Files to read one after another, `-` for stdin (default: stdin)
+    #[arg(value_name = "FILE")]
+    pub files: Vec<PathBuf>,
+
+    /// Run this command and format its stdout and stderr, marking the stream of each line
+    /// and exiting with its exit code
+    #[arg(
+        last = true,
+        value_name = "COMMAND",
+        conflicts_with_all = ["files", "follow_name", "decompress", "listen"]
+    )]
+    pub exec: Vec<String>,
+
+    /// Apply the named profile of the config file (~/.config/jlif/config.toml)
+    #[arg(long, value_name = "NAME", env = "JLIF_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Keep following FILE by name, reopening it when rotated or truncated (like `tail -F`)
+    #[arg(long, requires = "files")]
+    pub follow_name: bool,
+
+    /// Also decompress gzip, zstd or bzip2 compressed stdin, as is always done for files
+    #[arg(long, conflicts_with = "follow_name")]
+    pub decompress: bool,
+
+    /// Process the FILEs on this many threads, outputting each file as a whole in the
+    /// given order, for batch runs over many files
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..),
+        requires = "files",
+        conflicts_with_all = [
+            "follow_name", "merge_by", "listen", "tee", "quiet", "max_count", "skip", "head",
+            "tail", "sample", "every", "dedup", "dedup_state", "group_by", "sort", "slurp",
+            "report", "watch_summary", "rate_limit", "split_by_level", "refresh", "heartbeat",
+            "flush_timeout",
+        ]
+    )]
+    pub jobs: Option<u16>,
+
+    /// Interleave the records of all FILEs by the time in this field, each file being in
+    /// order already, and keep the file of each line in the prefix field of records
+    #[arg(
+        long,
+        value_name = "FIELD",
+        requires = "files",
+        conflicts_with = "follow_name"
+    )]
+    pub merge_by: Option<String>,
+
+    /// Tag every record with the FILE it was read from in the prefix field, and text lines
+    /// with a `[FILE] ` prefix
+    #[arg(long, requires = "files", conflicts_with = "merge_by")]
+    pub label: bool,
+
+    /// Attach a `_jlif` object with the input, line number and time of receipt to every
+    /// record, and start text lines with `[INPUT:LINE] `
+    #[arg(long, conflicts_with = "merge_by")]
+    pub annotate: bool,
+
+    /// Accept log streams from the network or a Unix socket instead of reading files,
+    /// e.g. tcp://0.0.0.0:5000, udp://:514 or unix:///tmp/jlif.sock
+    #[arg(long, value_name = "URL", conflicts_with_all = ["files", "follow_name", "decompress"])]
+    pub listen: Option<ListenAddress>,
+
+    /// Start every line with the address of the client that sent it, kept in the prefix field of records
+    #[arg(long, requires = "listen")]
+    pub connection_prefix: bool,
+
     /// Maximum lines to buffer for multi-line JSON parsing
-    #[arg(long, default_value = "10")]
+    #[arg(long, env = "JLIF_MAX_LINES", default_value_t = DEFAULT_MAX_LINES)]
     pub max_lines: usize,
 
+    /// Maximum bytes to buffer for multi-line JSON parsing (e.g. `64KiB`, `1M`)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_buffer_bytes: Option<u64>,
+
+    /// Memory for records held by `--sort`, `--slurp` and `--group-by`, shared between them,
+    /// beyond which records move to temporary files or groups are output early. Also caps
+    /// the multi-line buffer unless `--max-buffer-bytes` is given
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_memory: Option<u64>,
+
     /// Regex pattern for filtering output
     #[arg(short, long)]
     pub filter: Option<String>,
@@ -20,27 +122,819 @@ pub struct JlifArgs {
     #[arg(short = 's', long)]
     pub case_sensitive: bool,
 
+    /// Filter case-sensitively only if the pattern contains an uppercase letter
+    #[arg(short = 'S', long, conflicts_with = "case_sensitive")]
+    pub smart_case: bool,
+
+    /// Take the filter pattern as a literal string instead of a regex, like `grep -F`, ignoring
+    /// the case of ASCII letters only
+    #[arg(short = 'F', long, requires = "filter")]
+    pub fixed_strings: bool,
+
+    /// Search the filter pattern only in the value of this field of JSON records, e.g. `msg`
+    #[arg(long, value_name = "FIELD", requires = "filter")]
+    pub filter_field: Option<String>,
+
+    /// jq expression selecting JSON records, e.g. '.status >= 500'
+    #[arg(long, value_name = "FILTER")]
+    pub query: Option<String>,
+
+    /// Only show JSON records whose field equals a value, e.g. `level=error` (repeatable)
+    #[arg(long = "where", value_name = "FIELD=VALUE", value_parser = parse_key_value)]
+    pub conditions: Vec<(String, String)>,
+
+    /// Only show records at or above this level (trace, debug, info, warn, error, fatal)
+    #[arg(long, value_name = "LEVEL", value_parser = parse_level)]
+    pub level: Option<Level>,
+
+    /// Only show records from this time on, e.g. `2024-05-01T12:00:00Z` or `15m ago`
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound)]
+    pub since: Option<Timestamp>,
+
+    /// Only show records up to this time, e.g. `2024-05-01 13:00` or `5m ago`
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound)]
+    pub until: Option<Timestamp>,
+
     /// Show only JSON content, suppress non-JSON pass-through
     #[arg(short, long)]
     pub json_only: bool,
 
+    /// Fail with the offending lines if lines starting with `{` or `[` aren't valid JSON,
+    /// instead of passing them through as text
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Only check the input: count the JSON documents and the lines starting like
+    /// JSON that didn't parse, exiting with 1 if there are any
+    #[arg(
+        long,
+        conflicts_with_all = ["quiet", "max_count", "head", "report", "watch_summary", "split_by_level"]
+    )]
+    pub check: bool,
+
     /// Output JSON in compact format instead of pretty-printed
+    /// (default when stdout is not a terminal)
     #[arg(short, long)]
     pub compact: bool,
 
-    /// Disable colored output
+    /// Pretty-print JSON even when stdout is not a terminal
+    #[arg(short, long, conflicts_with = "compact")]
+    pub pretty: bool,
+
+    /// Output exactly one compact, uncolored JSON document per line, wrapping
+    /// text lines as `{"text": ...}` or dropping them with `--ndjson=drop`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "TEXT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "wrap",
+        conflicts_with_all = [
+            "pretty", "highlight", "level_colors", "output_format", "raw_newlines",
+            "after_context", "before_context", "context", "heartbeat", "report",
+            "watch_summary", "show_size", "show_deltas",
+        ]
+    )]
+    pub ndjson: Option<TextLines>,
+
+    /// Output text lines as `{"message": ..., "jlif_parsed": false}` JSON records
+    #[arg(long)]
+    pub wrap_text: bool,
+
+    /// Keep the lines of Java and Python stack traces together as one text block
+    #[arg(long)]
+    pub group_traces: bool,
+
+    /// When to color output: auto colors terminals, honoring NO_COLOR and CLICOLOR_FORCE
+    #[arg(long, value_enum, value_name = "WHEN", default_value = "auto")]
+    pub color: ColorChoice,
+
+    /// Highlight what the filter pattern matched, like `grep --color`
     #[arg(long)]
+    pub highlight: bool,
+
+    /// Color whole records by their log level instead of highlighting JSON syntax
+    #[arg(long)]
+    pub level_colors: bool,
+
+    /// Disable colored output, same as `--color never`
+    #[arg(long, conflicts_with = "color")]
     pub no_color: bool,
 
+    /// Representation used for parsed records
+    #[arg(long, value_enum, default_value = "json", visible_alias = "output")]
+    pub output_format: OutputFormat,
+
+    /// Render JSON records through a template, e.g. '{level} {msg} ({request_id|"-"}) {...}'
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["pretty", "output_format", "ndjson"]
+    )]
+    pub format: Option<String>,
+
+    /// Output only the raw value of this field per JSON record, like `jq -r`, skipping records
+    /// without it
+    #[arg(
+        long,
+        value_name = "FIELD",
+        conflicts_with_all = ["pretty", "output_format", "ndjson", "format"]
+    )]
+    pub extract: Option<String>,
+
+    /// Fields shown as columns of `--output table`, e.g. `ts,level,msg`
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        required_if_eq("output_format", "table")
+    )]
+    pub columns: Vec<String>,
+
+    /// Sort object keys alphabetically at every level, for stable diffs between runs
+    #[arg(long)]
+    pub sort_keys: bool,
+
+    /// Escape all non-ASCII characters in JSON strings as `\uXXXX`
+    #[arg(long)]
+    pub escape_non_ascii: bool,
+
+    /// Show line breaks inside JSON strings as real line breaks (display only, not valid JSON)
+    #[arg(long)]
+    pub raw_newlines: bool,
+
+    /// Reduce JSON objects to these comma-separated fields, e.g. `timestamp,level,http.status`
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    pub fields: Vec<String>,
+
+    /// Remove these comma-separated fields from JSON objects, e.g. `kubernetes,metadata.labels`
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    pub drop: Vec<String>,
+
+    /// Replace string values containing JSON objects or arrays with the parsed structure
+    #[arg(long)]
+    pub unwrap_nested: bool,
+
+    /// Replace the values of these comma-separated keys with "[REDACTED]", at any depth
+    #[arg(long, value_name = "KEYS", value_delimiter = ',')]
+    pub redact: Vec<String>,
+
+    /// Set a field of every JSON object to a static string, e.g. `env=staging` (repeatable)
+    #[arg(long = "set", value_name = "FIELD=VALUE", value_parser = parse_key_value)]
+    pub set_fields: Vec<(String, String)>,
+
+    /// Flatten nested objects into dotted keys, like `{"http.status": 200}`
+    #[arg(long)]
+    pub flatten: bool,
+
+    /// Separator joining the keys of flattened objects
+    #[arg(long, value_name = "SEP", default_value = ".", requires = "flatten")]
+    pub flatten_separator: String,
+
+    /// Also flatten arrays, with the index of each item as its key, like `tags.0`
+    #[arg(long, requires = "flatten")]
+    pub flatten_arrays: bool,
+
+    /// Shorten longer strings to this many characters, followed by their original length
+    #[arg(long, value_name = "CHARS")]
+    pub max_string_len: Option<usize>,
+
+    /// Shorten longer arrays to this many items, followed by their original length
+    #[arg(long, value_name = "ITEMS")]
+    pub max_array_items: Option<usize>,
+
+    /// Collapse objects and arrays nested deeper than this into summaries like `{…5 keys}`
+    #[arg(long, value_name = "DEPTH", value_parser = clap::value_parser!(u16).range(1..))]
+    pub max_depth: Option<u16>,
+
+    /// Comma-separated stages to run first, in this order, e.g. `redact,flatten,filter`;
+    /// the others follow in their default order
+    #[arg(long, value_name = "STAGES", value_delimiter = ',')]
+    pub pipeline: Vec<Stage>,
+
+    /// Rewrite timestamps, whether RFC 3339 or epoch numbers, in local time, UTC, or relative
+    /// to the first one
+    #[arg(long, value_name = "MODE")]
+    pub time: Option<TimeMode>,
+
+    /// Comma-separated fields holding the timestamps rewritten by `--time` and compared by
+    /// `--since` and `--until`
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        default_value = "timestamp,@timestamp,time,ts"
+    )]
+    pub time_field: Vec<String>,
+
+    /// `strptime` pattern for timestamps that aren't RFC 3339 or epoch numbers, e.g.
+    /// `%d/%b/%Y:%H:%M:%S %z` (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    pub time_format: Vec<String>,
+
+    /// Display a key under another name, e.g. `ts=timestamp` (repeatable, output only)
+    #[arg(long, value_name = "KEY=NAME", value_parser = parse_key_value)]
+    pub alias: Vec<(String, String)>,
+
+    /// Color theme for the builtin engine: default, solarized, monokai, dracula, mono or a .toml file
+    #[arg(
+        long,
+        value_name = "THEME",
+        env = "JLIF_THEME",
+        default_value = "default"
+    )]
+    pub theme: String,
+
+    /// Highlighting engine for colored output
+    #[arg(long, value_enum, default_value = "builtin")]
+    pub theme_engine: ThemeEngine,
+
+    /// Color scheme for the syntect engine: a bundled theme name or a .tmTheme file
+    #[arg(long, value_name = "THEME", default_value = "base16-ocean.dark")]
+    pub syntect_theme: String,
+
+    /// Format of non-JSON lines to parse into records as well
+    /// (default: syslog for UDP listeners, otherwise json)
+    #[arg(long, value_enum, visible_alias = "input")]
+    pub input_format: Option<InputFormat>,
+
+    /// Parse JSON followed by other text on the same line, outputting the text after the record
+    #[arg(long)]
+    pub allow_trailing: bool,
+
+    /// Add text following JSON on a line to the record under this key instead
+    #[arg(long, value_name = "NAME", requires = "allow_trailing")]
+    pub trailing_field: Option<String>,
+
+    /// Treat input starting with `[` as one JSON array and output its elements as records
+    #[arg(long)]
+    pub split_array: bool,
+
+    /// Unwrap the `log` field of Docker json-file log entries before parsing it
+    #[arg(long)]
+    pub docker: bool,
+
+    /// Keep the stream and time of Docker log entries in a `docker` field
+    #[arg(long, requires = "docker")]
+    pub docker_metadata: bool,
+
+    /// Unwrap lines in the CRI log format of containerd and CRI-O before parsing them
+    #[arg(long, conflicts_with = "docker")]
+    pub cri: bool,
+
+    /// Keep the time and stream of CRI log lines in a `cri` field
+    #[arg(long, requires = "cri")]
+    pub cri_metadata: bool,
+
+    /// Remove ANSI escape sequences like colors from the input before parsing it
+    #[arg(long)]
+    pub strip_ansi: bool,
+
+    /// Remove a per-line prefix like `api-1  | ` before parsing, keeping it in a field
+    /// (default: docker compose and `kubectl logs --prefix` prefixes)
+    #[arg(long, value_name = "REGEX", num_args = 0..=1, require_equals = true)]
+    pub strip_prefix: Option<Option<String>>,
+
+    /// Field receiving the value of the prefix removed by `--strip-prefix` or `--connection-prefix`,
+    /// or the input named by `--label` and `--merge-by`
+    #[arg(
+        long,
+        value_name = "NAME",
+        default_value = "source",
+        requires = "line_prefix"
+    )]
+    pub prefix_field: String,
+
+    /// Output every element of a parsed top-level array as its own record
+    #[arg(long)]
+    pub explode: bool,
+
     /// Invert filter behavior - output everything that does NOT match
-    #[arg(short = 'v', long)]
+    #[arg(short = 'v', long, visible_alias = "invert")]
     pub invert_match: bool,
+
+    /// Output nothing, only report through the exit status whether a record matched
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Stop reading after NUM matching records
+    #[arg(short = 'm', long, value_name = "NUM")]
+    pub max_count: Option<u64>,
+
+    /// Output only this fraction of the records passing the filters, picked at random,
+    /// e.g. `0.05` or `5%`
+    #[arg(long, value_name = "RATE", value_parser = parse_rate, conflicts_with = "every")]
+    pub sample: Option<f64>,
+
+    /// Output only every Nth record passing the filters, starting with the first
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub every: Option<u64>,
+
+    /// Output all text lines, leaving them out of `--sample` and `--every`
+    #[arg(long)]
+    pub unsampled_text: bool,
+
+    /// Output at most this many records per period, e.g. `100/s` or `1000/5m`, reporting how
+    /// many were suppressed
+    #[arg(long, value_name = "RATE", value_parser = parse_rate_limit)]
+    pub rate_limit: Option<RateLimit>,
+
+    /// Leave out the first NUM records passing the filters
+    #[arg(long, value_name = "NUM")]
+    pub skip: Option<u64>,
+
+    /// Output only the first NUM records passing the filters, then stop reading
+    #[arg(long, value_name = "NUM")]
+    pub head: Option<u64>,
+
+    /// Output only the last NUM records passing the filters, once the input ended
+    #[arg(
+        long,
+        value_name = "NUM",
+        conflicts_with_all = ["after_context", "before_context", "context"]
+    )]
+    pub tail: Option<usize>,
+
+    /// Output the records ordered by this field, e.g. a timestamp, once the input ended
+    #[arg(
+        long,
+        value_name = "FIELD",
+        conflicts_with_all = ["follow_name", "listen", "after_context", "before_context", "context"]
+    )]
+    pub sort: Option<String>,
+
+    /// Memory for records held back by `--sort`, beyond which they are sorted in temporary
+    /// files (default: 256MiB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, requires = "sort")]
+    pub sort_memory: Option<u64>,
+
+    /// Also output NUM records after each match
+    #[arg(short = 'A', long, value_name = "NUM")]
+    pub after_context: Option<usize>,
+
+    /// Also output NUM records before each match
+    #[arg(short = 'B', long, value_name = "NUM")]
+    pub before_context: Option<usize>,
+
+    /// Also output NUM records before and after each match
+    #[arg(short = 'C', long, value_name = "NUM")]
+    pub context: Option<usize>,
+
+    /// Emit a marker line when no input arrived for this long (e.g. `30s`)
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub heartbeat: Option<Duration>,
+
+    /// Flush lines held back for a JSON document once input stalls this long (e.g. `500ms`)
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub flush_timeout: Option<Duration>,
+
+    /// Split the input at this separator instead of newlines, e.g. `\0` or `\x1e`
+    #[arg(long, value_name = "SEP", value_parser = parse_separator)]
+    pub input_separator: Option<LineSeparator>,
+
+    /// Also copy the unmodified input to this file, like `tee`
+    #[arg(long, value_name = "PATH")]
+    pub tee: Option<PathBuf>,
+
+    /// Write the output to this file instead of stdout, uncolored unless asked for
+    #[arg(short, long, value_name = "PATH", conflicts_with = "quiet")]
+    pub output_file: Option<PathBuf>,
+
+    /// Append to the output file instead of replacing it
+    #[arg(long, requires = "output_file")]
+    pub append: bool,
+
+    /// Write records into one file per log level in this directory, e.g. `error.log`
+    #[arg(long, value_name = "DIR", conflicts_with = "quiet")]
+    pub split_by_level: Option<PathBuf>,
+
+    /// Still output all records while splitting them by level
+    #[arg(long, requires = "split_by_level")]
+    pub echo: bool,
+
+    /// Write text lines to stderr, leaving only JSON records on stdout
+    #[arg(long, conflicts_with_all = ["quiet", "ndjson", "wrap_text"])]
+    pub split_streams: bool,
+
+    /// Flush the output after every record, e.g. when tailing into another pipe
+    #[arg(short, long, conflicts_with = "buffer_size")]
+    pub unbuffered: bool,
+
+    /// Size of the output buffer when stdout is not a terminal (default: 64KiB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub buffer_size: Option<u64>,
+
+    /// Print a summary of lines, records and throughput to stderr at the end and on SIGUSR1
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Print a histogram of this field's values at the end instead of the records
+    #[arg(long, value_name = "FIELD")]
+    pub histogram: Option<String>,
+
+    /// Number of equal-width buckets a numeric `--histogram` field is grouped into (default: 10)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..), requires = "histogram")]
+    pub buckets: Option<u16>,
+
+    /// Print how many records have each value of these comma-separated fields at the end,
+    /// most frequent first, instead of the records
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    pub count_by: Vec<String>,
+
+    /// Print the N most frequent values of FIELD with their share at the end, instead of the
+    /// records
+    #[arg(long, num_args = 2, value_names = ["N", "FIELD"])]
+    pub top: Vec<String>,
+
+    /// Print the minimum, maximum, mean and percentiles of this numeric field at the end,
+    /// instead of the records
+    #[arg(long, value_name = "FIELD")]
+    pub stats_field: Option<String>,
+
+    /// Print a JSON Schema of the records at the end, with the types, examples and number of
+    /// distinct values of every field, instead of the records
+    #[arg(long)]
+    pub infer_schema: bool,
+
+    /// Output the records as well, printing the report of `--histogram`, `--count-by`, `--top`,
+    /// `--stats-field` or `--infer-schema` to stderr
+    #[arg(long, requires = "report", conflicts_with = "refresh")]
+    pub with_records: bool,
+
+    /// Redraw the `--histogram`, `--count-by`, `--top`, `--stats-field` or `--infer-schema`
+    /// report at this interval, e.g. when following a file
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, requires = "report")]
+    pub refresh: Option<Duration>,
+
+    /// Redraw a live summary (levels, top messages, rate) at this interval instead of the records
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, conflicts_with = "report")]
+    pub watch_summary: Option<Duration>,
+
+    /// Collect all JSON records into one array, output at the end, like `jq -s`
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "report", "watch_summary", "format", "columns", "context", "after_context",
+            "before_context", "show_size", "show_deltas", "split_by_level", "split_streams",
+        ]
+    )]
+    pub slurp: bool,
+
+    /// Map a level value to a canonical level (e.g. `sev=2=error`, `100=fatal`), repeatable
+    #[arg(long, value_name = "VALUE=LEVEL", value_parser = parse_level_mapping)]
+    pub level_map: Vec<(String, Level)>,
+
+    /// Suppress records already output in previous runs, remembered in this file
+    #[arg(long, value_name = "PATH")]
+    pub dedup_state: Option<PathBuf>,
+
+    /// Output a run of identical records once, followed by how often it was repeated
+    #[arg(long)]
+    pub dedup: bool,
+
+    /// Leave these comma-separated fields out when comparing records for `--dedup`,
+    /// e.g. `timestamp,request_id`
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',', requires = "dedup")]
+    pub dedup_ignore: Vec<String>,
+
+    /// Output the records sharing a value of this id field together at the end, e.g.
+    /// `request_id`, each group under a line with its elapsed time
+    #[arg(
+        long,
+        value_name = "FIELD",
+        conflicts_with_all = ["slurp", "report", "watch_summary", "tail", "after_context", "before_context", "context"]
+    )]
+    pub group_by: Option<String>,
+
+    /// Output a `--group-by` group once no record joined it for this long, instead of at the end
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration, requires = "group_by")]
+    pub group_timeout: Option<Duration>,
+
+    /// Prefix every record with its original size in bytes
+    #[arg(long)]
+    pub show_size: bool,
+
+    /// Prefix every record with the time elapsed since the previous one, taken from its
+    /// timestamp, or from when it arrived when following files, listening or running a command
+    #[arg(long)]
+    pub show_deltas: bool,
+
+    /// Only output records of at least this size (e.g. `512`, `4KiB`, `1M`)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub min_size: Option<u64>,
+
+    /// Only output records of at most this size (e.g. `512`, `4KiB`, `1M`)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+}
+
+impl JlifArgs {
+    /// Resolves whether JSON should be rendered compact.
+    ///
+    /// Pipelines (`jlif | other-tool`) get one record per line by default,
+    /// while interactive use stays pretty unless told otherwise.
+    pub fn use_compact(&self, stdout_is_terminal: bool) -> bool {
+        self.compact || self.ndjson.is_some() || (!self.pretty && !stdout_is_terminal)
+    }
+
+    /// Resolves the records to output before and after each match.
+    ///
+    /// `-A` and `-B` take precedence over `-C` for their side, unset if none
+    /// of them was given.
+    pub fn context_records(&self) -> Option<(usize, usize)> {
+        if self.after_context.is_none() && self.before_context.is_none() {
+            return self.context.map(|context| (context, context));
+        }
+        let context = self.context.unwrap_or(0);
+        Some((
+            self.before_context.unwrap_or(context),
+            self.after_context.unwrap_or(context),
+        ))
+    }
+
+    /// Resolves the number of values and the field of `--top`, if given
+    pub fn top_values(&self) -> Result<Option<(usize, &str)>, String> {
+        let [count, field] = self.top.as_slice() else {
+            return Ok(None);
+        };
+        let count = count
+            .parse()
+            .map_err(|_| format!("'{}' is not a number of values for --top", count))?;
+        Ok(Some((count, field.as_str())))
+    }
+
+    /// The share of `--max-memory` for each of `--sort`, `--slurp` and
+    /// `--group-by` in use, if a budget was given
+    pub fn record_memory(&self) -> Option<usize> {
+        let holders = [self.sort.is_some(), self.slurp, self.group_by.is_some()]
+            .into_iter()
+            .filter(|holds| *holds)
+            .count();
+        self.max_memory
+            .map(|budget| budget as usize / holders.max(1))
+    }
+
+    /// Decides whether output is colored.
+    ///
+    /// An explicit `--color always|never` or `--no-color` wins. Otherwise a
+    /// non-empty `NO_COLOR` disables colors and `CLICOLOR_FORCE` forces them
+    /// even when piping, e.g. into `less -R`.
+    pub fn use_color(&self, stdout_is_terminal: bool) -> bool {
+        let env = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+        // Colors would corrupt JSON text sequences
+        let choice = if self.no_color
+            || self.ndjson.is_some()
+            || self.output_format == OutputFormat::JsonSeq
+        {
+            ColorChoice::Never
+        } else {
+            self.color
+        };
+
+        choice.resolve(
+            stdout_is_terminal,
+            env("NO_COLOR").is_some(),
+            env("CLICOLOR_FORCE").is_some_and(|value| value != "0"),
+        )
+    }
+}
+
+/// When to color output
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    /// Color terminals, unless NO_COLOR is set or CLICOLOR_FORCE asks for color anyway
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn resolve(self, stdout_is_terminal: bool, no_color: bool, force_color: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => !no_color && (force_color || stdout_is_terminal),
+        }
+    }
+}
+
+/// How `--time` shows timestamps
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TimeMode {
+    /// RFC 3339 in the local time zone
+    Local,
+    /// RFC 3339 in UTC
+    Utc,
+    /// Offset from the first timestamp, like `+2.3s`
+    Relative,
+}
+
+/// What `--ndjson` does with lines that aren't JSON
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TextLines {
+    /// Output them as `{"text": "..."}` records
+    Wrap,
+    /// Leave them out
+    Drop,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Generate a synthetic stream of mixed JSON and text log lines
+    Gen(GenArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct GenArgs {
+    /// Shape of the generated JSON records
+    #[arg(long, value_enum, default_value = "pino")]
+    pub format: GenFormat,
+
+    /// Records per second (as fast as possible if omitted)
+    #[arg(long)]
+    pub rate: Option<u64>,
+
+    /// Number of records to generate (endless if omitted)
+    #[arg(short = 'n', long)]
+    pub count: Option<u64>,
+
+    /// Share of plain text lines, e.g. `10%`
+    #[arg(long, default_value = "0%", value_parser = parse_percentage)]
+    pub mixed_text: f64,
+
+    /// Share of JSON records pretty-printed across multiple lines
+    #[arg(long, default_value = "0%", value_parser = parse_percentage)]
+    pub multi_line: f64,
+
+    /// Share of JSON records emitted truncated and unparsable
+    #[arg(long, default_value = "0%", value_parser = parse_percentage)]
+    pub malformed: f64,
+
+    /// Seed for reproducible output
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+/// Parses a `key=value` pair
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("'{}' is not in KEY=VALUE form", value)),
+    }
+}
+
+/// Parses a percentage like `10%` or `10` into a fraction between 0 and 1
+fn parse_percentage(value: &str) -> Result<f64, String> {
+    let number = value.strip_suffix('%').unwrap_or(value).trim();
+    let percent: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid percentage", value))?;
+
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("'{}' must be between 0% and 100%", value));
+    }
+
+    Ok(percent / 100.0)
+}
+
+/// Parses `VALUE=LEVEL`, splitting at the last `=` so values may contain one
+fn parse_level_mapping(value: &str) -> Result<(String, Level), String> {
+    let (raw, level) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("'{}' is not in VALUE=LEVEL format", value))?;
+    if raw.trim().is_empty() {
+        return Err(format!("'{}' has an empty value", value));
+    }
+
+    Ok((raw.to_string(), parse_level(level)?))
+}
+
+/// Parses a sampling rate, a fraction like `0.05` or a percentage like `5%`
+fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate = if value.trim_end().ends_with('%') {
+        parse_percentage(value)?
+    } else {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' is not a valid rate", value))?
+    };
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(format!("'{}' must be between 0 and 1", value));
+    }
+    Ok(rate)
+}
+
+/// Parses a rate limit like `100/s`, `500/min` or `1000/5m`
+fn parse_rate_limit(value: &str) -> Result<RateLimit, String> {
+    let invalid = || format!("'{}' is not a rate like 100/s", value);
+    let (records, period) = value.split_once('/').ok_or_else(invalid)?;
+    let records: u64 = records.trim().parse().map_err(|_| invalid())?;
+    let period = period.trim();
+    // A bare unit like `s` means one of it
+    let period = if period.starts_with(|c: char| c.is_ascii_digit()) {
+        humantime::parse_duration(period)
+    } else {
+        humantime::parse_duration(&format!("1{}", period))
+    }
+    .map_err(|_| invalid())?;
+    if records == 0 || period.is_zero() {
+        return Err(format!("'{}' must allow some records per period", value));
+    }
+    Ok(RateLimit { records, period })
+}
+
+/// Parses a level name or number
+fn parse_level(value: &str) -> Result<Level, String> {
+    Level::parse(value).ok_or_else(|| {
+        format!(
+            "'{}' is not a known level (trace, debug, info, warn, error, fatal)",
+            value
+        )
+    })
+}
+
+/// Parses a `--since` or `--until` time, a timestamp or a duration before now
+fn parse_time_bound(value: &str) -> Result<Timestamp, String> {
+    timestamp::parse_bound(value, Timestamp::now())
+}
+
+/// Parses a byte size with an optional binary unit suffix, e.g. `512`, `4K`, `4KiB` or `1MB`
+fn parse_size(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid size", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => return Err(format!("'{}' has an unknown size unit", value)),
+    };
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("'{}' is too large", value))
+}
+
+/// Parses a separator, a literal string with the escapes `\0`, `\n`, `\r`,
+/// `\t`, `\\` and `\xHH`
+fn parse_separator(value: &str) -> Result<LineSeparator, String> {
+    let mut separator = Vec::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            separator.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('0') => separator.push(b'\0'),
+            Some('n') => separator.push(b'\n'),
+            Some('r') => separator.push(b'\r'),
+            Some('t') => separator.push(b'\t'),
+            Some('\\') => separator.push(b'\\'),
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == 2)
+                    .ok_or_else(|| format!("'\\x{}' is not a valid byte escape", digits))?;
+                separator.push(byte);
+            }
+            Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+            None => return Err("trailing '\\' without an escape".to_string()),
+        }
+    }
+
+    if separator.is_empty() {
+        return Err("the separator must not be empty".to_string());
+    }
+    Ok(LineSeparator::Custom(separator))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        ColorChoice, JlifArgs, parse_key_value, parse_level_mapping, parse_percentage, parse_rate,
+        parse_rate_limit, parse_separator, parse_size,
+    };
     use assert_cmd::Command;
+    use clap::Parser;
+    use jlif::level::Level;
+    use jlif::processor::RateLimit;
+    use jlif::source::LineSeparator;
     use predicates::prelude::*;
+    use rstest::rstest;
+    use std::io::Write;
+    use std::time::Duration;
 
     #[test]
     fn test_help_output() {
@@ -70,4 +964,1500 @@ mod tests {
             .failure()
             .stderr(predicate::str::contains("unexpected argument"));
     }
+
+    #[test]
+    fn test_gen_subcommand_output() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["gen", "--count", "3", "--seed", "1"])
+            .assert()
+            .success()
+            .stdout(predicate::str::is_match(r#"^(\{"level":\d+.*\}\n){3}$"#).unwrap());
+    }
+
+    #[rstest]
+    #[case("10%", 0.1)]
+    #[case("25", 0.25)]
+    #[case("0%", 0.0)]
+    #[case("100%", 1.0)]
+    fn test_parse_percentage(#[case] input: &str, #[case] expected: f64) {
+        assert_eq!(parse_percentage(input).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("abc")]
+    #[case("150%")]
+    #[case("-5%")]
+    fn test_parse_percentage_rejects_invalid(#[case] input: &str) {
+        assert!(parse_percentage(input).is_err());
+    }
+
+    #[rstest]
+    #[case("0.05", Ok(0.05))]
+    #[case("5%", Ok(0.05))]
+    #[case("1", Ok(1.0))]
+    #[case("5", Err("'5' must be between 0 and 1".to_string()))]
+    #[case("often", Err("'often' is not a valid rate".to_string()))]
+    fn test_parse_rate(#[case] input: &str, #[case] expected: Result<f64, String>) {
+        assert_eq!(parse_rate(input), expected);
+    }
+
+    #[rstest]
+    #[case("100/s", 100, 1)]
+    #[case("500 / min", 500, 60)]
+    #[case("1000/5m", 1000, 300)]
+    fn test_parse_rate_limit(#[case] input: &str, #[case] records: u64, #[case] seconds: u64) {
+        assert_eq!(
+            parse_rate_limit(input),
+            Ok(RateLimit {
+                records,
+                period: Duration::from_secs(seconds)
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("100")]
+    #[case("many/s")]
+    #[case("100/fortnight")]
+    #[case("0/s")]
+    fn test_parse_rate_limit_rejects_invalid(#[case] input: &str) {
+        assert!(parse_rate_limit(input).is_err());
+    }
+
+    #[rstest]
+    #[case(&[], true, false)]
+    #[case(&[], false, true)]
+    #[case(&["--compact"], true, true)]
+    #[case(&["--pretty"], false, false)]
+    fn test_use_compact_defaults_to_terminal_detection(
+        #[case] flags: &[&str],
+        #[case] stdout_is_terminal: bool,
+        #[case] expected: bool,
+    ) {
+        let args = JlifArgs::parse_from(std::iter::once("jlif").chain(flags.iter().copied()));
+        assert_eq!(args.use_compact(stdout_is_terminal), expected);
+    }
+
+    #[rstest]
+    #[case(&[], None)]
+    #[case(&["-C", "2"], Some((2, 2)))]
+    #[case(&["-A", "1"], Some((0, 1)))]
+    #[case(&["-B", "3", "-A", "1"], Some((3, 1)))]
+    #[case(&["-C", "2", "-A", "5"], Some((2, 5)))]
+    fn test_context_records(#[case] flags: &[&str], #[case] expected: Option<(usize, usize)>) {
+        let args = JlifArgs::parse_from(std::iter::once("jlif").chain(flags.iter().copied()));
+        assert_eq!(args.context_records(), expected);
+    }
+
+    #[rstest]
+    #[case(&["--slurp"], None)]
+    #[case(&["--max-memory", "1M", "--slurp"], Some(1024 * 1024))]
+    #[case(&["--max-memory", "1M", "--sort", "ts", "--group-by", "id"], Some(512 * 1024))]
+    fn test_record_memory(#[case] flags: &[&str], #[case] expected: Option<usize>) {
+        let args = JlifArgs::parse_from(std::iter::once("jlif").chain(flags.iter().copied()));
+        assert_eq!(args.record_memory(), expected);
+    }
+
+    #[test]
+    fn test_context_separates_groups() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-C", "1", "-f", "breach"])
+            .write_stdin("Alpha\nBeta\nbreach one\nGamma\nDelta\nEpsilon\nbreach two\n")
+            .assert()
+            .success()
+            .stdout("Beta\nbreach one\nGamma\n--\nEpsilon\nbreach two\n");
+    }
+
+    #[rstest]
+    #[case(ColorChoice::Auto, true, false, false, true)]
+    #[case(ColorChoice::Auto, false, false, false, false)]
+    #[case(ColorChoice::Auto, true, true, false, false)]
+    #[case(ColorChoice::Auto, false, false, true, true)]
+    #[case(ColorChoice::Auto, true, true, true, false)]
+    #[case(ColorChoice::Always, false, true, false, true)]
+    #[case(ColorChoice::Never, true, false, true, false)]
+    fn test_color_choice_resolution(
+        #[case] choice: ColorChoice,
+        #[case] stdout_is_terminal: bool,
+        #[case] no_color: bool,
+        #[case] force_color: bool,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(
+            choice.resolve(stdout_is_terminal, no_color, force_color),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(&["--color", "always"], &[], true)]
+    #[case(&[], &[], false)]
+    #[case(&[], &[("CLICOLOR_FORCE", "1")], true)]
+    #[case(&[], &[("CLICOLOR_FORCE", "0")], false)]
+    #[case(&[], &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")], false)]
+    #[case(&["--color", "always"], &[("NO_COLOR", "1")], true)]
+    #[case(&["--no-color"], &[("CLICOLOR_FORCE", "1")], false)]
+    fn test_piped_output_color(
+        #[case] args: &[&str],
+        #[case] env: &[(&str, &str)],
+        #[case] colored: bool,
+    ) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.env_remove("NO_COLOR").env_remove("CLICOLOR_FORCE");
+        for (name, value) in env {
+            cmd.env(name, value);
+        }
+
+        let output = cmd
+            .args(args)
+            .write_stdin("{\"station\": \"DS9\"}\n")
+            .output()
+            .unwrap();
+
+        assert_eq!(output.stdout.contains(&b'\x1b'), colored);
+    }
+
+    #[test]
+    fn test_piped_output_is_compact() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.write_stdin("{\n  \"station\": \"DS9\"\n}\n")
+            .assert()
+            .success()
+            .stdout("{\"station\":\"DS9\"}\n");
+    }
+
+    #[test]
+    fn test_pretty_overrides_piped_default() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--pretty")
+            .write_stdin("{\"station\":\"DS9\"}\n")
+            .assert()
+            .success()
+            .stdout("{\n  \"station\": \"DS9\"\n}\n");
+    }
+
+    #[test]
+    fn test_compact_conflicts_with_pretty() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--compact", "--pretty"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[rstest]
+    #[case("ts=timestamp", ("ts", "timestamp"))]
+    #[case("query=a=b", ("query", "a=b"))]
+    #[case("empty=", ("empty", ""))]
+    fn test_parse_key_value(#[case] input: &str, #[case] expected: (&str, &str)) {
+        assert_eq!(
+            parse_key_value(input).unwrap(),
+            (expected.0.to_string(), expected.1.to_string())
+        );
+    }
+
+    #[rstest]
+    #[case("timestamp")]
+    #[case("=timestamp")]
+    fn test_parse_key_value_rejects_invalid(#[case] input: &str) {
+        assert!(parse_key_value(input).is_err());
+    }
+
+    #[test]
+    fn test_reads_files_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.log");
+        let second = dir.path().join("second.log");
+        std::fs::write(&first, "{\"shift\": \"alpha\"}\n{\n").unwrap();
+        std::fs::write(&second, "Shift change\n").unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--compact")
+            .arg(&first)
+            .arg("-")
+            .arg(&second)
+            .write_stdin("{\"shift\": \"beta\"}\n")
+            .assert()
+            .success()
+            .stdout("{\"shift\":\"alpha\"}\n{\n{\"shift\":\"beta\"}\nShift change\n");
+    }
+
+    #[test]
+    fn test_missing_file_fails() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("/nonexistent/promenade.log")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "Cannot open '/nonexistent/promenade.log'",
+            ));
+    }
+
+    #[test]
+    fn test_invert_suppresses_matching_records() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args([
+            "--compact",
+            "--invert",
+            "--json-only",
+            "--where",
+            "level=debug",
+        ])
+        .write_stdin("{\"level\": \"debug\"}\nStation log\n{\"level\": \"error\"}\n")
+        .assert()
+        .success()
+        .stdout("{\"level\":\"error\"}\n");
+    }
+
+    #[test]
+    fn test_unknown_theme_fails() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--theme", "lcars"])
+            .write_stdin("{}\n")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Unknown theme 'lcars'"));
+    }
+
+    #[rstest]
+    #[case(&[], &[], "Unknown theme 'cardassian'")]
+    #[case(&["--profile", "ops"], &[], "Unknown theme 'klingon'")]
+    #[case(&["--profile", "ops"], &[("JLIF_THEME", "romulan")], "Unknown theme 'romulan'")]
+    #[case(&["--theme", "lcars"], &[("JLIF_THEME", "romulan")], "Unknown theme 'lcars'")]
+    #[case(&[], &[("JLIF_PROFILE", "ops")], "Unknown theme 'klingon'")]
+    #[case(&["--profile", "dominion"], &[], "Unknown profile 'dominion', available profiles: ops")]
+    fn test_config_file_precedence(
+        #[case] args: &[&str],
+        #[case] env: &[(&str, &str)],
+        #[case] expected: &str,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("jlif")).unwrap();
+        std::fs::write(
+            dir.path().join("jlif").join("config.toml"),
+            "theme = \"cardassian\"\n\n[profiles.ops]\ntheme = \"klingon\"\n",
+        )
+        .unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.env("XDG_CONFIG_HOME", dir.path())
+            .env_remove("JLIF_THEME")
+            .env_remove("JLIF_PROFILE")
+            .envs(env.iter().copied())
+            .args(args)
+            .write_stdin("{}\n")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+
+    #[test]
+    fn test_config_file_sets_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("jlif")).unwrap();
+        std::fs::write(
+            dir.path().join("jlif").join("config.toml"),
+            "json-only = true\nfields = [\"ship\"]\n",
+        )
+        .unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.env("XDG_CONFIG_HOME", dir.path())
+            .arg("--compact")
+            .write_stdin("Docking request\n{\"ship\": \"Defiant\", \"pylon\": 1}\n")
+            .assert()
+            .success()
+            .stdout("{\"ship\":\"Defiant\"}\n");
+    }
+
+    #[rstest]
+    #[case(&["-f", "defiant"], 0, "Defiant docked\n")]
+    #[case(&["-f", "jem'hadar"], 1, "")]
+    #[case(&["-q", "-f", "defiant"], 0, "")]
+    #[case(&["--quiet", "-f", "jem'hadar"], 1, "")]
+    // Without a filter there is nothing to miss
+    #[case(&["-q"], 0, "")]
+    #[case(&["-f", "("], 2, "")]
+    fn test_exit_status(#[case] args: &[&str], #[case] status: i32, #[case] stdout: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .write_stdin("Defiant docked\nRio Grande departed\n")
+            .assert()
+            .code(status)
+            .stdout(stdout.to_string());
+    }
+
+    #[rstest]
+    #[case(&["--fixed-strings", "-f", "(nx"], "Defiant (NX-74205)\ndefiant (nx-74205)\n")]
+    #[case(&["--fixed-strings", "-s", "-f", "(NX"], "Defiant (NX-74205)\n")]
+    #[case(&["-F", "-f", "(nx"], "Defiant (NX-74205)\ndefiant (nx-74205)\n")]
+    #[case(&["-S", "-f", "Defiant"], "Defiant (NX-74205)\n")]
+    #[case(&["-S", "-f", "defiant"], "Defiant (NX-74205)\ndefiant (nx-74205)\n")]
+    fn test_pattern_modes(#[case] args: &[&str], #[case] stdout: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .write_stdin("Defiant (NX-74205)\ndefiant (nx-74205)\nRio Grande\n")
+            .assert()
+            .success()
+            .stdout(stdout);
+    }
+
+    #[test]
+    fn test_max_count() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-m", "1", "-f", "defiant"])
+            .write_stdin("Defiant docked\nRio Grande departed\nDefiant departed\n")
+            .assert()
+            .success()
+            .stdout("Defiant docked\n");
+    }
+
+    #[rstest]
+    #[case(&["--head", "2"], "Defiant docked\nRio Grande departed\n")]
+    #[case(&["--tail", "1"], "Mekong docked\n")]
+    #[case(&["--skip", "1", "--head", "1", "-f", "docked"], "Mekong docked\n")]
+    fn test_record_range(#[case] args: &[&str], #[case] stdout: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .write_stdin("Defiant docked\nRio Grande departed\nMekong docked\n")
+            .assert()
+            .success()
+            .stdout(stdout);
+    }
+
+    #[rstest]
+    #[case(&["--every", "2"], "A\nSisko\n")]
+    #[case(&["--every", "2", "--unsampled-text"], "A\nB\nC\n")]
+    #[case(&["--sample", "100%"], "A\nB\nSisko\nC\n")]
+    #[case(&["--sample", "0"], "")]
+    fn test_sampling(#[case] args: &[&str], #[case] stdout: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--format", "{msg}"])
+            .args(args)
+            .write_stdin("{\"msg\":\"A\"}\nB\n{\"msg\":\"Sisko\"}\nC\n")
+            .assert()
+            .success()
+            .stdout(stdout);
+    }
+
+    #[rstest]
+    #[case(&["--every", "2"], "1\n3\n5\n", "2\n4\n6\n")]
+    #[case(&["--tail", "1"], "6\n", "1\n2\n3\n4\n5\n")]
+    #[case(&["--rate-limit", "2/1h"], "1\n2\n--- suppressed 4 records over the rate limit ---\n", "3\n4\n5\n6\n")]
+    fn test_dedup_state_only_remembers_output_records(
+        #[case] args: &[&str],
+        #[case] first: &'static str,
+        #[case] second: &'static str,
+    ) {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("state.db");
+        let input = "{\"n\":1}\n{\"n\":2}\n{\"n\":3}\n{\"n\":4}\n{\"n\":5}\n{\"n\":6}\n";
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--format", "{n}", "--dedup-state"])
+            .arg(&state)
+            .args(args)
+            .write_stdin(input)
+            .assert()
+            .success()
+            .stdout(first);
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--format", "{n}", "--dedup-state"])
+            .arg(&state)
+            .write_stdin(input)
+            .assert()
+            .success()
+            .stdout(second);
+    }
+
+    #[test]
+    fn test_dedup_consecutive() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--dedup", "--dedup-ignore", "ts"])
+            .write_stdin(
+                "{\"ts\":1,\"alert\":\"Red\"}\n\
+                 {\"ts\":2,\"alert\":\"Red\"}\n\
+                 {\"ts\":3,\"alert\":\"Red\"}\n\
+                 Quark complains\n",
+            )
+            .assert()
+            .success()
+            .stdout(
+                "{\"ts\":1,\"alert\":\"Red\"}\n\
+                 --- last record repeated 2 times ---\n\
+                 Quark complains\n",
+            );
+    }
+
+    #[test]
+    fn test_count_by() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--count-by", "level"])
+            .write_stdin(
+                "{\"level\":\"info\"}\n{\"level\":\"error\"}\nWarp core online\n\
+                 {\"level\":\"error\"}\n",
+            )
+            .assert()
+            .success()
+            .stdout("level (3 records)\n  2  error\n  1  info\n");
+    }
+
+    #[test]
+    fn test_top() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--top", "1", "code"])
+            .write_stdin("{\"code\":404}\n{\"code\":200}\n{\"code\":404}\n{\"code\":500}\n")
+            .assert()
+            .success()
+            .stdout("code (4 values)\n  404  2   50.0%\n  … 2 more values (2 records, 50.0%)\n");
+    }
+
+    #[test]
+    fn test_top_rejects_invalid_count() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--top", "code", "10"])
+            .write_stdin("{\"code\":404}\n")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("'code' is not a number of values"));
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        let output = cmd
+            .args(["--histogram", "ms", "--buckets", "2"])
+            .write_stdin("{\"ms\":0}\n{\"ms\":40}\n{\"ms\":100}\n")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("  [0, 50)   │"));
+        assert!(lines[1].ends_with(" 2"));
+        assert!(lines[2].starts_with("  [50, 100] │"));
+        assert!(lines[2].ends_with(" 1"));
+    }
+
+    #[rstest]
+    #[case(&["--group-traces"], "Exception: Warp core breach\n\tat Engineering.eject\n")]
+    #[case(
+        &["--group-traces", "--wrap-text", "-c"],
+        "{\"message\":\"Exception: Warp core breach\\n\\tat Engineering.eject\",\"jlif_parsed\":false}\n"
+    )]
+    fn test_group_traces(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .args(["-f", "breach"])
+            .write_stdin(
+                "{\"msg\":\"Docked\"}\nException: Warp core breach\n\tat Engineering.eject\n",
+            )
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--group-by", "req", "--fields", "msg"])
+            .write_stdin(
+                "{\"req\":\"a\",\"ts\":1714564800,\"msg\":\"Docking request\"}\n\
+                 {\"req\":\"b\",\"ts\":1714564801,\"msg\":\"Cargo manifest\"}\n\
+                 {\"req\":\"a\",\"ts\":1714564801.25,\"msg\":\"Docked\"}\n",
+            )
+            .assert()
+            .success()
+            .stdout(
+                "--- req a: 2 records in 1.25s ---\n{\"msg\":\"Docking request\"}\n{\"msg\":\"Docked\"}\n\
+                 --- req b: 1 record in 0s ---\n{\"msg\":\"Cargo manifest\"}\n",
+            );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_group_timeout_outputs_idle_groups() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--group-by", "req", "--group-timeout", "100ms"])
+            .args(["--fields", "msg", "--", "sh", "-c"])
+            .arg(
+                "echo '{\"req\": \"a\", \"msg\": \"Docking request\"}'; sleep 1; \
+                 echo '{\"req\": \"a\", \"msg\": \"Docked\"}'",
+            )
+            .assert()
+            .success()
+            .stdout(
+                "--- req a: 1 record in 0s ---\n{\"msg\":\"Docking request\"}\n\
+                 --- req a: 1 record in 0s ---\n{\"msg\":\"Docked\"}\n",
+            );
+    }
+
+    #[rstest]
+    #[case(&[], "Station log\n{\"ts\":1}\nboot\n{\"ts\":2}\n{\"ts\":10}\n")]
+    #[case(&["--tail", "2"], "{\"ts\":2}\n{\"ts\":10}\n")]
+    #[case(&["--sort-memory", "1"], "Station log\n{\"ts\":1}\nboot\n{\"ts\":2}\n{\"ts\":10}\n")]
+    fn test_sort(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--sort", "ts"])
+            .args(args)
+            .write_stdin("Station log\n{\"ts\":10}\n{\"ts\":1}\nboot\n{\"ts\":2}\n")
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+    }
+
+    #[test]
+    fn test_strict() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--strict"])
+            .write_stdin("{\"ship\":\"Defiant\"}\nWarp core online\n{\"ship\": Rio Grande}\n")
+            .assert()
+            .code(2)
+            .stdout("{\"ship\":\"Defiant\"}\nWarp core online\n{\"ship\": Rio Grande}\n")
+            .stderr(predicate::str::starts_with(
+                "Error: 1 line started like JSON but didn't parse:\n  {\"ship\": Rio Grande}\n",
+            ));
+    }
+
+    #[test]
+    fn test_strict_passes_valid_input() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--strict", "--", "sh", "-c"])
+            .arg("echo '{\"ship\": \"Defiant\"}'; echo 'Hull breach' >&2")
+            .assert()
+            .success();
+    }
+
+    #[test]
+    fn test_check() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--check", "-f", "Rio"])
+            .write_stdin(
+                "{\"ship\":\"Defiant\"}\nWarp core online\n{\"ship\": Rio Grande}\n{\"ship\":\"Orinoco\"}\n",
+            )
+            .assert()
+            .code(1)
+            .stdout("json documents:  2\nmalformed lines: 1\n  {\"ship\": Rio Grande}\n");
+    }
+
+    #[test]
+    fn test_check_passes_valid_input() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--check")
+            .write_stdin("{\"ship\":\"Defiant\"}\nWarp core online\n")
+            .assert()
+            .success()
+            .stdout("json documents:  1\nmalformed lines: 0\n");
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        let output = cmd
+            .arg("--infer-schema")
+            .write_stdin("{\"level\":\"info\",\"ms\":12}\nWarp core online\n{\"level\":\"warn\"}\n")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        let schema: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!(schema["required"], serde_json::json!(["level"]));
+        assert_eq!(schema["properties"]["level"]["x-cardinality"], 2);
+        assert_eq!(schema["properties"]["ms"]["type"], "integer");
+    }
+
+    #[test]
+    fn test_stats_field_with_records() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--stats-field", "ms", "--with-records"])
+            .write_stdin("{\"ms\":12}\nWarp core online\n{\"ms\":30}\n")
+            .assert()
+            .success()
+            .stdout("{\"ms\":12}\nWarp core online\n{\"ms\":30}\n")
+            .stderr(
+                "ms (2 values)\n  min   12\n  max   30\n  mean  21\n  p50   12\n  p95   30\n\
+                 \x20 p99   30\n",
+            );
+    }
+
+    #[test]
+    fn test_filter_field() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--filter-field", "msg", "-f", "error"])
+            .write_stdin(
+                "{\"msg\":\"Docking error\"}\n\
+                 {\"msg\":\"Docked\",\"error\":null}\n\
+                 {\"error_count\":3}\n",
+            )
+            .assert()
+            .success()
+            .stdout("{\"msg\":\"Docking error\"}\n");
+    }
+
+    #[test]
+    fn test_stats_summary_on_stderr() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--compact", "--stats", "-f", "defiant"])
+            .write_stdin("Defiant docked\n{\"ship\": \"Rio Grande\"}\n")
+            .assert()
+            .success()
+            .stdout("Defiant docked\n")
+            .stderr(
+                predicate::str::contains("lines:          2\n")
+                    .and(predicate::str::contains("json records:   1\n"))
+                    .and(predicate::str::contains("filtered out:   1\n")),
+            );
+    }
+
+    #[rstest]
+    #[case(&["--ndjson"], "{\"text\":\"Defiant docked\"}\n{\"ship\":\"Rio Grande\"}\n")]
+    #[case(&["--ndjson=wrap", "--color", "always"], "{\"text\":\"Defiant docked\"}\n{\"ship\":\"Rio Grande\"}\n")]
+    #[case(&["--ndjson=drop"], "{\"ship\":\"Rio Grande\"}\n")]
+    fn test_ndjson_outputs_one_document_per_line(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .write_stdin("Defiant docked\n{\n  \"ship\": \"Rio Grande\"\n}\n")
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+    }
+
+    #[rstest]
+    #[case(&["--wrap-text"], "{\"message\":\"Defiant docked\",\"jlif_parsed\":false}\n{\"ship\":\"Rio Grande\"}\n")]
+    #[case(&["--wrap-text", "--ndjson"], "{\"message\":\"Defiant docked\",\"jlif_parsed\":false}\n{\"ship\":\"Rio Grande\"}\n")]
+    #[case(&["--wrap-text", "--fields", "message"], "{\"message\":\"Defiant docked\"}\n{}\n")]
+    fn test_wrap_text(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--compact")
+            .args(args)
+            .write_stdin("Defiant docked\n{\"ship\": \"Rio Grande\"}\n")
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+    }
+
+    #[rstest]
+    #[case(&["--ndjson", "--pretty"])]
+    #[case(&["--ndjson", "--show-size"])]
+    #[case(&["--ndjson", "-C", "1"])]
+    #[case(&["--ndjson", "--output-format", "toml"])]
+    #[case(&["--ndjson", "--output", "yaml"])]
+    fn test_ndjson_conflicts_with_non_json_output(#[case] args: &[&str]) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[test]
+    fn test_format_template() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--format", "{level} {msg} ({request_id|\"-\"}) {...}"])
+            .write_stdin(
+                "Defiant docked\n{\"level\": \"warn\", \"msg\": \"Hull breach\", \"deck\": 5}\n",
+            )
+            .assert()
+            .success()
+            .stdout("Defiant docked\nwarn Hull breach (-) {\"deck\":5}\n");
+    }
+
+    #[test]
+    fn test_format_rejects_invalid_template() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--format", "{level"])
+            .write_stdin("{}\n")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "Template error: Unclosed placeholder",
+            ));
+    }
+
+    #[test]
+    fn test_table_output() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--output", "table", "--columns", "level,msg"])
+            .write_stdin(
+                "{\"level\": \"info\", \"msg\": \"Docked\"}\n{\"level\": \"warn\", \"msg\": \"Cloak failing\"}\n",
+            )
+            .assert()
+            .success()
+            .stdout("level  msg\ninfo   Docked\nwarn   Cloak failing\n");
+    }
+
+    #[rstest]
+    #[case(&["--output", "table"], "--columns <FIELDS>")]
+    #[case(&["--columns", "level"], "--columns requires --output table")]
+    fn test_table_columns_go_together(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .write_stdin("{}\n")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(expected));
+    }
+
+    #[rstest]
+    #[case(&["--output-format", "yaml"])]
+    #[case(&["--output", "yaml"])]
+    fn test_yaml_output(#[case] args: &[&str]) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .write_stdin("Defiant docked\n{\"ship\": \"Rio Grande\", \"crew\": [\"Dax\"]}\n")
+            .assert()
+            .success()
+            .stdout("Defiant docked\n---\nship: Rio Grande\ncrew:\n- Dax\n");
+    }
+
+    #[test]
+    fn test_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("records.log");
+        std::fs::write(&path, "Stale record\n").unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("-o")
+            .arg(&path)
+            .write_stdin("{\"ship\": \"Defiant\"}\n")
+            .assert()
+            .success()
+            .stdout("");
+        // Neither colored nor pretty-printed, like any output that isn't a terminal
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"ship\":\"Defiant\"}\n"
+        );
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--append", "--output-file"])
+            .arg(&path)
+            .write_stdin("Rio Grande docked\n")
+            .assert()
+            .success();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"ship\":\"Defiant\"}\nRio Grande docked\n"
+        );
+    }
+
+    #[test]
+    fn test_tee_copies_unfiltered_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("raw.log");
+        let input = "{\"level\": \"info\", \"msg\": \"Docking\"}\r\n{\"level\": \"error\",\n \"msg\": \"Warp core breach\"}\nNo newline";
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--level", "error", "--tee"])
+            .arg(&path)
+            .write_stdin(input)
+            .assert()
+            .success()
+            .stdout("{\"level\":\"error\",\"msg\":\"Warp core breach\"}\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), input);
+    }
+
+    #[rstest]
+    #[case(&[], "")]
+    #[case(&["--echo"], "{\"level\":\"error\",\"msg\":\"Warp core breach\"}\nPromenade quiet\n")]
+    fn test_split_by_level(#[case] args: &[&str], #[case] expected: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let split_dir = dir.path().join("levels");
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--split-by-level")
+            .arg(&split_dir)
+            .args(args)
+            .write_stdin("{\"level\": \"error\", \"msg\": \"Warp core breach\"}\nPromenade quiet\n")
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+        assert_eq!(
+            std::fs::read_to_string(split_dir.join("error.log")).unwrap(),
+            "{\"level\":\"error\",\"msg\":\"Warp core breach\"}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(split_dir.join("unknown.log")).unwrap(),
+            "Promenade quiet\n"
+        );
+    }
+
+    #[test]
+    fn test_input_logfmt() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--input", "logfmt", "-c", "--level", "error"])
+            .write_stdin(
+                "level=info msg=\"Replicator online\"\n\
+                 level=error msg=\"Warp core breach\" user=quark\n\
+                 Quark's bar opens\n",
+            )
+            .assert()
+            .success()
+            .stdout("{\"level\":\"error\",\"msg\":\"Warp core breach\",\"user\":\"quark\"}\n");
+    }
+
+    #[test]
+    fn test_input_syslog() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--input", "syslog", "-c", "--level", "warn"])
+            .write_stdin(
+                "<14>1 2024-05-01T12:00:03Z ds9 api - - - {\"msg\":\"Docked\"}\n\
+                 <12>1 2024-05-01T12:00:04Z ds9 api - - - Shields low\n",
+            )
+            .assert()
+            .success()
+            .stdout(
+                "{\"timestamp\":\"2024-05-01T12:00:04Z\",\"host\":\"ds9\",\"app\":\"api\",\"facility\":\"user\",\"level\":\"warning\",\"msg\":\"Shields low\"}\n",
+            );
+    }
+
+    #[rstest]
+    #[case(&["--allow-trailing"], "{\"msg\":\"Docked\"}\ntook 34ms\n")]
+    #[case(
+        &["--allow-trailing", "--trailing-field", "trailing"],
+        "{\"msg\":\"Docked\",\"trailing\":\"took 34ms\"}\n"
+    )]
+    fn test_allow_trailing(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("-c")
+            .args(args)
+            .write_stdin("{\"msg\":\"Docked\"} took 34ms\n")
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+    }
+
+    #[test]
+    fn test_decompress() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder
+            .write_all(b"{\"msg\": \"Docked\"}\nQuark's bar opens\n")
+            .unwrap();
+        let compressed = encoder.finish().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ds9.log.gz");
+        std::fs::write(&path, &compressed).unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("-c")
+            .arg(&path)
+            .assert()
+            .success()
+            .stdout("{\"msg\":\"Docked\"}\nQuark's bar opens\n");
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--decompress"])
+            .write_stdin(compressed)
+            .assert()
+            .success()
+            .stdout("{\"msg\":\"Docked\"}\nQuark's bar opens\n");
+    }
+
+    #[test]
+    fn test_max_string_len_and_array_items() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--max-string-len", "4", "--max-array-items", "2"])
+            .write_stdin("{\"blob\": \"aGVsbG8=\", \"crew\": [\"Sisko\", \"Kira\", \"Odo\"]}\n")
+            .assert()
+            .success()
+            .stdout("{\"blob\":\"aGVs… (8 chars)\",\"crew\":[\"Sisk… (5 chars)\",\"Kira\",\"… (3 items)\"]}\n");
+    }
+
+    #[rstest]
+    #[case(&["--flatten"], "{\"http.status\":200,\"tags\":[\"ds9\"]}\n")]
+    #[case(&["--flatten", "--flatten-separator", "_", "--flatten-arrays"], "{\"http_status\":200,\"tags_0\":\"ds9\"}\n")]
+    fn test_flatten(#[case] args: &[&str], #[case] expected: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("-c")
+            .args(args)
+            .write_stdin("{\"http\": {\"status\": 200}, \"tags\": [\"ds9\"]}\n")
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+
+    #[rstest]
+    // The filter sees the secret by default, the redacted record when it runs later
+    #[case(&[], 0, "{\"token\":\"[REDACTED]\",\"msg\":\"Login\"}\n")]
+    #[case(&["--pipeline", "redact,filter"], 1, "")]
+    #[case(&["--pipeline", "strip-ansi,parse,redact,filter,format"], 1, "")]
+    fn test_pipeline(#[case] args: &[&str], #[case] code: i32, #[case] expected: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--redact", "token", "-f", "quark"])
+            .args(args)
+            .write_stdin("{\"token\": \"quark-1234\", \"msg\": \"Login\"}\n")
+            .assert()
+            .code(code)
+            .stdout(expected);
+    }
+
+    #[rstest]
+    #[case("redact,redact", "Stage 'redact' is listed more than once")]
+    #[case(
+        "filter,format,redact",
+        "Stage 'format' always runs last, it can't be moved"
+    )]
+    fn test_pipeline_rejects_invalid_order(#[case] stages: &str, #[case] message: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--pipeline", stages])
+            .write_stdin("{}\n")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(message));
+    }
+
+    #[test]
+    fn test_set() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--set", "env=staging", "--set", "station.sector=Bajor", "--fields", "msg"])
+            .write_stdin("{\"msg\": \"Docked\", \"level\": \"info\"}\nQuark complains\n")
+            .assert()
+            .success()
+            .stdout("{\"msg\":\"Docked\",\"env\":\"staging\",\"station\":{\"sector\":\"Bajor\"}}\nQuark complains\n");
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--extract", "msg"])
+            .write_stdin(
+                "{\"msg\": \"Docked\"}\n{\"level\": \"info\"}\nQuark complains\n{\"msg\": 47}\n",
+            )
+            .assert()
+            .success()
+            .stdout("Docked\n47\n");
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--extract", "ship.name", "-v", "-f", "Defiant"])
+            .write_stdin("{\"ship\": {\"name\": \"Defiant\"}}\n{\"msg\": \"Docked\"}\n")
+            .assert()
+            .code(1)
+            .stdout("");
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--max-depth", "1"])
+            .write_stdin("{\"msg\": \"Docked\", \"ship\": {\"name\": \"Defiant\", \"crew\": 50}}\n")
+            .assert()
+            .success()
+            .stdout("{\"msg\":\"Docked\",\"ship\":\"{…2 keys}\"}\n");
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--max-depth", "0"]).assert().code(2);
+    }
+
+    #[test]
+    fn test_show_deltas() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--show-deltas", "--time-field", "meta.ts"])
+            .write_stdin("{\"meta\": {\"ts\": 1714564801}}\n{\"meta\": {\"ts\": 1714564861500}}\n")
+            .assert()
+            .success()
+            .stdout("[+0s] {\"meta\":{\"ts\":1714564801}}\n[+60.5s] {\"meta\":{\"ts\":1714564861500}}\n");
+    }
+
+    #[rstest]
+    #[case(&["--since", "2024-05-01T12:00:02Z"], "{\"ts\":1714564803}\n")]
+    #[case(&["--until", "2024-05-01 12:00:02"], "{\"ts\":1714564801}\n")]
+    #[case(&["--since", "1h ago"], "")]
+    #[case(&["--since", "2024-05-01", "-v", "--where", "ts=1714564801"], "{\"ts\":1714564803}\n")]
+    fn test_since_until(#[case] args: &[&str], #[case] expected: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("-c")
+            .args(args)
+            .write_stdin("{\"ts\": 1714564801}\nQuark complains\n{\"ts\": 1714564803}\n")
+            .assert()
+            .stdout(expected);
+    }
+
+    #[rstest]
+    #[case(
+        "utc",
+        "{\"ts\":\"2024-05-01T12:00:03Z\"}\n{\"ts\":\"2024-05-01T12:00:05.3Z\"}\n"
+    )]
+    #[case("relative", "{\"ts\":\"+0s\"}\n{\"ts\":\"+2.3s\"}\n")]
+    fn test_time(#[case] mode: &str, #[case] expected: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--time", mode])
+            .write_stdin("{\"ts\": 1714564803}\n{\"ts\": \"2024-05-01T14:00:05.300+02:00\"}\n")
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+
+    #[rstest]
+    #[case(&["--since", "2024-05-02T10:00:00Z"], "{\"ts\":\"02/May/2024:10:00:00 +0000\"}\n")]
+    #[case(&["--time", "utc"], "{\"ts\":\"2024-05-02T10:00:00Z\"}\n{\"ts\":\"2024-05-01T22:00:00Z\"}\n")]
+    #[case(&["--sort", "ts"], "{\"ts\":\"02/May/2024:00:00:00 +0200\"}\n{\"ts\":\"02/May/2024:10:00:00 +0000\"}\n")]
+    fn test_time_format(#[case] args: &[&str], #[case] expected: &'static str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args([
+            "-c",
+            "--time-format",
+            "%Y%m%d",
+            "--time-format",
+            "%d/%b/%Y:%H:%M:%S %z",
+        ])
+        .args(args)
+        .write_stdin(
+            "{\"ts\": \"02/May/2024:10:00:00 +0000\"}\n{\"ts\": \"02/May/2024:00:00:00 +0200\"}\n",
+        )
+        .assert()
+        .success()
+        .stdout(expected);
+    }
+
+    #[test]
+    fn test_label() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ops.log"), "{\"msg\":\"Docked\"}\n").unwrap();
+        std::fs::write(dir.path().join("bar.log"), "Quark complains\n").unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.current_dir(dir.path())
+            .args([
+                "-c",
+                "--label",
+                "--prefix-field",
+                "file",
+                "ops.log",
+                "bar.log",
+            ])
+            .assert()
+            .success()
+            .stdout("{\"msg\":\"Docked\",\"file\":\"ops.log\"}\n[bar.log] Quark complains\n");
+    }
+
+    #[test]
+    fn test_annotate() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("ops.log"),
+            "Station log\n{\n  \"msg\": \"Docked\"\n}\n",
+        )
+        .unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.current_dir(dir.path())
+            .args([
+                "-c",
+                "--annotate",
+                "--format",
+                "{msg} {_jlif.source}:{_jlif.line}",
+                "ops.log",
+            ])
+            .assert()
+            .success()
+            .stdout("[ops.log:1] Station log\nDocked ops.log:2\n");
+    }
+
+    #[test]
+    fn test_merge_by() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("ops.log"),
+            "{\"ts\":\"2024-05-01T12:00:01Z\",\"msg\":\"Docked\"}\n\
+             {\"ts\":\"2024-05-01T12:00:03Z\",\"msg\":\"Undocked\"}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("bar.log"),
+            "{\"ts\":\"2024-05-01T12:00:02Z\",\"msg\":\"Bar opens\"}\nQuark complains\n",
+        )
+        .unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.current_dir(dir.path())
+            .args(["-c", "--merge-by", "ts", "ops.log", "bar.log"])
+            .assert()
+            .success()
+            .stdout(
+                "{\"ts\":\"2024-05-01T12:00:01Z\",\"msg\":\"Docked\",\"source\":\"ops.log\"}\n\
+                 {\"ts\":\"2024-05-01T12:00:02Z\",\"msg\":\"Bar opens\",\"source\":\"bar.log\"}\n\
+                 [bar.log] Quark complains\n\
+                 {\"ts\":\"2024-05-01T12:00:03Z\",\"msg\":\"Undocked\",\"source\":\"ops.log\"}\n",
+            );
+    }
+
+    #[test]
+    fn test_jobs_outputs_files_in_argument_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut expected = String::new();
+        let mut files = Vec::new();
+        for number in 1..=6 {
+            let name = format!("deck{}.log", number);
+            let lines: String = (1..=200)
+                .map(|line| format!("{{\"deck\":{},\"line\":{}}}\n", number, line))
+                .collect();
+            std::fs::write(dir.path().join(&name), &lines).unwrap();
+            expected.push_str(&lines);
+            files.push(name);
+        }
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.current_dir(dir.path())
+            .args(["-c", "--jobs", "3"])
+            .args(&files)
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+
+    #[test]
+    fn test_jobs_adds_up_results_of_all_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("ops.log"),
+            "{\"ship\":\"Defiant\"}\n{\"ship\": Rio\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("bar.log"),
+            "{\"ship\":\"Orinoco\"}\n[1, 2\n",
+        )
+        .unwrap();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.current_dir(dir.path())
+            .args(["--check", "--jobs", "2", "ops.log", "bar.log"])
+            .assert()
+            .code(1)
+            .stdout("json documents:  2\nmalformed lines: 2\n  {\"ship\": Rio\n  [1, 2\n");
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.current_dir(dir.path())
+            .args(["-f", "Starfleet", "--jobs", "2", "ops.log", "bar.log"])
+            .assert()
+            .code(1)
+            .stdout("");
+    }
+
+    #[rstest]
+    #[case(&["--listen", "udp:5000"], "expected an address like tcp://0.0.0.0:5000")]
+    #[case(&["--connection-prefix"], "--listen <URL>")]
+    #[case(&["--listen", "tcp://127.0.0.1:0", "ds9.log"], "cannot be used with")]
+    fn test_listen_usage_errors(#[case] args: &[&str], #[case] message: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(message));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-c", "--", "sh", "-c"])
+            .arg("echo '{\"msg\": \"Docked\"}'; echo 'Hull breach' >&2; exit 3")
+            .assert()
+            .code(3)
+            .stdout(predicate::str::contains(
+                "{\"msg\":\"Docked\",\"source\":\"stdout\"}\n",
+            ))
+            .stdout(predicate::str::contains("[stderr] Hull breach\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sigterm_exits_while_waiting_for_input() {
+        use std::process::{Command, Stdio};
+        use std::time::Instant;
+
+        // Stdin stays open without any input, like `tail -f` on a quiet log
+        let mut jlif = Command::new(assert_cmd::cargo::cargo_bin("jlif"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+
+        Command::new("kill")
+            .args(["-TERM", &jlif.id().to_string()])
+            .status()
+            .unwrap();
+
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = jlif.try_wait().unwrap() {
+                break status;
+            }
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "jlif kept running"
+            );
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert_eq!(status.code(), Some(143));
+    }
+
+    #[test]
+    fn test_exec_missing_command() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--", "jlif-no-such-command"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "Cannot run 'jlif-no-such-command'",
+            ));
+    }
+
+    #[test]
+    fn test_docker() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--docker", "-c", "--level", "warn"])
+            .write_stdin(
+                "{\"log\":\"{\\\"level\\\":\\\"warn\\\",\\\"msg\\\":\\\"Cloaked ship\\\"}\\n\",\"stream\":\"stdout\"}\n\
+                 {\"log\":\"{\\\"level\\\":\\\"info\\\",\\\"msg\\\":\\\"Docked\\\"}\\n\",\"stream\":\"stdout\"}\n",
+            )
+            .assert()
+            .success()
+            .stdout("{\"level\":\"warn\",\"msg\":\"Cloaked ship\"}\n");
+    }
+
+    #[test]
+    fn test_cri() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--cri", "--cri-metadata", "-c"])
+            .write_stdin(
+                "2024-05-01T12:00:03Z stdout P {\"msg\":\n\
+                 2024-05-01T12:00:03Z stdout F  \"Docked\"}\n\
+                 2024-05-01T12:00:04Z stderr F Quark's bar opens\n",
+            )
+            .assert()
+            .success()
+            .stdout(
+                "{\"msg\":\"Docked\",\"cri\":{\"time\":\"2024-05-01T12:00:03Z\",\"stream\":\"stdout\"}}\n\
+                 Quark's bar opens\n",
+            );
+    }
+
+    #[rstest]
+    #[case(&["--strip-prefix"], "{\"msg\":\"Docked\",\"source\":\"ops-1\"}\nops-1  | Quark's bar opens\n")]
+    #[case(&["--strip-prefix=^\\S+ +\\| ", "--prefix-field", "service"], "{\"msg\":\"Docked\",\"service\":\"ops-1  |\"}\nops-1  | Quark's bar opens\n")]
+    #[case(&["--strip-prefix", "--where", "source=promenade-1"], "")]
+    fn test_strip_prefix(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .arg("-c")
+            .write_stdin("ops-1  | {\"msg\": \"Docked\"}\nops-1  | Quark's bar opens\n")
+            .assert()
+            .stdout(expected.to_string());
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--strip-ansi", "-f", "Defiant"])
+            .write_stdin("\x1b[32m{\"ship\": \"Defiant\"}\x1b[0m\n\x1b[1mDefiant undocked\x1b[0m\n")
+            .assert()
+            .success()
+            .stdout("{\"ship\":\"Defiant\"}\nDefiant undocked\n");
+    }
+
+    #[test]
+    fn test_explode() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--explode", "-c", "-f", "Defiant"])
+            .write_stdin("[{\"ship\": \"Defiant\"}, {\"ship\": \"Rio Grande\"}]\n")
+            .assert()
+            .success()
+            .stdout("{\"ship\":\"Defiant\"}\n");
+    }
+
+    #[rstest]
+    #[case(&["--slurp", "-c"], "[{\"crew\":\"Sisko\"},{\"crew\":\"Kira\"}]\n")]
+    #[case(&["--slurp", "--pretty"], "[\n  {\n    \"crew\": \"Sisko\"\n  },\n  {\n    \"crew\": \"Kira\"\n  }\n]\n")]
+    #[case(&["--slurp", "-c", "--wrap-text"], "[{\"crew\":\"Sisko\"},{\"message\":\"Ops\",\"jlif_parsed\":false},{\"crew\":\"Kira\"}]\n")]
+    fn test_slurp(#[case] args: &[&str], #[case] expected: &str) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .write_stdin("{\"crew\": \"Sisko\"}\nOps\n{\"crew\": \"Kira\"}\n")
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+    }
+
+    #[test]
+    fn test_slurp_without_records_outputs_empty_array() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--slurp")
+            .write_stdin("Quark's bar is closed\n")
+            .assert()
+            .success()
+            .stdout("[]\n");
+    }
+
+    #[test]
+    fn test_slurp_beyond_max_memory_outputs_array_from_disk() {
+        let input: String = (1..=50)
+            .map(|deck| format!("{{\"deck\":{}}}\n", deck))
+            .collect();
+
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        let output = cmd
+            .args(["-c", "--slurp", "--max-memory", "1K"])
+            .write_stdin(input)
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        let array: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(array.as_array().unwrap().len(), 50);
+        assert_eq!(array[49], serde_json::json!({"deck": 50}));
+    }
+
+    #[test]
+    fn test_input_separator() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--input-separator", "\\0", "-c"])
+            .write_stdin("{\"ship\":\n \"Defiant\"}\0Sisko\nKira\0")
+            .assert()
+            .success()
+            .stdout("{\"ship\":\"Defiant\"}\nSisko\nKira\n");
+    }
+
+    #[test]
+    fn test_json_seq_output() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["--output", "json-seq", "--color", "always"])
+            .write_stdin("\u{1e}{\"ship\": \"Defiant\"}\n\u{1e}{\"ship\": \"Rio Grande\"}\n")
+            .assert()
+            .success()
+            .stdout("\u{1e}{\"ship\":\"Defiant\"}\n\u{1e}{\"ship\":\"Rio Grande\"}\n");
+    }
+
+    #[test]
+    fn test_split_streams() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--split-streams")
+            .write_stdin("Docking clamps released\n{\"ship\": \"Defiant\"}\nUndocked\n")
+            .assert()
+            .success()
+            .stdout("{\"ship\":\"Defiant\"}\n")
+            .stderr("Docking clamps released\nUndocked\n");
+    }
+
+    #[rstest]
+    #[case(&["--append"], "--output-file <PATH>")]
+    #[case(&["-o", "out.log", "-q"], "cannot be used with")]
+    #[case(&["--echo"], "--split-by-level <DIR>")]
+    #[case(&["--split-streams", "--wrap-text"], "cannot be used with")]
+    #[case(&["--split-by-level", "levels", "-q"], "cannot be used with")]
+    fn test_output_file_rejects_invalid_combinations(
+        #[case] args: &[&str],
+        #[case] expected: &str,
+    ) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args)
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(expected));
+    }
+
+    #[rstest]
+    #[case(&["--unbuffered"])]
+    #[case(&["--buffer-size", "1"])]
+    #[case(&["--buffer-size", "1MiB"])]
+    fn test_output_buffering_keeps_output(#[case] args: &[&str]) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.arg("--compact")
+            .args(args)
+            .write_stdin("Defiant docked\n{\n\"ship\": \"Rio Grande\"\n}\n")
+            .assert()
+            .success()
+            .stdout("Defiant docked\n{\"ship\":\"Rio Grande\"}\n");
+    }
+
+    #[test]
+    fn test_unbuffered_conflicts_with_buffer_size() {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(["-u", "--buffer-size", "4KiB"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    #[rstest]
+    #[case(&["--follow-name"])]
+    #[case(&["--follow-name", "a.log", "b.log"])]
+    #[case(&["--follow-name", "-"])]
+    fn test_follow_name_requires_single_file(#[case] args: &[&str]) {
+        let mut cmd = Command::cargo_bin("jlif").unwrap();
+        cmd.args(args).assert().failure();
+    }
+
+    #[rstest]
+    #[case("512", 512)]
+    #[case("512B", 512)]
+    #[case("4K", 4096)]
+    #[case("4 KiB", 4096)]
+    #[case("1mb", 1_048_576)]
+    #[case("2GiB", 2_147_483_648)]
+    fn test_parse_size(#[case] input: &str, #[case] expected: u64) {
+        assert_eq!(parse_size(input).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("KiB")]
+    #[case("1.5K")]
+    #[case("12 parsecs")]
+    fn test_parse_size_rejects_invalid(#[case] input: &str) {
+        assert!(parse_size(input).is_err());
+    }
+
+    #[rstest]
+    #[case("\\0", b"\0".to_vec())]
+    #[case("\\x1e", b"\x1e".to_vec())]
+    #[case("\\x1E", b"\x1e".to_vec())]
+    #[case("\\r\\n\\t\\\\", b"\r\n\t\\".to_vec())]
+    #[case("---", b"---".to_vec())]
+    #[case("§\\n", "§\n".as_bytes().to_vec())]
+    fn test_parse_separator(#[case] input: &str, #[case] expected: Vec<u8>) {
+        assert_eq!(
+            parse_separator(input).unwrap(),
+            LineSeparator::Custom(expected)
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("\\")]
+    #[case("\\q")]
+    #[case("\\x1")]
+    #[case("\\xzz")]
+    fn test_parse_separator_rejects_invalid(#[case] input: &str) {
+        assert!(parse_separator(input).is_err());
+    }
+
+    #[rstest]
+    #[case("sev=2=error", ("sev=2", Level::Error))]
+    #[case("100=fatal", ("100", Level::Fatal))]
+    #[case("NOTE=WARNING", ("NOTE", Level::Warn))]
+    fn test_parse_level_mapping(#[case] input: &str, #[case] expected: (&str, Level)) {
+        assert_eq!(
+            parse_level_mapping(input).unwrap(),
+            (expected.0.to_string(), expected.1)
+        );
+    }
+
+    #[rstest]
+    #[case("error")]
+    #[case("=error")]
+    #[case("sev=2=catastrophic")]
+    fn test_parse_level_mapping_rejects_invalid(#[case] input: &str) {
+        assert!(parse_level_mapping(input).is_err());
+    }
 }
@@ -0,0 +1,350 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::cli::JlifArgs;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command, CommandFactory, Parser};
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Cannot read config file '{path}': {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid config file '{path}': {message}")]
+    Invalid { path: PathBuf, message: String },
+    #[error("Unknown profile '{name}', available profiles: {available}")]
+    UnknownProfile { name: String, available: String },
+    #[error("Cannot select profile '{name}' without a config file")]
+    NoConfigFile { name: String },
+}
+
+/// Location of the config file, `$XDG_CONFIG_HOME/jlif/config.toml` or
+/// `~/.config/jlif/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("jlif").join("config.toml"))
+}
+
+/// Parses the command line, completed by the settings of the config file.
+///
+/// The config file sets defaults for any long option by its name, and named
+/// profiles under `[profiles.<name>]` that override them when selected with
+/// `--profile`:
+///
+/// ```toml
+/// theme = "dracula"
+/// max-lines = 20
+///
+/// [profiles.k8s]
+/// unwrap-nested = true
+/// fields = ["ts", "level", "msg"]
+/// where = ["namespace=prod"]
+/// ```
+///
+/// Options given on the command line or through their environment variable
+/// take precedence, as do options conflicting with them, so a configured
+/// `compact = true` yields to `--pretty`.
+pub fn parse_args() -> Result<JlifArgs, ConfigError> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+
+    let Some(path) = config_path() else {
+        return resolve(args, None);
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => resolve(args, Some((path, contents))),
+        Err(e) if e.kind() == ErrorKind::NotFound => resolve(args, None),
+        Err(source) => Err(ConfigError::Io { path, source }),
+    }
+}
+
+fn resolve(
+    args: Vec<OsString>,
+    config: Option<(PathBuf, String)>,
+) -> Result<JlifArgs, ConfigError> {
+    let mut command = JlifArgs::command();
+    command.build();
+    // Exits on invalid arguments and --help, like a regular parse
+    let matches = command.clone().get_matches_from(&args);
+    let profile = matches.get_one::<String>("profile");
+
+    let Some((path, contents)) = config else {
+        if let Some(name) = profile {
+            return Err(ConfigError::NoConfigFile { name: name.clone() });
+        }
+        return Ok(JlifArgs::parse_from(args));
+    };
+
+    let invalid = |message: String| ConfigError::Invalid {
+        path: path.clone(),
+        message,
+    };
+    let settings = settings(&contents, profile.map(String::as_str), &path)?;
+
+    let explicit: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|arg| is_explicit(&matches, arg))
+        .collect();
+
+    let mut config_args = Vec::new();
+    for (key, value) in &settings {
+        let arg = find_option(&command, key).ok_or_else(|| invalid(unknown_option(key)))?;
+        if is_explicit(&matches, arg) || conflicts(&command, arg, &explicit) {
+            continue;
+        }
+        config_args.extend(option_values(arg, key, value).map_err(invalid)?);
+    }
+
+    // Config options come first, so they can't end up after a `--`
+    let merged = args
+        .iter()
+        .take(1)
+        .cloned()
+        .chain(config_args)
+        .chain(args.iter().skip(1).cloned());
+    JlifArgs::try_parse_from(merged).map_err(|e| {
+        let rendered = e.to_string();
+        let message = rendered.lines().next().unwrap_or_default();
+        invalid(message.trim_start_matches("error: ").to_string())
+    })
+}
+
+/// Merges the defaults of the config file with the selected profile
+fn settings(
+    contents: &str,
+    profile: Option<&str>,
+    path: &Path,
+) -> Result<toml::Table, ConfigError> {
+    let invalid = |message: String| ConfigError::Invalid {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    let mut settings: toml::Table =
+        toml::from_str(contents).map_err(|e| invalid(e.message().to_string()))?;
+
+    let profiles = match settings.remove("profiles") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => {
+            return Err(invalid(
+                "'profiles' must be a table of profiles".to_string(),
+            ));
+        }
+        None => toml::Table::new(),
+    };
+
+    if let Some(name) = profile {
+        let Some(selected) = profiles.get(name) else {
+            let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            return Err(ConfigError::UnknownProfile {
+                name: name.to_string(),
+                available: if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                },
+            });
+        };
+        let toml::Value::Table(selected) = selected else {
+            return Err(invalid(format!("profile '{}' must be a table", name)));
+        };
+        settings.extend(selected.clone());
+    }
+
+    Ok(settings)
+}
+
+fn find_option<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    command.get_arguments().find(|arg| {
+        arg.get_long() == Some(key)
+            && key != "profile"
+            && !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version)
+    })
+}
+
+fn unknown_option(key: &str) -> String {
+    if key == "profile" {
+        "'profile' can only be selected on the command line or through JLIF_PROFILE".to_string()
+    } else {
+        format!("unknown option '{}'", key)
+    }
+}
+
+/// Whether the option was given on the command line or through its environment variable
+fn is_explicit(matches: &ArgMatches, arg: &Arg) -> bool {
+    matches!(
+        matches.value_source(arg.get_id().as_str()),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+fn conflicts(command: &Command, arg: &Arg, explicit: &[&Arg]) -> bool {
+    // Conflicts are only declared on one of the two arguments
+    explicit.iter().any(|other| {
+        command
+            .get_arg_conflicts_with(arg)
+            .iter()
+            .any(|conflict| conflict.get_id() == other.get_id())
+            || command
+                .get_arg_conflicts_with(other)
+                .iter()
+                .any(|conflict| conflict.get_id() == arg.get_id())
+    })
+}
+
+/// Turns a setting into the command line arguments it stands for
+fn option_values(arg: &Arg, key: &str, value: &toml::Value) -> Result<Vec<OsString>, String> {
+    if !arg.get_action().takes_values() {
+        return match value {
+            toml::Value::Boolean(true) => Ok(vec![format!("--{}", key).into()]),
+            toml::Value::Boolean(false) => Ok(Vec::new()),
+            _ => Err(format!("'{}' must be true or false", key)),
+        };
+    }
+
+    // Options with an optional value, like `--strip-prefix`, are enabled by `true`
+    let optional_value = arg
+        .get_num_args()
+        .is_some_and(|range| range.min_values() == 0);
+    if optional_value && value == &toml::Value::Boolean(true) {
+        return Ok(vec![format!("--{}", key).into()]);
+    }
+
+    let scalar = |value: &toml::Value| {
+        let text = match value {
+            toml::Value::String(text) => text.clone(),
+            toml::Value::Integer(number) => number.to_string(),
+            toml::Value::Float(number) => number.to_string(),
+            toml::Value::Boolean(enabled) => enabled.to_string(),
+            _ => return Err(format!("'{}' must be a string, number or list", key)),
+        };
+        Ok(format!("--{}={}", key, text).into())
+    };
+
+    match value {
+        toml::Value::Array(items) => items.iter().map(scalar).collect(),
+        value => Ok(vec![scalar(value)?]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const CONFIG: &str = r#"
+        theme = "dracula"
+        max-lines = 20
+        compact = true
+
+        [profiles.k8s]
+        max-lines = 50
+        unwrap-nested = true
+        where = ["namespace=ops", "deck=5"]
+
+        [profiles.quiet]
+        json-only = false
+    "#;
+
+    fn resolve_with(args: &[&str], contents: &str) -> Result<JlifArgs, ConfigError> {
+        let args = std::iter::once("jlif")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        resolve(
+            args,
+            Some((PathBuf::from("config.toml"), contents.to_string())),
+        )
+    }
+
+    #[test]
+    fn test_defaults_apply_without_profile() {
+        let args = resolve_with(&[], CONFIG).unwrap();
+
+        assert_eq!(args.theme, "dracula");
+        assert_eq!(args.max_lines, 20);
+        assert!(args.compact);
+        assert!(!args.unwrap_nested);
+    }
+
+    #[test]
+    fn test_profile_overrides_defaults() {
+        let args = resolve_with(&["--profile", "k8s"], CONFIG).unwrap();
+
+        assert_eq!(args.theme, "dracula");
+        assert_eq!(args.max_lines, 50);
+        assert!(args.unwrap_nested);
+        assert_eq!(
+            args.conditions,
+            vec![
+                ("namespace".to_string(), "ops".to_string()),
+                ("deck".to_string(), "5".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_line_overrides_config() {
+        let args = resolve_with(
+            &["--profile", "k8s", "--max-lines", "3", "--pretty"],
+            CONFIG,
+        )
+        .unwrap();
+
+        assert_eq!(args.max_lines, 3);
+        // Conflicts with the given --pretty, so the configured default is dropped
+        assert!(!args.compact);
+        assert!(args.pretty);
+    }
+
+    #[test]
+    fn test_false_flag_is_left_unset() {
+        let args = resolve_with(&["--profile", "quiet"], CONFIG).unwrap();
+
+        assert!(!args.json_only);
+    }
+
+    #[rstest]
+    #[case("strip-prefix = true", None)]
+    #[case("strip-prefix = '^\\S+ '", Some("^\\S+ "))]
+    fn test_optional_value(#[case] contents: &str, #[case] expected: Option<&str>) {
+        let args = resolve_with(&[], contents).unwrap();
+
+        assert_eq!(args.strip_prefix, Some(expected.map(str::to_string)));
+    }
+
+    #[test]
+    fn test_unknown_profile_lists_available_profiles() {
+        let error = resolve_with(&["--profile", "cardassia"], CONFIG).unwrap_err();
+
+        assert!(matches!(error, ConfigError::UnknownProfile { .. }));
+        assert!(error.to_string().contains("k8s, quiet"));
+    }
+
+    #[rstest]
+    #[case("max-linez = 20", "unknown option 'max-linez'")]
+    #[case("profile = \"k8s\"", "'profile' can only be selected")]
+    #[case("compact = \"yes\"", "'compact' must be true or false")]
+    #[case("theme = { name = \"mono\" }", "'theme' must be a string")]
+    #[case("max-lines = \"many\"", "invalid value 'many'")]
+    #[case("profiles = 1", "'profiles' must be a table")]
+    fn test_rejects_invalid_settings(#[case] contents: &str, #[case] expected: &str) {
+        let error = resolve_with(&[], contents).unwrap_err();
+
+        assert!(
+            error.to_string().contains(expected),
+            "unexpected error: {}",
+            error
+        );
+    }
+}
@@ -0,0 +1,129 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::envelope::Envelope;
+use serde_json::{Map, Value, json};
+
+// Field receiving the time and stream of a line
+const METADATA_FIELD: &str = "cri";
+
+/// Unwraps lines in the CRI log format written by containerd and CRI-O, as
+/// found in `/var/log/pods` on Kubernetes nodes.
+///
+/// Every line looks like `2024-01-01T00:00:00.000000000Z stdout F {...}`: a
+/// timestamp, the stream, a tag and what the container wrote. The tag `P`
+/// marks a partial line continued by the next one, `F` the full or final part.
+#[derive(Debug, Default)]
+pub struct CriUnwrapper {
+    metadata: bool,
+    // Content of partial lines still waiting for their final part
+    partial: String,
+}
+
+impl CriUnwrapper {
+    /// Creates an unwrapper, optionally keeping each line's time and stream
+    pub fn new(metadata: bool) -> Self {
+        Self {
+            metadata,
+            partial: String::new(),
+        }
+    }
+}
+
+impl Envelope for CriUnwrapper {
+    fn unwrap(&mut self, line: String) -> Option<(String, Option<Map<String, Value>>)> {
+        let mut parts = line.splitn(4, ' ');
+        let (Some(time), Some(stream), Some(tag)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Some((line, None));
+        };
+        let content = parts.next().unwrap_or_default();
+        let is_cri = time.starts_with(|c: char| c.is_ascii_digit())
+            && matches!(stream, "stdout" | "stderr")
+            && (tag.starts_with('P') || tag.starts_with('F'));
+        if !is_cri {
+            return Some((line, None));
+        }
+
+        self.partial.push_str(content);
+        if tag.starts_with('P') {
+            return None;
+        }
+        let content = std::mem::take(&mut self.partial);
+
+        let metadata = self.metadata.then(|| {
+            let mut fields = Map::new();
+            fields.insert(
+                METADATA_FIELD.to_string(),
+                json!({"time": time, "stream": stream}),
+            );
+            fields
+        });
+        Some((content, metadata))
+    }
+
+    fn take_partial(&mut self) -> Option<String> {
+        (!self.partial.is_empty()).then(|| std::mem::take(&mut self.partial))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "2024-05-01T12:00:03.123456789Z stdout F {\"msg\": \"Docked\"}",
+        "{\"msg\": \"Docked\"}"
+    )]
+    #[case(
+        "2024-05-01T12:00:04.000000000+02:00 stderr F Quark's bar opens",
+        "Quark's bar opens"
+    )]
+    #[case("2024-05-01T12:00:05Z stdout F", "")]
+    #[case("Odo stdout F shapeshifts", "Odo stdout F shapeshifts")]
+    #[case("2024-05-01 12:00:05 INFO docked", "2024-05-01 12:00:05 INFO docked")]
+    #[case("{\"msg\": \"Docked\"}", "{\"msg\": \"Docked\"}")]
+    fn test_unwraps_lines(#[case] line: &str, #[case] expected: &str) {
+        let mut unwrapper = CriUnwrapper::new(false);
+
+        assert_eq!(
+            unwrapper.unwrap(line.to_string()),
+            Some((expected.to_string(), None))
+        );
+    }
+
+    #[test]
+    fn test_joins_partial_lines() {
+        let mut unwrapper = CriUnwrapper::new(false);
+
+        assert_eq!(
+            unwrapper.unwrap("2024-05-01T12:00:03Z stdout P {\"msg\":".to_string()),
+            None
+        );
+        assert_eq!(
+            unwrapper.unwrap("2024-05-01T12:00:03Z stdout F  \"Docked\"}".to_string()),
+            Some(("{\"msg\": \"Docked\"}".to_string(), None))
+        );
+
+        unwrapper.unwrap("2024-05-01T12:00:04Z stdout P Rio Grande".to_string());
+        assert_eq!(unwrapper.take_partial(), Some("Rio Grande".to_string()));
+        assert_eq!(unwrapper.take_partial(), None);
+    }
+
+    #[test]
+    fn test_keeps_metadata() {
+        let mut unwrapper = CriUnwrapper::new(true);
+
+        let (_, metadata) = unwrapper
+            .unwrap("2024-05-01T12:00:03Z stderr F {\"msg\": \"Docked\"}".to_string())
+            .unwrap();
+
+        assert_eq!(
+            Value::Object(metadata.unwrap()),
+            json!({"cri": {"time": "2024-05-01T12:00:03Z", "stream": "stderr"}})
+        );
+    }
+}
@@ -0,0 +1,159 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use ruzstd::decoding::StreamingDecoder;
+use std::io::{self, Cursor, ErrorKind, Read};
+
+// Longest magic number to look at
+const MAGIC_LEN: usize = 4;
+
+/// Compression formats recognized by their magic number
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Detects the compression of data starting with these bytes
+    pub fn detect(start: &[u8]) -> Option<Self> {
+        if start.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if start.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if start.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reader transparently decompressing gzip, zstd or bzip2 compressed input,
+/// like rotated log files, and passing anything else through unchanged.
+///
+/// The compression is detected on the first read, so opening a pipe doesn't
+/// block until it has written something.
+pub struct DecompressReader<R> {
+    source: Option<R>,
+    reader: Option<Box<dyn Read + Send>>,
+}
+
+impl<R: Read + Send + 'static> DecompressReader<R> {
+    pub fn new(source: R) -> Self {
+        Self {
+            source: Some(source),
+            reader: None,
+        }
+    }
+
+    fn detect(mut source: R) -> io::Result<Box<dyn Read + Send>> {
+        let mut start = Vec::with_capacity(MAGIC_LEN);
+        (&mut source)
+            .take(MAGIC_LEN as u64)
+            .read_to_end(&mut start)?;
+        let compression = Compression::detect(&start);
+        // The peeked bytes still belong to the input
+        let input = Cursor::new(start).chain(source);
+
+        Ok(match compression {
+            None => Box::new(input),
+            Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(input)),
+            Some(Compression::Bzip2) => Box::new(MultiBzDecoder::new(input)),
+            Some(Compression::Zstd) => Box::new(
+                StreamingDecoder::new(input)
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.to_string()))?,
+            ),
+        })
+    }
+}
+
+impl<R: Read + Send + 'static> Read for DecompressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(source) = self.source.take() {
+            self.reader = Some(Self::detect(source)?);
+        }
+        match &mut self.reader {
+            Some(reader) => reader.read(buf),
+            // Detection failed before, which was reported already
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::io::Write;
+
+    const LOG: &str = "{\"msg\": \"Docked\"}\nQuark's bar opens\n";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn bzip2(data: &[u8]) -> Vec<u8> {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        ruzstd::encoding::compress_to_vec(data, ruzstd::encoding::CompressionLevel::Fastest)
+    }
+
+    fn read_all(data: Vec<u8>) -> String {
+        let mut output = String::new();
+        DecompressReader::new(Cursor::new(data))
+            .read_to_string(&mut output)
+            .unwrap();
+        output
+    }
+
+    #[rstest]
+    #[case(gzip(LOG.as_bytes()), Some(Compression::Gzip))]
+    #[case(zstd(LOG.as_bytes()), Some(Compression::Zstd))]
+    #[case(bzip2(LOG.as_bytes()), Some(Compression::Bzip2))]
+    #[case(LOG.as_bytes().to_vec(), None)]
+    fn test_decompresses_detected_formats(
+        #[case] data: Vec<u8>,
+        #[case] compression: Option<Compression>,
+    ) {
+        assert_eq!(Compression::detect(&data), compression);
+        assert_eq!(read_all(data), LOG);
+    }
+
+    #[test]
+    fn test_joins_concatenated_gzip_members() {
+        let mut data = gzip(b"{\"msg\": \"Docked\"}\n");
+        data.extend(gzip(b"Quark's bar opens\n"));
+
+        assert_eq!(read_all(data), LOG);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("{}")]
+    #[case("BZ")]
+    fn test_passes_short_input_through(#[case] data: &str) {
+        assert_eq!(read_all(data.as_bytes().to_vec()), data);
+    }
+
+    #[test]
+    fn test_reports_corrupt_input() {
+        let mut data = gzip(LOG.as_bytes());
+        data.truncate(12);
+        let mut output = String::new();
+
+        let result = DecompressReader::new(Cursor::new(data)).read_to_string(&mut output);
+
+        assert!(result.is_err());
+    }
+}
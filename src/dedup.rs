@@ -0,0 +1,363 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::buffer::BufferResult;
+use crate::field_path::FieldPath;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+// Identifies the file format, bumped whenever the hashing scheme changes
+const MAGIC: &[u8; 8] = b"JLIFDD01";
+
+// Separate seeds keep a text line from colliding with an identical JSON string
+const JSON_SEED: u64 = 0;
+const TEXT_SEED: u64 = 1;
+
+#[derive(Error, Debug)]
+pub enum DedupError {
+    #[error("Cannot access dedup state file '{path}': {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("'{path}' is not a jlif dedup state file")]
+    InvalidStateFile { path: PathBuf },
+}
+
+/// Set of record hashes persisted across invocations.
+///
+/// The state file holds a format marker followed by one little-endian 64-bit
+/// xxh3 hash per record. Hashes are loaded into memory on start and new ones
+/// are appended as records are output, so an interrupted run still keeps
+/// everything it already wrote. Records dropped after being admitted, by
+/// `--every` or `--tail` for example, are only suppressed for the rest of the
+/// run. At 8 bytes per record on disk, even millions of records stay in the
+/// tens of megabytes.
+pub struct DedupState {
+    seen: HashSet<u64>,
+    // Admitted records waiting to be output, by the hash of their output form
+    pending: HashMap<u64, u64>,
+    file: BufWriter<File>,
+}
+
+impl DedupState {
+    /// Opens the state file at `path`, creating it if it doesn't exist yet
+    pub fn open(path: &Path) -> Result<Self, DedupError> {
+        let io_error = |source| DedupError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(io_error)?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).map_err(io_error)?;
+
+        let seen = if contents.is_empty() {
+            file.write_all(MAGIC).map_err(io_error)?;
+            HashSet::new()
+        } else {
+            let Some(hashes) = contents.strip_prefix(MAGIC) else {
+                return Err(DedupError::InvalidStateFile {
+                    path: path.to_path_buf(),
+                });
+            };
+
+            // A run killed mid-write may leave a partial hash behind, cut it
+            // off so appended hashes stay aligned
+            let partial = hashes.len() % 8;
+            if partial != 0 {
+                file.set_len((contents.len() - partial) as u64)
+                    .map_err(io_error)?;
+            }
+
+            hashes
+                .chunks_exact(8)
+                .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect()
+        };
+
+        Ok(Self {
+            seen,
+            pending: HashMap::new(),
+            file: BufWriter::new(file),
+        })
+    }
+
+    /// Returns the hash of a record that hasn't been seen before, suppressing
+    /// it for the rest of the run.
+    ///
+    /// The record is only stored in the state file once it is output, see
+    /// [`DedupState::expect`]. Incomplete results are never considered new.
+    pub fn admit(&mut self, result: &BufferResult) -> io::Result<Option<u64>> {
+        let Some(hash) = hash(result)? else {
+            return Ok(None);
+        };
+        Ok(self.seen.insert(hash).then_some(hash))
+    }
+
+    /// Stores the record admitted as `hash` once a record hashing to `output`
+    /// is output, which differs if the record was changed after admission
+    pub fn expect(&mut self, hash: u64, output: u64) {
+        self.pending.insert(output, hash);
+    }
+
+    /// Stores a record being output in the state file if it was admitted
+    pub fn output(&mut self, result: &BufferResult) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(hash) = hash(result)?.and_then(|output| self.pending.remove(&output)) {
+            self.file.write_all(&hash.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes out hashes still held in the buffer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Hash identifying a record, none for incomplete results
+pub fn hash(result: &BufferResult) -> io::Result<Option<u64>> {
+    Ok(match result {
+        BufferResult::Json(value, _) => {
+            Some(xxh3_64_with_seed(&serde_json::to_vec(value)?, JSON_SEED))
+        }
+        BufferResult::Text(text) => Some(xxh3_64_with_seed(text.as_bytes(), TEXT_SEED)),
+        BufferResult::Incomplete(_) => None,
+    })
+}
+
+/// Collapses runs of identical records, like syslog's "last message repeated
+/// N times".
+///
+/// Fields like timestamps that differ between otherwise identical records can
+/// be left out of the comparison.
+pub struct ConsecutiveDedup {
+    ignored: Vec<FieldPath>,
+    last: Option<u64>,
+    repeated: u64,
+}
+
+impl ConsecutiveDedup {
+    pub fn new(ignored: &[String]) -> Self {
+        Self {
+            ignored: ignored.iter().map(|path| FieldPath::parse(path)).collect(),
+            last: None,
+            repeated: 0,
+        }
+    }
+
+    /// Whether a record differs from the one before it, along with the number
+    /// of repeats of that one if it does
+    pub fn admit(&mut self, result: &BufferResult) -> io::Result<(bool, u64)> {
+        let hash = match result {
+            BufferResult::Json(value, _) if !self.ignored.is_empty() => {
+                let mut value = value.clone();
+                for path in &self.ignored {
+                    path.remove(&mut value);
+                }
+                xxh3_64_with_seed(&serde_json::to_vec(&value)?, JSON_SEED)
+            }
+            BufferResult::Json(value, _) => {
+                xxh3_64_with_seed(&serde_json::to_vec(value)?, JSON_SEED)
+            }
+            BufferResult::Text(text) => xxh3_64_with_seed(text.as_bytes(), TEXT_SEED),
+            BufferResult::Incomplete(_) => return Ok((true, 0)),
+        };
+
+        if self.last == Some(hash) {
+            self.repeated += 1;
+            return Ok((false, 0));
+        }
+        self.last = Some(hash);
+        Ok((true, std::mem::take(&mut self.repeated)))
+    }
+
+    /// Number of repeats of the last record not reported yet, at the end of
+    /// the input
+    pub fn finish(&mut self) -> u64 {
+        std::mem::take(&mut self.repeated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn json(value: serde_json::Value) -> BufferResult {
+        BufferResult::Json(value, 0)
+    }
+
+    /// Admits a record and outputs it right away, returning whether it was new
+    fn pass(state: &mut DedupState, result: &BufferResult) -> bool {
+        let Some(hash) = state.admit(result).unwrap() else {
+            return false;
+        };
+        state.expect(hash, hash);
+        state.output(result).unwrap();
+        true
+    }
+
+    #[test]
+    fn test_counts_consecutive_repeats() {
+        let mut dedup = ConsecutiveDedup::new(&[]);
+        let breach = || json(json!({"alert": "Hull breach"}));
+
+        assert_eq!(dedup.admit(&breach()).unwrap(), (true, 0));
+        assert_eq!(dedup.admit(&breach()).unwrap(), (false, 0));
+        assert_eq!(dedup.admit(&breach()).unwrap(), (false, 0));
+        assert_eq!(
+            dedup.admit(&BufferResult::Text("Odo".into())).unwrap(),
+            (true, 2)
+        );
+        assert_eq!(dedup.admit(&breach()).unwrap(), (true, 0));
+        assert_eq!(dedup.admit(&breach()).unwrap(), (false, 0));
+        assert_eq!(dedup.finish(), 1);
+        assert_eq!(dedup.finish(), 0);
+    }
+
+    #[test]
+    fn test_ignores_fields_when_comparing() {
+        let mut dedup = ConsecutiveDedup::new(&["ts".to_string(), "meta.seq".to_string()]);
+
+        assert_eq!(
+            dedup
+                .admit(&json(json!({"ts": 1, "meta": {"seq": 1}, "alert": "Red"})))
+                .unwrap(),
+            (true, 0)
+        );
+        assert_eq!(
+            dedup
+                .admit(&json(json!({"ts": 2, "meta": {"seq": 2}, "alert": "Red"})))
+                .unwrap(),
+            (false, 0)
+        );
+        assert_eq!(
+            dedup
+                .admit(&json(
+                    json!({"ts": 3, "meta": {"seq": 3}, "alert": "Yellow"})
+                ))
+                .unwrap(),
+            (true, 1)
+        );
+    }
+
+    #[test]
+    fn test_remembers_records_across_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+
+        let mut state = DedupState::open(&path).unwrap();
+        assert!(pass(&mut state, &json(json!({"captain": "Sisko"}))));
+        assert!(pass(&mut state, &BufferResult::Text("Odo".into())));
+        assert!(!pass(&mut state, &json(json!({"captain": "Sisko"}))));
+        state.flush().unwrap();
+        drop(state);
+
+        let mut state = DedupState::open(&path).unwrap();
+        assert!(!pass(&mut state, &json(json!({"captain": "Sisko"}))));
+        assert!(!pass(&mut state, &BufferResult::Text("Odo".into())));
+        assert!(pass(&mut state, &json(json!({"captain": "Kira"}))));
+    }
+
+    #[test]
+    fn test_text_and_json_strings_are_distinct() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = DedupState::open(&dir.path().join("state.db")).unwrap();
+
+        assert!(pass(&mut state, &json(json!("Quark"))));
+        assert!(pass(&mut state, &BufferResult::Text("\"Quark\"".into())));
+    }
+
+    #[test]
+    fn test_incomplete_results_are_never_new() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = DedupState::open(&dir.path().join("state.db")).unwrap();
+
+        assert!(!pass(
+            &mut state,
+            &BufferResult::Incomplete(vec!["{".into()])
+        ));
+    }
+
+    #[test]
+    fn test_truncates_partially_written_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+
+        let mut state = DedupState::open(&path).unwrap();
+        pass(&mut state, &json(json!({"station": "DS9"})));
+        state.flush().unwrap();
+        drop(state);
+
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&[0xde, 0xad])
+            .unwrap();
+
+        let mut state = DedupState::open(&path).unwrap();
+        assert!(!pass(&mut state, &json(json!({"station": "DS9"}))));
+        assert!(pass(&mut state, &json(json!({"station": "Terok Nor"}))));
+        state.flush().unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 8 + 2 * 8);
+    }
+
+    #[test]
+    fn test_only_stores_records_that_were_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+        let sisko = json(json!({"captain": "Sisko"}));
+        let annotated = json(json!({"captain": "Kira", "_jlif": {"line": 2}}));
+
+        let mut state = DedupState::open(&path).unwrap();
+        assert!(state.admit(&sisko).unwrap().is_some());
+        assert!(state.admit(&sisko).unwrap().is_none());
+        let kira = state
+            .admit(&json(json!({"captain": "Kira"})))
+            .unwrap()
+            .unwrap();
+        state.expect(kira, hash(&annotated).unwrap().unwrap());
+        state.output(&annotated).unwrap();
+        state.flush().unwrap();
+        drop(state);
+
+        let mut state = DedupState::open(&path).unwrap();
+        assert!(state.admit(&sisko).unwrap().is_some());
+        assert!(
+            state
+                .admit(&json(json!({"captain": "Kira"})))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_rejects_foreign_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.txt");
+        std::fs::write(&path, "Rules of Acquisition").unwrap();
+
+        assert!(matches!(
+            DedupState::open(&path),
+            Err(DedupError::InvalidStateFile { .. })
+        ));
+    }
+}
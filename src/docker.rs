@@ -0,0 +1,133 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::envelope::Envelope;
+use serde_json::{Map, Value};
+
+// Field receiving the stream and time of an entry
+const METADATA_FIELD: &str = "docker";
+
+/// Unwraps lines written by Docker's json-file logging driver.
+///
+/// Every entry looks like `{"log":"...\n","stream":"stdout","time":"..."}`.
+/// The `log` content is what the container wrote, so it is handed on for JSON
+/// detection in place of the entry. Docker splits lines longer than 16KiB into
+/// several entries, only the last of which ends with a newline; these are
+/// joined again. Lines that aren't entries are left as they are.
+#[derive(Debug, Default)]
+pub struct DockerUnwrapper {
+    metadata: bool,
+    // Content of entries still waiting for the end of their line
+    partial: String,
+}
+
+impl DockerUnwrapper {
+    /// Creates an unwrapper, optionally keeping each entry's stream and time
+    pub fn new(metadata: bool) -> Self {
+        Self {
+            metadata,
+            partial: String::new(),
+        }
+    }
+}
+
+impl Envelope for DockerUnwrapper {
+    fn unwrap(&mut self, line: String) -> Option<(String, Option<Map<String, Value>>)> {
+        // Entries always start with the log field, which spares parsing other lines
+        if !line.starts_with("{\"log\":") {
+            return Some((line, None));
+        }
+        let Ok(Value::Object(mut entry)) = serde_json::from_str::<Value>(&line) else {
+            return Some((line, None));
+        };
+        let Some(Value::String(log)) = entry.remove("log") else {
+            return Some((line, None));
+        };
+
+        self.partial.push_str(&log);
+        if !self.partial.ends_with('\n') {
+            return None;
+        }
+        let mut content = std::mem::take(&mut self.partial);
+        content.pop();
+        if content.ends_with('\r') {
+            content.pop();
+        }
+
+        let metadata = self.metadata.then(|| {
+            let entry: Map<String, Value> = ["stream", "time"]
+                .into_iter()
+                .filter_map(|key| entry.remove(key).map(|value| (key.to_string(), value)))
+                .collect();
+            let mut fields = Map::new();
+            fields.insert(METADATA_FIELD.to_string(), Value::Object(entry));
+            fields
+        });
+        Some((content, metadata))
+    }
+
+    fn take_partial(&mut self) -> Option<String> {
+        (!self.partial.is_empty()).then(|| std::mem::take(&mut self.partial))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(
+        r#"{"log":"{\"msg\":\"Docked\"}\n","stream":"stdout","time":"2024-05-01T12:00:03Z"}"#,
+        "{\"msg\":\"Docked\"}"
+    )]
+    #[case(
+        r#"{"log":"Quark's bar opens\r\n","stream":"stderr","time":"2024-05-01T12:00:04Z"}"#,
+        "Quark's bar opens"
+    )]
+    #[case(r#"{"msg":"Docked"}"#, r#"{"msg":"Docked"}"#)]
+    #[case(r#"{"log": 42}"#, r#"{"log": 42}"#)]
+    #[case("{\"log\":\"Odo", "{\"log\":\"Odo")]
+    fn test_unwraps_entries(#[case] line: &str, #[case] expected: &str) {
+        let mut unwrapper = DockerUnwrapper::new(false);
+
+        assert_eq!(
+            unwrapper.unwrap(line.to_string()),
+            Some((expected.to_string(), None))
+        );
+    }
+
+    #[test]
+    fn test_joins_split_lines() {
+        let mut unwrapper = DockerUnwrapper::new(false);
+
+        assert_eq!(
+            unwrapper.unwrap(r#"{"log":"{\"msg\":","stream":"stdout"}"#.to_string()),
+            None
+        );
+        assert_eq!(
+            unwrapper.unwrap(r#"{"log":"\"Docked\"}\n","stream":"stdout"}"#.to_string()),
+            Some(("{\"msg\":\"Docked\"}".to_string(), None))
+        );
+
+        unwrapper.unwrap(r#"{"log":"Rio Grande","stream":"stdout"}"#.to_string());
+        assert_eq!(unwrapper.take_partial(), Some("Rio Grande".to_string()));
+        assert_eq!(unwrapper.take_partial(), None);
+    }
+
+    #[test]
+    fn test_keeps_metadata() {
+        let mut unwrapper = DockerUnwrapper::new(true);
+        let line =
+            r#"{"log":"{\"msg\":\"Docked\"}\n","stream":"stderr","time":"2024-05-01T12:00:03Z"}"#;
+
+        let (_, metadata) = unwrapper.unwrap(line.to_string()).unwrap();
+
+        assert_eq!(
+            Value::Object(metadata.unwrap()),
+            json!({"docker": {"stream": "stderr", "time": "2024-05-01T12:00:03Z"}})
+        );
+    }
+}
@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::cri::CriUnwrapper;
+use crate::docker::DockerUnwrapper;
+use enum_dispatch::enum_dispatch;
+use serde_json::{Map, Value};
+
+/// Trait for log formats wrapping every line a container wrote in an envelope
+/// of their own, like Docker's json-file driver or the CRI format of Kubernetes
+#[enum_dispatch]
+pub trait Envelope {
+    /// Unwraps a line into its content and, if requested, metadata fields of
+    /// the envelope to add to the record. Lines without an envelope are left
+    /// as they are.
+    ///
+    /// Returns `None` while a line split into several envelopes is incomplete.
+    fn unwrap(&mut self, line: String) -> Option<(String, Option<Map<String, Value>>)>;
+
+    /// Content of a split line whose last envelope never arrived
+    fn take_partial(&mut self) -> Option<String>;
+}
+
+/// Enum dispatch for the supported envelope formats
+#[enum_dispatch(Envelope)]
+#[derive(Debug)]
+pub enum LogEnvelope {
+    Docker(DockerUnwrapper),
+    Cri(CriUnwrapper),
+}
+
+/// Adds metadata fields to a record, keeping fields it has already
+pub fn attach_metadata(record: &mut Value, metadata: &Map<String, Value>) {
+    if let Value::Object(map) = record {
+        for (key, value) in metadata {
+            if !map.contains_key(key) {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_attach_keeps_existing_fields() {
+        let metadata = json!({"docker": {"stream": "stderr"}, "pod": "ops"});
+        let Value::Object(metadata) = metadata else {
+            unreachable!()
+        };
+        let mut record = json!({"msg": "Docked", "pod": "promenade"});
+        let mut array = json!(["Sisko"]);
+
+        attach_metadata(&mut record, &metadata);
+        attach_metadata(&mut array, &metadata);
+
+        assert_eq!(
+            record,
+            json!({"msg": "Docked", "pod": "promenade", "docker": {"stream": "stderr"}})
+        );
+        assert_eq!(array, json!(["Sisko"]));
+    }
+}
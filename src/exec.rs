@@ -0,0 +1,75 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::source::{ChannelReader, forward_lines};
+use std::io::{self, ErrorKind};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+/// Runs `command` with its stdout and stderr captured, returning the child to
+/// wait for and a reader over the lines of both streams.
+///
+/// Every line starts with `[stdout] ` or `[stderr] `, the form the default
+/// `--strip-prefix` pattern turns into a field, so the stream each line came
+/// from isn't lost like with `cmd 2>&1 | jlif`. The reader ends once the
+/// command closed both streams, usually by exiting.
+pub fn spawn(command: &[String]) -> io::Result<(Child, ChannelReader)> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(io::Error::new(ErrorKind::InvalidInput, "no command given"));
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let (sender, reader) = ChannelReader::new();
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stdout_sender = sender.clone();
+    thread::spawn(move || forward_lines(stdout, Some("[stdout] ".to_string()), stdout_sender));
+    thread::spawn(move || forward_lines(stderr, Some("[stderr] ".to_string()), sender));
+
+    Ok((child, reader))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn command(script: &str) -> Vec<String> {
+        ["sh", "-c", script].map(String::from).to_vec()
+    }
+
+    #[test]
+    fn test_marks_lines_with_their_stream() {
+        let (mut child, mut reader) = spawn(&command(
+            "echo '{\"msg\": \"Docked\"}'; echo 'Hull breach' >&2; exit 3",
+        ))
+        .unwrap();
+        let mut output = String::new();
+
+        reader.read_to_string(&mut output).unwrap();
+
+        let mut lines: Vec<&str> = output.lines().collect();
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec!["[stderr] Hull breach", "[stdout] {\"msg\": \"Docked\"}"]
+        );
+        assert_eq!(child.wait().unwrap().code(), Some(3));
+    }
+
+    #[test]
+    fn test_reports_missing_command() {
+        let kind = |command: &[String]| spawn(command).err().map(|e| e.kind());
+
+        assert_eq!(
+            kind(&["jlif-no-such-command".to_string()]),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(kind(&[]), Some(ErrorKind::InvalidInput));
+    }
+}
@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// A dotted path into a JSON value, e.g. `http.request.method` or `items.0.id`.
+///
+/// Segments address object keys. When the current value is an array, a numeric
+/// segment is used as an index instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPath {
+    segments: Vec<String>,
+}
+
+impl FieldPath {
+    pub fn parse(path: &str) -> Self {
+        Self {
+            segments: path.split('.').map(str::to_string).collect(),
+        }
+    }
+
+    /// Looks up the value at this path, if present
+    pub fn get<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(value, |current, segment| match current {
+                Value::Object(map) => map.get(segment),
+                Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            })
+    }
+
+    /// Looks up the value at this path for modification, if present
+    pub fn get_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        self.segments
+            .iter()
+            .try_fold(value, |current, segment| match current {
+                Value::Object(map) => map.get_mut(segment),
+                Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+                _ => None,
+            })
+    }
+
+    /// Sets the value at this path, creating missing objects along the way.
+    ///
+    /// Anything in the way that isn't an object is replaced by one.
+    pub fn insert(&self, target: &mut Value, value: Value) {
+        let mut current = target;
+        for segment in &self.segments {
+            if !current.is_object() {
+                *current = Value::Object(Map::new());
+            }
+            let Value::Object(map) = current else {
+                unreachable!()
+            };
+            current = map.entry(segment.clone()).or_insert(Value::Null);
+        }
+        *current = value;
+    }
+
+    /// Removes the value at this path, keeping the order of the remaining keys
+    pub fn remove(&self, target: &mut Value) -> Option<Value> {
+        let (parent_path, last) = self.split_last();
+        match parent_path.get_mut(target)? {
+            Value::Object(map) => map.shift_remove(last),
+            Value::Array(items) => {
+                let index = last.parse::<usize>().ok().filter(|&i| i < items.len())?;
+                Some(items.remove(index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the path has no segments, addressing the value itself
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Splits the path into the path of its parent and the last segment
+    pub fn split_last(&self) -> (FieldPath, &str) {
+        match self.segments.split_last() {
+            Some((last, parent)) => (
+                FieldPath {
+                    segments: parent.to_vec(),
+                },
+                last,
+            ),
+            None => (FieldPath { segments: vec![] }, ""),
+        }
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.segments.join("."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("captain", Some(json!("Sisko")))]
+    #[case("station.name", Some(json!("Deep Space Nine")))]
+    #[case("crew.1.name", Some(json!("Kira")))]
+    #[case("crew.7.name", None)]
+    #[case("station.sector", None)]
+    #[case("captain.name", None)]
+    fn test_get(#[case] path: &str, #[case] expected: Option<Value>) {
+        let value = json!({
+            "captain": "Sisko",
+            "station": {"name": "Deep Space Nine"},
+            "crew": [{"name": "Odo"}, {"name": "Kira"}],
+        });
+
+        assert_eq!(FieldPath::parse(path).get(&value), expected.as_ref());
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut value = json!({"station": {"name": "Terok Nor"}});
+
+        *FieldPath::parse("station.name")
+            .get_mut(&mut value)
+            .unwrap() = json!("Deep Space Nine");
+
+        assert_eq!(value, json!({"station": {"name": "Deep Space Nine"}}));
+        assert!(
+            FieldPath::parse("station.owner")
+                .get_mut(&mut value)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_insert_creates_missing_objects() {
+        let mut value = json!({"station": {"name": "Deep Space Nine"}, "sector": 9});
+
+        FieldPath::parse("station.commander").insert(&mut value, json!("Sisko"));
+        FieldPath::parse("sector.quadrant").insert(&mut value, json!("Alpha"));
+        FieldPath::parse("wormhole.stable").insert(&mut value, json!(true));
+
+        assert_eq!(
+            value,
+            json!({
+                "station": {"name": "Deep Space Nine", "commander": "Sisko"},
+                "sector": {"quadrant": "Alpha"},
+                "wormhole": {"stable": true},
+            })
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut value = json!({
+            "captain": "Sisko",
+            "station": {"name": "Deep Space Nine", "owner": "Bajor", "sector": 9},
+            "crew": ["Odo", "Kira", "Dax"],
+        });
+
+        assert_eq!(
+            FieldPath::parse("station.owner").remove(&mut value),
+            Some(json!("Bajor"))
+        );
+        assert_eq!(
+            FieldPath::parse("crew.1").remove(&mut value),
+            Some(json!("Kira"))
+        );
+        assert_eq!(FieldPath::parse("crew.7").remove(&mut value), None);
+        assert_eq!(FieldPath::parse("captain.rank").remove(&mut value), None);
+        assert_eq!(FieldPath::parse("doctor").remove(&mut value), None);
+
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"captain":"Sisko","station":{"name":"Deep Space Nine","sector":9},"crew":["Odo","Dax"]}"#
+        );
+    }
+
+    #[test]
+    fn test_split_last() {
+        let path = FieldPath::parse("station.crew.captain");
+        let (parent, last) = path.split_last();
+
+        assert_eq!(parent, FieldPath::parse("station.crew"));
+        assert_eq!(last, "captain");
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        assert_eq!(FieldPath::parse("a.b.0").to_string(), "a.b.0");
+    }
+}
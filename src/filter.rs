@@ -3,8 +3,19 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::buffer::BufferResult;
+use crate::field_path::FieldPath;
+use crate::level::{Level, LevelMap};
+use crate::timestamp::TimeFormats;
 use enum_dispatch::enum_dispatch;
+use jaq_core::load::{Arena, File, Loader};
+use jaq_core::{Compiler, Ctx, Native, RcIter, ValT};
+use jaq_json::Val;
+use jiff::Timestamp;
+use memchr::memmem::Finder;
 use regex::Regex;
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Range;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -15,6 +26,8 @@ pub enum FormatterError {
         #[source]
         source: regex::Error,
     },
+    #[error("Invalid query '{query}': {message}")]
+    InvalidQuery { query: String, message: String },
 }
 
 #[derive(Error, Debug)]
@@ -41,7 +54,7 @@ impl<'a> TryFrom<&'a BufferResult> for FilterInput<'a> {
 
     fn try_from(result: &'a BufferResult) -> Result<Self, Self::Error> {
         match result {
-            BufferResult::Json(value) => Ok(FilterInput::Json(value)),
+            BufferResult::Json(value, _) => Ok(FilterInput::Json(value)),
             BufferResult::Text(text) => Ok(FilterInput::Text(text)),
             BufferResult::Incomplete(_) => Err(ConversionError::IncompleteResult),
         }
@@ -67,6 +80,20 @@ pub trait Filter {
 
     /// Returns true if this filter will potentially suppress content
     fn is_active(&self) -> bool;
+
+    /// Returns true if the content is to be output, which inactive filters
+    /// never suppress
+    fn passes(&self, input: &FilterInput) -> bool {
+        !self.is_active() || self.matches(input)
+    }
+
+    /// Returns the byte ranges of rendered output this filter matched, for highlighting
+    ///
+    /// `input` is what `rendered` was rendered from. Only pattern based filters
+    /// have something to point at, the default is none.
+    fn match_ranges(&self, _input: &FilterInput, _rendered: &str) -> Vec<Range<usize>> {
+        Vec::new()
+    }
 }
 
 /// No-op filter that passes all content through
@@ -132,6 +159,10 @@ impl Filter for JsonOnlyFilter {
     fn is_active(&self) -> bool {
         true
     }
+
+    fn match_ranges(&self, input: &FilterInput, rendered: &str) -> Vec<Range<usize>> {
+        self.inner_filter.match_ranges(input, rendered)
+    }
 }
 
 /// Regex-based filter with case sensitivity control
@@ -175,6 +206,462 @@ impl Filter for RegexFilter {
     fn is_active(&self) -> bool {
         true
     }
+
+    fn match_ranges(&self, _input: &FilterInput, rendered: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(rendered)
+            .map(|found| found.range())
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+}
+
+/// Text pattern content has to contain
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Regular expression
+    Regex(String),
+    /// String taken as is, see [`LiteralFilter`]
+    Literal(String),
+    /// Pattern searched in the value of a field only, see [`FieldScopeFilter`]
+    Field(String, Box<Pattern>),
+}
+
+/// Filter matching content containing a literal string, like `grep -F`
+///
+/// The pattern is taken as is, so characters like `[` or `.` need no
+/// escaping. Without case sensitivity, ASCII letters match regardless of
+/// their case, other characters have to match exactly.
+///
+/// Text lines are searched as they are. JSON records are searched in the
+/// same compact form the regex filter sees, serialized into a buffer that is
+/// reused for every record, like the case folded copy of text lines.
+#[derive(Debug)]
+pub struct LiteralFilter {
+    finder: Box<Finder<'static>>,
+    case_sensitive: bool,
+    haystack: RefCell<Vec<u8>>,
+}
+
+impl LiteralFilter {
+    pub fn new(pattern: &str, case_sensitive: bool) -> Self {
+        let finder = if case_sensitive {
+            Finder::new(pattern).into_owned()
+        } else {
+            Finder::new(&pattern.to_ascii_lowercase()).into_owned()
+        };
+        Self {
+            finder: Box::new(finder),
+            case_sensitive,
+            haystack: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl Filter for LiteralFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        if let (FilterInput::Text(text), true) = (input, self.case_sensitive) {
+            return self.finder.find(text.as_bytes()).is_some();
+        }
+
+        let mut haystack = self.haystack.borrow_mut();
+        haystack.clear();
+        match input {
+            FilterInput::Json(value) => {
+                if serde_json::to_writer(&mut *haystack, value).is_err() {
+                    return false;
+                }
+            }
+            FilterInput::Text(text) => haystack.extend_from_slice(text.as_bytes()),
+        }
+        if !self.case_sensitive {
+            haystack.make_ascii_lowercase();
+        }
+
+        self.finder.find(&haystack).is_some()
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+
+    fn match_ranges(&self, _input: &FilterInput, rendered: &str) -> Vec<Range<usize>> {
+        // ASCII case folding keeps byte offsets intact
+        let folded;
+        let haystack = if self.case_sensitive {
+            rendered
+        } else {
+            folded = rendered.to_ascii_lowercase();
+            &folded
+        };
+        let length = self.finder.needle().len();
+        self.finder
+            .find_iter(haystack.as_bytes())
+            .map(|start| start..start + length)
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+}
+
+/// Structured filter evaluating a jq expression against parsed JSON
+///
+/// A record matches if the expression yields at least one truthy value
+/// (anything but `false` and `null`). Expressions that fail at runtime, e.g.
+/// comparing incompatible types, count as no match. Text never matches.
+pub struct QueryFilter {
+    query: String,
+    filter: jaq_core::Filter<Native<Val>>,
+}
+
+impl QueryFilter {
+    pub fn new(query: String) -> Result<Self, FormatterError> {
+        let invalid = |message: String| FormatterError::InvalidQuery {
+            query: query.clone(),
+            message,
+        };
+
+        let program = File {
+            code: query.as_str(),
+            path: (),
+        };
+        let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+        let arena = Arena::default();
+        let modules = loader.load(&arena, program).map_err(|errors| {
+            invalid(
+                errors
+                    .into_iter()
+                    .map(|(_, error)| describe_load_error(error))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        })?;
+
+        let filter = Compiler::default()
+            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+            .compile(modules)
+            .map_err(|errors| {
+                invalid(
+                    errors
+                        .into_iter()
+                        .flat_map(|(_, undefined)| undefined)
+                        .map(|(name, kind)| format!("undefined {} '{}'", kind.as_str(), name))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+            })?;
+
+        Ok(Self { query, filter })
+    }
+}
+
+fn describe_load_error(error: jaq_core::load::Error<&str>) -> String {
+    use jaq_core::load::Error;
+
+    let describe = |expected: &str, found: &str| match found {
+        "" => format!("expected {} at end of query", expected),
+        found => format!("expected {} before '{}'", expected, found),
+    };
+    match error {
+        Error::Io(errors) => errors
+            .into_iter()
+            .map(|(path, message)| format!("cannot load '{}': {}", path, message))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Error::Lex(errors) => errors
+            .into_iter()
+            .map(|(expected, found)| describe(expected.as_str(), found))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Error::Parse(errors) => errors
+            .into_iter()
+            .map(|(expected, found)| describe(expected.as_str(), found))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+impl fmt::Debug for QueryFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryFilter")
+            .field("query", &self.query)
+            .finish()
+    }
+}
+
+impl Filter for QueryFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        match input {
+            FilterInput::Json(value) => {
+                let inputs = RcIter::new(core::iter::empty());
+                let value = Val::from((*value).clone());
+                self.filter
+                    .run((Ctx::new([], &inputs), value))
+                    .any(|output| output.is_ok_and(|output| output.as_bool()))
+            }
+            FilterInput::Text(_) => false,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+}
+
+/// Filter comparing a field of parsed JSON with an expected value
+///
+/// Strings are compared as is, other values through their JSON representation,
+/// so `status=500` matches both `500` and `"500"`. Missing fields and text never
+/// match.
+#[derive(Debug)]
+pub struct FieldFilter {
+    path: FieldPath,
+    expected: String,
+    // The expected value read as JSON, for comparing numbers, booleans and null
+    expected_json: Option<serde_json::Value>,
+}
+
+impl FieldFilter {
+    pub fn new(path: &str, expected: String) -> Self {
+        Self {
+            path: FieldPath::parse(path),
+            expected_json: serde_json::from_str(&expected).ok(),
+            expected,
+        }
+    }
+}
+
+impl Filter for FieldFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        match input {
+            FilterInput::Json(value) => match self.path.get(value) {
+                Some(serde_json::Value::String(actual)) => *actual == self.expected,
+                Some(actual) => self.expected_json.as_ref() == Some(actual),
+                None => false,
+            },
+            FilterInput::Text(_) => false,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+}
+
+/// Filter applying a pattern filter to the value of one field instead of the
+/// whole serialized record, so keys and other fields can't match by accident
+///
+/// String values are searched without their quotes, other values as compact
+/// JSON. Records without the field never match, text lines are searched as a
+/// whole, as they have no fields to pick from.
+#[derive(Debug)]
+pub struct FieldScopeFilter {
+    path: FieldPath,
+    inner_filter: Box<OutputFilter>,
+}
+
+impl FieldScopeFilter {
+    pub fn new(path: &str, inner_filter: OutputFilter) -> Self {
+        Self {
+            path: FieldPath::parse(path),
+            inner_filter: Box::new(inner_filter),
+        }
+    }
+}
+
+impl Filter for FieldScopeFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        match input {
+            FilterInput::Json(value) => match self.path.get(value) {
+                Some(serde_json::Value::String(text)) => {
+                    self.inner_filter.matches(&FilterInput::Text(text))
+                }
+                Some(value) => self
+                    .inner_filter
+                    .matches(&FilterInput::Text(&value.to_string())),
+                None => false,
+            },
+            FilterInput::Text(_) => self.inner_filter.matches(input),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+
+    fn match_ranges(&self, input: &FilterInput, rendered: &str) -> Vec<Range<usize>> {
+        let FilterInput::Json(value) = input else {
+            return self.inner_filter.match_ranges(input, rendered);
+        };
+        let Some(field) = self.path.get(value) else {
+            return Vec::new();
+        };
+
+        // Only matches inside the rendered field value count, which shows up
+        // as is in templates and JSON-encoded in records
+        let text = match field {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let encoded = field.to_string();
+        let spans: Vec<Range<usize>> = [text.as_str(), encoded.as_str()]
+            .into_iter()
+            .filter(|needle| !needle.is_empty())
+            .flat_map(|needle| {
+                rendered
+                    .match_indices(needle)
+                    .map(|(start, found)| start..start + found.len())
+            })
+            .collect();
+        self.inner_filter
+            .match_ranges(&FilterInput::Text(&text), rendered)
+            .into_iter()
+            .filter(|range| {
+                spans
+                    .iter()
+                    .any(|span| span.start <= range.start && range.end <= span.end)
+            })
+            .collect()
+    }
+}
+
+/// Filter passing JSON records that have a field, whatever its value
+#[derive(Debug)]
+pub struct HasFieldFilter {
+    path: FieldPath,
+}
+
+impl HasFieldFilter {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: FieldPath::parse(path),
+        }
+    }
+}
+
+impl Filter for HasFieldFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        match input {
+            FilterInput::Json(value) => self.path.get(value).is_some(),
+            FilterInput::Text(_) => false,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+}
+
+/// Filter passing records at or above a minimum log level
+///
+/// JSON records are checked through their common level fields, text lines
+/// through a level keyword near their start. Content without a recognizable
+/// level never matches.
+#[derive(Debug)]
+pub struct LevelFilter {
+    min_level: Level,
+    level_map: LevelMap,
+}
+
+impl LevelFilter {
+    pub fn new(min_level: Level, level_map: LevelMap) -> Self {
+        Self {
+            min_level,
+            level_map,
+        }
+    }
+}
+
+impl Filter for LevelFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        let level = match input {
+            FilterInput::Json(value) => self.level_map.detect(value),
+            FilterInput::Text(text) => self.level_map.detect_text(text),
+        };
+        level.is_some_and(|level| level >= self.min_level)
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+}
+
+/// Filter passing records whose timestamp lies within a time range
+///
+/// The timestamp is read from the first of the fields that holds one, see
+/// [`TimeFormats::parse`]. Both bounds are inclusive. Records without a
+/// timestamp and text never match.
+#[derive(Debug)]
+pub struct TimeRangeFilter {
+    fields: Vec<FieldPath>,
+    formats: TimeFormats,
+    since: Option<Timestamp>,
+    until: Option<Timestamp>,
+}
+
+impl TimeRangeFilter {
+    pub fn new(fields: &[String], since: Option<Timestamp>, until: Option<Timestamp>) -> Self {
+        Self {
+            fields: fields.iter().map(|field| FieldPath::parse(field)).collect(),
+            formats: TimeFormats::default(),
+            since,
+            until,
+        }
+    }
+
+    /// Also reads timestamp strings in these formats
+    pub fn with_time_formats(mut self, formats: TimeFormats) -> Self {
+        self.formats = formats;
+        self
+    }
+}
+
+impl Filter for TimeRangeFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        let FilterInput::Json(value) = input else {
+            return false;
+        };
+        let time = self
+            .fields
+            .iter()
+            .find_map(|field| field.get(value).and_then(|time| self.formats.parse(time)));
+        time.is_some_and(|time| {
+            self.since.is_none_or(|since| time >= since)
+                && self.until.is_none_or(|until| time <= until)
+        })
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+}
+
+/// Filter that only passes content matching all of its inner filters
+#[derive(Debug)]
+pub struct AllFilter {
+    filters: Vec<OutputFilter>,
+}
+
+impl AllFilter {
+    pub fn new(filters: Vec<OutputFilter>) -> Self {
+        Self { filters }
+    }
+}
+
+impl Filter for AllFilter {
+    fn matches(&self, input: &FilterInput) -> bool {
+        self.filters.iter().all(|filter| filter.matches(input))
+    }
+
+    fn is_active(&self) -> bool {
+        self.filters.iter().any(|filter| filter.is_active())
+    }
+
+    fn match_ranges(&self, input: &FilterInput, rendered: &str) -> Vec<Range<usize>> {
+        self.filters
+            .iter()
+            .flat_map(|filter| filter.match_ranges(input, rendered))
+            .collect()
+    }
 }
 
 /// Enum dispatch for different filter implementations
@@ -183,50 +670,93 @@ impl Filter for RegexFilter {
 pub enum OutputFilter {
     None(NoFilter),
     Regex(RegexFilter),
+    Literal(LiteralFilter),
     JsonOnly(JsonOnlyFilter),
     Invert(InvertFilter),
+    Query(QueryFilter),
+    Field(FieldFilter),
+    FieldScope(FieldScopeFilter),
+    HasField(HasFieldFilter),
+    Level(LevelFilter),
+    TimeRange(TimeRangeFilter),
+    All(AllFilter),
 }
 
 impl OutputFilter {
     /// Creates a new OutputFilter from CLI arguments
     ///
+    /// Pattern, query, field conditions and level all have to match if several
+    /// are given.
+    ///
     /// # Arguments
-    /// * `pattern` - Optional regex pattern string. If None, returns NoFilter
-    /// * `case_sensitive` - Whether the regex should be case sensitive
+    /// * `pattern` - Optional regex or literal pattern. If None, returns NoFilter
+    /// * `case_sensitive` - Whether the pattern should be case sensitive
+    /// * `query` - Optional jq expression
+    /// * `conditions` - Field path and value pairs that JSON records must equal
+    /// * `level` - Optional minimum level filter
     /// * `json_only` - Whether to suppress all non-JSON output
     /// * `invert_match` - Whether to invert the filter behavior
     ///
     /// # Returns
     /// * `Ok(OutputFilter)` - Successfully created filter
-    /// * `Err(FormatterError)` - Invalid regex pattern
+    /// * `Err(FormatterError)` - Invalid regex pattern or query
     pub fn from_args(
-        pattern: Option<String>,
+        pattern: Option<Pattern>,
         case_sensitive: bool,
+        query: Option<String>,
+        conditions: Vec<(String, String)>,
+        level: Option<LevelFilter>,
         json_only: bool,
         invert_match: bool,
     ) -> Result<Self, FormatterError> {
-        let base_filter = match pattern {
-            Some(pattern_str) => {
-                let regex_filter = RegexFilter::new(pattern_str, case_sensitive)?;
-                OutputFilter::Regex(regex_filter)
-            }
-            None => OutputFilter::None(NoFilter),
+        let mut filters = Vec::new();
+        if let Some(pattern) = pattern {
+            filters.push(Self::from_pattern(pattern, case_sensitive)?);
+        }
+        if let Some(query) = query {
+            filters.push(OutputFilter::Query(QueryFilter::new(query)?));
+        }
+        filters.extend(
+            conditions
+                .into_iter()
+                .map(|(path, expected)| OutputFilter::Field(FieldFilter::new(&path, expected))),
+        );
+        filters.extend(level.map(OutputFilter::Level));
+
+        let base_filter = match filters.len() {
+            0 => OutputFilter::None(NoFilter),
+            1 => filters.remove(0),
+            _ => OutputFilter::All(AllFilter::new(filters)),
         };
 
-        let filter_with_json_only = if json_only {
-            OutputFilter::JsonOnly(JsonOnlyFilter::new(base_filter))
+        // Only the conditions are inverted, non-JSON content stays suppressed
+        let filter = if invert_match {
+            OutputFilter::Invert(InvertFilter::new(base_filter))
         } else {
             base_filter
         };
 
-        if invert_match {
-            Ok(OutputFilter::Invert(InvertFilter::new(
-                filter_with_json_only,
-            )))
+        if json_only {
+            Ok(OutputFilter::JsonOnly(JsonOnlyFilter::new(filter)))
         } else {
-            Ok(filter_with_json_only)
+            Ok(filter)
         }
     }
+
+    fn from_pattern(pattern: Pattern, case_sensitive: bool) -> Result<Self, FormatterError> {
+        Ok(match pattern {
+            Pattern::Regex(pattern_str) => {
+                OutputFilter::Regex(RegexFilter::new(pattern_str, case_sensitive)?)
+            }
+            Pattern::Literal(pattern_str) => {
+                OutputFilter::Literal(LiteralFilter::new(&pattern_str, case_sensitive))
+            }
+            Pattern::Field(path, pattern) => OutputFilter::FieldScope(FieldScopeFilter::new(
+                &path,
+                Self::from_pattern(*pattern, case_sensitive)?,
+            )),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -234,9 +764,19 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_inactive_filter_passes_everything() {
+        // Inverting no pattern leaves nothing to suppress
+        let filter = OutputFilter::Invert(InvertFilter::new(OutputFilter::None(NoFilter)));
+        let input = FilterInput::Text("Klingon proverb");
+
+        assert!(!filter.matches(&input));
+        assert!(filter.passes(&input));
+    }
+
     #[test]
     fn test_filter_input_conversion_json() {
-        let buffer_result = BufferResult::Json(json!({"test": "value"}));
+        let buffer_result = BufferResult::Json(json!({"test": "value"}), 16);
         let filter_input = FilterInput::try_from(&buffer_result).unwrap();
 
         match filter_input {
@@ -312,6 +852,66 @@ mod tests {
         assert!(!filter.matches(&text_no_match));
     }
 
+    #[test]
+    fn test_literal_filter_takes_pattern_as_is() {
+        let filter = OutputFilter::Literal(LiteralFilter::new("[docking.bay]", true));
+
+        assert!(filter.matches(&FilterInput::Text("Sisko at [docking.bay] 5")));
+        assert!(!filter.matches(&FilterInput::Text("Sisko at docking-bay 5")));
+        assert!(!filter.matches(&FilterInput::Text("Sisko at [DOCKING.BAY] 5")));
+        assert!(filter.matches(&FilterInput::Json(&json!({"location": "[docking.bay]"}))));
+    }
+
+    #[test]
+    fn test_literal_filter_case_insensitive() {
+        let filter = OutputFilter::Literal(LiteralFilter::new("Sisko", false));
+
+        assert!(filter.matches(&FilterInput::Text("CAPTAIN SISKO")));
+        assert!(filter.matches(&FilterInput::Json(&json!({"captain": "sisko"}))));
+        assert!(!filter.matches(&FilterInput::Text("Captain Kira")));
+        assert!(filter.matches(&FilterInput::Text("Captain Sisko")));
+        let rendered = "sisko meets SISKO";
+        assert_eq!(
+            filter.match_ranges(&FilterInput::Text(rendered), rendered),
+            vec![0..5, 12..17]
+        );
+    }
+
+    #[test]
+    fn test_literal_filter_folds_ascii_case_only() {
+        let filter = OutputFilter::Literal(LiteralFilter::new("Ödo", false));
+
+        assert!(filter.matches(&FilterInput::Text("constable Ödo")));
+        assert!(filter.matches(&FilterInput::Text("constable ÖDO")));
+        assert!(!filter.matches(&FilterInput::Text("constable ödo")));
+    }
+
+    #[test]
+    fn test_field_scope_filter_ignores_other_fields() {
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Field(
+                "ship.name".to_string(),
+                Box::new(Pattern::Regex("^defiant$".to_string())),
+            )),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let docked = json!({"ship": {"name": "Defiant"}, "msg": "Docked"});
+        let escorted = json!({"ship": {"name": "Rio Grande"}, "escort": "Defiant"});
+        let registry = json!({"ship": {"name": 74205}});
+
+        assert!(filter.matches(&FilterInput::Json(&docked)));
+        assert!(!filter.matches(&FilterInput::Json(&escorted)));
+        assert!(!filter.matches(&FilterInput::Json(&registry)));
+        assert!(!filter.matches(&FilterInput::Json(&json!({"msg": "defiant"}))));
+        assert!(filter.matches(&FilterInput::Text("Defiant")));
+    }
+
     #[test]
     fn test_regex_filter_json_content_matching() {
         let filter = OutputFilter::Regex(RegexFilter::new("sisko".to_string(), false).unwrap());
@@ -344,18 +944,47 @@ mod tests {
     #[test]
     fn test_from_args_creates_correct_filter() {
         // No pattern creates NoFilter
-        let no_filter = OutputFilter::from_args(None, false, false, false).unwrap();
+        let no_filter =
+            OutputFilter::from_args(None, false, None, vec![], None, false, false).unwrap();
         assert!(!no_filter.is_active());
 
+        let literal_filter = OutputFilter::from_args(
+            Some(Pattern::Literal("[".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        assert!(matches!(literal_filter, OutputFilter::Literal(_)));
+
         // Pattern creates RegexFilter
-        let regex_filter =
-            OutputFilter::from_args(Some("test".to_string()), true, false, false).unwrap();
+        let regex_filter = OutputFilter::from_args(
+            Some(Pattern::Regex("test".to_string())),
+            true,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
         assert!(regex_filter.is_active());
     }
 
     #[test]
     fn test_from_args_invalid_regex() {
-        let result = OutputFilter::from_args(Some("[".to_string()), false, false, false);
+        let result = OutputFilter::from_args(
+            Some(Pattern::Regex("[".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        );
         assert!(result.is_err());
 
         let error = result.unwrap_err();
@@ -363,12 +992,13 @@ mod tests {
             FormatterError::InvalidRegex { pattern, .. } => {
                 assert_eq!(pattern, "[");
             }
+            other => panic!("Expected InvalidRegex error, got {:?}", other),
         }
     }
 
     #[test]
     fn test_json_only_filter_standalone() {
-        let filter = OutputFilter::from_args(None, false, true, false).unwrap();
+        let filter = OutputFilter::from_args(None, false, None, vec![], None, true, false).unwrap();
 
         let json_value = json!({"test": "data"});
         let json_input = FilterInput::Json(&json_value);
@@ -381,8 +1011,16 @@ mod tests {
 
     #[test]
     fn test_json_only_filter_with_regex() {
-        let filter =
-            OutputFilter::from_args(Some("error".to_string()), false, true, false).unwrap();
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex("error".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            true,
+            false,
+        )
+        .unwrap();
 
         let json_match_value = json!({"status": "error"});
         let json_no_match_value = json!({"status": "ok"});
@@ -402,27 +1040,54 @@ mod tests {
     #[test]
     fn test_from_args_combinations() {
         // No filter, no json-only
-        let filter1 = OutputFilter::from_args(None, false, false, false).unwrap();
+        let filter1 =
+            OutputFilter::from_args(None, false, None, vec![], None, false, false).unwrap();
         assert!(!filter1.is_active());
 
         // Regex filter, no json-only
-        let filter2 =
-            OutputFilter::from_args(Some("test".to_string()), true, false, false).unwrap();
+        let filter2 = OutputFilter::from_args(
+            Some(Pattern::Regex("test".to_string())),
+            true,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
         assert!(filter2.is_active());
 
         // No filter, json-only
-        let filter3 = OutputFilter::from_args(None, false, true, false).unwrap();
+        let filter3 =
+            OutputFilter::from_args(None, false, None, vec![], None, true, false).unwrap();
         assert!(filter3.is_active());
 
         // Regex filter + json-only
-        let filter4 = OutputFilter::from_args(Some("test".to_string()), true, true, false).unwrap();
+        let filter4 = OutputFilter::from_args(
+            Some(Pattern::Regex("test".to_string())),
+            true,
+            None,
+            vec![],
+            None,
+            true,
+            false,
+        )
+        .unwrap();
         assert!(filter4.is_active());
     }
 
     #[test]
     fn test_invert_filter_basic() {
-        let filter =
-            OutputFilter::from_args(Some("error".to_string()), false, false, true).unwrap();
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex("error".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            true,
+        )
+        .unwrap();
 
         let json_match_value = json!({"status": "error"});
         let json_no_match_value = json!({"status": "ok"});
@@ -442,7 +1107,7 @@ mod tests {
     #[test]
     fn test_invert_filter_with_no_pattern() {
         // Inverting NoFilter should still be active but invert everything
-        let filter = OutputFilter::from_args(None, false, false, true).unwrap();
+        let filter = OutputFilter::from_args(None, false, None, vec![], None, false, true).unwrap();
 
         let json_value = json!({"test": "data"});
         let json_input = FilterInput::Json(&json_value);
@@ -456,7 +1121,16 @@ mod tests {
 
     #[test]
     fn test_invert_filter_with_json_only() {
-        let filter = OutputFilter::from_args(Some("error".to_string()), false, true, true).unwrap();
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex("error".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            true,
+            true,
+        )
+        .unwrap();
 
         let json_match_value = json!({"status": "error"});
         let json_no_match_value = json!({"status": "ok"});
@@ -468,17 +1142,33 @@ mod tests {
         // With json-only + invert: text is always suppressed, JSON is inverted
         assert!(!filter.matches(&json_match)); // JSON error matches, so inverted = false
         assert!(filter.matches(&json_no_match)); // JSON ok doesn't match, so inverted = true
-        assert!(filter.matches(&text_match)); // Text is suppressed by json-only, so inverted = true
-        assert!(filter.matches(&text_no_match)); // Text is suppressed by json-only, so inverted = true
+        assert!(!filter.matches(&text_match)); // Text is suppressed by json-only
+        assert!(!filter.matches(&text_no_match)); // Text is suppressed by json-only
         assert!(filter.is_active());
     }
 
     #[test]
     fn test_invert_filter_case_sensitivity() {
-        let case_sensitive_filter =
-            OutputFilter::from_args(Some("ERROR".to_string()), true, false, true).unwrap();
-        let case_insensitive_filter =
-            OutputFilter::from_args(Some("ERROR".to_string()), false, false, true).unwrap();
+        let case_sensitive_filter = OutputFilter::from_args(
+            Some(Pattern::Regex("ERROR".to_string())),
+            true,
+            None,
+            vec![],
+            None,
+            false,
+            true,
+        )
+        .unwrap();
+        let case_insensitive_filter = OutputFilter::from_args(
+            Some(Pattern::Regex("ERROR".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            true,
+        )
+        .unwrap();
 
         let text_upper = FilterInput::Text("ERROR occurred");
         let text_lower = FilterInput::Text("error occurred");
@@ -495,16 +1185,305 @@ mod tests {
     #[test]
     fn test_invert_filter_combinations() {
         // Test all combinations with invert_match = true
-        let filter1 = OutputFilter::from_args(None, false, false, true).unwrap();
+        let filter1 =
+            OutputFilter::from_args(None, false, None, vec![], None, false, true).unwrap();
         assert!(!filter1.is_active()); // NoFilter inverted is still not active
 
-        let filter2 = OutputFilter::from_args(Some("test".to_string()), true, false, true).unwrap();
+        let filter2 = OutputFilter::from_args(
+            Some(Pattern::Regex("test".to_string())),
+            true,
+            None,
+            vec![],
+            None,
+            false,
+            true,
+        )
+        .unwrap();
         assert!(filter2.is_active());
 
-        let filter3 = OutputFilter::from_args(None, false, true, true).unwrap();
+        let filter3 = OutputFilter::from_args(None, false, None, vec![], None, true, true).unwrap();
         assert!(filter3.is_active());
 
-        let filter4 = OutputFilter::from_args(Some("test".to_string()), true, true, true).unwrap();
+        let filter4 = OutputFilter::from_args(
+            Some(Pattern::Regex("test".to_string())),
+            true,
+            None,
+            vec![],
+            None,
+            true,
+            true,
+        )
+        .unwrap();
         assert!(filter4.is_active());
     }
+
+    #[test]
+    fn test_query_filter_matches_structured_conditions() {
+        let filter = OutputFilter::Query(
+            QueryFilter::new(r#".level == "error" and .status >= 500"#.to_string()).unwrap(),
+        );
+
+        let outage_value = json!({"level": "error", "status": 503, "system": "replicator"});
+        let missing_value = json!({"level": "error", "status": 404});
+        let info_value = json!({"level": "info", "status": 500});
+        let outage = FilterInput::Json(&outage_value);
+        let missing = FilterInput::Json(&missing_value);
+        let info = FilterInput::Json(&info_value);
+
+        assert!(filter.matches(&outage));
+        assert!(!filter.matches(&missing));
+        assert!(!filter.matches(&info));
+        assert!(!filter.matches(&FilterInput::Text("level == error")));
+        assert!(filter.is_active());
+    }
+
+    #[test]
+    fn test_query_filter_truthiness() {
+        let crew_value = json!({"crew": ["Sisko", "Kira"], "cloaked": null});
+        let crew = FilterInput::Json(&crew_value);
+        let query = |query: &str| QueryFilter::new(query.to_string()).unwrap();
+
+        // Any truthy output is enough
+        assert!(query(r#".crew[] | select(. == "Kira")"#).matches(&crew));
+        assert!(query(".crew").matches(&crew));
+        assert!(!query(".cloaked").matches(&crew));
+        assert!(!query("empty").matches(&crew));
+        // Runtime errors don't match
+        assert!(!query(".crew.name").matches(&crew));
+    }
+
+    #[test]
+    fn test_query_filter_rejects_invalid_queries() {
+        match QueryFilter::new(".level ==".to_string()).unwrap_err() {
+            FormatterError::InvalidQuery { query, .. } => assert_eq!(query, ".level =="),
+            other => panic!("Expected InvalidQuery error, got {:?}", other),
+        }
+        assert!(QueryFilter::new("changeling(.)".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_from_args_combines_pattern_and_query() {
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex("station".to_string())),
+            false,
+            Some(".docked > 2".to_string()),
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+
+        let busy_value = json!({"station": "DS9", "docked": 3});
+        let quiet_value = json!({"station": "DS9", "docked": 1});
+        let ship_value = json!({"ship": "Defiant", "docked": 3});
+
+        assert!(filter.matches(&FilterInput::Json(&busy_value)));
+        assert!(!filter.matches(&FilterInput::Json(&quiet_value)));
+        assert!(!filter.matches(&FilterInput::Json(&ship_value)));
+    }
+
+    #[test]
+    fn test_field_filter_compares_values() {
+        let record = json!({
+            "level": "error",
+            "http": {"status": 503},
+            "cloaked": false,
+            "ship": {"name": "Defiant"}
+        });
+        let input = FilterInput::Json(&record);
+        let field = |path: &str, expected: &str| FieldFilter::new(path, expected.to_string());
+
+        assert!(field("level", "error").matches(&input));
+        assert!(!field("level", "ERROR").matches(&input));
+        assert!(field("http.status", "503").matches(&input));
+        assert!(field("cloaked", "false").matches(&input));
+        assert!(field("ship.name", "Defiant").matches(&input));
+        assert!(!field("ship", "Defiant").matches(&input));
+        assert!(!field("ship.registry", "NX-74205").matches(&input));
+        assert!(!field("level", "error").matches(&FilterInput::Text("level=error")));
+    }
+
+    #[test]
+    fn test_field_filter_compares_strings_with_number_representation() {
+        let record = json!({"status": "500"});
+
+        assert!(FieldFilter::new("status", "500".to_string()).matches(&FilterInput::Json(&record)));
+    }
+
+    #[test]
+    fn test_from_args_requires_all_conditions() {
+        let filter = OutputFilter::from_args(
+            None,
+            false,
+            None,
+            vec![
+                ("level".to_string(), "error".to_string()),
+                ("station".to_string(), "DS9".to_string()),
+            ],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+
+        let both_value = json!({"level": "error", "station": "DS9"});
+        let one_value = json!({"level": "error", "station": "Empok Nor"});
+
+        assert!(filter.matches(&FilterInput::Json(&both_value)));
+        assert!(!filter.matches(&FilterInput::Json(&one_value)));
+        assert!(filter.is_active());
+    }
+
+    #[test]
+    fn test_has_field_filter_passes_records_with_field() {
+        let filter = HasFieldFilter::new("http.status");
+
+        let present_value = json!({"http": {"status": null}});
+        let missing_value = json!({"http": {"method": "GET"}});
+
+        assert!(filter.matches(&FilterInput::Json(&present_value)));
+        assert!(!filter.matches(&FilterInput::Json(&missing_value)));
+        assert!(!filter.matches(&FilterInput::Text("http.status")));
+        assert!(filter.is_active());
+    }
+
+    #[test]
+    fn test_level_filter_passes_records_at_or_above_threshold() {
+        let filter = LevelFilter::new(Level::Warn, LevelMap::default());
+
+        let error_value = json!({"level": "error", "msg": "Warp core breach"});
+        let warn_value = json!({"severity": "WARNING"});
+        let pino_value = json!({"level": 30, "msg": "Docking clamps released"});
+        let unleveled_value = json!({"msg": "Quark's is open"});
+
+        assert!(filter.matches(&FilterInput::Json(&error_value)));
+        assert!(filter.matches(&FilterInput::Json(&warn_value)));
+        assert!(!filter.matches(&FilterInput::Json(&pino_value)));
+        assert!(!filter.matches(&FilterInput::Json(&unleveled_value)));
+        assert!(filter.matches(&FilterInput::Text("FATAL: hull breach on deck 5")));
+        assert!(!filter.matches(&FilterInput::Text("info: shift change")));
+        assert!(!filter.matches(&FilterInput::Text("Promenade quiet")));
+        assert!(filter.is_active());
+    }
+
+    #[test]
+    fn test_level_filter_uses_level_map() {
+        let level_map = LevelMap::new(vec![("sev=2".to_string(), Level::Error)]);
+        let filter = LevelFilter::new(Level::Error, level_map);
+        let record = json!({"level": "sev=2"});
+
+        assert!(filter.matches(&FilterInput::Json(&record)));
+    }
+
+    #[test]
+    fn test_time_range_filter_passes_records_within_range() {
+        let fields = ["timestamp".to_string(), "ts".to_string()];
+        let filter = TimeRangeFilter::new(
+            &fields,
+            Some("2024-05-01T12:00:00Z".parse().unwrap()),
+            Some("2024-05-01T13:00:00Z".parse().unwrap()),
+        );
+
+        let start_value = json!({"timestamp": "2024-05-01T12:00:00Z", "msg": "Docked"});
+        let epoch_value = json!({"ts": 1714566600, "msg": "Bar opens"});
+        let early_value = json!({"timestamp": "2024-05-01T13:59:59+02:00"});
+        let late_value = json!({"ts": 1714568400001_u64});
+        let untimed_value = json!({"msg": "Quark's is open"});
+
+        assert!(filter.matches(&FilterInput::Json(&start_value)));
+        assert!(filter.matches(&FilterInput::Json(&epoch_value)));
+        assert!(!filter.matches(&FilterInput::Json(&early_value)));
+        assert!(!filter.matches(&FilterInput::Json(&late_value)));
+        assert!(!filter.matches(&FilterInput::Json(&untimed_value)));
+        assert!(!filter.matches(&FilterInput::Text("2024-05-01T12:30:00Z Docked")));
+        assert!(filter.is_active());
+    }
+
+    #[test]
+    fn test_time_range_filter_with_open_end() {
+        let filter = TimeRangeFilter::new(
+            &["ts".to_string()],
+            Some("2024-05-01T12:00:00Z".parse().unwrap()),
+            None,
+        );
+        let record = json!({"ts": "2375-03-12T08:00:00Z"});
+
+        assert!(filter.matches(&FilterInput::Json(&record)));
+    }
+
+    #[test]
+    fn test_match_ranges() {
+        let record = json!({"ship": "Defiant", "captain": "Sisko"});
+        let input = FilterInput::Json(&record);
+        let rendered = r#"{"ship":"Defiant","captain":"Sisko"}"#;
+
+        let regex = OutputFilter::from_args(
+            Some(Pattern::Regex("defiant|sisko".to_string())),
+            false,
+            Some(".ship".to_string()),
+            vec![],
+            None,
+            true,
+            false,
+        )
+        .unwrap();
+        assert_eq!(regex.match_ranges(&input, rendered), vec![9..16, 29..34]);
+
+        // Inverted filters output what didn't match, there is nothing to point at
+        let inverted = OutputFilter::from_args(
+            Some(Pattern::Regex("sisko".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            true,
+        )
+        .unwrap();
+        assert!(inverted.match_ranges(&input, rendered).is_empty());
+
+        // Empty matches are not worth highlighting
+        let empty = OutputFilter::Regex(RegexFilter::new("x*".to_string(), false).unwrap());
+        assert!(empty.match_ranges(&input, rendered).is_empty());
+    }
+
+    #[test]
+    fn test_field_scope_match_ranges_stay_within_field() {
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Field(
+                "msg".to_string(),
+                Box::new(Pattern::Regex("sisko".to_string())),
+            )),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let record = json!({"captain": "Sisko", "msg": "Sisko docked"});
+        let input = FilterInput::Json(&record);
+
+        let rendered = r#"{"captain":"Sisko","msg":"Sisko docked"}"#;
+        assert_eq!(filter.match_ranges(&input, rendered), vec![26..31]);
+
+        let templated = "Sisko: Sisko docked";
+        assert_eq!(filter.match_ranges(&input, templated), vec![7..12]);
+
+        let missing = json!({"captain": "Sisko"});
+        let rendered = r#"{"captain":"Sisko"}"#;
+        assert!(
+            filter
+                .match_ranges(&FilterInput::Json(&missing), rendered)
+                .is_empty()
+        );
+
+        let text = "Sisko docked";
+        assert_eq!(
+            filter.match_ranges(&FilterInput::Text(text), text),
+            vec![0..5]
+        );
+    }
 }
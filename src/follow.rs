@@ -0,0 +1,211 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, ErrorKind, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// How often the end of the file is checked for new data or a rotation
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Reader following a file by name, like `tail -F`.
+///
+/// Reads the file from the start and then waits for appended data instead of
+/// reporting EOF. Whenever the end is reached, the path is checked again: if it
+/// now refers to a different file (logrotate moved it away and created a new
+/// one) or the file shrank (copytruncate), it is reopened and read from the
+/// start. A missing file is waited for.
+pub struct FollowReader {
+    path: PathBuf,
+    file: Option<File>,
+    position: u64,
+    identity: Option<FileIdentity>,
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl FollowReader {
+    pub fn new(path: PathBuf) -> Self {
+        let mut reader = Self {
+            path,
+            file: None,
+            position: 0,
+            identity: None,
+            interrupt: None,
+        };
+        reader.reopen();
+        reader
+    }
+
+    /// Reports EOF instead of waiting for more data once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    fn reopen(&mut self) {
+        // A missing file is not an error, it may be created later
+        self.file = File::open(&self.path).ok();
+        self.identity = self
+            .file
+            .as_ref()
+            .and_then(|file| file.metadata().ok())
+            .map(|metadata| FileIdentity::of(&metadata));
+        self.position = 0;
+    }
+
+    /// Checks whether the path was rotated or truncated since it was opened
+    fn is_replaced(&self) -> io::Result<bool> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Moved away but not yet recreated, keep reading the old file
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        Ok(self.identity != Some(FileIdentity::of(&metadata)) || metadata.len() < self.position)
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(file) = &mut self.file {
+                let read = file.read(buf)?;
+                if read > 0 {
+                    self.position += read as u64;
+                    return Ok(read);
+                }
+            }
+
+            // At the end of the current file, wait for more data or a new file
+            if self.file.is_none() || self.is_replaced()? {
+                self.reopen();
+                if self.file.is_some() {
+                    continue;
+                }
+            }
+            if self
+                .interrupt
+                .as_ref()
+                .is_some_and(|interrupt| interrupt.load(Ordering::Relaxed))
+            {
+                return Ok(0);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// Identifies the file behind a path, to notice when it got replaced
+#[derive(Debug, PartialEq)]
+struct FileIdentity {
+    #[cfg(unix)]
+    device: u64,
+    #[cfg(unix)]
+    inode: u64,
+}
+
+impl FileIdentity {
+    #[cfg(unix)]
+    fn of(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        }
+    }
+
+    // Without inodes, rotation is only detected through the file shrinking
+    #[cfg(not(unix))]
+    fn of(_metadata: &Metadata) -> Self {
+        Self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+
+    fn append(path: &PathBuf, text: &str) {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    fn next_line(reader: &mut BufReader<FollowReader>) -> String {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line
+    }
+
+    #[test]
+    fn test_follows_appends_rotation_and_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ops.log");
+        append(&path, "Alpha shift\n");
+
+        let mut reader = BufReader::new(FollowReader::new(path.clone()));
+        assert_eq!(next_line(&mut reader), "Alpha shift\n");
+
+        // Appended data
+        append(&path, "Beta shift\n");
+        assert_eq!(next_line(&mut reader), "Beta shift\n");
+
+        // logrotate style: rename and create a new file
+        append(&path, "Last words before rotation\n");
+        fs::rename(&path, dir.path().join("ops.log.1")).unwrap();
+        append(&path, "Gamma shift\n");
+        assert_eq!(next_line(&mut reader), "Last words before rotation\n");
+        assert_eq!(next_line(&mut reader), "Gamma shift\n");
+
+        // copytruncate style: the same file shrinks
+        fs::write(&path, "Delta\n").unwrap();
+        assert_eq!(next_line(&mut reader), "Delta\n");
+    }
+
+    #[test]
+    fn test_waits_for_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("later.log");
+
+        let mut reader = BufReader::new(FollowReader::new(path.clone()));
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            append(&path, "Station online\n");
+        });
+
+        assert_eq!(next_line(&mut reader), "Station online\n");
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_interrupt_ends_waiting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ops.log");
+        append(&path, "Red alert\n");
+
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let mut reader =
+            BufReader::new(FollowReader::new(path).with_interrupt(Arc::clone(&interrupt)));
+        assert_eq!(next_line(&mut reader), "Red alert\n");
+
+        let signal = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            interrupt.store(true, Ordering::Relaxed);
+        });
+
+        assert_eq!(next_line(&mut reader), "");
+        signal.join().unwrap();
+    }
+}
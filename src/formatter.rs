@@ -2,10 +2,64 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::field_path::FieldPath;
+use crate::source::RECORD_SEPARATOR;
+use crate::template::Template;
 use anyhow::Result;
-use colored_json::ColoredFormatter;
+use clap::ValueEnum;
+use colored_json::{ColorMode, ColoredFormatter, Styler};
 use enum_dispatch::enum_dispatch;
 use serde_json::ser::{CompactFormatter, PrettyFormatter};
+use std::cell::RefCell;
+use std::path::Path;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::{LinesWithEndings, as_24_bit_terminal_escaped};
+use thiserror::Error;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Widest a column other than the last one grows, longer values are truncated
+const MAX_COLUMN_WIDTH: usize = 40;
+// Spaces between table columns
+const COLUMN_GAP: usize = 2;
+
+#[derive(Error, Debug)]
+pub enum ThemeError {
+    #[error("Unknown theme '{name}', available themes: {available}")]
+    UnknownTheme { name: String, available: String },
+    #[error("Cannot load theme file '{path}': {source}")]
+    InvalidThemeFile {
+        path: String,
+        #[source]
+        source: syntect::LoadingError,
+    },
+    #[error("Invalid theme file '{path}': {message}")]
+    InvalidThemeConfig { path: String, message: String },
+}
+
+/// Highlighting engine used for colored output
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ThemeEngine {
+    /// Built-in colored_json highlighting
+    Builtin,
+    /// syntect highlighting with editor color schemes (TextMate themes)
+    Syntect,
+}
+
+/// Representation used for parsed records
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    /// TOML where representable, JSON otherwise
+    Toml,
+    /// YAML documents, each starting with `---`
+    Yaml,
+    /// JSON text sequences (RFC 7464), each record preceded by an RS byte
+    JsonSeq,
+    /// Aligned table of the fields selected with `--columns`
+    Table,
+}
 
 /// JSON formatter variants that handle both colored and non-colored output.
 ///
@@ -23,6 +77,15 @@ pub enum JsonFormatter {
     ColoredPretty(ColoredPrettyFormatter),
     PlainCompact(PlainCompactFormatter),
     PlainPretty(PlainPrettyFormatter),
+    Syntect(SyntectFormatter),
+    Toml(TomlFormatter),
+    Yaml(YamlFormatter),
+    JsonSeq(JsonSeqFormatter),
+    Template(TemplateFormatter),
+    Extract(ExtractFormatter),
+    Table(TableFormatter),
+    Escaping(EscapingFormatter),
+    SortedKeys(SortedKeysFormatter),
 }
 
 impl JsonFormatter {
@@ -30,11 +93,25 @@ impl JsonFormatter {
     pub fn from_args(compact: bool, no_color: bool) -> Self {
         match (compact, no_color) {
             (true, true) => JsonFormatter::PlainCompact(PlainCompactFormatter::new()),
-            (true, false) => JsonFormatter::ColoredCompact(ColoredCompactFormatter::new()),
+            (true, false) => {
+                JsonFormatter::ColoredCompact(ColoredCompactFormatter::new(Styler::default()))
+            }
             (false, true) => JsonFormatter::PlainPretty(PlainPrettyFormatter::new()),
-            (false, false) => JsonFormatter::ColoredPretty(ColoredPrettyFormatter::new()),
+            (false, false) => {
+                JsonFormatter::ColoredPretty(ColoredPrettyFormatter::new(Styler::default()))
+            }
         }
     }
+
+    /// Colors output with the given theme, plain formatters are left as they are
+    pub fn with_styler(mut self, styler: Styler) -> Self {
+        match &mut self {
+            JsonFormatter::ColoredCompact(formatter) => formatter.styler = styler,
+            JsonFormatter::ColoredPretty(formatter) => formatter.styler = styler,
+            _ => {}
+        }
+        self
+    }
 }
 
 #[enum_dispatch]
@@ -43,38 +120,43 @@ pub trait Formatter {
 }
 
 /// Colored compact JSON formatter using colored_json with CompactFormatter
-pub struct ColoredCompactFormatter;
+pub struct ColoredCompactFormatter {
+    styler: Styler,
+}
 
 impl ColoredCompactFormatter {
-    pub fn new() -> Self {
-        Self
+    pub fn new(styler: Styler) -> Self {
+        Self { styler }
     }
 }
 
 impl Formatter for ColoredCompactFormatter {
     fn format_json(&self, value: &serde_json::Value) -> Result<String> {
-        let formatter = ColoredFormatter::new(CompactFormatter {});
-        Ok(formatter.to_colored_json_auto(value)?)
+        let formatter = ColoredFormatter::with_styler(CompactFormatter {}, self.styler);
+        Ok(formatter.to_colored_json(value, ColorMode::On)?)
     }
 }
 
 /// Colored pretty-printed JSON formatter using colored_json with PrettyFormatter
-pub struct ColoredPrettyFormatter;
+pub struct ColoredPrettyFormatter {
+    styler: Styler,
+}
 
 impl ColoredPrettyFormatter {
-    pub fn new() -> Self {
-        Self
+    pub fn new(styler: Styler) -> Self {
+        Self { styler }
     }
 }
 
 impl Formatter for ColoredPrettyFormatter {
     fn format_json(&self, value: &serde_json::Value) -> Result<String> {
-        let formatter = ColoredFormatter::new(PrettyFormatter::new());
-        Ok(formatter.to_colored_json_auto(value)?)
+        let formatter = ColoredFormatter::with_styler(PrettyFormatter::new(), self.styler);
+        Ok(formatter.to_colored_json(value, ColorMode::On)?)
     }
 }
 
 /// Plain compact JSON formatter using serde_json::to_string
+#[derive(Default)]
 pub struct PlainCompactFormatter;
 
 impl PlainCompactFormatter {
@@ -90,6 +172,7 @@ impl Formatter for PlainCompactFormatter {
 }
 
 /// Plain pretty-printed JSON formatter using serde_json::to_string_pretty
+#[derive(Default)]
 pub struct PlainPrettyFormatter;
 
 impl PlainPrettyFormatter {
@@ -103,3 +186,646 @@ impl Formatter for PlainPrettyFormatter {
         Ok(serde_json::to_string_pretty(value)?)
     }
 }
+
+/// JSON formatter highlighting output with syntect and a TextMate color scheme,
+/// so output can match the colors of the user's editor
+pub struct SyntectFormatter {
+    syntax_set: SyntaxSet,
+    theme: Box<Theme>, // Boxed to keep JsonFormatter variants small
+    compact: bool,
+}
+
+impl SyntectFormatter {
+    /// Creates a formatter for one of syntect's bundled themes or a `.tmTheme` file
+    pub fn new(theme: &str, compact: bool) -> Result<Self, ThemeError> {
+        let theme = if theme.ends_with(".tmTheme") {
+            ThemeSet::get_theme(Path::new(theme)).map_err(|source| {
+                ThemeError::InvalidThemeFile {
+                    path: theme.to_string(),
+                    source,
+                }
+            })?
+        } else {
+            let mut themes = ThemeSet::load_defaults().themes;
+            let available = themes.keys().cloned().collect::<Vec<_>>().join(", ");
+            themes
+                .remove(theme)
+                .ok_or_else(|| ThemeError::UnknownTheme {
+                    name: theme.to_string(),
+                    available,
+                })?
+        };
+
+        Ok(Self {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme: Box::new(theme),
+            compact,
+        })
+    }
+}
+
+impl Formatter for SyntectFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        let json = if self.compact {
+            serde_json::to_string(value)?
+        } else {
+            serde_json::to_string_pretty(value)?
+        };
+
+        let syntax = self
+            .syntax_set
+            .find_syntax_by_extension("json")
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+
+        let mut output = String::with_capacity(json.len() * 4);
+        for line in LinesWithEndings::from(&json) {
+            let ranges = highlighter.highlight_line(line, &self.syntax_set)?;
+            output.push_str(&as_24_bit_terminal_escaped(&ranges, false));
+        }
+        // Reset so the following output isn't tinted by the last token color
+        output.push_str("\x1b[0m");
+
+        Ok(output)
+    }
+}
+
+/// Renders records as TOML documents.
+///
+/// TOML can only represent tables at the top level and has no null value, so
+/// records that don't fit (arrays, scalars, nulls anywhere) are rendered by the
+/// fallback formatter instead.
+pub struct TomlFormatter {
+    fallback: Box<JsonFormatter>,
+}
+
+impl TomlFormatter {
+    pub fn new(fallback: JsonFormatter) -> Self {
+        Self {
+            fallback: Box::new(fallback),
+        }
+    }
+}
+
+impl Formatter for TomlFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        match toml::to_string_pretty(value) {
+            Ok(document) => Ok(document),
+            Err(_) => self.fallback.format_json(value),
+        }
+    }
+}
+
+/// Renders records as YAML documents.
+///
+/// Every record starts its own document with a `---` marker, so consecutive
+/// records stay apart when skimming and the output parses as a YAML stream.
+#[derive(Default)]
+pub struct YamlFormatter;
+
+impl YamlFormatter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Formatter for YamlFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        let document = serde_yaml::to_string(value)?;
+        Ok(format!("---\n{}", document.trim_end_matches('\n')))
+    }
+}
+
+/// Renders records as JSON text sequences (RFC 7464).
+///
+/// Every record is preceded by an RS (0x1E) byte, which lets `application/json-seq`
+/// consumers resynchronize after a truncated record. The records themselves are
+/// rendered by the inner formatter, which must not add colors.
+pub struct JsonSeqFormatter {
+    inner: Box<JsonFormatter>,
+}
+
+impl JsonSeqFormatter {
+    pub fn new(inner: JsonFormatter) -> Self {
+        Self {
+            inner: Box::new(inner),
+        }
+    }
+}
+
+impl Formatter for JsonSeqFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        Ok(format!(
+            "{}{}",
+            RECORD_SEPARATOR,
+            self.inner.format_json(value)?
+        ))
+    }
+}
+
+/// Renders records through a line template, see [`Template`]
+pub struct TemplateFormatter {
+    template: Template,
+}
+
+impl TemplateFormatter {
+    pub fn new(template: Template) -> Self {
+        Self { template }
+    }
+}
+
+impl Formatter for TemplateFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        Ok(self.template.render(value))
+    }
+}
+
+/// Renders the value of a single field, like `jq -r`: strings without
+/// quotes, anything else as compact JSON. Records without the field render
+/// as an empty line, so they should be filtered out before.
+pub struct ExtractFormatter {
+    path: FieldPath,
+}
+
+impl ExtractFormatter {
+    pub fn new(path: FieldPath) -> Self {
+        Self { path }
+    }
+}
+
+impl Formatter for ExtractFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        match self.path.get(value) {
+            Some(serde_json::Value::String(text)) => Ok(text.clone()),
+            Some(value) => Ok(serde_json::to_string(value)?),
+            None => Ok(String::new()),
+        }
+    }
+}
+
+/// Renders records as rows of an aligned table, one column per field.
+///
+/// Columns start as wide as their header and grow with wider values, up to
+/// `MAX_COLUMN_WIDTH`. The header is repeated whenever a column grew, so rows
+/// below it line up again. The last column isn't padded, and is truncated to
+/// the terminal width if one is given. Records other than objects are
+/// rendered as compact JSON.
+pub struct TableFormatter {
+    columns: Vec<FieldPath>,
+    headers: Vec<String>,
+    terminal_width: Option<usize>,
+    // Current column widths, None until the header was output
+    widths: RefCell<Option<Vec<usize>>>,
+}
+
+impl TableFormatter {
+    pub fn new(columns: &[String], terminal_width: Option<usize>) -> Self {
+        Self {
+            columns: columns
+                .iter()
+                .map(|column| FieldPath::parse(column))
+                .collect(),
+            headers: columns.to_vec(),
+            terminal_width,
+            widths: RefCell::new(None),
+        }
+    }
+
+    fn render_row(&self, cells: &[String], widths: &[usize]) -> String {
+        let mut row = String::new();
+        let last = cells.len().saturating_sub(1);
+        for (index, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            if index == last {
+                let remaining = self
+                    .terminal_width
+                    .map(|terminal| terminal.saturating_sub(row.width()).max(1));
+                match remaining {
+                    Some(remaining) => row.push_str(&truncate(cell, remaining)),
+                    None => row.push_str(cell),
+                }
+            } else {
+                let cell = truncate(cell, *width);
+                let padding = width - cell.width() + COLUMN_GAP;
+                row.push_str(&cell);
+                row.push_str(&" ".repeat(padding));
+            }
+        }
+        row
+    }
+}
+
+impl Formatter for TableFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        if !value.is_object() {
+            return Ok(value.to_string());
+        }
+
+        let cells: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| match column.get(value) {
+                Some(serde_json::Value::String(text)) => single_line(text),
+                Some(other) => other.to_string(),
+                None => String::new(),
+            })
+            .collect();
+
+        let mut widths = self.widths.borrow_mut();
+        let previous = widths.clone();
+        let current = widths.get_or_insert_with(|| {
+            self.headers
+                .iter()
+                .map(|header| header.width().min(MAX_COLUMN_WIDTH))
+                .collect()
+        });
+        // The last column isn't padded, its width doesn't matter
+        let padded = cells.len().saturating_sub(1);
+        for (width, cell) in current.iter_mut().zip(&cells).take(padded) {
+            *width = (*width).max(cell.width().min(MAX_COLUMN_WIDTH));
+        }
+
+        let row = self.render_row(&cells, current);
+        if previous.as_ref() == Some(current) {
+            return Ok(row);
+        }
+        let header = self.render_row(&self.headers, current);
+        Ok(format!("{}\n{}", header.trim_end(), row))
+    }
+}
+
+/// Replaces line breaks and tabs, which would break the table layout
+fn single_line(text: &str) -> String {
+    text.replace(['\n', '\r', '\t'], " ")
+}
+
+/// Cuts text to at most `width` columns, marking the cut with an ellipsis
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width >= width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Rewrites string escapes in the output of another formatter.
+///
+/// Serialized JSON only contains backslashes and non-ASCII characters inside
+/// strings, and ANSI color sequences consist of plain ASCII, so the rewrite
+/// works on colored output as well without having to understand its structure.
+pub struct EscapingFormatter {
+    inner: Box<JsonFormatter>,
+    escape_non_ascii: bool,
+    raw_newlines: bool,
+}
+
+impl EscapingFormatter {
+    /// * `escape_non_ascii` - Emit `\uXXXX` escapes for every non-ASCII character
+    /// * `raw_newlines` - Render `\n` inside strings as actual line breaks (display only,
+    ///   the output is no longer valid JSON)
+    pub fn new(inner: JsonFormatter, escape_non_ascii: bool, raw_newlines: bool) -> Self {
+        Self {
+            inner: Box::new(inner),
+            escape_non_ascii,
+            raw_newlines,
+        }
+    }
+}
+
+impl Formatter for EscapingFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        let formatted = self.inner.format_json(value)?;
+
+        let mut output = String::with_capacity(formatted.len());
+        let mut chars = formatted.chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                // Consume the escaped character as well, so `\\n` stays untouched
+                match chars.next() {
+                    Some('n') if self.raw_newlines => output.push('\n'),
+                    Some(escaped) => {
+                        output.push(c);
+                        output.push(escaped);
+                    }
+                    None => output.push(c),
+                }
+            } else if self.escape_non_ascii && !c.is_ascii() {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    output.push_str(&format!("\\u{:04x}", unit));
+                }
+            } else {
+                output.push(c);
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Sorts object keys recursively before handing records to another formatter.
+///
+/// Keys normally keep the order the application wrote them in, which may vary
+/// between runs. Sorted output diffs cleanly regardless.
+pub struct SortedKeysFormatter {
+    inner: Box<JsonFormatter>,
+}
+
+impl SortedKeysFormatter {
+    pub fn new(inner: JsonFormatter) -> Self {
+        Self {
+            inner: Box::new(inner),
+        }
+    }
+}
+
+impl Formatter for SortedKeysFormatter {
+    fn format_json(&self, value: &serde_json::Value) -> Result<String> {
+        let mut sorted = value.clone();
+        sort_keys(&mut sorted);
+        self.inner.format_json(&sorted)
+    }
+}
+
+fn sort_keys(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.sort_keys();
+            map.values_mut().for_each(sort_keys);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(sort_keys),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn strip_ansi(text: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                // Skip until the end of the SGR sequence
+                for c in chars.by_ref() {
+                    if c == 'm' {
+                        break;
+                    }
+                }
+            } else {
+                plain.push(c);
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn test_syntect_formatter_highlights_compact_json() {
+        let formatter = SyntectFormatter::new("Solarized (dark)", true).unwrap();
+        let value = json!({"station": "Deep Space Nine", "docking_ports": 18});
+
+        let output = formatter.format_json(&value).unwrap();
+
+        assert!(output.contains("\x1b[38;2;"));
+        assert_eq!(
+            strip_ansi(&output),
+            r#"{"station":"Deep Space Nine","docking_ports":18}"#
+        );
+    }
+
+    #[test]
+    fn test_syntect_formatter_highlights_pretty_json() {
+        let formatter = SyntectFormatter::new("base16-ocean.dark", false).unwrap();
+        let value = json!({"captain": "Sisko"});
+
+        let output = formatter.format_json(&value).unwrap();
+
+        assert_eq!(strip_ansi(&output), "{\n  \"captain\": \"Sisko\"\n}");
+    }
+
+    #[test]
+    fn test_syntect_formatter_unknown_theme() {
+        let error = SyntectFormatter::new("Cardassian Grey", false)
+            .err()
+            .unwrap();
+
+        assert!(matches!(error, ThemeError::UnknownTheme { .. }));
+        assert!(error.to_string().contains("Solarized (dark)"));
+    }
+
+    #[test]
+    fn test_with_styler_themes_colored_formatters() {
+        let styler = Styler {
+            key: colored_json::Color::Magenta.bold(),
+            ..Styler::default()
+        };
+
+        match JsonFormatter::from_args(false, false).with_styler(styler) {
+            JsonFormatter::ColoredPretty(formatter) => {
+                assert_eq!(formatter.styler.key, colored_json::Color::Magenta.bold())
+            }
+            _ => panic!("Expected ColoredPretty variant"),
+        }
+        assert!(matches!(
+            JsonFormatter::from_args(true, true).with_styler(styler),
+            JsonFormatter::PlainCompact(_)
+        ));
+    }
+
+    #[test]
+    fn test_toml_formatter_renders_tables() {
+        let formatter = TomlFormatter::new(JsonFormatter::from_args(true, true));
+        let value = json!({"name": "Defiant", "class": "escort", "crew": {"captain": "Sisko"}});
+
+        let output = formatter.format_json(&value).unwrap();
+
+        assert_eq!(
+            output,
+            "name = \"Defiant\"\nclass = \"escort\"\n\n[crew]\ncaptain = \"Sisko\"\n"
+        );
+    }
+
+    #[test]
+    fn test_toml_formatter_falls_back_to_json() {
+        let formatter = TomlFormatter::new(JsonFormatter::from_args(true, true));
+
+        assert_eq!(
+            formatter.format_json(&json!(["Worf", "Dax"])).unwrap(),
+            r#"["Worf","Dax"]"#
+        );
+        assert_eq!(
+            formatter.format_json(&json!({"cloak": null})).unwrap(),
+            r#"{"cloak":null}"#
+        );
+    }
+
+    #[rstest]
+    #[case(
+        json!({"name": "Defiant", "crew": {"captain": "Sisko", "officers": ["Worf", "Dax"]}}),
+        "---\nname: Defiant\ncrew:\n  captain: Sisko\n  officers:\n  - Worf\n  - Dax"
+    )]
+    #[case(json!({"cloak": null, "warp": 9.5}), "---\ncloak: null\nwarp: 9.5")]
+    #[case(json!("Quark's"), "---\nQuark's")]
+    fn test_yaml_formatter_renders_documents(
+        #[case] value: serde_json::Value,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(YamlFormatter::new().format_json(&value).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_json_seq_formatter_prefixes_records() {
+        let formatter = JsonSeqFormatter::new(JsonFormatter::from_args(true, true));
+
+        assert_eq!(
+            formatter
+                .format_json(&json!({"ship": "Defiant", "warp": 9.5}))
+                .unwrap(),
+            "\u{1e}{\"ship\":\"Defiant\",\"warp\":9.5}"
+        );
+    }
+
+    #[rstest]
+    #[case(json!({"msg": "Hull breach\non deck 5"}), "msg", "Hull breach\non deck 5")]
+    #[case(json!({"http": {"status": 503}}), "http.status", "503")]
+    #[case(json!({"crew": ["Sisko", "Kira"]}), "crew", "[\"Sisko\",\"Kira\"]")]
+    #[case(json!({"msg": "Docked"}), "ship", "")]
+    fn test_extract_formatter_renders_raw_values(
+        #[case] value: serde_json::Value,
+        #[case] path: &str,
+        #[case] expected: &str,
+    ) {
+        let formatter = ExtractFormatter::new(FieldPath::parse(path));
+
+        assert_eq!(formatter.format_json(&value).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_table_formatter_grows_columns() {
+        let columns = [
+            "level".to_string(),
+            "ship.name".to_string(),
+            "msg".to_string(),
+        ];
+        let formatter = TableFormatter::new(&columns, None);
+
+        assert_eq!(
+            formatter
+                .format_json(
+                    &json!({"level": "info", "ship": {"name": "Defiant"}, "msg": "Docked"})
+                )
+                .unwrap(),
+            "level  ship.name  msg\ninfo   Defiant    Docked"
+        );
+        assert_eq!(
+            formatter
+                .format_json(&json!({"level": "warn", "msg": "Cloak\nengaged"}))
+                .unwrap(),
+            "warn              Cloak engaged"
+        );
+        // A wider value grows its column and repeats the header
+        assert_eq!(
+            formatter
+                .format_json(
+                    &json!({"level": "error", "ship": {"name": "USS Rio Grande"}, "msg": "Lost"})
+                )
+                .unwrap(),
+            "level  ship.name       msg\nerror  USS Rio Grande  Lost"
+        );
+    }
+
+    #[test]
+    fn test_table_formatter_truncates() {
+        let columns = ["id".to_string(), "msg".to_string()];
+        let formatter = TableFormatter::new(&columns, Some(45));
+        let id = "x".repeat(50);
+
+        let output = formatter
+            .format_json(&json!({"id": id, "msg": "Wormhole opening"}))
+            .unwrap();
+
+        let row = output.lines().nth(1).unwrap();
+        assert_eq!(row, format!("{}…  Wo…", "x".repeat(39)));
+    }
+
+    #[rstest]
+    #[case("Quark", 10, "Quark")]
+    #[case("Morn speaks", 6, "Morn …")]
+    #[case("日本語テキスト", 7, "日本語…")]
+    fn test_truncate(#[case] text: &str, #[case] width: usize, #[case] expected: &str) {
+        assert_eq!(truncate(text, width), expected);
+    }
+
+    #[test]
+    fn test_escaping_formatter_escapes_non_ascii() {
+        let formatter = EscapingFormatter::new(JsonFormatter::from_args(true, true), true, false);
+        let value = json!({"säule": "Quark’s 🍸"});
+
+        assert_eq!(
+            formatter.format_json(&value).unwrap(),
+            r#"{"s\u00e4ule":"Quark\u2019s \ud83c\udf78"}"#
+        );
+    }
+
+    #[test]
+    fn test_escaping_formatter_renders_raw_newlines() {
+        let formatter = EscapingFormatter::new(JsonFormatter::from_args(true, true), false, true);
+        let value = json!({"trace": "Error: breach\n  at warp_core\n", "path": "C:\\new"});
+
+        assert_eq!(
+            formatter.format_json(&value).unwrap(),
+            "{\"trace\":\"Error: breach\n  at warp_core\n\",\"path\":\"C:\\\\new\"}"
+        );
+    }
+
+    #[test]
+    fn test_escaping_formatter_handles_colored_output() {
+        let formatter = EscapingFormatter::new(JsonFormatter::from_args(true, false), true, true);
+        let value = json!({"message": "Jadzia\nDax"});
+
+        let output = formatter.format_json(&value).unwrap();
+
+        assert_eq!(strip_ansi(&output), "{\"message\":\"Jadzia\nDax\"}");
+    }
+
+    #[rstest]
+    #[case(
+        JsonFormatter::from_args(true, true),
+        r#"{"a":[{"x":1,"y":2}],"b":{"c":3,"d":4}}"#
+    )]
+    #[case(
+        JsonFormatter::from_args(false, true),
+        "{\n  \"a\": [\n    {\n      \"x\": 1,\n      \"y\": 2\n    }\n  ],\n  \"b\": {\n    \"c\": 3,\n    \"d\": 4\n  }\n}"
+    )]
+    #[case(
+        JsonFormatter::from_args(true, false),
+        r#"{"a":[{"x":1,"y":2}],"b":{"c":3,"d":4}}"#
+    )]
+    #[case(
+        JsonFormatter::from_args(false, false),
+        "{\n  \"a\": [\n    {\n      \"x\": 1,\n      \"y\": 2\n    }\n  ],\n  \"b\": {\n    \"c\": 3,\n    \"d\": 4\n  }\n}"
+    )]
+    fn test_sorted_keys_formatter_sorts_recursively(
+        #[case] inner: JsonFormatter,
+        #[case] expected: &str,
+    ) {
+        let formatter = SortedKeysFormatter::new(inner);
+        let value = json!({"b": {"d": 4, "c": 3}, "a": [{"y": 2, "x": 1}]});
+
+        let output = formatter.format_json(&value).unwrap();
+
+        assert_eq!(strip_ansi(&output), expected);
+    }
+}
@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::{Value, json};
+use std::io::{ErrorKind, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Shape of the JSON records produced by the generator
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum GenFormat {
+    /// pino style: numeric levels, epoch millisecond `time`, `msg`
+    Pino,
+    /// Generic structured logs: string levels, ISO 8601 `timestamp`, `message`
+    Json,
+}
+
+/// Configuration for a synthetic log stream.
+///
+/// All fractions are in the range `0.0..=1.0` and are evaluated independently
+/// for every generated record.
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub format: GenFormat,
+    /// Records per second, `None` generates as fast as possible
+    pub rate: Option<u64>,
+    /// Number of records to emit, `None` runs until the writer goes away
+    pub count: Option<u64>,
+    /// Fraction of records emitted as plain text instead of JSON
+    pub mixed_text: f64,
+    /// Fraction of JSON records emitted pretty-printed across multiple lines
+    pub multi_line: f64,
+    /// Fraction of JSON records emitted truncated and therefore unparsable
+    pub malformed: f64,
+    /// Seed for reproducible output
+    pub seed: Option<u64>,
+}
+
+// Weighted level distribution roughly resembling a healthy production service
+const LEVELS: [(&str, u64, u32); 6] = [
+    ("trace", 10, 5),
+    ("debug", 20, 15),
+    ("info", 30, 55),
+    ("warn", 40, 15),
+    ("error", 50, 8),
+    ("fatal", 60, 2),
+];
+
+const MESSAGES: [&str; 10] = [
+    "Docking clamps released",
+    "Replicator pattern buffer degraded",
+    "Request handled",
+    "Cache miss for holosuite program",
+    "Upstream connection reset by Cardassian relay",
+    "Promenade sensor sweep completed",
+    "Retrying wormhole transit authorization",
+    "User session started",
+    "Failed to reach Bajoran comm array",
+    "Ore processing queue drained",
+];
+
+const TEXT_LINES: [&str; 6] = [
+    "Starting station operations daemon",
+    "Loading configuration from /etc/ds9/ops.conf",
+    "WARNING: deprecated subspace protocol in use",
+    "Listening on 0.0.0.0:4711",
+    "Garbage collector pass finished",
+    "Shutting down airlock controller gracefully",
+];
+
+const ROUTES: [&str; 5] = [
+    "/api/crew",
+    "/api/cargo",
+    "/api/docking",
+    "/api/replicator",
+    "/health",
+];
+
+pub struct LogGenerator {
+    config: GeneratorConfig,
+    rng: fastrand::Rng,
+}
+
+impl LogGenerator {
+    pub fn new(config: GeneratorConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        };
+        Self { config, rng }
+    }
+
+    /// Writes generated records until `count` is reached or the writer is closed.
+    ///
+    /// A closed pipe (e.g. `jlif gen | head`) ends generation successfully.
+    pub fn run<W: Write>(&mut self, mut writer: W) -> Result<()> {
+        let interval = self
+            .config
+            .rate
+            .filter(|rate| *rate > 0)
+            .map(|rate| Duration::from_secs_f64(1.0 / rate as f64));
+        let start = Instant::now();
+        let mut emitted: u64 = 0;
+
+        while self.config.count.is_none_or(|count| emitted < count) {
+            if let Some(interval) = interval {
+                // Schedule against the start time so sleeping jitter doesn't accumulate
+                let target = start + interval.mul_f64(emitted as f64);
+                let now = Instant::now();
+                if target > now {
+                    std::thread::sleep(target - now);
+                }
+            }
+
+            let record = self.next_record();
+            let written = writeln!(writer, "{}", record).and_then(|_| {
+                if interval.is_some() {
+                    writer.flush()
+                } else {
+                    Ok(())
+                }
+            });
+            match written {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+
+            emitted += 1;
+        }
+
+        match writer.flush() {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Produces the next record, which may span multiple lines.
+    pub fn next_record(&mut self) -> String {
+        if self.chance(self.config.mixed_text) {
+            return self.text_line();
+        }
+
+        let value = self.json_record();
+
+        if self.chance(self.config.malformed) {
+            // Cutting a compact object anywhere before its closing brace
+            // always leaves an unparsable JSON-looking line
+            let compact = value.to_string();
+            let cut = self.rng.usize(1..compact.len() - 1);
+            return compact[..cut].to_string();
+        }
+
+        if self.chance(self.config.multi_line) {
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn chance(&mut self, fraction: f64) -> bool {
+        fraction > 0.0 && self.rng.f64() < fraction
+    }
+
+    fn pick_level(&mut self) -> (&'static str, u64) {
+        let total: u32 = LEVELS.iter().map(|(_, _, weight)| weight).sum();
+        let mut roll = self.rng.u32(0..total);
+        for (name, number, weight) in LEVELS {
+            if roll < weight {
+                return (name, number);
+            }
+            roll -= weight;
+        }
+        ("info", 30)
+    }
+
+    fn json_record(&mut self) -> Value {
+        let (level_name, level_number) = self.pick_level();
+        let message = MESSAGES[self.rng.usize(..MESSAGES.len())];
+        let request_id = format!("{:016x}", self.rng.u64(..));
+        let route = ROUTES[self.rng.usize(..ROUTES.len())];
+        let status = if level_number >= 50 { 500 } else { 200 };
+        let duration_ms = self.rng.u32(1..1500);
+        let now = SystemTime::now();
+
+        match self.config.format {
+            GenFormat::Pino => json!({
+                "level": level_number,
+                "time": now.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+                "pid": 4711,
+                "hostname": "ds9-ops",
+                "req_id": request_id,
+                "route": route,
+                "status": status,
+                "duration_ms": duration_ms,
+                "msg": message,
+            }),
+            GenFormat::Json => json!({
+                "timestamp": humantime::format_rfc3339_millis(now).to_string(),
+                "level": level_name,
+                "message": message,
+                "request_id": request_id,
+                "http": {"route": route, "status": status},
+                "duration_ms": duration_ms,
+            }),
+        }
+    }
+
+    fn text_line(&mut self) -> String {
+        TEXT_LINES[self.rng.usize(..TEXT_LINES.len())].to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn config(format: GenFormat) -> GeneratorConfig {
+        GeneratorConfig {
+            format,
+            rate: None,
+            count: Some(200),
+            mixed_text: 0.0,
+            multi_line: 0.0,
+            malformed: 0.0,
+            seed: Some(47),
+        }
+    }
+
+    fn generate(config: GeneratorConfig) -> String {
+        let mut output = Vec::new();
+        LogGenerator::new(config).run(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_generates_requested_count_of_single_line_json() {
+        let output = generate(config(GenFormat::Pino));
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 200);
+        for line in lines {
+            let value: Value = serde_json::from_str(line).unwrap();
+            assert!(value["level"].is_u64());
+            assert!(value["msg"].is_string());
+        }
+    }
+
+    #[test]
+    fn test_json_format_uses_string_levels_and_timestamps() {
+        let output = generate(config(GenFormat::Json));
+        let value: Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+
+        assert!(value["level"].is_string());
+        assert!(value["message"].is_string());
+        assert!(value["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_json_timestamps_are_rfc3339_utc_with_millis() {
+        let before = SystemTime::now();
+        let output = generate(config(GenFormat::Json));
+        let value: Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        let timestamp = value["timestamp"].as_str().unwrap();
+
+        // Like 2024-05-13T10:00:00.000Z
+        assert_eq!(timestamp.len(), 24);
+        assert_eq!(&timestamp[19..20], ".");
+        let parsed = humantime::parse_rfc3339(timestamp).unwrap();
+        let drift = before.duration_since(parsed).unwrap_or_default();
+        assert!(drift < std::time::Duration::from_millis(1), "{timestamp}");
+    }
+
+    #[test]
+    fn test_seed_makes_output_reproducible() {
+        let mut config = config(GenFormat::Json);
+        config.mixed_text = 0.5;
+        let first = generate(config.clone());
+        let second = generate(config);
+
+        // Timestamps differ between runs, everything else is seeded
+        let strip = |output: &str| -> Vec<String> {
+            output
+                .lines()
+                .map(|line| match serde_json::from_str::<Value>(line) {
+                    Ok(mut value) => {
+                        value["timestamp"] = Value::Null;
+                        value.to_string()
+                    }
+                    Err(_) => line.to_string(),
+                })
+                .collect()
+        };
+        assert_eq!(strip(&first), strip(&second));
+    }
+
+    #[rstest]
+    #[case(1.0, 0.0, 0.0)]
+    #[case(0.0, 1.0, 0.0)]
+    #[case(0.0, 0.0, 1.0)]
+    fn test_fractions_control_record_shapes(
+        #[case] mixed_text: f64,
+        #[case] multi_line: f64,
+        #[case] malformed: f64,
+    ) {
+        let mut config = config(GenFormat::Pino);
+        config.count = Some(20);
+        config.mixed_text = mixed_text;
+        config.multi_line = multi_line;
+        config.malformed = malformed;
+        let mut generator = LogGenerator::new(config);
+
+        for _ in 0..20 {
+            let record = generator.next_record();
+            let parsed = serde_json::from_str::<Value>(&record);
+            if mixed_text > 0.0 {
+                assert!(TEXT_LINES.contains(&record.as_str()));
+            } else if multi_line > 0.0 {
+                assert!(record.lines().count() > 1);
+                assert!(parsed.is_ok());
+            } else {
+                assert!(record.starts_with('{'));
+                assert!(parsed.is_err());
+            }
+        }
+    }
+}
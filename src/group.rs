@@ -0,0 +1,346 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::buffer::BufferResult;
+use crate::field_path::FieldPath;
+use crate::timestamp::TimeFormats;
+use jiff::Timestamp;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Estimated bookkeeping per held record on top of its size
+const RECORD_OVERHEAD: usize = 64;
+
+/// Records sharing the same id, ready for output
+#[derive(Debug, PartialEq)]
+pub struct Group {
+    /// The id, strings without their quotes
+    pub id: String,
+    pub records: Vec<BufferResult>,
+    /// Time between the first and the last record
+    pub elapsed: Duration,
+}
+
+/// A group still collecting records
+struct PendingGroup {
+    id: String,
+    records: Vec<BufferResult>,
+    first: Timestamp,
+    last: Timestamp,
+    last_added: Instant,
+    memory: usize,
+}
+
+/// Collects records by the value of an id field, like a request or trace id,
+/// so the records of one request in an interleaved stream are output together.
+///
+/// Groups are output in the order their first record arrived, once the input
+/// ends or, with a timeout, once no record joined them for that long. The
+/// elapsed time of a group is taken from the first of the time fields holding
+/// a timestamp, or from the arrival of records without one. Text lines and
+/// records without the id field don't belong to any group.
+///
+/// With a memory limit, the oldest groups are output early once the records
+/// held exceed it. Later records with their id start a new group.
+pub struct RecordGroups {
+    field: FieldPath,
+    time_fields: Vec<FieldPath>,
+    time_formats: TimeFormats,
+    timeout: Option<Duration>,
+    memory_limit: Option<usize>,
+    memory: usize,
+    groups: Vec<PendingGroup>,
+    index: HashMap<String, usize>,
+}
+
+impl RecordGroups {
+    pub fn new(field: FieldPath, time_fields: Vec<FieldPath>) -> Self {
+        Self {
+            field,
+            time_fields,
+            time_formats: TimeFormats::default(),
+            timeout: None,
+            memory_limit: None,
+            memory: 0,
+            groups: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Also reads timestamp strings in these formats
+    pub fn with_time_formats(mut self, formats: TimeFormats) -> Self {
+        self.time_formats = formats;
+        self
+    }
+
+    /// Outputs groups no record joined for this long, instead of only at the
+    /// end of the input
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Outputs the oldest groups early once the held records take up more
+    /// than this many bytes
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// The id field records are grouped by
+    pub fn field(&self) -> &FieldPath {
+        &self.field
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Adds a record to its group, returning it if it doesn't belong to any
+    pub fn add(&mut self, result: BufferResult, now: Instant) -> Option<BufferResult> {
+        let BufferResult::Json(record, _) = &result else {
+            return Some(result);
+        };
+        let id = match self.field.get(record) {
+            None | Some(Value::Null) => return Some(result),
+            Some(Value::String(id)) => id.clone(),
+            Some(other) => other.to_string(),
+        };
+        let time = self
+            .time_fields
+            .iter()
+            .find_map(|field| {
+                field
+                    .get(record)
+                    .and_then(|time| self.time_formats.parse(time))
+            })
+            .unwrap_or_else(Timestamp::now);
+        let memory = result.size() + RECORD_OVERHEAD;
+        self.memory += memory;
+
+        match self.index.get(&id) {
+            Some(&position) => {
+                let group = &mut self.groups[position];
+                group.first = group.first.min(time);
+                group.last = group.last.max(time);
+                group.last_added = now;
+                group.memory += memory;
+                group.records.push(result);
+            }
+            None => {
+                self.index.insert(id.clone(), self.groups.len());
+                self.groups.push(PendingGroup {
+                    id,
+                    records: vec![result],
+                    first: time,
+                    last: time,
+                    last_added: now,
+                    memory,
+                });
+            }
+        }
+        None
+    }
+
+    /// Time until the next group times out, if any can
+    pub fn next_due(&self, now: Instant) -> Option<Duration> {
+        let timeout = self.timeout?;
+        self.groups
+            .iter()
+            .map(|group| timeout.saturating_sub(now.duration_since(group.last_added)))
+            .min()
+    }
+
+    /// Removes the groups no record joined for the timeout
+    pub fn take_idle(&mut self, now: Instant) -> Vec<Group> {
+        let Some(timeout) = self.timeout else {
+            return Vec::new();
+        };
+        if !self
+            .groups
+            .iter()
+            .any(|group| now.duration_since(group.last_added) >= timeout)
+        {
+            return Vec::new();
+        }
+
+        let (idle, pending) = std::mem::take(&mut self.groups)
+            .into_iter()
+            .partition(|group| now.duration_since(group.last_added) >= timeout);
+        self.groups = pending;
+        self.reindex();
+        idle.into_iter().map(Group::from).collect()
+    }
+
+    /// Removes the oldest groups while the held records exceed the memory limit
+    pub fn take_excess(&mut self) -> Vec<Group> {
+        let Some(limit) = self.memory_limit else {
+            return Vec::new();
+        };
+        let mut excess = 0;
+        let mut memory = self.memory;
+        while memory > limit && excess < self.groups.len() {
+            memory -= self.groups[excess].memory;
+            excess += 1;
+        }
+        if excess == 0 {
+            return Vec::new();
+        }
+
+        let excess: Vec<PendingGroup> = self.groups.drain(..excess).collect();
+        self.reindex();
+        excess.into_iter().map(Group::from).collect()
+    }
+
+    /// Removes all groups, e.g. at the end of the input
+    pub fn take_all(&mut self) -> Vec<Group> {
+        self.memory = 0;
+        self.index.clear();
+        std::mem::take(&mut self.groups)
+            .into_iter()
+            .map(Group::from)
+            .collect()
+    }
+}
+
+impl RecordGroups {
+    // Updates the positions of the ids and the memory after groups were removed
+    fn reindex(&mut self) {
+        self.memory = self.groups.iter().map(|group| group.memory).sum();
+        self.index = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(position, group)| (group.id.clone(), position))
+            .collect();
+    }
+}
+
+impl From<PendingGroup> for Group {
+    fn from(group: PendingGroup) -> Self {
+        Group {
+            id: group.id,
+            records: group.records,
+            elapsed: group.last.duration_since(group.first).unsigned_abs(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(value: Value) -> BufferResult {
+        BufferResult::Json(value, 0)
+    }
+
+    fn groups() -> RecordGroups {
+        RecordGroups::new(FieldPath::parse("request_id"), vec![FieldPath::parse("ts")])
+    }
+
+    #[test]
+    fn test_collects_records_by_id_in_order_of_first_record() {
+        let mut groups = groups();
+        let now = Instant::now();
+        let records = [
+            json!({"request_id": "a", "ts": "2024-05-01T12:00:00Z", "msg": "Docking request"}),
+            json!({"request_id": 7, "ts": "2024-05-01T12:00:01Z", "msg": "Cargo manifest"}),
+            json!({"request_id": "a", "ts": "2024-05-01T12:00:02.5Z", "msg": "Docked"}),
+        ];
+        for value in &records {
+            assert_eq!(groups.add(record(value.clone()), now), None);
+        }
+
+        assert_eq!(
+            groups.take_all(),
+            vec![
+                Group {
+                    id: "a".to_string(),
+                    records: vec![record(records[0].clone()), record(records[2].clone())],
+                    elapsed: Duration::from_millis(2500),
+                },
+                Group {
+                    id: "7".to_string(),
+                    records: vec![record(records[1].clone())],
+                    elapsed: Duration::ZERO,
+                },
+            ]
+        );
+        assert_eq!(groups.take_all(), vec![]);
+    }
+
+    #[test]
+    fn test_passes_through_records_without_id() {
+        let mut groups = groups();
+        let now = Instant::now();
+
+        assert_eq!(
+            groups.add(record(json!({"msg": "Station stable"})), now),
+            Some(record(json!({"msg": "Station stable"})))
+        );
+        assert_eq!(
+            groups.add(record(json!({"request_id": null})), now),
+            Some(record(json!({"request_id": null})))
+        );
+        assert_eq!(
+            groups.add(BufferResult::Text("Quark's bar opens".to_string()), now),
+            Some(BufferResult::Text("Quark's bar opens".to_string()))
+        );
+        assert_eq!(groups.take_all(), vec![]);
+    }
+
+    #[test]
+    fn test_takes_groups_idle_for_the_timeout() {
+        let mut groups = groups().with_timeout(Duration::from_secs(5));
+        let start = Instant::now();
+        groups.add(record(json!({"request_id": "a"})), start);
+        groups.add(
+            record(json!({"request_id": "b"})),
+            start + Duration::from_secs(3),
+        );
+
+        assert_eq!(
+            groups.next_due(start + Duration::from_secs(4)),
+            Some(Duration::from_secs(1))
+        );
+        let idle = groups.take_idle(start + Duration::from_secs(6));
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].id, "a");
+
+        // Later records of the remaining group still find it
+        groups.add(
+            record(json!({"request_id": "b"})),
+            start + Duration::from_secs(7),
+        );
+        let all = groups.take_all();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].records.len(), 2);
+    }
+
+    #[test]
+    fn test_takes_oldest_groups_beyond_memory_limit() {
+        let mut groups = groups().with_memory_limit(3 * (100 + RECORD_OVERHEAD));
+        let now = Instant::now();
+        for id in ["a", "b", "a", "c"] {
+            groups.add(BufferResult::Json(json!({"request_id": id}), 100), now);
+        }
+
+        let excess = groups.take_excess();
+        assert_eq!(excess.len(), 1);
+        assert_eq!(excess[0].id, "a");
+        assert_eq!(excess[0].records.len(), 2);
+        assert_eq!(groups.take_excess(), vec![]);
+
+        // A later record of an output group starts a new one
+        groups.add(BufferResult::Json(json!({"request_id": "a"}), 100), now);
+        let ids: Vec<String> = groups
+            .take_all()
+            .into_iter()
+            .map(|group| group.id)
+            .collect();
+        assert_eq!(ids, ["b", "c", "a"]);
+    }
+}
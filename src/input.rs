@@ -0,0 +1,30 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::{logfmt, syslog};
+use clap::ValueEnum;
+use serde_json::Value;
+
+/// Format of text lines parsed into records, besides JSON
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum InputFormat {
+    /// Only JSON, other lines are text
+    #[default]
+    Json,
+    /// logfmt lines like `level=error msg="boom"`
+    Logfmt,
+    /// Syslog lines in the RFC 5424 or RFC 3164 format, with JSON messages merged in
+    Syslog,
+}
+
+impl InputFormat {
+    /// Parses a line that isn't JSON into a record, if it is in this format
+    pub fn parse(&self, line: &str) -> Option<Value> {
+        match self {
+            InputFormat::Json => None,
+            InputFormat::Logfmt => logfmt::parse(line).map(Value::Object),
+            InputFormat::Syslog => syslog::parse(line).map(Value::Object),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::field_path::FieldPath;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+// Fields commonly used by logging libraries to carry the level, in lookup order
+static LEVEL_FIELDS: LazyLock<Vec<FieldPath>> = LazyLock::new(|| {
+    [
+        "level",
+        "severity",
+        "loglevel",
+        "log_level",
+        "lvl",
+        "log.level",
+    ]
+    .iter()
+    .map(|path| FieldPath::parse(path))
+    .collect()
+});
+
+// Number of leading words of a text line searched for a level keyword
+const TEXT_LEVEL_WORDS: usize = 3;
+
+/// Canonical log level, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    pub const ALL: [Level; 6] = [
+        Level::Trace,
+        Level::Debug,
+        Level::Info,
+        Level::Warn,
+        Level::Error,
+        Level::Fatal,
+    ];
+
+    /// Interprets a level field value, either a name or a number
+    pub fn from_value(value: &Value) -> Option<Level> {
+        match value {
+            Value::String(name) => Level::parse(name),
+            Value::Number(number) => number.as_u64().and_then(Level::from_number),
+            _ => None,
+        }
+    }
+
+    /// Parses level names and their common spellings, case-insensitively
+    pub fn parse(name: &str) -> Option<Level> {
+        let name = name.trim();
+        if let Ok(number) = name.parse::<u64>() {
+            return Level::from_number(number);
+        }
+
+        match name.to_ascii_lowercase().as_str() {
+            "trace" | "trc" | "verbose" => Some(Level::Trace),
+            "debug" | "dbg" => Some(Level::Debug),
+            "info" | "inf" | "information" | "notice" => Some(Level::Info),
+            "warn" | "wrn" | "warning" => Some(Level::Warn),
+            "error" | "err" | "eror" => Some(Level::Error),
+            "fatal" | "ftl" | "critical" | "crit" | "alert" | "emerg" | "emergency" | "panic" => {
+                Some(Level::Fatal)
+            }
+            _ => None,
+        }
+    }
+
+    /// Maps numeric levels: pino/bunyan levels (10-60) and syslog severities (0-7)
+    pub fn from_number(number: u64) -> Option<Level> {
+        match number {
+            0..=2 => Some(Level::Fatal),
+            3 => Some(Level::Error),
+            4 => Some(Level::Warn),
+            5 | 6 => Some(Level::Info),
+            7 => Some(Level::Debug),
+            8 | 9 => None,
+            10..=19 => Some(Level::Trace),
+            20..=29 => Some(Level::Debug),
+            30..=39 => Some(Level::Info),
+            40..=49 => Some(Level::Warn),
+            50..=59 => Some(Level::Error),
+            _ => Some(Level::Fatal),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Fatal => "fatal",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Maps level representations to canonical levels.
+///
+/// User-defined entries (e.g. `sev=2` → error, `100` → fatal) are consulted
+/// first, everything else falls back to the built-in spellings and numeric
+/// ranges of `Level::from_value`. Every level-aware feature resolves levels
+/// through this map, so a mapping applies to all of them alike.
+#[derive(Debug, Clone, Default)]
+pub struct LevelMap {
+    // Keys are lowercased strings, numbers use their JSON representation
+    entries: HashMap<String, Level>,
+}
+
+impl LevelMap {
+    pub fn new(entries: Vec<(String, Level)>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(|(raw, level)| (raw.trim().to_ascii_lowercase(), level))
+                .collect(),
+        }
+    }
+
+    /// Detects the level of a record from its common level fields
+    pub fn detect(&self, record: &Value) -> Option<Level> {
+        LEVEL_FIELDS
+            .iter()
+            .find_map(|path| path.get(record))
+            .and_then(|value| self.resolve(value))
+    }
+
+    /// Detects the level of a text line from a level keyword near its start.
+    ///
+    /// Only the first few words are considered, skipping numbers like those of
+    /// a leading timestamp, so `2024-05-01 12:00:03 WARN disk full` is a warning
+    /// while a message merely mentioning an error further on is not.
+    pub fn detect_text(&self, text: &str) -> Option<Level> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty() && !word.chars().all(|c| c.is_ascii_digit()))
+            .take(TEXT_LEVEL_WORDS)
+            .find_map(|word| self.resolve(&Value::String(word.to_string())))
+    }
+
+    /// Interprets a level field value, preferring user-defined mappings
+    pub fn resolve(&self, value: &Value) -> Option<Level> {
+        let raw = match value {
+            Value::String(name) => name.trim().to_ascii_lowercase(),
+            Value::Number(number) => number.to_string(),
+            _ => return None,
+        };
+
+        self.entries
+            .get(&raw)
+            .copied()
+            .or_else(|| Level::from_value(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(json!({"level": "info"}), Some(Level::Info))]
+    #[case(json!({"level": "WARNING"}), Some(Level::Warn))]
+    #[case(json!({"level": 50}), Some(Level::Error))]
+    #[case(json!({"severity": "CRITICAL"}), Some(Level::Fatal))]
+    #[case(json!({"lvl": "dbg"}), Some(Level::Debug))]
+    #[case(json!({"log": {"level": "trace"}}), Some(Level::Trace))]
+    #[case(json!({"loglevel": "30"}), Some(Level::Info))]
+    #[case(json!({"level": "unknown"}), None)]
+    #[case(json!({"message": "no level here"}), None)]
+    #[case(json!(["not", "an", "object"]), None)]
+    fn test_detect(#[case] record: Value, #[case] expected: Option<Level>) {
+        assert_eq!(LevelMap::default().detect(&record), expected);
+    }
+
+    #[rstest]
+    #[case(json!({"level": "sev=2"}), Some(Level::Error))]
+    #[case(json!({"level": "SEV=2"}), Some(Level::Error))]
+    #[case(json!({"level": 100}), Some(Level::Fatal))]
+    #[case(json!({"level": 30}), Some(Level::Warn))]
+    #[case(json!({"level": "30"}), Some(Level::Warn))]
+    #[case(json!({"level": "info"}), Some(Level::Info))]
+    fn test_detect_with_custom_mapping(#[case] record: Value, #[case] expected: Option<Level>) {
+        let map = LevelMap::new(vec![
+            ("sev=2".to_string(), Level::Error),
+            ("100".to_string(), Level::Fatal),
+            ("30".to_string(), Level::Warn),
+        ]);
+
+        assert_eq!(map.detect(&record), expected);
+    }
+
+    #[rstest]
+    #[case("ERROR: warp core breach", Some(Level::Error))]
+    #[case("2024-05-01 12:00:03 [warn] shields at 40%", Some(Level::Warn))]
+    #[case("[DBG] replicator idle", Some(Level::Debug))]
+    #[case("Station log: all systems nominal", None)]
+    #[case("Odo reports that an error occurred", None)]
+    #[case("", None)]
+    fn test_detect_text(#[case] text: &str, #[case] expected: Option<Level>) {
+        assert_eq!(LevelMap::default().detect_text(text), expected);
+    }
+
+    #[rstest]
+    #[case(10, Some(Level::Trace))]
+    #[case(20, Some(Level::Debug))]
+    #[case(30, Some(Level::Info))]
+    #[case(40, Some(Level::Warn))]
+    #[case(50, Some(Level::Error))]
+    #[case(60, Some(Level::Fatal))]
+    #[case(3, Some(Level::Error))]
+    #[case(7, Some(Level::Debug))]
+    #[case(9, None)]
+    fn test_from_number(#[case] number: u64, #[case] expected: Option<Level>) {
+        assert_eq!(Level::from_number(number), expected);
+    }
+
+    #[test]
+    fn test_levels_are_ordered_by_severity() {
+        assert!(Level::Trace < Level::Debug);
+        assert!(Level::Warn < Level::Error);
+        assert!(Level::Error < Level::Fatal);
+    }
+}
@@ -0,0 +1,73 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Detection, filtering and formatting of JSON in streaming input.
+//!
+//! jlif finds JSON documents in arbitrary line-based input, including
+//! documents spanning multiple lines, and passes everything else through as
+//! text. The `jlif` binary is a thin command line wrapper around this crate,
+//! so the same pipeline can be embedded into other tools:
+//!
+//! ```
+//! use jlif::Pipeline;
+//! use jlif::filter::{OutputFilter, RegexFilter};
+//!
+//! let input = "Docking request\n{\n  \"ship\": \"Defiant\"\n}\n{\"ship\": \"Rio Grande\"}\n";
+//! let mut output = Vec::new();
+//!
+//! Pipeline::new()
+//!     .with_filter(OutputFilter::Regex(RegexFilter::new("defiant".to_string(), false)?))
+//!     .run(input.as_bytes(), &mut output)?;
+//!
+//! assert_eq!(String::from_utf8(output)?, "{\"ship\":\"Defiant\"}\n");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [`Pipeline::build`] returns the underlying [`StreamProcessor`], whose
+//! `with_*` methods add the optional stages like transforms, deduplication or
+//! context records.
+
+pub mod aggregate;
+pub mod alias;
+pub mod ansi;
+pub mod array_stream;
+pub mod buffer;
+pub mod cri;
+pub mod decompress;
+pub mod dedup;
+pub mod docker;
+pub mod envelope;
+pub mod exec;
+pub mod field_path;
+pub mod filter;
+pub mod follow;
+pub mod formatter;
+pub mod generator;
+pub mod group;
+pub mod input;
+pub mod level;
+pub mod listen;
+pub mod logfmt;
+pub mod merge;
+pub mod pipeline;
+pub mod prefix;
+pub mod processor;
+pub mod sample;
+pub mod schema;
+pub mod slurp;
+pub mod sort;
+pub mod source;
+pub mod split;
+pub mod stage;
+pub mod stats;
+pub mod syslog;
+pub mod tee;
+pub mod template;
+pub mod theme;
+pub mod timestamp;
+pub mod trace;
+pub mod transform;
+
+pub use pipeline::Pipeline;
+pub use processor::StreamProcessor;
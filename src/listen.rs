@@ -0,0 +1,355 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::source::{ChannelReader, forward_lines};
+use std::fmt;
+use std::io::{self, Read};
+use std::net::{TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::SyncSender;
+use std::thread;
+
+// Largest possible UDP payload
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// Address to accept log streams on, given as a URL like `tcp://0.0.0.0:5000`.
+/// A missing host like in `udp://:514` means all interfaces.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddress {
+    /// Every TCP connection is a stream of lines
+    Tcp(String),
+    /// Every UDP datagram is a line of its own, as sent by syslog
+    Udp(String),
+    /// Every connection to a Unix domain socket at this path is a stream of
+    /// lines, e.g. `unix:///tmp/jlif.sock`
+    Unix(PathBuf),
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(address) => write!(f, "tcp://{}", address),
+            ListenAddress::Udp(address) => write!(f, "udp://{}", address),
+            ListenAddress::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for ListenAddress {
+    type Err = String;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let address = |address: &str| match address {
+            "" => None,
+            _ if address.starts_with(':') => Some(format!("0.0.0.0{}", address)),
+            _ => Some(address.to_string()),
+        };
+        match url.split_once("://") {
+            Some(("tcp", rest)) => address(rest).map(ListenAddress::Tcp),
+            Some(("udp", rest)) => address(rest).map(ListenAddress::Udp),
+            Some(("unix", path)) if !path.is_empty() => Some(ListenAddress::Unix(path.into())),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            format!(
+                "expected an address like tcp://0.0.0.0:5000, udp://:514 or unix:///tmp/jlif.sock, got '{}'",
+                url
+            )
+        })
+    }
+}
+
+/// Reader merging the lines sent by all clients of a listener into one input.
+///
+/// Every TCP connection is read on a thread of its own, which hands on
+/// complete lines only, so lines of concurrent clients never interleave. UDP
+/// datagrams are handed on as single lines, line breaks within them replaced
+/// by spaces, which keeps JSON intact. Optionally
+/// each line starts with `[address] ` of the client that sent it, the form
+/// the default `--strip-prefix` pattern turns back into a field. Clients of a
+/// Unix domain socket have no address and are numbered instead.
+///
+/// The input never ends by itself, only once an interrupt is requested.
+pub struct ListenReader {
+    lines: ChannelReader,
+    local_address: String,
+    _socket_file: Option<SocketFile>,
+}
+
+impl ListenReader {
+    /// Starts listening on `address` and accepting clients in the background
+    pub fn bind(address: &ListenAddress, client_prefix: bool) -> io::Result<Self> {
+        let (sender, lines) = ChannelReader::new();
+        let mut socket_file = None;
+        let local_address = match address {
+            ListenAddress::Tcp(address) => {
+                let listener = TcpListener::bind(address)?;
+                let local_address = listener.local_addr()?.to_string();
+                thread::spawn(move || accept_tcp(listener, sender, client_prefix));
+                local_address
+            }
+            ListenAddress::Udp(address) => {
+                let socket = UdpSocket::bind(address)?;
+                let local_address = socket.local_addr()?.to_string();
+                thread::spawn(move || receive_udp(socket, sender, client_prefix));
+                local_address
+            }
+            ListenAddress::Unix(path) => {
+                let listener = bind_unix(path)?;
+                socket_file = Some(SocketFile(path.clone()));
+                thread::spawn(move || accept_unix(listener, sender, client_prefix));
+                path.display().to_string()
+            }
+        };
+
+        Ok(Self {
+            lines,
+            local_address,
+            _socket_file: socket_file,
+        })
+    }
+
+    /// Reports EOF instead of waiting for more lines once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.lines = self.lines.with_interrupt(interrupt);
+        self
+    }
+
+    /// Address actually bound, e.g. with the port chosen for port 0
+    pub fn local_address(&self) -> &str {
+        &self.local_address
+    }
+}
+
+/// Socket file removed once done listening
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+impl Read for ListenReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.lines.read(buf)
+    }
+}
+
+fn accept_tcp(listener: TcpListener, sender: SyncSender<Vec<u8>>, client_prefix: bool) {
+    for stream in listener.incoming() {
+        // A client failing to connect doesn't concern the others
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let prefix = client_prefix
+            .then(|| stream.peer_addr().ok())
+            .flatten()
+            .map(|address| format!("[{}] ", address));
+        let sender = sender.clone();
+        thread::spawn(move || forward_lines(stream, prefix, sender));
+    }
+}
+
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    // A socket left behind by an earlier run nobody listens on anymore is
+    // replaced, an active one or any other file is not
+    let is_socket = std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket());
+    if is_socket && UnixStream::connect(path).is_err() {
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+#[cfg(not(unix))]
+fn bind_unix(_path: &Path) -> io::Result<std::convert::Infallible> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix domain sockets are not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+fn accept_unix(
+    listener: std::os::unix::net::UnixListener,
+    sender: SyncSender<Vec<u8>>,
+    client_prefix: bool,
+) {
+    for (stream, number) in listener.incoming().flatten().zip(1..) {
+        let prefix = client_prefix.then(|| format!("[#{}] ", number));
+        let sender = sender.clone();
+        thread::spawn(move || forward_lines(stream, prefix, sender));
+    }
+}
+
+#[cfg(not(unix))]
+fn accept_unix(listener: std::convert::Infallible, _: SyncSender<Vec<u8>>, _: bool) {
+    match listener {}
+}
+
+fn receive_udp(socket: UdpSocket, sender: SyncSender<Vec<u8>>, client_prefix: bool) {
+    let mut datagram = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        let Ok((size, address)) = socket.recv_from(&mut datagram) else {
+            continue;
+        };
+        let mut line = if client_prefix {
+            format!("[{}] ", address).into_bytes()
+        } else {
+            Vec::new()
+        };
+        // Syslog senders may end a message with a line break or a NUL byte
+        let message = datagram[..size].trim_ascii_end();
+        let message = message.strip_suffix(b"\0").unwrap_or(message);
+        line.extend(message.iter().map(|&byte| match byte {
+            b'\n' | b'\r' => b' ',
+            byte => byte,
+        }));
+        line.push(b'\n');
+        if sender.send(line).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+
+    fn read_lines(reader: &mut BufReader<ListenReader>, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                line
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case("tcp://0.0.0.0:5000", Ok(ListenAddress::Tcp("0.0.0.0:5000".to_string())))]
+    #[case("tcp://[::1]:5000", Ok(ListenAddress::Tcp("[::1]:5000".to_string())))]
+    #[case("tcp://:5000", Ok(ListenAddress::Tcp("0.0.0.0:5000".to_string())))]
+    #[case("udp://:514", Ok(ListenAddress::Udp("0.0.0.0:514".to_string())))]
+    #[case("unix:///tmp/jlif.sock", Ok(ListenAddress::Unix("/tmp/jlif.sock".into())))]
+    #[case("unix://", Err(()))]
+    #[case("tcp://", Err(()))]
+    #[case("0.0.0.0:5000", Err(()))]
+    #[case("ftp://ds9:21", Err(()))]
+    fn test_parse_address(#[case] url: &str, #[case] expected: Result<ListenAddress, ()>) {
+        assert_eq!(url.parse::<ListenAddress>().map_err(|_| ()), expected);
+    }
+
+    #[test]
+    fn test_reads_lines_of_all_clients() {
+        let address = ListenAddress::Tcp("127.0.0.1:0".to_string());
+        let listener = ListenReader::bind(&address, false).unwrap();
+        let local_address = listener.local_address().to_string();
+        let mut reader = BufReader::new(listener);
+
+        let mut client = TcpStream::connect(&local_address).unwrap();
+        client.write_all(b"{\"msg\": \"Docked\"}\nQuark's").unwrap();
+        drop(client);
+        assert_eq!(
+            read_lines(&mut reader, 2),
+            vec!["{\"msg\": \"Docked\"}\n", "Quark's\n"]
+        );
+
+        let mut client = TcpStream::connect(&local_address).unwrap();
+        client.write_all(b"Undocked\n").unwrap();
+        assert_eq!(read_lines(&mut reader, 1), vec!["Undocked\n"]);
+    }
+
+    #[test]
+    fn test_prefixes_lines_with_client_address() {
+        let address = ListenAddress::Tcp("127.0.0.1:0".to_string());
+        let listener = ListenReader::bind(&address, true).unwrap();
+        let mut client = TcpStream::connect(listener.local_address()).unwrap();
+        let client_address = client.local_addr().unwrap();
+        let mut reader = BufReader::new(listener);
+
+        client.write_all(b"Docked\n").unwrap();
+
+        assert_eq!(
+            read_lines(&mut reader, 1),
+            vec![format!("[{}] Docked\n", client_address)]
+        );
+    }
+
+    #[test]
+    fn test_reads_datagrams_as_lines() {
+        let address = ListenAddress::Udp("127.0.0.1:0".to_string());
+        let listener = ListenReader::bind(&address, true).unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client_address = client.local_addr().unwrap();
+        client.connect(listener.local_address()).unwrap();
+        let mut reader = BufReader::new(listener);
+
+        client
+            .send(b"<14>1 2024-05-01T12:00:03Z ds9 api - - - Docked\n")
+            .unwrap();
+        client.send(b"{\n  \"msg\": \"Undocked\"\n}\0").unwrap();
+
+        assert_eq!(
+            read_lines(&mut reader, 2),
+            vec![
+                format!(
+                    "[{}] <14>1 2024-05-01T12:00:03Z ds9 api - - - Docked\n",
+                    client_address
+                ),
+                format!("[{}] {{   \"msg\": \"Undocked\" }}\n", client_address),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reads_unix_socket_clients() {
+        use std::os::unix::net::UnixStream;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jlif.sock");
+        // Left behind by a run that didn't get to clean up
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let address = ListenAddress::Unix(path.clone());
+        let mut reader = BufReader::new(ListenReader::bind(&address, true).unwrap());
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"Docked\n").unwrap();
+        drop(client);
+        assert_eq!(read_lines(&mut reader, 1), vec!["[#1] Docked\n"]);
+
+        // Clients may come back after disconnecting
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"{\"msg\": \"Undocked\"}\n").unwrap();
+        assert_eq!(
+            read_lines(&mut reader, 1),
+            vec!["[#2] {\"msg\": \"Undocked\"}\n"]
+        );
+
+        drop(reader);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_interrupt_ends_input() {
+        let address = ListenAddress::Tcp("127.0.0.1:0".to_string());
+        let interrupt = Arc::new(AtomicBool::new(true));
+        let mut reader = ListenReader::bind(&address, false)
+            .unwrap()
+            .with_interrupt(interrupt);
+
+        assert_eq!(reader.read(&mut [0; 16]).unwrap(), 0);
+    }
+}
@@ -0,0 +1,103 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use serde_json::{Map, Value};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Parses a logfmt line like `level=error msg="boom" user=quark` into an object.
+///
+/// Values are kept as strings, quoted ones with their escapes resolved. Lines
+/// are only accepted if they consist of nothing but `key=value` pairs, so plain
+/// text that happens to contain an `=` is left alone.
+pub fn parse(line: &str) -> Option<Map<String, Value>> {
+    let mut record = Map::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|&c| c != '=' && c != '"' && !c.is_whitespace()) {
+            key.push(c);
+        }
+        if key.is_empty() || chars.next_if_eq(&'=').is_none() {
+            return None;
+        }
+
+        let value = if chars.next_if_eq(&'"').is_some() {
+            parse_quoted(&mut chars)?
+        } else {
+            let mut value = String::new();
+            while let Some(c) = chars.next_if(|&c| c != '"' && !c.is_whitespace()) {
+                value.push(c);
+            }
+            value
+        };
+        // Pairs are separated by whitespace
+        if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+            return None;
+        }
+        record.insert(key, Value::String(value));
+    }
+
+    (!record.is_empty()).then_some(record)
+}
+
+/// Reads a quoted value after its opening quote, `None` if it is never closed
+fn parse_quoted(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                other => value.push(other),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(
+        "level=error msg=\"Warp core breach\" deck=12",
+        json!({"level": "error", "msg": "Warp core breach", "deck": "12"})
+    )]
+    #[case(
+        "  at=info   path=/replicator  ",
+        json!({"at": "info", "path": "/replicator"})
+    )]
+    #[case(
+        r#"msg="Quark said \"no\"\n" empty= quoted="""#,
+        json!({"msg": "Quark said \"no\"\n", "empty": "", "quoted": ""})
+    )]
+    #[case("ship=Defiant ship=\"Rio Grande\"", json!({"ship": "Rio Grande"}))]
+    fn test_parses_pairs(#[case] line: &str, #[case] expected: Value) {
+        assert_eq!(parse(line).map(Value::Object), Some(expected));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("Quark's bar opens")]
+    #[case("Starting server on port=8080")]
+    #[case("msg=\"unterminated")]
+    #[case("=value")]
+    #[case("msg=\"Docked\"deck=3")]
+    #[case("a=b=c\"")]
+    fn test_rejects_other_lines(#[case] line: &str) {
+        assert_eq!(parse(line), None);
+    }
+}
@@ -2,54 +2,1118 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-mod buffer;
 mod cli;
-mod filter;
-mod formatter;
-mod processor;
+mod config;
 
 use anyhow::Result;
 use mimalloc::MiMalloc;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
-use buffer::LineBuffer;
-use clap::Parser;
-use cli::JlifArgs;
-use filter::OutputFilter;
-use formatter::JsonFormatter;
-use processor::StreamProcessor;
-use std::io;
+use cli::{Command, JlifArgs, TextLines, TimeMode};
+use jiff::tz::TimeZone;
+use jlif::Pipeline;
+use jlif::aggregate::{
+    Aggregation, CountAggregator, HistogramAggregator, NumericAggregator, SummaryAggregator,
+    TopAggregator,
+};
+use jlif::alias::KeyAliases;
+use jlif::buffer::TrailingText;
+use jlif::cri::CriUnwrapper;
+use jlif::decompress::DecompressReader;
+use jlif::dedup::{ConsecutiveDedup, DedupState};
+use jlif::docker::DockerUnwrapper;
+use jlif::envelope::LogEnvelope;
+use jlif::exec;
+use jlif::field_path::FieldPath;
+use jlif::filter::{
+    AllFilter, HasFieldFilter, LevelFilter, OutputFilter, Pattern, TimeRangeFilter,
+};
+use jlif::follow::FollowReader;
+use jlif::formatter::{
+    EscapingFormatter, ExtractFormatter, JsonFormatter, JsonSeqFormatter, OutputFormat,
+    SortedKeysFormatter, SyntectFormatter, TableFormatter, TemplateFormatter, ThemeEngine,
+    TomlFormatter, YamlFormatter,
+};
+use jlif::generator::{GeneratorConfig, LogGenerator};
+use jlif::group::RecordGroups;
+use jlif::input::InputFormat;
+use jlif::level::LevelMap;
+use jlif::listen::{ListenAddress, ListenReader};
+use jlif::merge::MergeReader;
+use jlif::prefix::{DEFAULT_PREFIX_PATTERN, InputLabels, LinePrefix, Provenance};
+use jlif::processor::{
+    AnsiStripping, DeltaClock, MalformedLines, RecordRange, SizeLimits, StreamProcessor,
+    TextWrapping,
+};
+use jlif::sample::{Sampler, Sampling};
+use jlif::schema::SchemaAggregator;
+use jlif::slurp::SlurpedRecords;
+use jlif::sort::RecordSorter;
+use jlif::split::LevelSplit;
+use jlif::stage::{self, Stage};
+use jlif::stats::Stats;
+use jlif::tee::TeeReader;
+use jlif::template::Template;
+use jlif::theme::load_theme;
+use jlif::timestamp::TimeFormats;
+use jlif::transform::{
+    DepthTransform, DropTransform, FlattenTransform, ProjectTransform, RecordTransform,
+    RedactTransform, SetTransform, TimeTransform, TruncateTransform, UnwrapTransform,
+};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitCode, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, mpsc};
 
-fn main() -> Result<()> {
-    let args = JlifArgs::parse();
+// Exit statuses like grep: a match, no match while filtering, or an error
+const EXIT_NO_MATCH: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+// --check found lines starting like JSON that didn't parse
+const EXIT_MALFORMED: u8 = 1;
+
+// Output buffer when stdout is not a terminal, unless --buffer-size says otherwise
+const OUTPUT_BUFFER_SIZE: usize = 64 * 1024;
+
+type Processor = StreamProcessor<Box<dyn Read + Send>, Box<dyn Write>>;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn run() -> Result<ExitCode> {
+    let args = config::parse_args().map_err(|e| anyhow::anyhow!("Config error: {}", e))?;
+
+    if let Some(Command::Gen(gen_args)) = args.command {
+        let mut generator = LogGenerator::new(GeneratorConfig {
+            format: gen_args.format,
+            rate: gen_args.rate,
+            count: gen_args.count,
+            mixed_text: gen_args.mixed_text,
+            multi_line: gen_args.multi_line,
+            malformed: gen_args.malformed,
+            seed: gen_args.seed,
+        });
+        generator.run(io::BufWriter::new(io::stdout()))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Resolve output defaults before any processor is built.
+    // An output file is never a terminal, wherever stdout goes.
+    // Split files are rendered like the output, so it is never treated as a terminal
+    let output_is_terminal =
+        args.output_file.is_none() && args.split_by_level.is_none() && io::stdout().is_terminal();
+    // Built before any input or output is opened, so a bad pattern or theme
+    // fails first. Parallel jobs build one for each input.
+    let pipeline = build_pipeline(&args, output_is_terminal)?;
+
+    // Interactive input has no producer that ends along with Ctrl-C, so it
+    // keeps the default of exiting right away
+    let reads_terminal = io::stdin().is_terminal()
+        && args.listen.is_none()
+        && args.exec.is_empty()
+        && (args.files.is_empty() || args.files.iter().any(|path| path.as_os_str() == "-"));
+    let interrupt = if reads_terminal {
+        None
+    } else {
+        Some(drain_on_signals()?)
+    };
+
+    // Open all inputs upfront, so a typo in the last path fails before any output
+    let mut child = None;
+    let mut inputs = if !args.exec.is_empty() {
+        let (process, input) = exec_input(&args.exec, interrupt.as_ref())?;
+        child = Some(process);
+        input
+    } else if let Some(address) = &args.listen {
+        listen_input(address, args.connection_prefix, interrupt.as_ref())?
+    } else if args.follow_name {
+        follow_input(&args.files, interrupt.as_ref())?
+    } else if let Some(field) = &args.merge_by {
+        let formats = TimeFormats::new(args.time_format.clone());
+        merge_inputs(&args.files, args.decompress, field, formats)?
+    } else {
+        open_inputs(&args.files, args.decompress)?
+    };
+    if let Some(path) = &args.tee {
+        let copy = File::create(path)
+            .map_err(|e| anyhow::anyhow!("Cannot open tee file '{}': {}", path.display(), e))?;
+        let copy = Arc::new(copy);
+        inputs = inputs
+            .into_iter()
+            .map(|input| -> Box<dyn Read + Send> {
+                Box::new(TeeReader::new(input, Arc::clone(&copy)))
+            })
+            .collect();
+    }
+
+    let output: Box<dyn Write> = match &args.output_file {
+        _ if args.quiet || args.check => Box::new(io::sink()),
+        _ if args.split_by_level.is_some() && !args.echo => Box::new(io::sink()),
+        Some(path) => Box::new(create_output_file(path, args.append)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    // Terminals get every line right away, anything else is block buffered
+    let writer: Box<dyn Write> = if let Some(size) = args.buffer_size {
+        Box::new(BufWriter::with_capacity(size as usize, output))
+    } else if args.unbuffered || output_is_terminal || args.quiet {
+        output
+    } else {
+        Box::new(BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, output))
+    };
+    // --check counts the JSON documents with the stats
+    let stats = if args.stats || args.check {
+        let stats = Arc::new(Stats::new());
+        report_stats_on_signal(Arc::clone(&stats))?;
+        Some(stats)
+    } else {
+        None
+    };
+    let session = Session {
+        output_is_terminal,
+        stats,
+        interrupt,
+    };
+
+    let outcome = match args.jobs {
+        Some(jobs) => process_in_parallel(&args, &session, inputs, writer, jobs.into())?,
+        None => {
+            let first_input = inputs.remove(0);
+            let mut stream_processor =
+                build_processor(&args, &session, pipeline, first_input, writer, &args.files)?
+                    .with_inputs(inputs);
+
+            // Process the stream
+            stream_processor.process()?;
+            Outcome::of(&stream_processor)
+        }
+    };
+
+    if let Some(stats) = session.stats.as_ref().filter(|_| args.stats) {
+        stats.render(&mut io::stderr().lock())?;
+    }
+
+    if args.check {
+        let documents = session
+            .stats
+            .as_ref()
+            .map_or(0, |stats| stats.json_records());
+        let malformed = outcome.malformed.unwrap_or_default();
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "json documents:  {}", documents)?;
+        writeln!(stdout, "malformed lines: {}", malformed.count)?;
+        for line in malformed_examples(&malformed) {
+            writeln!(stdout, "  {}", line)?;
+        }
+        return Ok(if malformed.count > 0 {
+            ExitCode::from(EXIT_MALFORMED)
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+
+    if let Some(malformed) = outcome.malformed.filter(|malformed| malformed.count > 0) {
+        let mut message = format!(
+            "{} line{} started like JSON but didn't parse:",
+            malformed.count,
+            if malformed.count == 1 { "" } else { "s" }
+        );
+        for line in malformed_examples(&malformed) {
+            message.push_str(&format!("\n  {}", line));
+        }
+        return Err(anyhow::anyhow!(message));
+    }
+
+    // Exit with the status of the command whose output was formatted
+    if let Some(mut child) = child {
+        return Ok(exit_code_of(child.wait()?));
+    }
+
+    // Exit like the shell reports a process killed by the signal
+    if let Some(signal) = session.interrupt.as_ref().and_then(Interrupt::received) {
+        return Ok(ExitCode::from(128 + signal as u8));
+    }
+
+    if outcome.filtering && outcome.matched == 0 {
+        return Ok(ExitCode::from(EXIT_NO_MATCH));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Assembles the pipeline from the buffer limits, filter and formatter given
+/// in the arguments
+fn build_pipeline(args: &JlifArgs, output_is_terminal: bool) -> Result<Pipeline> {
+    let compact = args.use_compact(output_is_terminal);
+    let colored = args.use_color(output_is_terminal);
+    // Level colors and match highlighting are applied to plain output, as
+    // syntax highlighting would override them or split up matches
+    let level_colors = args.level_colors && colored;
+    let highlight = args.highlight && colored && args.filter.is_some();
+    let level_map = LevelMap::new(args.level_map.clone());
 
     // Create filter from CLI arguments
+    let case_sensitive = args.case_sensitive
+        || (args.smart_case
+            && args
+                .filter
+                .as_ref()
+                .is_some_and(|pattern| pattern.chars().any(char::is_uppercase)));
+    let pattern = args.filter.clone().map(|pattern| {
+        if args.fixed_strings {
+            Pattern::Literal(pattern)
+        } else {
+            Pattern::Regex(pattern)
+        }
+    });
+    let pattern = match args.filter_field.clone() {
+        Some(field) => pattern.map(|pattern| Pattern::Field(field, Box::new(pattern))),
+        None => pattern,
+    };
     let filter = OutputFilter::from_args(
-        args.filter,
-        args.case_sensitive,
-        args.json_only,
+        pattern,
+        case_sensitive,
+        args.query.clone(),
+        args.conditions.clone(),
+        args.level
+            .map(|min_level| LevelFilter::new(min_level, level_map.clone())),
+        args.json_only || args.ndjson == Some(TextLines::Drop),
         args.invert_match,
     )
     .map_err(|e| anyhow::anyhow!("Filter error: {}", e))?;
-
-    // Create LineBuffer with user-specified max_lines
-    let line_buffer = LineBuffer::new(args.max_lines);
+    // The time window and the extracted field aren't inverted by `-v` along
+    // with the other filters
+    let mut required = Vec::new();
+    if args.since.is_some() || args.until.is_some() {
+        let time_range = TimeRangeFilter::new(&args.time_field, args.since, args.until)
+            .with_time_formats(TimeFormats::new(args.time_format.clone()));
+        required.push(OutputFilter::TimeRange(time_range));
+    }
+    if let Some(path) = &args.extract {
+        required.push(OutputFilter::HasField(HasFieldFilter::new(path)));
+    }
+    let filter = if required.is_empty() {
+        filter
+    } else {
+        required.insert(0, filter);
+        OutputFilter::All(AllFilter::new(required))
+    };
 
     // Create the appropriate JSON formatter based on flags
-    let json_formatter = JsonFormatter::from_args(args.compact, args.no_color);
+    let json_formatter = match args.theme_engine {
+        _ if level_colors || highlight => JsonFormatter::from_args(compact, true),
+        ThemeEngine::Syntect if colored => JsonFormatter::Syntect(
+            SyntectFormatter::new(&args.syntect_theme, compact)
+                .map_err(|e| anyhow::anyhow!("Theme error: {}", e))?,
+        ),
+        _ => JsonFormatter::from_args(compact, !colored).with_styler(
+            load_theme(&args.theme).map_err(|e| anyhow::anyhow!("Theme error: {}", e))?,
+        ),
+    };
+    let json_formatter = if args.escape_non_ascii || args.raw_newlines {
+        JsonFormatter::Escaping(EscapingFormatter::new(
+            json_formatter,
+            args.escape_non_ascii,
+            args.raw_newlines,
+        ))
+    } else {
+        json_formatter
+    };
+
+    let json_formatter = match args.output_format {
+        OutputFormat::Json => json_formatter,
+        OutputFormat::Toml => JsonFormatter::Toml(TomlFormatter::new(json_formatter)),
+        OutputFormat::Yaml => JsonFormatter::Yaml(YamlFormatter::new()),
+        OutputFormat::JsonSeq => JsonFormatter::JsonSeq(JsonSeqFormatter::new(json_formatter)),
+        OutputFormat::Table => {
+            // Only a terminal has a width to truncate to
+            let terminal_width = terminal_size::terminal_size()
+                .filter(|_| output_is_terminal)
+                .map(|(width, _)| width.0 as usize);
+            JsonFormatter::Table(TableFormatter::new(&args.columns, terminal_width))
+        }
+    };
+
+    if !args.columns.is_empty() && args.output_format != OutputFormat::Table {
+        return Err(anyhow::anyhow!("--columns requires --output table"));
+    }
+
+    let json_formatter = match (&args.format, &args.extract) {
+        (Some(template), _) => JsonFormatter::Template(TemplateFormatter::new(
+            Template::parse(template).map_err(|e| anyhow::anyhow!("Template error: {}", e))?,
+        )),
+        (None, Some(path)) => JsonFormatter::Extract(ExtractFormatter::new(FieldPath::parse(path))),
+        (None, None) => json_formatter,
+    };
+
+    let json_formatter = if args.sort_keys {
+        JsonFormatter::SortedKeys(SortedKeysFormatter::new(json_formatter))
+    } else {
+        json_formatter
+    };
+
+    let mut pipeline = Pipeline::new()
+        .with_max_lines(args.max_lines)
+        .with_filter(filter)
+        .with_formatter(json_formatter);
+    if let Some(max_bytes) = args.max_buffer_bytes.or(args.max_memory) {
+        pipeline = pipeline.with_max_buffer_bytes(max_bytes as usize);
+    }
+    if args.allow_trailing {
+        let trailing = match args.trailing_field.clone() {
+            Some(key) => TrailingText::Field(key),
+            None => TrailingText::Separate,
+        };
+        pipeline = pipeline.with_trailing_text(trailing);
+    }
+
+    Ok(pipeline)
+}
+
+/// Builds the processor for `input` from the arguments, reading `files`
+/// Creates the transform of a pipeline stage, if its options enable it
+fn build_transform(
+    args: &JlifArgs,
+    stage: Stage,
+    time_formats: &TimeFormats,
+) -> Option<RecordTransform> {
+    let transform = match stage {
+        Stage::Unwrap if args.unwrap_nested => RecordTransform::Unwrap(UnwrapTransform::default()),
+        Stage::Redact if !args.redact.is_empty() => {
+            RecordTransform::Redact(RedactTransform::new(&args.redact))
+        }
+        Stage::Time => {
+            let time = match args.time? {
+                TimeMode::Local => TimeTransform::zoned(&args.time_field, TimeZone::system()),
+                TimeMode::Utc => TimeTransform::zoned(&args.time_field, TimeZone::UTC),
+                TimeMode::Relative => TimeTransform::relative(&args.time_field),
+            };
+            RecordTransform::Time(time.with_time_formats(time_formats.clone()))
+        }
+        Stage::Fields if !args.fields.is_empty() => {
+            RecordTransform::Project(ProjectTransform::new(&args.fields))
+        }
+        Stage::Drop if !args.drop.is_empty() => {
+            RecordTransform::Drop(DropTransform::new(&args.drop))
+        }
+        Stage::Set if !args.set_fields.is_empty() => {
+            RecordTransform::Set(SetTransform::new(&args.set_fields))
+        }
+        Stage::Flatten if args.flatten => RecordTransform::Flatten(FlattenTransform::new(
+            args.flatten_separator.clone(),
+            args.flatten_arrays,
+        )),
+        Stage::Truncate if args.max_string_len.is_some() || args.max_array_items.is_some() => {
+            RecordTransform::Truncate(TruncateTransform::new(
+                args.max_string_len,
+                args.max_array_items,
+            ))
+        }
+        Stage::Depth => RecordTransform::Depth(DepthTransform::new(args.max_depth? as usize)),
+        _ => return None,
+    };
+    Some(transform)
+}
+
+fn build_processor(
+    args: &JlifArgs,
+    session: &Session,
+    pipeline: Pipeline,
+    input: Box<dyn Read + Send>,
+    writer: Box<dyn Write>,
+    files: &[PathBuf],
+) -> Result<Processor> {
+    let colored = args.use_color(session.output_is_terminal);
+    let level_colors = args.level_colors && colored;
+    let highlight = args.highlight && colored && args.filter.is_some();
+    let context = args.context_records();
+    let record_memory = args.record_memory();
+    let time_formats = TimeFormats::new(args.time_format.clone());
+    let top = args
+        .top_values()
+        .map_err(|e| anyhow::anyhow!(e))?
+        .map(|(count, field)| (count, FieldPath::parse(field)));
+
+    // Shared by every level-aware feature
+    let level_map = LevelMap::new(args.level_map.clone());
+
+    let mut stream_processor = pipeline.build(input, writer);
+
+    if args.quiet {
+        stream_processor = stream_processor.with_quiet();
+    }
+
+    if let Some(count) = args.max_count {
+        stream_processor = stream_processor.with_max_count(count);
+    }
+
+    let sampling = match (args.sample, args.every) {
+        (Some(rate), _) => Some(Sampling::Random(rate)),
+        (None, Some(every)) => Some(Sampling::Every(every)),
+        (None, None) => None,
+    };
+    if let Some(sampling) = sampling {
+        let sampler = Sampler::new(sampling);
+        let sampler = if args.unsampled_text {
+            sampler.keeping_text()
+        } else {
+            sampler
+        };
+        stream_processor = stream_processor.with_sampler(sampler);
+    }
+
+    if args.dedup {
+        let dedup = ConsecutiveDedup::new(&args.dedup_ignore);
+        stream_processor = stream_processor.with_repeats_collapsed(dedup, colored);
+    }
+
+    if let Some(field) = &args.group_by {
+        let time_fields = args
+            .time_field
+            .iter()
+            .map(|field| FieldPath::parse(field))
+            .collect();
+        let mut groups = RecordGroups::new(FieldPath::parse(field), time_fields)
+            .with_time_formats(time_formats.clone());
+        if let Some(timeout) = args.group_timeout {
+            groups = groups.with_timeout(timeout);
+        }
+        if let Some(memory) = record_memory {
+            groups = groups.with_memory_limit(memory);
+        }
+        stream_processor = stream_processor.with_grouping(groups, colored);
+    }
+
+    if let Some(limit) = args.rate_limit {
+        stream_processor = stream_processor.with_rate_limit(limit, colored);
+    }
+
+    if let Some(field) = &args.sort {
+        let mut sorter =
+            RecordSorter::new(FieldPath::parse(field)).with_time_formats(time_formats.clone());
+        if let Some(memory) = args
+            .sort_memory
+            .map(|memory| memory as usize)
+            .or(record_memory)
+        {
+            sorter = sorter.with_memory_limit(memory);
+        }
+        stream_processor = stream_processor.with_sorting(sorter);
+    }
+
+    if args.skip.is_some() || args.head.is_some() || args.tail.is_some() {
+        stream_processor = stream_processor.with_record_range(RecordRange {
+            skip: args.skip.unwrap_or(0),
+            head: args.head,
+            tail: args.tail,
+        });
+    }
+
+    if args.strict || args.check {
+        stream_processor = stream_processor.with_strict();
+    }
+
+    if args.group_traces {
+        stream_processor = stream_processor.with_trace_grouping();
+    }
+
+    if args.wrap_text {
+        stream_processor = stream_processor.with_wrapped_text(TextWrapping::Message);
+    } else if args.ndjson == Some(TextLines::Wrap) {
+        stream_processor = stream_processor.with_wrapped_text(TextWrapping::Text);
+    }
+
+    if args.unbuffered {
+        stream_processor = stream_processor.with_unbuffered_output();
+    }
+
+    if let Some(interval) = args.watch_summary {
+        let summary = SummaryAggregator::new(level_map.clone());
+        stream_processor = stream_processor
+            .with_aggregation(Aggregation::Summary(summary))
+            .with_refresh(interval);
+    }
+
+    if let Some(path) = &args.dedup_state {
+        let state = DedupState::open(path).map_err(|e| anyhow::anyhow!("Dedup error: {}", e))?;
+        stream_processor = stream_processor.with_dedup(state);
+    }
+
+    if args.split_array {
+        stream_processor = stream_processor.with_array_splitting();
+    }
+
+    if args.min_size.is_some() || args.max_size.is_some() {
+        stream_processor = stream_processor.with_size_limits(SizeLimits {
+            min: args.min_size,
+            max: args.max_size,
+        });
+    }
+
+    if args.show_size {
+        stream_processor = stream_processor.with_size_annotations(colored);
+    }
+
+    if args.show_deltas {
+        // Live input is timed by its arrival, which is what reveals stalls
+        let live = args.follow_name || args.listen.is_some() || !args.exec.is_empty();
+        let clock = if live {
+            DeltaClock::Arrival
+        } else {
+            DeltaClock::Fields(
+                args.time_field
+                    .iter()
+                    .map(|field| FieldPath::parse(field))
+                    .collect(),
+                time_formats.clone(),
+            )
+        };
+        stream_processor = stream_processor.with_delta_annotations(clock, colored);
+    }
+
+    if let Some((before, after)) = context {
+        stream_processor = stream_processor.with_context(before, after);
+    }
+
+    // Transforms listed before the filter reshape records before filtering
+    let mut filtered = false;
+    for stage in stage::order(&args.pipeline)? {
+        if stage == Stage::Filter {
+            filtered = true;
+        } else if let Some(transform) = build_transform(args, stage, &time_formats) {
+            stream_processor = if filtered {
+                stream_processor.with_transform(transform)
+            } else {
+                stream_processor.with_pre_filter_transform(transform)
+            };
+        }
+    }
+
+    if !args.alias.is_empty() {
+        stream_processor = stream_processor.with_aliases(KeyAliases::new(args.alias.clone()));
+    }
+
+    if highlight {
+        stream_processor = stream_processor.with_match_highlighting();
+    }
+
+    if let Some(separator) = args.input_separator.clone() {
+        stream_processor = stream_processor.with_separator(separator);
+    }
+
+    if args.docker {
+        let docker = DockerUnwrapper::new(args.docker_metadata);
+        stream_processor = stream_processor.with_envelope(LogEnvelope::Docker(docker));
+    } else if args.cri {
+        let cri = CriUnwrapper::new(args.cri_metadata);
+        stream_processor = stream_processor.with_envelope(LogEnvelope::Cri(cri));
+    }
+
+    // Syslog is what is usually sent over UDP
+    let input_format = args.input_format.unwrap_or(match args.listen {
+        Some(ListenAddress::Udp(_)) => InputFormat::Syslog,
+        _ => InputFormat::Json,
+    });
+
+    if args.strip_ansi {
+        // Text lines only keep their colors where the output is colored anyway,
+        // unless they may be parsed into records as well
+        let stripping = if colored && input_format == InputFormat::Json {
+            AnsiStripping::Json
+        } else {
+            AnsiStripping::All
+        };
+        stream_processor = stream_processor.with_ansi_stripping(stripping);
+    }
+
+    // Client addresses, command streams and merged files are written in a form
+    // the default pattern recognizes
+    let strip_prefix = match &args.strip_prefix {
+        None if args.connection_prefix || !args.exec.is_empty() || args.merge_by.is_some() => {
+            Some(&None)
+        }
+        strip_prefix => strip_prefix.as_ref(),
+    };
+    if let Some(pattern) = strip_prefix {
+        let pattern = pattern.as_deref().unwrap_or(DEFAULT_PREFIX_PATTERN);
+        let prefix = LinePrefix::new(pattern, args.prefix_field.clone())
+            .map_err(|e| anyhow::anyhow!("Prefix error: {}", e))?;
+        stream_processor = stream_processor.with_line_prefix(prefix);
+    }
+
+    if args.label {
+        let labels = files.iter().map(|path| input_label(path)).collect();
+        let labels = InputLabels::new(labels, args.prefix_field.clone());
+        stream_processor = stream_processor.with_input_labels(labels);
+    }
+
+    if args.annotate {
+        let sources = if !args.exec.is_empty() {
+            vec![args.exec.join(" ")]
+        } else if let Some(address) = &args.listen {
+            vec![address.to_string()]
+        } else if files.is_empty() {
+            vec![input_label(Path::new("-"))]
+        } else {
+            files.iter().map(|path| input_label(path)).collect()
+        };
+        stream_processor = stream_processor.with_provenance(Provenance::new(sources));
+    }
+
+    if input_format != InputFormat::Json {
+        stream_processor = stream_processor.with_input_format(input_format);
+    }
 
-    // Create StreamProcessor with stdin, stdout, buffer, filter, and formatter
-    let mut stream_processor = StreamProcessor::new(
-        io::stdin(),
-        io::stdout(),
-        line_buffer,
-        filter,
-        json_formatter,
-    );
+    if args.explode {
+        stream_processor = stream_processor.with_exploded_arrays();
+    }
 
-    // Process the stream
-    stream_processor.process()?;
+    if args.slurp {
+        let mut records = SlurpedRecords::new();
+        if let Some(memory) = record_memory {
+            records = records.with_memory_limit(memory);
+        }
+        stream_processor = stream_processor.with_slurp(records);
+    }
 
+    if args.split_streams {
+        stream_processor = stream_processor.with_text_output(Box::new(io::stderr()));
+    }
+
+    if let Some(directory) = &args.split_by_level {
+        std::fs::create_dir_all(directory).map_err(|e| {
+            anyhow::anyhow!(
+                "Cannot create split directory '{}': {}",
+                directory.display(),
+                e
+            )
+        })?;
+        let split = LevelSplit::new(directory.clone(), level_map.clone());
+        stream_processor = stream_processor.with_level_split(split);
+    }
+
+    if level_colors {
+        stream_processor = stream_processor.with_level_colors(level_map);
+    }
+
+    if let Some(interrupt) = &session.interrupt {
+        stream_processor = stream_processor.with_interrupt(Arc::clone(&interrupt.requested));
+    }
+
+    if let Some(timeout) = args.flush_timeout {
+        stream_processor = stream_processor.with_flush_timeout(timeout);
+    }
+
+    if let Some(interval) = args.heartbeat {
+        stream_processor = stream_processor.with_heartbeat(interval, colored);
+    }
+
+    if let Some(field) = &args.histogram {
+        let mut histogram = HistogramAggregator::new(FieldPath::parse(field));
+        if let Some(buckets) = args.buckets {
+            histogram = histogram.with_buckets(buckets.into());
+        }
+        stream_processor = stream_processor.with_aggregation(Aggregation::Histogram(histogram));
+    }
+
+    if !args.count_by.is_empty() {
+        let fields = args.count_by.iter().map(|field| FieldPath::parse(field));
+        let count = CountAggregator::new(fields.collect());
+        stream_processor = stream_processor.with_aggregation(Aggregation::Count(count));
+    }
+
+    if let Some((count, field)) = top {
+        let top = TopAggregator::new(field, count);
+        stream_processor = stream_processor.with_aggregation(Aggregation::Top(top));
+    }
+
+    if let Some(field) = &args.stats_field {
+        let numeric = NumericAggregator::new(FieldPath::parse(field));
+        stream_processor = stream_processor.with_aggregation(Aggregation::Numeric(numeric));
+    }
+
+    if args.infer_schema {
+        stream_processor =
+            stream_processor.with_aggregation(Aggregation::Schema(SchemaAggregator::new()));
+    }
+
+    if args.with_records {
+        stream_processor = stream_processor.with_report_output(Box::new(io::stderr()));
+    }
+
+    if let Some(interval) = args.refresh {
+        stream_processor = stream_processor.with_refresh(interval);
+    }
+
+    if let Some(stats) = &session.stats {
+        stream_processor = stream_processor.with_stats(Arc::clone(stats));
+    }
+
+    Ok(stream_processor)
+}
+
+/// What the processors of all inputs share
+struct Session {
+    output_is_terminal: bool,
+    // Also counts the JSON documents for --check
+    stats: Option<Arc<Stats>>,
+    interrupt: Option<Interrupt>,
+}
+
+/// What is left to report once the input was processed
+#[derive(Default)]
+struct Outcome {
+    filtering: bool,
+    matched: u64,
+    malformed: Option<MalformedLines>,
+}
+
+impl Outcome {
+    fn of(processor: &Processor) -> Self {
+        Self {
+            filtering: processor.is_filtering(),
+            matched: processor.matched_records(),
+            malformed: processor.malformed_lines().cloned(),
+        }
+    }
+
+    /// Adds up the outcome of processing another input
+    fn merge(&mut self, other: Outcome) {
+        self.filtering |= other.filtering;
+        self.matched += other.matched;
+        self.malformed = match (self.malformed.take(), other.malformed) {
+            (Some(mut malformed), Some(other)) => {
+                malformed.merge(other);
+                Some(malformed)
+            }
+            (malformed, other) => malformed.or(other),
+        };
+    }
+}
+
+/// Inputs handed out to the workers of `--jobs`
+struct Jobs {
+    inputs: Vec<Option<Box<dyn Read + Send>>>,
+    next: usize,
+    written: usize,
+}
+
+/// Processes each input on one of `jobs` threads into a temporary file, and
+/// writes the output of each input as a whole, in the order of the arguments
+fn process_in_parallel(
+    args: &JlifArgs,
+    session: &Session,
+    inputs: Vec<Box<dyn Read + Send>>,
+    mut writer: Box<dyn Write>,
+    jobs: usize,
+) -> Result<Outcome> {
+    let count = inputs.len();
+    // Inputs are taken in order, at most this far ahead of the output, which
+    // bounds the temporary files waiting for an earlier input to finish
+    let ahead = jobs * 2;
+    let queue = Mutex::new(Jobs {
+        inputs: inputs.into_iter().map(Some).collect(),
+        next: 0,
+        written: 0,
+    });
+    let taken = Condvar::new();
+    let interrupted = || {
+        session
+            .interrupt
+            .as_ref()
+            .is_some_and(|interrupt| interrupt.requested.load(Ordering::Relaxed))
+    };
+    // Keeps workers from taking further inputs
+    let stop = || {
+        queue.lock().unwrap().next = count;
+        taken.notify_all();
+    };
+
+    std::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..jobs.min(count) {
+            let sender = sender.clone();
+            let (queue, taken) = (&queue, &taken);
+            scope.spawn(move || {
+                loop {
+                    let (index, input) = {
+                        let mut jobs = queue.lock().unwrap();
+                        while jobs.next < count && jobs.next >= jobs.written + ahead {
+                            jobs = taken.wait(jobs).unwrap();
+                        }
+                        if jobs.next >= count || interrupted() {
+                            return;
+                        }
+                        let index = jobs.next;
+                        jobs.next += 1;
+                        (index, jobs.inputs[index].take().unwrap())
+                    };
+                    let files = args.files.get(index).map_or(&[][..], std::slice::from_ref);
+                    let result = process_file(args, session, input, files);
+                    if sender.send((index, result)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut outcome = Outcome::default();
+        let mut finished = BTreeMap::new();
+        let mut written = 0;
+        for (index, result) in receiver {
+            finished.insert(index, result);
+            while let Some(result) = finished.remove(&written) {
+                let (mut output, file_outcome) = match result {
+                    Ok(done) => done,
+                    Err(e) => {
+                        stop();
+                        return Err(e);
+                    }
+                };
+                outcome.merge(file_outcome);
+                match io::copy(&mut output, &mut writer) {
+                    // The reading end went away, as with `jlif --jobs 4 *.log | head`
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                        stop();
+                        return Ok(outcome);
+                    }
+                    result => result?,
+                };
+                written += 1;
+                queue.lock().unwrap().written = written;
+                taken.notify_all();
+            }
+        }
+        match writer.flush() {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(outcome),
+            result => result.map(|_| outcome).map_err(Into::into),
+        }
+    })
+}
+
+/// Processes one input into a temporary file, returned rewound for reading
+fn process_file(
+    args: &JlifArgs,
+    session: &Session,
+    input: Box<dyn Read + Send>,
+    files: &[PathBuf],
+) -> Result<(File, Outcome)> {
+    let mut output = tempfile::tempfile()?;
+    let writer = BufWriter::with_capacity(OUTPUT_BUFFER_SIZE, output.try_clone()?);
+    let pipeline = build_pipeline(args, session.output_is_terminal)?;
+    let mut processor = build_processor(args, session, pipeline, input, Box::new(writer), files)?;
+    processor.process()?;
+    let outcome = Outcome::of(&processor);
+    // Flushes what is left in the buffer
+    drop(processor);
+    output.rewind()?;
+    Ok((output, outcome))
+}
+
+/// The malformed lines kept as examples, followed by how many more there were
+fn malformed_examples(malformed: &MalformedLines) -> Vec<String> {
+    let mut lines = malformed.examples.clone();
+    let more = malformed.count - malformed.examples.len() as u64;
+    if more > 0 {
+        lines.push(format!("… {} more", more));
+    }
+    lines
+}
+
+/// Exit code reporting the status of a child process like the shell does
+fn exit_code_of(status: ExitStatus) -> ExitCode {
+    if let Some(code) = status.code() {
+        return ExitCode::from(code as u8);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return ExitCode::from(128 + signal as u8);
+        }
+    }
+    ExitCode::FAILURE
+}
+
+/// Prints the statistics summary whenever SIGUSR1 arrives, e.g. through
+/// `kill -USR1 <pid>`, without interrupting the stream
+#[cfg(unix)]
+fn report_stats_on_signal(stats: Arc<Stats>) -> Result<()> {
+    use signal_hook::consts::SIGUSR1;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGUSR1])?;
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            // Nowhere to report a failure to write to stderr
+            let _ = stats.render(&mut io::stderr().lock());
+        }
+    });
     Ok(())
 }
+
+#[cfg(not(unix))]
+fn report_stats_on_signal(_stats: Arc<Stats>) -> Result<()> {
+    Ok(())
+}
+
+/// SIGINT or SIGTERM received while processing
+#[derive(Default)]
+struct Interrupt {
+    requested: Arc<AtomicBool>,
+    signal: Arc<AtomicUsize>,
+}
+
+impl Interrupt {
+    fn received(&self) -> Option<usize> {
+        Some(self.signal.load(Ordering::Relaxed)).filter(|signal| *signal != 0)
+    }
+}
+
+/// Keeps SIGINT and SIGTERM from killing jlif mid-stream: the first one stops
+/// reading, so buffered lines are still drained and the output flushed, and a
+/// second one exits right away
+#[cfg(unix)]
+fn drain_on_signals() -> Result<Interrupt> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::flag;
+
+    let interrupt = Interrupt::default();
+    for signal in [SIGINT, SIGTERM] {
+        // Registered first, so it only sees the flag set by an earlier signal
+        flag::register_conditional_shutdown(
+            signal,
+            128 + signal,
+            Arc::clone(&interrupt.requested),
+        )?;
+        flag::register(signal, Arc::clone(&interrupt.requested))?;
+        flag::register_usize(signal, Arc::clone(&interrupt.signal), signal as usize)?;
+    }
+    Ok(interrupt)
+}
+
+#[cfg(not(unix))]
+fn drain_on_signals() -> Result<Interrupt> {
+    Ok(Interrupt::default())
+}
+
+/// Follows the single input file by name, until interrupted
+fn follow_input(
+    paths: &[PathBuf],
+    interrupt: Option<&Interrupt>,
+) -> Result<Vec<Box<dyn Read + Send>>> {
+    match paths {
+        [path] if path.as_os_str() != "-" => {
+            let mut reader = FollowReader::new(path.clone());
+            if let Some(interrupt) = interrupt {
+                reader = reader.with_interrupt(Arc::clone(&interrupt.requested));
+            }
+            Ok(vec![Box::new(reader)])
+        }
+        _ => Err(anyhow::anyhow!("--follow-name requires exactly one file")),
+    }
+}
+
+/// Runs the command whose output is formatted
+fn exec_input(
+    command: &[String],
+    interrupt: Option<&Interrupt>,
+) -> Result<(Child, Vec<Box<dyn Read + Send>>)> {
+    let (child, mut reader) =
+        exec::spawn(command).map_err(|e| anyhow::anyhow!("Cannot run '{}': {}", command[0], e))?;
+    if let Some(interrupt) = interrupt {
+        reader = reader.with_interrupt(Arc::clone(&interrupt.requested));
+    }
+    Ok((child, vec![Box::new(reader)]))
+}
+
+/// Starts listening for clients sending log lines
+fn listen_input(
+    address: &ListenAddress,
+    connection_prefix: bool,
+    interrupt: Option<&Interrupt>,
+) -> Result<Vec<Box<dyn Read + Send>>> {
+    let mut reader = ListenReader::bind(address, connection_prefix)
+        .map_err(|e| anyhow::anyhow!("Cannot listen on '{}': {}", address, e))?;
+    if let Some(interrupt) = interrupt {
+        reader = reader.with_interrupt(Arc::clone(&interrupt.requested));
+    }
+    Ok(vec![Box::new(reader)])
+}
+
+/// Creates the output file, or opens it for appending
+fn create_output_file(path: &Path, append: bool) -> Result<File> {
+    let mut options = OpenOptions::new();
+    if append {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+    options
+        .create(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Cannot open output file '{}': {}", path.display(), e))
+}
+
+/// Opens the input files to be merged by the time in `field`, labeled with
+/// their paths
+fn merge_inputs(
+    paths: &[PathBuf],
+    decompress_stdin: bool,
+    field: &str,
+    formats: TimeFormats,
+) -> Result<Vec<Box<dyn Read + Send>>> {
+    let inputs = paths
+        .iter()
+        .map(|path| input_label(path))
+        .zip(open_inputs(paths, decompress_stdin)?)
+        .collect();
+    Ok(vec![Box::new(
+        MergeReader::new(inputs, FieldPath::parse(field)).with_time_formats(formats),
+    )])
+}
+
+/// Name of an input in labels and prefixes
+fn input_label(path: &Path) -> String {
+    if path.as_os_str() == "-" {
+        "stdin".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// Opens the input files in order, `-` or no files at all meaning stdin
+fn open_inputs(paths: &[PathBuf], decompress_stdin: bool) -> Result<Vec<Box<dyn Read + Send>>> {
+    let stdin = || -> Box<dyn Read + Send> {
+        if decompress_stdin {
+            Box::new(DecompressReader::new(io::stdin()))
+        } else {
+            Box::new(io::stdin())
+        }
+    };
+    if paths.is_empty() {
+        return Ok(vec![stdin()]);
+    }
+
+    paths
+        .iter()
+        .map(|path| -> Result<Box<dyn Read + Send>> {
+            if path.as_os_str() == "-" {
+                return Ok(stdin());
+            }
+            let file = File::open(path)
+                .map_err(|e| anyhow::anyhow!("Cannot open '{}': {}", path.display(), e))?;
+            // Rotated logs are often compressed
+            Ok(Box::new(DecompressReader::new(file)))
+        })
+        .collect()
+}
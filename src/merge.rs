@@ -0,0 +1,189 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::field_path::FieldPath;
+use crate::timestamp::TimeFormats;
+use jiff::Timestamp;
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// One of the inputs being merged
+struct MergeInput {
+    reader: BufReader<Box<dyn Read + Send>>,
+    prefix: String,
+    // Next line to output, `None` once the input ended
+    next: Option<Vec<u8>>,
+    // Time of the next line, or of the last record before it for text lines
+    time: Option<Timestamp>,
+}
+
+impl MergeInput {
+    fn advance(&mut self, field: &FieldPath, formats: &TimeFormats) -> io::Result<()> {
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            self.next = None;
+            return Ok(());
+        }
+        let time = serde_json::from_slice::<Value>(&line)
+            .ok()
+            .and_then(|record| field.get(&record).and_then(|time| formats.parse(time)));
+        if time.is_some() {
+            self.time = time;
+        }
+        self.next = Some(line);
+        Ok(())
+    }
+}
+
+/// Reader interleaving the lines of several inputs in the order of the
+/// timestamp field of their records, like `sort -m` does for sorted files.
+///
+/// Every input must be ordered by time itself, as only their next lines are
+/// compared. Each line starts with `[<label>] ` naming its input, the form the
+/// default `--strip-prefix` pattern turns into a field. Lines without a
+/// timestamp, like stack traces, stay with the record before them, and equal
+/// times keep the order of the inputs.
+pub struct MergeReader {
+    inputs: Vec<MergeInput>,
+    field: FieldPath,
+    formats: TimeFormats,
+    started: bool,
+    line: Vec<u8>,
+    position: usize,
+}
+
+impl MergeReader {
+    pub fn new(inputs: Vec<(String, Box<dyn Read + Send>)>, field: FieldPath) -> Self {
+        let inputs = inputs
+            .into_iter()
+            .map(|(label, input)| MergeInput {
+                reader: BufReader::new(input),
+                prefix: format!("[{}] ", label),
+                next: None,
+                time: None,
+            })
+            .collect();
+        Self {
+            inputs,
+            field,
+            formats: TimeFormats::default(),
+            started: false,
+            line: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Also reads timestamp strings in these formats
+    pub fn with_time_formats(mut self, formats: TimeFormats) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Moves the earliest pending line into the output buffer, `false` once
+    /// all inputs ended
+    fn next_line(&mut self) -> io::Result<bool> {
+        if !self.started {
+            for input in &mut self.inputs {
+                input.advance(&self.field, &self.formats)?;
+            }
+            self.started = true;
+        }
+
+        let Some(input) = self
+            .inputs
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, input)| input.next.is_some())
+            .min_by_key(|(index, input)| (input.time, *index))
+            .map(|(_, input)| input)
+        else {
+            return Ok(false);
+        };
+
+        let line = input.next.take().unwrap_or_default();
+        self.line.clear();
+        self.line.extend_from_slice(input.prefix.as_bytes());
+        self.line.extend_from_slice(&line);
+        if !line.ends_with(b"\n") {
+            self.line.push(b'\n');
+        }
+        self.position = 0;
+        input.advance(&self.field, &self.formats)?;
+        Ok(true)
+    }
+}
+
+impl Read for MergeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.line.len() && !self.next_line()? {
+            return Ok(0);
+        }
+        let count = buf.len().min(self.line.len() - self.position);
+        buf[..count].copy_from_slice(&self.line[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn merge(inputs: &[(&str, &str)], field: &str) -> String {
+        let inputs = inputs
+            .iter()
+            .map(|(label, data)| -> (String, Box<dyn Read + Send>) {
+                (label.to_string(), Box::new(Cursor::new(data.to_string())))
+            })
+            .collect();
+        let mut output = String::new();
+        MergeReader::new(inputs, FieldPath::parse(field))
+            .read_to_string(&mut output)
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn test_interleaves_records_by_time() {
+        let ops = "{\"ts\": \"2024-05-01T12:00:01Z\", \"msg\": \"Docked\"}\n\
+                   {\"ts\": \"2024-05-01T12:00:04Z\", \"msg\": \"Undocked\"}\n";
+        let bar = "{\"ts\": \"2024-05-01T12:00:02Z\", \"msg\": \"Bar opens\"}\n\
+                   {\"ts\": \"2024-05-01T12:00:03Z\", \"msg\": \"Bar closes\"}";
+
+        assert_eq!(
+            merge(&[("ops", ops), ("bar", bar)], "ts"),
+            "[ops] {\"ts\": \"2024-05-01T12:00:01Z\", \"msg\": \"Docked\"}\n\
+             [bar] {\"ts\": \"2024-05-01T12:00:02Z\", \"msg\": \"Bar opens\"}\n\
+             [bar] {\"ts\": \"2024-05-01T12:00:03Z\", \"msg\": \"Bar closes\"}\n\
+             [ops] {\"ts\": \"2024-05-01T12:00:04Z\", \"msg\": \"Undocked\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_text_lines_with_their_record() {
+        let ops = "{\"meta\": {\"time\": 1714564801}, \"msg\": \"Hull breach\"}\n\
+                   Traceback (most recent call last):\n\
+                   {\"meta\": {\"time\": 1714564803}, \"msg\": \"Sealed\"}\n";
+        let bar = "{\"meta\": {\"time\": 1714564802000}, \"msg\": \"Bar opens\"}\n";
+
+        assert_eq!(
+            merge(&[("ops", ops), ("bar", bar)], "meta.time"),
+            "[ops] {\"meta\": {\"time\": 1714564801}, \"msg\": \"Hull breach\"}\n\
+             [ops] Traceback (most recent call last):\n\
+             [bar] {\"meta\": {\"time\": 1714564802000}, \"msg\": \"Bar opens\"}\n\
+             [ops] {\"meta\": {\"time\": 1714564803}, \"msg\": \"Sealed\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_input_order_for_equal_times() {
+        let record = "{\"ts\": \"2024-05-01T12:00:01Z\"}\n";
+
+        assert_eq!(
+            merge(&[("ops", record), ("bar", record), ("", "")], "ts"),
+            format!("[ops] {}[bar] {}", record, record)
+        );
+    }
+}
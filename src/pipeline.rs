@@ -0,0 +1,136 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::buffer::{LineBuffer, TrailingText};
+use crate::filter::{NoFilter, OutputFilter};
+use crate::formatter::JsonFormatter;
+use crate::processor::StreamProcessor;
+use anyhow::Result;
+use std::io::{Read, Write};
+
+/// Lines buffered while waiting for a multi-line JSON document to complete
+pub const DEFAULT_MAX_LINES: usize = 10;
+
+/// Builder for the JSON detection pipeline.
+///
+/// Configures how input is buffered, which records are kept and how JSON is
+/// rendered. Defaults to passing everything through as compact, uncolored
+/// JSON with a buffer of [`DEFAULT_MAX_LINES`] lines.
+pub struct Pipeline {
+    max_lines: usize,
+    max_buffer_bytes: Option<usize>,
+    trailing_text: Option<TrailingText>,
+    filter: OutputFilter,
+    formatter: JsonFormatter,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            max_lines: DEFAULT_MAX_LINES,
+            max_buffer_bytes: None,
+            trailing_text: None,
+            filter: OutputFilter::None(NoFilter),
+            formatter: JsonFormatter::from_args(true, true),
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum lines to buffer for multi-line JSON parsing
+    pub fn with_max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = max_lines;
+        self
+    }
+
+    /// Maximum bytes to buffer for multi-line JSON parsing, in addition to the line limit
+    pub fn with_max_buffer_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_buffer_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Accepts JSON followed by other text on the same line, handling the text as given
+    pub fn with_trailing_text(mut self, trailing: TrailingText) -> Self {
+        self.trailing_text = Some(trailing);
+        self
+    }
+
+    /// Selects the records to output
+    pub fn with_filter(mut self, filter: OutputFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Renders JSON records, text is always output as-is
+    pub fn with_formatter(mut self, formatter: JsonFormatter) -> Self {
+        self.formatter = formatter;
+        self
+    }
+
+    /// Creates the processor reading from `reader` and writing to `writer`
+    pub fn build<R: Read + Send + 'static, W: Write>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> StreamProcessor<R, W> {
+        let mut buffer = LineBuffer::new(self.max_lines);
+        if let Some(max_bytes) = self.max_buffer_bytes {
+            buffer = buffer.with_max_bytes(max_bytes);
+        }
+        if let Some(trailing) = self.trailing_text {
+            buffer = buffer.with_trailing_text(trailing);
+        }
+
+        StreamProcessor::new(reader, writer, buffer, self.filter, self.formatter)
+    }
+
+    /// Processes all of `reader` into `writer`
+    pub fn run<R: Read + Send + 'static, W: Write>(self, reader: R, writer: W) -> Result<()> {
+        self.build(reader, writer).process()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::RegexFilter;
+
+    #[test]
+    fn test_defaults_pass_everything_through_compact() {
+        let input = "Quark's is open\n{\n  \"bar\": \"Quark's\"\n}\n";
+        let mut output = Vec::new();
+
+        Pipeline::new().run(input.as_bytes(), &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Quark's is open\n{\"bar\":\"Quark's\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_configured_pipeline() {
+        let input = "{\n\"ship\": \"Defiant\"\n}\nDefiant docked\n{\"ship\": \"Rio Grande\"}";
+        let mut output = Vec::new();
+
+        Pipeline::new()
+            .with_max_lines(2)
+            .with_filter(OutputFilter::Regex(
+                RegexFilter::new("defiant".to_string(), false).unwrap(),
+            ))
+            .with_formatter(JsonFormatter::from_args(false, true))
+            .run(input.as_bytes(), &mut output)
+            .unwrap();
+
+        // The three line document doesn't fit into a buffer of two lines
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\"ship\": \"Defiant\"\nDefiant docked\n"
+        );
+    }
+}
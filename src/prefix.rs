@@ -0,0 +1,263 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::time::SystemTime;
+
+/// Prefixes of `docker compose logs` (`api-1  | `) and `kubectl logs --prefix`
+/// (`[pod/api-7d9f/app] `), capturing the service, pod or container
+pub const DEFAULT_PREFIX_PATTERN: &str = r"^(?:(\w[\w.-]*)\s+\|\s?|\[([^\]]+)\]\s)";
+
+/// Per-line prefix put in front of JSON by log multiplexers.
+///
+/// The prefix's value is the first capture group of the pattern that took part
+/// in the match, or the whole match without surrounding whitespace if there is
+/// none. It is kept in a field of the record, so filters and templates can use
+/// it to tell sources apart.
+#[derive(Debug)]
+pub struct LinePrefix {
+    pattern: Regex,
+    field: String,
+}
+
+impl LinePrefix {
+    pub fn new(pattern: &str, field: String) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            field,
+        })
+    }
+
+    /// Splits a line into the prefix's value and the rest, if the pattern
+    /// matches at its start
+    pub fn split<'a>(&self, line: &'a str) -> Option<(String, &'a str)> {
+        let captures = self.pattern.captures(line)?;
+        let whole = captures.get(0).filter(|whole| whole.start() == 0)?;
+        let value = captures
+            .iter()
+            .skip(1)
+            .flatten()
+            .next()
+            .map_or_else(|| whole.as_str().trim(), |group| group.as_str());
+        Some((value.to_string(), &line[whole.end()..]))
+    }
+
+    /// Stores the prefix's value in a record, unless it has the field already
+    pub fn attach(&self, record: &mut Value, value: &str) {
+        if let Value::Object(map) = record
+            && !map.contains_key(&self.field)
+        {
+            map.insert(self.field.clone(), Value::String(value.to_string()));
+        }
+    }
+}
+
+/// Labels naming the input each record came from, like `grep -H` prints the
+/// file of each match.
+///
+/// Records keep the label of their input in a field, text lines start with
+/// `[<label>] ` like the lines of a `--connection-prefix` listener.
+#[derive(Debug)]
+pub struct InputLabels {
+    labels: Vec<String>,
+    field: String,
+    current: usize,
+}
+
+impl InputLabels {
+    /// Labels for the inputs in the order they are read
+    pub fn new(labels: Vec<String>, field: String) -> Self {
+        Self {
+            labels,
+            field,
+            current: 0,
+        }
+    }
+
+    /// Moves on to the label of the next input
+    pub fn next_input(&mut self) {
+        self.current += 1;
+    }
+
+    /// Stores the label of the current input in a record, unless it has the
+    /// field already
+    pub fn attach(&self, record: &mut Value) {
+        if let (Value::Object(map), Some(label)) = (record, self.labels.get(self.current))
+            && !map.contains_key(&self.field)
+        {
+            map.insert(self.field.clone(), Value::String(label.clone()));
+        }
+    }
+
+    /// Puts the label of the current input in front of a text line
+    pub fn label_text(&self, text: &str) -> String {
+        match self.labels.get(self.current) {
+            Some(label) => format!("[{}] {}", label, text),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// Where records came from, for tracing formatted output back to the input.
+///
+/// Records get a `_jlif` object holding the input they were read from, the
+/// number of the line they start on and when they were received, text lines
+/// start with `[<input>:<line>] `.
+pub struct Provenance {
+    sources: Vec<String>,
+    current: usize,
+    // Lines read from the current input so far
+    line: u64,
+    // Line the record being read started on
+    record_line: u64,
+}
+
+impl Provenance {
+    /// Names of the inputs in the order they are read
+    pub fn new(sources: Vec<String>) -> Self {
+        Self {
+            sources,
+            current: 0,
+            line: 0,
+            record_line: 0,
+        }
+    }
+
+    /// Moves on to the next input, whose lines are counted from one again
+    pub fn next_input(&mut self) {
+        self.current += 1;
+        self.line = 0;
+    }
+
+    /// Counts a line read from the current input
+    pub fn next_line(&mut self, starts_record: bool) {
+        self.line += 1;
+        if starts_record {
+            self.record_line = self.line;
+        }
+    }
+
+    /// Stores the origin of the record being read in its `_jlif` field
+    pub fn attach(&self, record: &mut Value, received: SystemTime) {
+        let Value::Object(map) = record else {
+            return;
+        };
+        let mut metadata = Map::new();
+        if let Some(source) = self.sources.get(self.current) {
+            metadata.insert("source".to_string(), Value::String(source.clone()));
+        }
+        metadata.insert("line".to_string(), Value::from(self.record_line));
+        metadata.insert(
+            "received".to_string(),
+            Value::String(humantime::format_rfc3339_millis(received).to_string()),
+        );
+        map.insert("_jlif".to_string(), Value::Object(metadata));
+    }
+
+    /// Puts the origin of the record being read in front of a text line
+    pub fn annotate_text(&self, text: &str) -> String {
+        match self.sources.get(self.current) {
+            Some(source) => format!("[{}:{}] {}", source, self.record_line, text),
+            None => format!("[{}] {}", self.record_line, text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("api-1  | {\"msg\": \"up\"}", Some(("api-1", "{\"msg\": \"up\"}")))]
+    #[case("ops_replicator_1 |{\"msg\": \"up\"}", Some(("ops_replicator_1", "{\"msg\": \"up\"}")))]
+    #[case("[pod/ops-7d9f/app] {\"msg\": \"up\"}", Some(("pod/ops-7d9f/app", "{\"msg\": \"up\"}")))]
+    #[case("{\"msg\": \"a | b\"}", None)]
+    #[case("Quark's bar | open", None)]
+    fn test_default_pattern(#[case] line: &str, #[case] expected: Option<(&str, &str)>) {
+        let prefix = LinePrefix::new(DEFAULT_PREFIX_PATTERN, "source".to_string()).unwrap();
+
+        assert_eq!(
+            prefix.split(line),
+            expected.map(|(value, rest)| (value.to_string(), rest))
+        );
+    }
+
+    #[rstest]
+    #[case(r"^\S+ \S+ ", "2024-05-01 12:00:03 {}", Some("2024-05-01 12:00:03"))]
+    #[case(r"^\S+ (\S+) ", "2024-05-01 12:00:03 {}", Some("12:00:03"))]
+    #[case(r"\d+:\d+ ", "at 12:00 {}", None)]
+    fn test_custom_pattern(#[case] pattern: &str, #[case] line: &str, #[case] value: Option<&str>) {
+        let prefix = LinePrefix::new(pattern, "source".to_string()).unwrap();
+
+        assert_eq!(
+            prefix.split(line).map(|(value, _)| value),
+            value.map(str::to_string)
+        );
+    }
+
+    #[test]
+    fn test_attach_keeps_existing_field() {
+        let prefix = LinePrefix::new(DEFAULT_PREFIX_PATTERN, "service".to_string()).unwrap();
+        let mut record = json!({"msg": "Docked"});
+        let mut owned = json!({"service": "ops", "msg": "Docked"});
+        let mut array = json!(["Sisko"]);
+
+        prefix.attach(&mut record, "api-1");
+        prefix.attach(&mut owned, "api-1");
+        prefix.attach(&mut array, "api-1");
+
+        assert_eq!(record, json!({"msg": "Docked", "service": "api-1"}));
+        assert_eq!(owned, json!({"service": "ops", "msg": "Docked"}));
+        assert_eq!(array, json!(["Sisko"]));
+    }
+
+    #[test]
+    fn test_input_labels_follow_inputs() {
+        let mut labels = InputLabels::new(
+            vec!["ops.log".to_string(), "bar.log".to_string()],
+            "source".to_string(),
+        );
+        let mut first = json!({"msg": "Docked"});
+        let mut second = json!({"msg": "Bar opens"});
+
+        labels.attach(&mut first);
+        labels.next_input();
+        labels.attach(&mut second);
+
+        assert_eq!(first, json!({"msg": "Docked", "source": "ops.log"}));
+        assert_eq!(second, json!({"msg": "Bar opens", "source": "bar.log"}));
+        assert_eq!(labels.label_text("Quark"), "[bar.log] Quark");
+        labels.next_input();
+        assert_eq!(labels.label_text("Quark"), "Quark");
+    }
+
+    #[test]
+    fn test_provenance_counts_lines_per_input() {
+        let mut provenance = Provenance::new(vec!["ops.log".to_string(), "bar.log".to_string()]);
+        let received = SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1714564803250);
+        let mut record = json!({"msg": "Docked"});
+        let mut array = json!(["Sisko"]);
+
+        provenance.next_line(true);
+        provenance.next_line(true);
+        provenance.next_line(false);
+        provenance.attach(&mut record, received);
+        provenance.attach(&mut array, received);
+
+        assert_eq!(
+            record,
+            json!({
+                "msg": "Docked",
+                "_jlif": {"source": "ops.log", "line": 2, "received": "2024-05-01T12:00:03.250Z"}
+            })
+        );
+        assert_eq!(array, json!(["Sisko"]));
+        provenance.next_input();
+        provenance.next_line(true);
+        assert_eq!(provenance.annotate_text("Quark"), "[bar.log:1] Quark");
+    }
+}
@@ -2,343 +2,3316 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::aggregate::{Aggregation, Aggregator, shorten};
+use crate::alias::KeyAliases;
+use crate::ansi::strip_ansi;
+use crate::array_stream::ArraySplitter;
 use crate::buffer::{BufferResult, LineBuffer};
+use crate::dedup::{self, ConsecutiveDedup, DedupState};
+use crate::envelope::{Envelope, LogEnvelope, attach_metadata};
+use crate::field_path::FieldPath;
 use crate::filter::{Filter, FilterInput, OutputFilter};
 use crate::formatter::{Formatter, JsonFormatter};
+use crate::group::{Group, RecordGroups};
+use crate::input::InputFormat;
+use crate::level::{Level, LevelMap};
+use crate::prefix::{InputLabels, LinePrefix, Provenance};
+use crate::sample::Sampler;
+use crate::slurp::{Slurped, SlurpedRecords, SpilledRecords};
+use crate::sort::RecordSorter;
+use crate::source::{LineSeparator, LineSource, NextLine, RECORD_SEPARATOR};
+use crate::split::LevelSplit;
+use crate::stats::Stats;
+use crate::timestamp::{self, TimeFormats};
+use crate::trace::TraceGrouper;
+use crate::transform::{RecordTransform, Transform};
 use anyhow::Result;
-use std::io::{BufRead, BufReader, Read, Write};
+use jiff::Timestamp;
+use serde_json::{Map, Value, json};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
-pub struct StreamProcessor<R: Read, W: Write> {
-    reader: BufReader<R>,
-    writer: W,
+// How often a threaded source wakes up to check for an interrupt while input stalls
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+// Malformed lines kept for the `--strict` report, and the width they are shortened to
+const MALFORMED_EXAMPLES: usize = 10;
+const MALFORMED_WIDTH: usize = 120;
+
+/// JSON record a text line is wrapped in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextWrapping {
+    /// `{"text": "..."}`
+    Text,
+    /// `{"message": "...", "jlif_parsed": false}`, telling wrapped lines apart
+    /// from parsed records
+    Message,
+}
+
+impl TextWrapping {
+    fn wrap(self, text: String) -> Value {
+        match self {
+            TextWrapping::Text => json!({ "text": text }),
+            TextWrapping::Message => json!({ "message": text, "jlif_parsed": false }),
+        }
+    }
+}
+
+/// Marker emitted periodically while no input arrives
+struct Heartbeat {
+    interval: Duration,
+    colored: bool,
+}
+
+/// Inclusive bounds on the original byte size of records that get output
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SizeLimits {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl SizeLimits {
+    fn contains(&self, size: u64) -> bool {
+        self.min.is_none_or(|min| size >= min) && self.max.is_none_or(|max| size <= max)
+    }
+}
+
+/// Maximum number of records output per period
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub records: u64,
+    pub period: Duration,
+}
+
+/// Output throttled to a [`RateLimit`], counting what it held back
+struct Throttle {
+    limit: RateLimit,
+    colored: bool,
+    window_start: Option<Instant>,
+    // Records output and suppressed since the window started
+    emitted: u64,
+    suppressed: u64,
+}
+
+impl Throttle {
+    /// Whether a record may be output at `now`, along with the number of
+    /// records suppressed in the period that ended before it
+    fn admit(&mut self, now: Instant) -> (bool, u64) {
+        let mut ended = 0;
+        if self
+            .window_start
+            .is_none_or(|start| now.duration_since(start) >= self.limit.period)
+        {
+            ended = std::mem::take(&mut self.suppressed);
+            self.window_start = Some(now);
+            self.emitted = 0;
+        }
+        if self.emitted < self.limit.records {
+            self.emitted += 1;
+            (true, ended)
+        } else {
+            self.suppressed += 1;
+            (false, ended)
+        }
+    }
+}
+
+/// Text lines that start like JSON but don't parse, counted in strict mode
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MalformedLines {
+    pub count: u64,
+    /// The first few of them, shortened
+    pub examples: Vec<String>,
+}
+
+impl MalformedLines {
+    fn record(&mut self, line: &str) {
+        self.count += 1;
+        if self.examples.len() < MALFORMED_EXAMPLES {
+            self.examples.push(shorten(line.trim(), MALFORMED_WIDTH));
+        }
+    }
+
+    /// Adds the lines found in another input, keeping the earlier examples
+    pub fn merge(&mut self, other: MalformedLines) {
+        self.count += other.count;
+        let room = MALFORMED_EXAMPLES.saturating_sub(self.examples.len());
+        self.examples.extend(other.examples.into_iter().take(room));
+    }
+}
+
+/// Runs of identical records collapsed into one
+struct Repeats {
+    dedup: ConsecutiveDedup,
+    colored: bool,
+}
+
+/// Records output together with the others sharing their id
+struct Grouping {
+    groups: RecordGroups,
+    colored: bool,
+}
+
+/// Prefix showing each record's original size
+struct SizeAnnotation {
+    colored: bool,
+}
+
+/// Where `--show-deltas` takes the time of each record from
+#[derive(Debug)]
+pub enum DeltaClock {
+    /// The first of these fields holding a timestamp, in one of the formats
+    Fields(Vec<FieldPath>, TimeFormats),
+    /// The time the record is output, for live input
+    Arrival,
+}
+
+/// Prefix showing the time elapsed since the previous record
+struct DeltaAnnotation {
+    clock: DeltaClock,
+    colored: bool,
+    previous: Option<Timestamp>,
+}
+
+/// Which input lines have their ANSI escape sequences removed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnsiStripping {
+    /// Every line
+    All,
+    /// Lines that may be JSON, text lines keep their colors
+    Json,
+}
+
+/// Which of the records passing the filters get output, applied in the order
+/// of the fields
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RecordRange {
+    /// Number of records to leave out first
+    pub skip: u64,
+    /// Number of records to output before stopping
+    pub head: Option<u64>,
+    /// Number of records to output at the end of the input, of those left
+    pub tail: Option<usize>,
+}
+
+/// Records passing the filters counted against a [`RecordRange`]
+struct RecordWindow {
+    range: RecordRange,
+    skipped: u64,
+    admitted: u64,
+    // The latest admitted records, held back for `tail`
+    last: VecDeque<BufferResult>,
+}
+
+impl RecordWindow {
+    /// Returns the record if it is to be output right away
+    fn admit(&mut self, result: BufferResult) -> Option<BufferResult> {
+        if self.skipped < self.range.skip {
+            self.skipped += 1;
+            return None;
+        }
+        if self.is_full() {
+            return None;
+        }
+        self.admitted += 1;
+
+        let Some(tail) = self.range.tail else {
+            return Some(result);
+        };
+        if tail > 0 {
+            if self.last.len() == tail {
+                self.last.pop_front();
+            }
+            self.last.push_back(result);
+        }
+        None
+    }
+
+    /// Whether `head` records were admitted already
+    fn is_full(&self) -> bool {
+        self.range.head.is_some_and(|head| self.admitted >= head)
+    }
+}
+
+/// Records emitted around matches, like grep's `-B` and `-A`
+struct Context {
+    before: usize,
+    after: usize,
+    // Recently suppressed records, at most `before` of them
+    recent: VecDeque<BufferResult>,
+    // Suppressed records still to be emitted after the last match
+    after_remaining: usize,
+    // Whether records were dropped since the last emitted one
+    gap: bool,
+    emitted_any: bool,
+}
+
+/// How input lines are turned into records
+struct InputStage {
     buffer: LineBuffer,
+    array_splitter: Option<ArraySplitter>,
+    separator: LineSeparator,
+    explode: bool,
+    ansi_stripping: Option<AnsiStripping>,
+    line_prefix: Option<LinePrefix>,
+    // Value of the prefix in front of the record being read
+    record_prefix: Option<String>,
+    envelope: Option<LogEnvelope>,
+    // Envelope metadata of the record being read
+    record_metadata: Option<Map<String, Value>>,
+    input_format: InputFormat,
+    input_labels: Option<InputLabels>,
+    provenance: Option<Provenance>,
+    malformed: Option<MalformedLines>,
+}
+
+impl InputStage {
+    fn new(buffer: LineBuffer) -> Self {
+        Self {
+            buffer,
+            array_splitter: None,
+            separator: LineSeparator::Newline,
+            explode: false,
+            ansi_stripping: None,
+            line_prefix: None,
+            record_prefix: None,
+            envelope: None,
+            record_metadata: None,
+            input_format: InputFormat::Json,
+            input_labels: None,
+            provenance: None,
+            malformed: None,
+        }
+    }
+}
+
+/// How JSON records are reshaped, before and after filtering
+#[derive(Default)]
+struct TransformStage {
+    before_filter: Vec<RecordTransform>,
+    after_filter: Vec<RecordTransform>,
+}
+
+impl TransformStage {
+    fn apply_before_filter(&self, record: Value) -> Value {
+        apply_transforms(&self.before_filter, record)
+    }
+
+    fn apply_after_filter(&self, record: Value) -> Value {
+        apply_transforms(&self.after_filter, record)
+    }
+}
+
+fn apply_transforms(transforms: &[RecordTransform], record: Value) -> Value {
+    transforms
+        .iter()
+        .fold(record, |value, transform| transform.apply(value))
+}
+
+/// Which records are output
+struct SelectionStage {
     filter: OutputFilter,
+    size_limits: SizeLimits,
+    dedup: Option<DedupState>,
+    max_count: Option<u64>,
+    matched: u64,
+    sampler: Option<Sampler>,
+    context: Option<Context>,
+    quiet: bool,
+}
+
+impl SelectionStage {
+    fn new(filter: OutputFilter) -> Self {
+        Self {
+            filter,
+            size_limits: SizeLimits::default(),
+            dedup: None,
+            max_count: None,
+            matched: 0,
+            sampler: None,
+            context: None,
+            quiet: false,
+        }
+    }
+}
+
+/// How selected records are held back, reordered or combined before writing
+#[derive(Default)]
+struct BatchingStage {
+    sorter: Option<RecordSorter>,
+    window: Option<RecordWindow>,
+    throttle: Option<Throttle>,
+    repeats: Option<Repeats>,
+    grouping: Option<Grouping>,
+    aggregation: Option<Aggregation>,
+    traces: Option<TraceGrouper>,
+    slurped: Option<SlurpedRecords>,
+}
+
+/// How records are rendered and written
+struct OutputStage {
     json_formatter: JsonFormatter,
+    aliases: KeyAliases,
+    size_annotation: Option<SizeAnnotation>,
+    delta_annotation: Option<DeltaAnnotation>,
+    level_colors: Option<LevelMap>,
+    level_split: Option<LevelSplit>,
+    highlight_matches: bool,
+    text_wrapping: Option<TextWrapping>,
+    text_writer: Option<Box<dyn Write>>,
+    report_writer: Option<Box<dyn Write>>,
+    unbuffered: bool,
+}
+
+impl OutputStage {
+    fn new(json_formatter: JsonFormatter) -> Self {
+        Self {
+            json_formatter,
+            aliases: KeyAliases::default(),
+            size_annotation: None,
+            delta_annotation: None,
+            level_colors: None,
+            level_split: None,
+            highlight_matches: false,
+            text_wrapping: None,
+            text_writer: None,
+            report_writer: None,
+            unbuffered: false,
+        }
+    }
+}
+
+/// Reads input lines and writes them with their JSON records formatted,
+/// passing the records through the stages configured by the `with_*` builders
+pub struct StreamProcessor<R: Read, W: Write> {
+    inputs: Vec<R>,
+    writer: W,
+    input: InputStage,
+    transforms: TransformStage,
+    selection: SelectionStage,
+    batching: BatchingStage,
+    output: OutputStage,
+    heartbeat: Option<Heartbeat>,
+    refresh: Option<Duration>,
+    flush_timeout: Option<Duration>,
+    stats: Option<Arc<Stats>>,
+    interrupt: Option<Arc<AtomicBool>>,
 }
 
-impl<R: Read, W: Write> StreamProcessor<R, W> {
-    pub fn new(
-        reader: R,
-        writer: W,
-        buffer: LineBuffer,
-        filter: OutputFilter,
-        json_formatter: JsonFormatter,
-    ) -> Self {
-        Self {
-            reader: BufReader::new(reader),
-            writer,
+impl<R: Read + Send + 'static, W: Write> StreamProcessor<R, W> {
+    pub fn new(
+        reader: R,
+        writer: W,
+        buffer: LineBuffer,
+        filter: OutputFilter,
+        json_formatter: JsonFormatter,
+    ) -> Self {
+        Self {
+            inputs: vec![reader],
+            writer,
+            input: InputStage::new(buffer),
+            transforms: TransformStage::default(),
+            selection: SelectionStage::new(filter),
+            batching: BatchingStage::default(),
+            output: OutputStage::new(json_formatter),
+            heartbeat: None,
+            refresh: None,
+            flush_timeout: None,
+            stats: None,
+            interrupt: None,
+        }
+    }
+
+    /// Colors each record as a whole according to its detected log level.
+    ///
+    /// Meant to be combined with a plain formatter, as syntax highlighting
+    /// would override the line color.
+    pub fn with_level_colors(mut self, level_map: LevelMap) -> Self {
+        self.output.level_colors = Some(level_map);
+        self
+    }
+
+    /// Collects all JSON records and outputs them as a single array at EOF,
+    /// like `jq -s`. Text lines are left out.
+    pub fn with_slurp(mut self, records: SlurpedRecords) -> Self {
+        self.batching.slurped = Some(records);
+        self
+    }
+
+    /// Removes ANSI escape sequences from input lines before parsing them, so
+    /// JSON logged by colorizing tools is recognized
+    pub fn with_ansi_stripping(mut self, stripping: AnsiStripping) -> Self {
+        self.input.ansi_stripping = Some(stripping);
+        self
+    }
+
+    /// Removes a prefix like `api-1  | ` from lines before parsing them and
+    /// keeps its value in a field of the record. Multi-line records take the
+    /// prefix of their first line, text lines are left as they are.
+    pub fn with_line_prefix(mut self, prefix: LinePrefix) -> Self {
+        self.input.line_prefix = Some(prefix);
+        self
+    }
+
+    /// Tags every record with the label of the input it was read from, see
+    /// [`InputLabels`]
+    pub fn with_input_labels(mut self, labels: InputLabels) -> Self {
+        self.input.input_labels = Some(labels);
+        self
+    }
+
+    /// Attaches where each record came from, see [`Provenance`]
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.input.provenance = Some(provenance);
+        self
+    }
+
+    /// Unwraps the envelopes of container log formats like Docker's json-file
+    /// driver, parsing what the container logged instead of the envelope
+    pub fn with_envelope(mut self, envelope: LogEnvelope) -> Self {
+        self.input.envelope = Some(envelope);
+        self
+    }
+
+    /// Parses text lines in another log format, like logfmt, into records so
+    /// they are formatted and filtered like JSON
+    pub fn with_input_format(mut self, input_format: InputFormat) -> Self {
+        self.input.input_format = input_format;
+        self
+    }
+
+    /// Handles every element of a parsed top-level array as a record of its
+    /// own, filtered individually
+    pub fn with_exploded_arrays(mut self) -> Self {
+        self.input.explode = true;
+        self
+    }
+
+    /// Splits the input at the given separator instead of newlines
+    pub fn with_separator(mut self, separator: LineSeparator) -> Self {
+        self.input.separator = separator;
+        self
+    }
+
+    /// Writes text lines to a separate writer, e.g. stderr, keeping the regular
+    /// output purely JSON
+    pub fn with_text_output(mut self, writer: Box<dyn Write>) -> Self {
+        self.output.text_writer = Some(writer);
+        self
+    }
+
+    /// Additionally writes every record into a file per detected log level,
+    /// without level colors or match highlights
+    pub fn with_level_split(mut self, split: LevelSplit) -> Self {
+        self.output.level_split = Some(split);
+        self
+    }
+
+    /// Highlights what the filter matched in each record, like `grep --color`.
+    ///
+    /// Matches are searched in the rendered output, so this is meant to be
+    /// combined with a plain formatter.
+    pub fn with_match_highlighting(mut self) -> Self {
+        self.output.highlight_matches = true;
+        self
+    }
+
+    /// Also emits up to `before` and `after` suppressed records around each match.
+    ///
+    /// Groups of records that aren't adjacent in the input are separated by a
+    /// `--` line, as grep does.
+    pub fn with_context(mut self, before: usize, after: usize) -> Self {
+        self.selection.context = Some(Context {
+            before,
+            after,
+            recent: VecDeque::with_capacity(before),
+            after_remaining: 0,
+            gap: false,
+            emitted_any: false,
+        });
+        self
+    }
+
+    /// Reshapes JSON records after filtering, in the order transforms are added
+    pub fn with_transform(mut self, transform: RecordTransform) -> Self {
+        self.transforms.after_filter.push(transform);
+        self
+    }
+
+    /// Reshapes JSON records before filtering, in the order transforms are added,
+    /// so the filter and everything after it only see the transformed record
+    pub fn with_pre_filter_transform(mut self, transform: RecordTransform) -> Self {
+        self.transforms.before_filter.push(transform);
+        self
+    }
+
+    /// Renames keys in formatted output without affecting filtering
+    pub fn with_aliases(mut self, aliases: KeyAliases) -> Self {
+        self.output.aliases = aliases;
+        self
+    }
+
+    /// Treats input starting with `[` as one top-level array and emits its
+    /// elements as individual records, however many lines the array spans
+    pub fn with_array_splitting(mut self) -> Self {
+        self.input.array_splitter = Some(ArraySplitter::new());
+        self
+    }
+
+    /// Only counts matching records instead of outputting them, and stops
+    /// reading at the first one, like `grep -q`
+    pub fn with_quiet(mut self) -> Self {
+        self.selection.quiet = true;
+        self
+    }
+
+    /// Stops reading after `count` matching records, like `grep -m`. Context
+    /// requested after matches is still output for the last one.
+    pub fn with_max_count(mut self, count: u64) -> Self {
+        self.selection.max_count = Some(count);
+        self
+    }
+
+    /// Outputs only a sample of the records passing the filters
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.selection.sampler = Some(sampler);
+        self
+    }
+
+    /// Outputs only a range of the records passing the filters, like piping
+    /// the output through `tail -n +N`, `head` and `tail`. Reading stops once
+    /// `head` records were output.
+    pub fn with_record_range(mut self, range: RecordRange) -> Self {
+        self.batching.window = Some(RecordWindow {
+            range,
+            skipped: 0,
+            admitted: 0,
+            last: VecDeque::new(),
+        });
+        self
+    }
+
+    /// Outputs a run of identical records once, followed by a marker line
+    /// telling how often it was repeated
+    pub fn with_repeats_collapsed(mut self, dedup: ConsecutiveDedup, colored: bool) -> Self {
+        self.batching.repeats = Some(Repeats { dedup, colored });
+        self
+    }
+
+    /// Outputs the records ordered by a field once the input ends, instead of
+    /// in input order, before `--skip`, `--head` and `--tail` pick from them
+    pub fn with_sorting(mut self, sorter: RecordSorter) -> Self {
+        self.batching.sorter = Some(sorter);
+        self
+    }
+
+    /// Holds back records with an id until their group is complete, then
+    /// outputs the group under a marker line with its id and elapsed time
+    pub fn with_grouping(mut self, groups: RecordGroups, colored: bool) -> Self {
+        self.batching.grouping = Some(Grouping { groups, colored });
+        self
+    }
+
+    /// Outputs at most `limit.records` records per period, replacing the rest
+    /// with a marker line telling how many were suppressed
+    pub fn with_rate_limit(mut self, limit: RateLimit, colored: bool) -> Self {
+        self.batching.throttle = Some(Throttle {
+            limit,
+            colored,
+            window_start: None,
+            emitted: 0,
+            suppressed: 0,
+        });
+        self
+    }
+
+    /// Counts lines, records and bytes into `stats`, which may be shared with
+    /// whoever reports them
+    pub fn with_stats(mut self, stats: Arc<Stats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Stops reading once `interrupt` is set, e.g. by a signal handler, and
+    /// finishes like at EOF: buffered lines are drained and the output flushed.
+    ///
+    /// Checked after every line, and periodically while waiting for input,
+    /// so a stalled input doesn't keep jlif from exiting.
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Flushes the writer after every record, so a buffered writer still
+    /// delivers records as soon as they are complete
+    pub fn with_unbuffered_output(mut self) -> Self {
+        self.output.unbuffered = true;
+        self
+    }
+
+    /// Joins the lines of stack traces into single text blocks, which are
+    /// held back until a line that doesn't continue them arrives
+    pub fn with_trace_grouping(mut self) -> Self {
+        self.batching.traces = Some(TraceGrouper::new());
+        self
+    }
+
+    /// Outputs text lines as JSON records of the given shape, so the output
+    /// is JSON only. Wrapped lines go through transforms like parsed records.
+    pub fn with_wrapped_text(mut self, wrapping: TextWrapping) -> Self {
+        self.output.text_wrapping = Some(wrapping);
+        self
+    }
+
+    /// Number of records that passed the filter so far
+    pub fn matched_records(&self) -> u64 {
+        self.selection.matched
+    }
+
+    /// Whether the filter may suppress records
+    pub fn is_filtering(&self) -> bool {
+        self.selection.filter.is_active()
+    }
+
+    /// Counts text lines starting with `{` or `[` that never parsed as JSON,
+    /// which are otherwise output as text without a word
+    pub fn with_strict(mut self) -> Self {
+        self.input.malformed = Some(MalformedLines::default());
+        self
+    }
+
+    /// Lines found malformed in strict mode so far
+    pub fn malformed_lines(&self) -> Option<&MalformedLines> {
+        self.input.malformed.as_ref()
+    }
+
+    /// Reads these inputs after the initial reader, one after another.
+    /// Buffered content is drained whenever one input ends, so incomplete
+    /// JSON never spans two inputs.
+    pub fn with_inputs(mut self, inputs: Vec<R>) -> Self {
+        self.inputs.extend(inputs);
+        self
+    }
+
+    /// Suppresses records whose original size falls outside of `limits`
+    pub fn with_size_limits(mut self, limits: SizeLimits) -> Self {
+        self.selection.size_limits = limits;
+        self
+    }
+
+    /// Prefixes every record with its original size in the input
+    pub fn with_size_annotations(mut self, colored: bool) -> Self {
+        self.output.size_annotation = Some(SizeAnnotation { colored });
+        self
+    }
+
+    /// Prefixes every record with the time elapsed since the previous one,
+    /// like `[+2.3s]`. Records without a time are output without it.
+    pub fn with_delta_annotations(mut self, clock: DeltaClock, colored: bool) -> Self {
+        self.output.delta_annotation = Some(DeltaAnnotation {
+            clock,
+            colored,
+            previous: None,
+        });
+        self
+    }
+
+    /// Suppresses records already output by this or a previous run
+    pub fn with_dedup(mut self, state: DedupState) -> Self {
+        self.selection.dedup = Some(state);
+        self
+    }
+
+    /// Feeds filtered JSON records into an aggregation instead of printing them.
+    /// The aggregated report is written once the input is exhausted.
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.batching.aggregation = Some(aggregation);
+        self
+    }
+
+    /// Outputs records as usual while they are fed into the aggregation, and
+    /// writes its report to a separate writer, e.g. stderr
+    pub fn with_report_output(mut self, writer: Box<dyn Write>) -> Self {
+        self.output.report_writer = Some(writer);
+        self
+    }
+
+    /// Clears the screen and redraws the aggregation report every `interval`,
+    /// turning it into a live view instead of a report at EOF
+    pub fn with_refresh(mut self, interval: Duration) -> Self {
+        self.refresh = Some(interval);
+        self
+    }
+
+    /// Emits a timestamped marker line whenever no input arrived for `interval`,
+    /// so a quiet stream can be told apart from a dead pipe
+    pub fn with_heartbeat(mut self, interval: Duration, colored: bool) -> Self {
+        self.heartbeat = Some(Heartbeat { interval, colored });
+        self
+    }
+
+    /// Flushes buffered lines once no input arrived for `timeout`, instead of
+    /// holding them back until a JSON document completes or the input ends
+    pub fn with_flush_timeout(mut self, timeout: Duration) -> Self {
+        self.flush_timeout = Some(timeout);
+        self
+    }
+
+    /// Process the stream line by line until EOF, then drain remaining buffer.
+    ///
+    /// Stops early without an error once the output is closed, e.g. by
+    /// `jlif | head`, like standard Unix filters.
+    pub fn process(&mut self) -> Result<()> {
+        match self.process_stream() {
+            Err(e) if is_broken_pipe(&e) => Ok(()),
+            result => result,
+        }
+    }
+
+    fn process_stream(&mut self) -> Result<()> {
+        // Timers and interrupts need reads that can time out, which only the
+        // threaded source offers
+        let inputs = std::mem::take(&mut self.inputs);
+        let group_timeout = self
+            .batching
+            .grouping
+            .as_ref()
+            .is_some_and(|grouping| grouping.groups.timeout().is_some());
+        let mut source = if self.heartbeat.is_some()
+            || self.refresh.is_some()
+            || self.flush_timeout.is_some()
+            || self.interrupt.is_some()
+            || group_timeout
+        {
+            LineSource::threaded(inputs, self.input.separator.clone())
+        } else {
+            LineSource::direct(inputs, self.input.separator.clone())
+        };
+
+        let mut last_input = Instant::now();
+        let mut last_line = Instant::now();
+        let mut last_refresh = Instant::now();
+
+        // Read lines until EOF
+        loop {
+            // Wake up for whichever timer is due first
+            let heartbeat_due = self
+                .heartbeat
+                .as_ref()
+                .map(|heartbeat| heartbeat.interval.saturating_sub(last_input.elapsed()));
+            let refresh_due = self
+                .refresh
+                .map(|interval| interval.saturating_sub(last_refresh.elapsed()));
+            // Only pending while lines wait in the buffer
+            let flush_due = self
+                .flush_timeout
+                .filter(|_| self.is_holding_lines())
+                .map(|timeout| timeout.saturating_sub(last_line.elapsed()));
+            let interrupt_due = self.interrupt.as_ref().map(|_| INTERRUPT_POLL);
+            let group_due = self
+                .batching
+                .grouping
+                .as_ref()
+                .and_then(|grouping| grouping.groups.next_due(Instant::now()));
+            let timeout = heartbeat_due
+                .into_iter()
+                .chain(refresh_due)
+                .chain(flush_due)
+                .chain(interrupt_due)
+                .chain(group_due)
+                .min();
+
+            match source.next_line(timeout)? {
+                NextLine::Line(line) => {
+                    last_input = Instant::now();
+                    last_line = Instant::now();
+                    if let Some(stats) = &self.stats {
+                        stats.record_line(line.len() + 1);
+                    }
+
+                    self.read_line(line)?;
+
+                    if self.is_done() {
+                        break;
+                    }
+                }
+                NextLine::Timeout => {
+                    let flush_due = self
+                        .flush_timeout
+                        .is_some_and(|timeout| last_line.elapsed() >= timeout);
+                    if flush_due && self.is_holding_lines() {
+                        let results = self.input.buffer.drain();
+                        self.handle_results(results)?;
+                        self.flush_trace()?;
+                        self.writer.flush()?;
+                    }
+
+                    let stalled = self
+                        .heartbeat
+                        .as_ref()
+                        .is_some_and(|heartbeat| last_input.elapsed() >= heartbeat.interval);
+                    if stalled {
+                        self.write_heartbeat()?;
+                        last_input = Instant::now();
+                    }
+                }
+                NextLine::EndOfInput => {
+                    self.drain_buffers()?;
+                    if self.input.array_splitter.is_some() {
+                        // Every input may hold its own top-level array
+                        self.input.array_splitter = Some(ArraySplitter::new());
+                    }
+                    if let Some(labels) = &mut self.input.input_labels {
+                        labels.next_input();
+                    }
+                    if let Some(provenance) = &mut self.input.provenance {
+                        provenance.next_input();
+                    }
+                }
+                NextLine::Eof => break,
+            }
+
+            if self.is_interrupted() {
+                break;
+            }
+
+            if let Some(grouping) = &mut self.batching.grouping {
+                let idle = grouping.groups.take_idle(Instant::now());
+                self.write_groups(idle)?;
+            }
+
+            // Busy streams never time out, so the refresh is checked after every line
+            if self
+                .refresh
+                .is_some_and(|interval| last_refresh.elapsed() >= interval)
+            {
+                self.redraw_aggregation()?;
+                last_refresh = Instant::now();
+            }
+        }
+
+        // Drain remaining buffered content at EOF
+        self.drain_buffers()?;
+
+        if let Some(sorter) = self.batching.sorter.take() {
+            for result in sorter.into_sorted()? {
+                self.release(result?)?;
+            }
+        }
+
+        if let Some(grouping) = &mut self.batching.grouping {
+            let groups = grouping.groups.take_all();
+            self.write_groups(groups)?;
+        }
+
+        if let Some(window) = &mut self.batching.window {
+            let last = std::mem::take(&mut window.last);
+            for result in last {
+                self.emit(result)?;
+            }
+        }
+        if let Some(repeats) = &mut self.batching.repeats {
+            let colored = repeats.colored;
+            let repeated = repeats.dedup.finish();
+            self.write_repeated(repeated, colored)?;
+        }
+        if let Some(throttle) = &mut self.batching.throttle {
+            let suppressed = std::mem::take(&mut throttle.suppressed);
+            self.write_suppressed(suppressed)?;
+        }
+
+        if let Some(slurped) = self.batching.slurped.take() {
+            match slurped.finish()? {
+                Slurped::Memory(records) => {
+                    let rendered = self
+                        .output
+                        .json_formatter
+                        .format_json(&Value::Array(records))?;
+                    writeln!(self.writer, "{}", rendered)?;
+                }
+                Slurped::Spilled(records) => self.write_spilled_array(records)?,
+            }
+        }
+
+        if let Some(dedup) = &mut self.selection.dedup {
+            dedup.flush()?;
+        }
+
+        if self.refresh.is_some() {
+            self.redraw_aggregation()?;
+        } else if let Some(aggregation) = &self.batching.aggregation {
+            match &mut self.output.report_writer {
+                Some(report_writer) => {
+                    aggregation.render(report_writer)?;
+                    report_writer.flush()?;
+                }
+                None => aggregation.render(&mut self.writer)?,
+            }
+        }
+
+        // Buffered writers only flush on drop, which swallows errors
+        self.writer.flush()?;
+        if let Some(text_writer) = &mut self.output.text_writer {
+            text_writer.flush()?;
+        }
+        if let Some(split) = &mut self.output.level_split {
+            split.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Prepares an input line for parsing and adds it
+    fn read_line(&mut self, line: String) -> Result<()> {
+        if let Some(provenance) = &mut self.input.provenance {
+            provenance.next_line(self.input.buffer.is_empty());
+        }
+        let line = match self.input.envelope {
+            Some(_) => match self.unwrap_envelope(line) {
+                Some(line) => line,
+                None => return Ok(()),
+            },
+            None => line,
+        };
+        let line = match self.input.ansi_stripping {
+            Some(stripping) => self.strip_ansi(line, stripping),
+            None => line,
+        };
+        let line = match self.input.line_prefix {
+            Some(_) => match self.strip_prefix(line) {
+                Ok(line) => line,
+                // Output right away, as text like `[pod/api] Started` would
+                // otherwise wait in the buffer as the start of a JSON array
+                Err(text) => return self.handle_results(vec![BufferResult::Text(text)]),
+            },
+            None => line,
+        };
+        if line.contains(RECORD_SEPARATOR) {
+            self.add_sequence_line(&line)
+        } else {
+            self.add_line(line)
+        }
+    }
+
+    fn unwrap_envelope(&mut self, line: String) -> Option<String> {
+        let starts_record = self.input.buffer.is_empty();
+        let (line, metadata) = self.input.envelope.as_mut()?.unwrap(line)?;
+        if starts_record {
+            self.input.record_metadata = metadata;
+        }
+        Some(line)
+    }
+
+    fn strip_ansi(&self, line: String, stripping: AnsiStripping) -> String {
+        let stripped = match strip_ansi(&line) {
+            Cow::Borrowed(_) => return line,
+            Cow::Owned(stripped) => stripped,
+        };
+        if stripping == AnsiStripping::All || self.may_be_json(&stripped) {
+            stripped
+        } else {
+            line
+        }
+    }
+
+    /// Removes the prefix of a line that may be a record, or returns a
+    /// prefixed line that can only be text as an error
+    fn strip_prefix(&mut self, line: String) -> Result<String, String> {
+        let starts_record = self.input.buffer.is_empty();
+        if starts_record {
+            self.input.record_prefix = None;
+        }
+        let Some((value, rest)) = self
+            .input
+            .line_prefix
+            .as_ref()
+            .and_then(|prefix| prefix.split(&line))
+        else {
+            return Ok(line);
+        };
+        if !(self.may_be_json(rest) || self.input.input_format.parse(rest).is_some()) {
+            return Err(line);
+        }
+
+        let rest = rest.to_string();
+        if starts_record {
+            self.input.record_prefix = Some(value);
+        }
+        Ok(rest)
+    }
+
+    /// Whether a line may be or continue JSON, other lines are plain text
+    fn may_be_json(&self, line: &str) -> bool {
+        // Lines continuing a buffered document are part of the JSON as well
+        !self.input.buffer.is_empty()
+            || self
+                .input
+                .array_splitter
+                .as_ref()
+                .is_some_and(|splitter| splitter.accepts(line))
+            || line.trim_start().starts_with(['{', '['])
+    }
+
+    fn add_line(&mut self, line: String) -> Result<()> {
+        // Process line through the array splitter or buffer
+        let results = match &mut self.input.array_splitter {
+            Some(splitter) if splitter.accepts(&line) => splitter.add_line(line),
+            _ => self.input.buffer.add_line(line),
+        };
+        self.handle_results(results)
+    }
+
+    /// Adds a line of a JSON text sequence (RFC 7464), where every RS starts a
+    /// new record, so whatever is still buffered ends before it
+    fn add_sequence_line(&mut self, line: &str) -> Result<()> {
+        let mut segments = line.split(RECORD_SEPARATOR);
+        if let Some(first) = segments.next().filter(|first| !first.is_empty()) {
+            self.add_line(first.to_string())?;
+        }
+        for segment in segments {
+            self.drain_buffers()?;
+            if !segment.is_empty() {
+                self.add_line(segment.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_interrupted(&self) -> bool {
+        self.interrupt
+            .as_ref()
+            .is_some_and(|interrupt| interrupt.load(Ordering::Relaxed))
+    }
+
+    fn drain_buffers(&mut self) -> Result<()> {
+        // A split line whose last envelope never arrived
+        if let Some(partial) = self
+            .input
+            .envelope
+            .as_mut()
+            .and_then(|envelope| envelope.take_partial())
+        {
+            self.add_line(partial)?;
+        }
+
+        if let Some(splitter) = &mut self.input.array_splitter {
+            let drain_results = splitter.drain();
+            self.handle_results(drain_results)?;
+        }
+        let drain_results = self.input.buffer.drain();
+        self.handle_results(drain_results)?;
+        self.flush_trace()
+    }
+
+    /// Whether lines wait for a JSON document or stack trace to continue
+    fn is_holding_lines(&self) -> bool {
+        !self.input.buffer.is_empty()
+            || self
+                .batching
+                .traces
+                .as_ref()
+                .is_some_and(TraceGrouper::is_pending)
+    }
+
+    fn redraw_aggregation(&mut self) -> Result<()> {
+        if let Some(aggregation) = &self.batching.aggregation {
+            // Clear screen and move the cursor home before redrawing
+            write!(self.writer, "\x1b[2J\x1b[H")?;
+            aggregation.render(&mut self.writer)?;
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Reports how often the last record output was repeated
+    fn write_repeated(&mut self, count: u64, colored: bool) -> Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        let marker = format!(
+            "--- last record repeated {} time{} ---",
+            count,
+            if count == 1 { "" } else { "s" }
+        );
+        self.write_marker(&marker, colored)
+    }
+
+    /// Writes a line of jlif's own, dim so it doesn't compete with records
+    fn write_marker(&mut self, marker: &str, colored: bool) -> Result<()> {
+        self.write_dim(marker, colored, "\n")
+    }
+
+    /// Writes text of jlif's own followed by `end`, dim if `colored`
+    fn write_dim(&mut self, text: &str, colored: bool, end: &str) -> Result<()> {
+        if colored {
+            write!(self.writer, "\x1b[2m{}\x1b[0m{}", text, end)?;
+        } else {
+            write!(self.writer, "{}{}", text, end)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the records slurped to disk as an array, one element at a time.
+    /// Elements are indented like those of an array rendered as a whole.
+    fn write_spilled_array(&mut self, records: SpilledRecords) -> Result<()> {
+        write!(self.writer, "[")?;
+        for (index, record) in records.enumerate() {
+            let rendered = self.output.json_formatter.format_json(&record?)?;
+            writeln!(self.writer, "{}", if index == 0 { "" } else { "," })?;
+            for (line_index, line) in rendered.lines().enumerate() {
+                if line_index > 0 {
+                    writeln!(self.writer)?;
+                }
+                write!(self.writer, "  {}", line)?;
+            }
+        }
+        writeln!(self.writer, "\n]")?;
+        Ok(())
+    }
+
+    /// Outputs complete groups of records, each under a marker line
+    fn write_groups(&mut self, groups: Vec<Group>) -> Result<()> {
+        let Some(grouping) = &self.batching.grouping else {
+            return Ok(());
+        };
+        let colored = grouping.colored;
+        let field = grouping.groups.field().to_string();
+        for group in groups {
+            let marker = format!(
+                "--- {} {}: {} record{} in {} ---",
+                field,
+                group.id,
+                group.records.len(),
+                if group.records.len() == 1 { "" } else { "s" },
+                timestamp::format_elapsed(group.elapsed)
+            );
+            self.write_marker(&marker, colored)?;
+            for record in group.records {
+                self.write_record(record)?;
+            }
+        }
+        if self.output.unbuffered {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Reports records the rate limit held back
+    fn write_suppressed(&mut self, count: u64) -> Result<()> {
+        let Some(throttle) = &self.batching.throttle else {
+            return Ok(());
+        };
+        if count == 0 {
+            return Ok(());
+        }
+        let colored = throttle.colored;
+        let marker = format!(
+            "--- suppressed {} record{} over the rate limit ---",
+            count,
+            if count == 1 { "" } else { "s" }
+        );
+        self.write_marker(&marker, colored)
+    }
+
+    fn write_heartbeat(&mut self) -> Result<()> {
+        if let Some(heartbeat) = &self.heartbeat {
+            let marker = format!(
+                "--- {} no input for {} ---",
+                humantime::format_rfc3339_seconds(SystemTime::now()),
+                humantime::format_duration(heartbeat.interval)
+            );
+            let colored = heartbeat.colored;
+            self.write_marker(&marker, colored)?;
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn write_delta_annotation(&mut self, result: &BufferResult) -> Result<()> {
+        let Some(annotation) = &mut self.output.delta_annotation else {
+            return Ok(());
+        };
+        let time = match (&annotation.clock, result) {
+            (DeltaClock::Arrival, _) => Some(Timestamp::now()),
+            (DeltaClock::Fields(fields, formats), BufferResult::Json(value, _)) => fields
+                .iter()
+                .find_map(|field| field.get(value).and_then(|time| formats.parse(time))),
+            (DeltaClock::Fields(..), _) => None,
+        };
+        let Some(time) = time else {
+            return Ok(());
+        };
+
+        let previous = annotation.previous.replace(time).unwrap_or(time);
+        let label = format!(
+            "[{}]",
+            timestamp::format_offset(time.duration_since(previous))
+        );
+        let colored = annotation.colored;
+        self.write_dim(&label, colored, " ")
+    }
+
+    fn write_size_annotation(&mut self, size: usize) -> Result<()> {
+        if let Some(annotation) = &self.output.size_annotation {
+            let label = format!("[{}]", format_size(size as u64));
+            let colored = annotation.colored;
+            self.write_dim(&label, colored, " ")?;
+        }
+        Ok(())
+    }
+
+    fn handle_results(&mut self, results: Vec<BufferResult>) -> Result<()> {
+        let results = if self.input.explode {
+            explode_arrays(results)
+        } else {
+            results
+        };
+        let results = results.into_iter().map(|result| match result {
+            BufferResult::Text(text) => match self.input.input_format.parse(&text) {
+                Some(record) => BufferResult::Json(record, text.len()),
+                None => BufferResult::Text(text),
+            },
+            result => result,
+        });
+        let results = match &mut self.batching.traces {
+            Some(traces) => traces.group(results.collect()),
+            None => results.collect(),
+        };
+        for result in results {
+            self.handle_result(result)?;
+        }
+        Ok(())
+    }
+
+    /// Outputs the stack trace held back for lines that may continue it
+    fn flush_trace(&mut self) -> Result<()> {
+        match self.batching.traces.as_mut().and_then(TraceGrouper::flush) {
+            Some(block) => self.handle_result(block),
+            None => Ok(()),
+        }
+    }
+
+    fn handle_result(&mut self, mut result: BufferResult) -> Result<()> {
+        if let (Some(malformed), BufferResult::Text(text)) = (&mut self.input.malformed, &result) {
+            // A prefix like `[stderr] ` doesn't make a line look like JSON
+            let rest = match &self.input.line_prefix {
+                Some(prefix) => prefix.split(text).map_or(text.as_str(), |(_, rest)| rest),
+                None => text,
+            };
+            if rest.trim_start().starts_with(['{', '[']) {
+                malformed.record(text);
+            }
+        }
+        if let BufferResult::Json(record, _) = &mut result {
+            if let (Some(prefix), Some(value)) =
+                (&self.input.line_prefix, &self.input.record_prefix)
+            {
+                prefix.attach(record, value);
+            }
+            if let Some(metadata) = &self.input.record_metadata {
+                attach_metadata(record, metadata);
+            }
+        }
+        if let Some(labels) = &self.input.input_labels {
+            match &mut result {
+                BufferResult::Json(record, _) => labels.attach(record),
+                BufferResult::Text(text) => *text = labels.label_text(text),
+                BufferResult::Incomplete(_) => {}
+            }
+        }
+        if let BufferResult::Json(record, size) = result {
+            result = BufferResult::Json(self.transforms.apply_before_filter(record), size);
+        }
+
+        // Try to convert BufferResult to FilterInput
+        // Incomplete results are automatically filtered out by the conversion
+        let Ok(filter_input) = FilterInput::try_from(&result) else {
+            return Ok(());
+        };
+
+        if let Some(stats) = &self.stats {
+            match &result {
+                BufferResult::Json(..) => stats.record_json(),
+                BufferResult::Text(text) => stats.record_text(text),
+                BufferResult::Incomplete(_) => {}
+            }
+        }
+
+        // Past the limit records only serve as context after the last match
+        if self.max_count_reached() {
+            self.annotate(&mut result);
+            self.suppress(result)?;
+            return Ok(());
+        }
+
+        // Apply filter to determine if content should be output
+        if !(self.selection.size_limits.contains(result.size() as u64)
+            && self.selection.filter.passes(&filter_input))
+        {
+            if let Some(stats) = &self.stats {
+                stats.record_filtered_out();
+            }
+            self.annotate(&mut result);
+            self.suppress(result)?;
+            return Ok(());
+        }
+
+        // Records are only stored as seen once they are actually written, as
+        // sampling, the record range or the rate limit may still drop them
+        let admitted = match &mut self.selection.dedup {
+            Some(dedup) => match dedup.admit(&result)? {
+                Some(hash) => Some(hash),
+                None => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_filtered_out();
+                    }
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+
+        // After deduplication, as the receipt time makes every record unique
+        self.annotate(&mut result);
+        if let (Some(dedup), Some(hash)) = (&mut self.selection.dedup, admitted) {
+            let output = match &self.input.provenance {
+                Some(_) => dedup::hash(&result)?.unwrap_or(hash),
+                None => hash,
+            };
+            dedup.expect(hash, output);
+        }
+
+        self.selection.matched += 1;
+        if self.selection.quiet {
+            return Ok(());
+        }
+
+        if let Some(sampler) = &mut self.selection.sampler
+            && !sampler.keep(&result)
+        {
+            return Ok(());
+        }
+
+        match &mut self.batching.sorter {
+            Some(sorter) => Ok(sorter.add(result)?),
+            None => self.release(result),
+        }
+    }
+
+    /// Outputs a record in its final order, unless the record range leaves it out
+    fn release(&mut self, result: BufferResult) -> Result<()> {
+        match &mut self.batching.window {
+            Some(window) => {
+                if let Some(result) = window.admit(result) {
+                    self.emit(result)?;
+                }
+            }
+            None => self.emit(result)?,
+        }
+        Ok(())
+    }
+
+    /// Outputs a record that passed the filters, or hands it to the aggregation
+    fn emit(&mut self, result: BufferResult) -> Result<()> {
+        // Aggregations consume JSON records, text has nothing to contribute
+        if let Some(aggregation) = &mut self.batching.aggregation {
+            if let BufferResult::Json(json_value, _) = &result {
+                aggregation.observe(json_value);
+            }
+            if self.output.report_writer.is_none() {
+                return Ok(());
+            }
+        }
+
+        let result = match &mut self.batching.grouping {
+            Some(grouping) => match grouping.groups.add(result, Instant::now()) {
+                Some(result) => result,
+                None => {
+                    let excess = grouping.groups.take_excess();
+                    return self.write_groups(excess);
+                }
+            },
+            None => result,
+        };
+
+        if let Some(repeats) = &mut self.batching.repeats {
+            let colored = repeats.colored;
+            let (admitted, repeated) = repeats.dedup.admit(&result)?;
+            self.write_repeated(repeated, colored)?;
+            if !admitted {
+                return Ok(());
+            }
+        }
+
+        if let Some(throttle) = &mut self.batching.throttle {
+            let (admitted, suppressed) = throttle.admit(Instant::now());
+            self.write_suppressed(suppressed)?;
+            if !admitted {
+                return Ok(());
+            }
+        }
+
+        if let Some(context) = &mut self.selection.context {
+            if context.gap && context.emitted_any {
+                writeln!(self.writer, "--")?;
+            }
+            context.gap = false;
+            context.emitted_any = true;
+            context.after_remaining = context.after;
+
+            let before = std::mem::take(&mut context.recent);
+            for record in before {
+                self.write_record(record)?;
+            }
+        }
+
+        self.write_record(result)
+    }
+
+    /// Attaches the origin of the record being read, see [`Provenance`]
+    fn annotate(&self, result: &mut BufferResult) {
+        if let Some(provenance) = &self.input.provenance {
+            match result {
+                BufferResult::Json(record, _) => provenance.attach(record, SystemTime::now()),
+                BufferResult::Text(text) => *text = provenance.annotate_text(text),
+                BufferResult::Incomplete(_) => {}
+            }
+        }
+    }
+
+    fn max_count_reached(&self) -> bool {
+        self.selection
+            .max_count
+            .is_some_and(|count| self.selection.matched >= count)
+    }
+
+    /// Whether nothing the rest of the input holds would change the output
+    fn is_done(&self) -> bool {
+        if self.selection.quiet {
+            return self.selection.matched > 0;
+        }
+        let context_pending = self
+            .selection
+            .context
+            .as_ref()
+            .is_some_and(|context| context.after_remaining > 0);
+        let window_full = self
+            .batching
+            .window
+            .as_ref()
+            .is_some_and(RecordWindow::is_full);
+        (self.max_count_reached() || window_full) && !context_pending
+    }
+
+    /// Handles a record the filter rejected, keeping it around as context
+    fn suppress(&mut self, result: BufferResult) -> Result<()> {
+        let Some(context) = &mut self.selection.context else {
+            return Ok(());
+        };
+        if self.batching.aggregation.is_some() && self.output.report_writer.is_none() {
+            return Ok(());
+        }
+
+        if context.after_remaining > 0 {
+            context.after_remaining -= 1;
+            return self.write_record(result);
+        }
+
+        if context.before == 0 {
+            context.gap = true;
+            return Ok(());
+        }
+        if context.recent.len() == context.before {
+            context.recent.pop_front();
+            context.gap = true;
+        }
+        context.recent.push_back(result);
+        Ok(())
+    }
+
+    /// Byte ranges of `rendered` to highlight, given the `input` it was rendered from
+    fn highlight_ranges(&self, input: &FilterInput, rendered: &str) -> Vec<Range<usize>> {
+        if self.output.highlight_matches {
+            self.selection.filter.match_ranges(input, rendered)
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn write_record(&mut self, result: BufferResult) -> Result<()> {
+        if let Some(dedup) = &mut self.selection.dedup {
+            dedup.output(&result)?;
+        }
+
+        // Detected before the transforms after filtering may drop the level
+        let detect = |level_map: &LevelMap| match &result {
+            BufferResult::Json(value, _) => level_map.detect(value),
+            BufferResult::Text(text) => level_map.detect_text(text),
+            BufferResult::Incomplete(_) => None,
+        };
+        let color = self
+            .output
+            .level_colors
+            .as_ref()
+            .and_then(detect)
+            .and_then(level_color);
+        let split_level = self
+            .output
+            .level_split
+            .as_ref()
+            .map(|split| detect(split.level_map()));
+
+        self.write_delta_annotation(&result)?;
+        self.write_size_annotation(result.size())?;
+
+        let result = match (result, self.output.text_wrapping) {
+            (BufferResult::Text(text), Some(wrapping)) => {
+                let size = text.len();
+                let wrapped = self.transforms.apply_before_filter(wrapping.wrap(text));
+                BufferResult::Json(wrapped, size)
+            }
+            (result, _) => result,
+        };
+
+        let is_text = matches!(result, BufferResult::Text(_));
+        let (rendered, ranges) = match result {
+            BufferResult::Json(json_value, size) => {
+                let json_value = self.transforms.apply_after_filter(json_value);
+
+                // Output JSON using the configured formatter
+                let display_value = self.output.aliases.apply(&json_value);
+                if let Some(slurped) = &mut self.batching.slurped {
+                    slurped.add(display_value.into_owned(), size)?;
+                    return Ok(());
+                }
+                let rendered = self.output.json_formatter.format_json(&display_value)?;
+                let ranges = self.highlight_ranges(&FilterInput::Json(&json_value), &rendered);
+                (rendered, ranges)
+            }
+            BufferResult::Text(_) if self.batching.slurped.is_some() => return Ok(()),
+            BufferResult::Text(text) => {
+                // Output text as-is
+                let ranges = self.highlight_ranges(&FilterInput::Text(&text), &text);
+                (text, ranges)
+            }
+            BufferResult::Incomplete(_) => {
+                // This should never happen due to FilterInput::try_from filtering,
+                // but we handle it defensively
+                return Ok(());
+            }
+        };
+
+        if let Some(split) = &mut self.output.level_split {
+            split.write(split_level.flatten(), &rendered)?;
+            if self.output.unbuffered {
+                split.flush()?;
+            }
+        }
+
+        let rendered = if self.output.highlight_matches {
+            highlight(&rendered, ranges, color.unwrap_or_default())
+        } else {
+            rendered
+        };
+
+        let writer: &mut dyn Write = match &mut self.output.text_writer {
+            Some(text_writer) if is_text => text_writer,
+            _ => &mut self.writer,
+        };
+        match color {
+            // Per line, so pretty-printed records stay colored in pagers
+            Some(color) => {
+                for line in rendered.lines() {
+                    writeln!(writer, "{}{}\x1b[0m", color, line)?;
+                }
+            }
+            None => writeln!(writer, "{}", rendered)?,
+        }
+        if self.output.unbuffered {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces array records by their elements, sized by their compact JSON
+fn explode_arrays(results: Vec<BufferResult>) -> Vec<BufferResult> {
+    let mut exploded = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            BufferResult::Json(Value::Array(elements), _) => {
+                exploded.extend(elements.into_iter().map(|element| {
+                    let size = element.to_string().len();
+                    BufferResult::Json(element, size)
+                }));
+            }
+            result => exploded.push(result),
+        }
+    }
+    exploded
+}
+
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::BrokenPipe)
+}
+
+/// ANSI color for records of a level, unset for info to keep regular output plain
+fn level_color(level: Level) -> Option<&'static str> {
+    match level {
+        Level::Trace | Level::Debug => Some("\x1b[2m"),
+        Level::Info => None,
+        Level::Warn => Some("\x1b[33m"),
+        Level::Error => Some("\x1b[31m"),
+        Level::Fatal => Some("\x1b[1;31m"),
+    }
+}
+
+/// Wraps the given byte ranges in grep's match color.
+///
+/// Overlapping ranges are merged. After each match, `resume` restores the color
+/// of the surrounding text.
+fn highlight(text: &str, mut ranges: Vec<Range<usize>>, resume: &str) -> String {
+    ranges.sort_by_key(|range| range.start);
+
+    let mut output = String::with_capacity(text.len());
+    let mut position = 0;
+    for range in ranges {
+        let start = range.start.max(position);
+        if start >= range.end {
+            continue;
+        }
+        output.push_str(&text[position..start]);
+        output.push_str("\x1b[1;31m");
+        output.push_str(&text[start..range.end]);
+        output.push_str("\x1b[0m");
+        output.push_str(resume);
+        position = range.end;
+    }
+    output.push_str(&text[position..]);
+    output
+}
+
+/// Formats a byte count using binary units, e.g. `512 B` or `1.5 KiB`
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::{HistogramAggregator, SummaryAggregator};
+    use crate::docker::DockerUnwrapper;
+    use crate::field_path::FieldPath;
+    use crate::filter::{
+        FieldScopeFilter, InvertFilter, LevelFilter, NoFilter, OutputFilter, Pattern, RegexFilter,
+    };
+    use crate::transform::{ProjectTransform, SetTransform};
+    use rstest::rstest;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_process_mixed_content() {
+        let input = r#"Regular text line
+{"valid": "json", "number": 42}
+Another text line
+{
+  "multiline": "json"
+}
+Final text line"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "Regular text line",
+                r#"{"valid":"json","number":42}"#, // Original key order preserved
+                "Another text line",
+                r#"{"multiline":"json"}"#,
+                "Final text line"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_incomplete_json_at_eof() {
+        let input = r#"Complete line
+{
+  "incomplete": "json without closing"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "Complete line",
+                "{",
+                r#"  "incomplete": "json without closing"#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_only_json() {
+        let input = r#"{"first": 1}
+{"second": 2}
+[1, 2, 3]
+"string value""#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"first":1}"#,
+                r#"{"second":2}"#,
+                r#"[1,2,3]"#,
+                r#""string value""#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_empty_input() {
+        let input = "";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(output_str, "");
+    }
+
+    #[test]
+    fn test_process_with_overflow() {
+        let input = r#"{invalid json start
+more invalid
+yet more invalid
+{"valid": "json"}
+final text"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(3); // Small buffer to trigger overflow
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "{invalid json start",
+                "more invalid",
+                "yet more invalid",
+                r#"{"valid":"json"}"#,
+                "final text"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_with_regex_filter() {
+        let input = r#"Regular text line
+{"status": "error", "message": "failed"}
+Info: everything ok
+{"status": "ok", "message": "success"}
+ERROR: critical system failure"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex("error".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        // Should only output lines containing "error" (case-insensitive)
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"status":"error","message":"failed"}"#,
+                "ERROR: critical system failure"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_with_case_sensitive_filter() {
+        let input = r#"error: lowercase
+ERROR: uppercase
+Error: mixed case
+info: no match"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex("ERROR".to_string())),
+            true,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        // Should only output lines with exact case match
+        assert_eq!(lines, vec!["ERROR: uppercase"]);
+    }
+
+    #[test]
+    fn test_process_filter_json_structure() {
+        let input = r#"{"status": "error", "code": 500}
+{"status": "ok", "code": 200}
+{"error": "not matching"}
+Plain text with status error"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        // Filter for JSON objects with status: error pattern
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex(r#""status"\s*:\s*"error""#.to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        // Should only match the first JSON object
+        assert_eq!(lines, vec![r#"{"status":"error","code":500}"#]);
+    }
+
+    #[test]
+    fn test_process_pretty_printed_output() {
+        let input = r#"{"name": "Deep Space Nine", "location": "Bajoran system"}
+Text line
+{"crew": {"captain": "Sisko", "science": "Dax"}}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(false, true); // pretty, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+
+        // Should contain pretty-printed JSON with indentation
+        assert!(output_str.contains("{\n  \"name\": \"Deep Space Nine\""));
+        assert!(output_str.contains("  \"location\": \"Bajoran system\"\n}"));
+        assert!(output_str.contains("{\n  \"crew\": {\n    \"captain\": \"Sisko\""));
+        assert!(output_str.contains("Text line"));
+    }
+
+    #[test]
+    fn test_process_flushes_buffer_after_timeout() {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        let feeder = std::thread::spawn(move || {
+            writeln!(writer, "{{").unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            writeln!(writer, "}}").unwrap();
+        });
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor = StreamProcessor::new(reader, &mut output, buffer, filter, formatter)
+            .with_flush_timeout(Duration::from_millis(20));
+
+        processor.process().unwrap();
+        feeder.join().unwrap();
+
+        // Without the timeout, both lines would have been joined into `{}`
+        assert_eq!(String::from_utf8(output).unwrap(), "{\n}\n");
+    }
+
+    #[test]
+    fn test_process_emits_heartbeat_while_input_is_quiet() {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        let feeder = std::thread::spawn(move || {
+            writeln!(writer, r#"{{"event": "docking"}}"#).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            writeln!(writer, r#"{{"event": "undocking"}}"#).unwrap();
+        });
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor = StreamProcessor::new(reader, &mut output, buffer, filter, formatter)
+            .with_heartbeat(Duration::from_millis(20), false);
+
+        processor.process().unwrap();
+        feeder.join().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let records: Vec<&str> = output_str
+            .lines()
+            .filter(|line| !line.starts_with("--- "))
+            .collect();
+        let heartbeats = output_str
+            .lines()
+            .filter(|line| line.starts_with("--- ") && line.ends_with("no input for 20ms ---"))
+            .count();
+
+        assert_eq!(
+            records,
+            vec![r#"{"event":"docking"}"#, r#"{"event":"undocking"}"#]
+        );
+        assert!(heartbeats >= 1);
+    }
+
+    #[test]
+    fn test_process_with_histogram_aggregation() {
+        let input = r#"{"status": 200}
+Text line
+{"status": 500}
+{"status": 200}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let histogram = HistogramAggregator::new(FieldPath::parse("status"));
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_aggregation(Aggregation::Histogram(histogram));
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output_str.lines().collect();
+
+        // Records are consumed by the aggregation, only the report is printed
+        assert_eq!(lines.len(), 1 + 10);
+        assert_eq!(lines[0], "status (3 values)");
+        assert!(lines[1].starts_with("  [200, 230) │"));
+        assert!(lines[1].ends_with(" 2"));
+        assert!(lines[10].ends_with(" 1"));
+    }
+
+    #[test]
+    fn test_process_aliases_only_affect_display() {
+        let input = r#"{"lvl": "error", "msg": "Warp core breach"}
+{"lvl": "info", "msg": "All systems nominal"}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        // Filters match against the original key names
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex(r#""lvl":"error""#.to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let aliases = KeyAliases::new(vec![("lvl".to_string(), "level".to_string())]);
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_aliases(aliases);
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output_str,
+            "{\"level\":\"error\",\"msg\":\"Warp core breach\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_transforms_after_filtering() {
+        let input = r#"{"level": "error", "msg": "Warp core breach", "deck": 12}
+Engineering reports
+{"level": "info", "msg": "All systems nominal", "deck": 3}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        // The filter still sees the fields dropped by the projection
+        let filter = OutputFilter::from_args(
+            None,
+            false,
+            None,
+            vec![],
+            Some(LevelFilter::new(Level::Error, LevelMap::default())),
+            false,
+            false,
+        )
+        .unwrap();
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let projection = ProjectTransform::new(&["msg".to_string(), "deck".to_string()]);
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_transform(RecordTransform::Project(projection));
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(output_str, "{\"msg\":\"Warp core breach\",\"deck\":12}\n");
+    }
+
+    #[test]
+    fn test_process_transforms_before_filtering() {
+        let input = r#"{"msg": "Warp core breach", "deck": 12}
+Engineering reports
+{"msg": "All systems nominal", "deck": 3}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        // Only the records the field was set on can match
+        let filter = OutputFilter::Regex(RegexFilter::new("defiant".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let static_fields = SetTransform::new(&[("ship".to_string(), "Defiant".to_string())]);
+        let projection = ProjectTransform::new(&["msg".to_string()]);
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_pre_filter_transform(RecordTransform::Set(static_fields))
+                .with_transform(RecordTransform::Project(projection));
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output_str,
+            "{\"msg\":\"Warp core breach\"}\n{\"msg\":\"All systems nominal\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_colors_records_by_level() {
+        let input = r#"{"level": "error", "msg": "Warp core breach"}
+WARN: shields at 40%
+{"level": "info", "msg": "All systems nominal"}
+Promenade quiet"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(false, true); // pretty, no_color
+        // Detection sees the level even though the projection drops it
+        let projection = ProjectTransform::new(&["msg".to_string()]);
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_transform(RecordTransform::Project(projection))
+                .with_level_colors(LevelMap::default());
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output_str,
+            "\x1b[31m{\x1b[0m\n\x1b[31m  \"msg\": \"Warp core breach\"\x1b[0m\n\x1b[31m}\x1b[0m\n\
+             \x1b[33mWARN: shields at 40%\x1b[0m\n\
+             {\n  \"msg\": \"All systems nominal\"\n}\n\
+             Promenade quiet\n"
+        );
+    }
+
+    #[test]
+    fn test_process_splits_records_by_level() {
+        let input = r#"{"level": "error", "msg": "Warp core breach"}
+WARN: shields at 40%
+{"level": "error", "msg": "Shields failing"}
+Promenade quiet"#;
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let split = LevelSplit::new(dir.path().to_path_buf(), LevelMap::default());
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_level_colors(LevelMap::default())
+                .with_level_split(split);
+
+        processor.process().unwrap();
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(
+            read("error.log"),
+            "{\"level\":\"error\",\"msg\":\"Warp core breach\"}\n\
+             {\"level\":\"error\",\"msg\":\"Shields failing\"}\n"
+        );
+        assert_eq!(read("warn.log"), "WARN: shields at 40%\n");
+        assert_eq!(read("unknown.log"), "Promenade quiet\n");
+        // The regular output still gets every record, colored
+        assert!(String::from_utf8(output).unwrap().starts_with("\x1b[31m{"));
+    }
+
+    #[rstest]
+    #[case(vec![], "", "Quark's bar")]
+    #[case(vec![0..5], "", "\x1b[1;31mQuark\x1b[0m's bar")]
+    #[case(vec![8..11, 0..5], "", "\x1b[1;31mQuark\x1b[0m's \x1b[1;31mbar\x1b[0m")]
+    #[case(vec![0..3, 2..5], "", "\x1b[1;31mQua\x1b[0m\x1b[1;31mrk\x1b[0m's bar")]
+    #[case(vec![0..5, 1..3], "", "\x1b[1;31mQuark\x1b[0m's bar")]
+    #[case(vec![8..11], "\x1b[33m", "Quark's \x1b[1;31mbar\x1b[0m\x1b[33m")]
+    fn test_highlight(
+        #[case] ranges: Vec<Range<usize>>,
+        #[case] resume: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(highlight("Quark's bar", ranges, resume), expected);
+    }
+
+    #[test]
+    fn test_process_highlights_matches() {
+        let input = r#"{"ship": "Defiant", "captain": "Sisko"}
+Defiant docked at upper pylon 1
+Rio Grande departed"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter =
+            OutputFilter::Regex(RegexFilter::new("defiant|pylon".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_match_highlighting();
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output_str,
+            "{\"ship\":\"\x1b[1;31mDefiant\x1b[0m\",\"captain\":\"Sisko\"}\n\
+             \x1b[1;31mDefiant\x1b[0m docked at upper \x1b[1;31mpylon\x1b[0m 1\n"
+        );
+    }
+
+    #[test]
+    fn test_process_highlights_field_scope_matches_only_within_field() {
+        let input = r#"{"captain": "Sisko", "msg": "Sisko docked at Deep Space 9"}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::FieldScope(FieldScopeFilter::new(
+            "msg",
+            OutputFilter::Regex(RegexFilter::new("sisko".to_string(), false).unwrap()),
+        ));
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_match_highlighting();
+
+        processor.process().unwrap();
+
+        let output_str = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output_str,
+            "{\"captain\":\"Sisko\",\"msg\":\"\x1b[1;31mSisko\x1b[0m docked at Deep Space 9\"}\n"
+        );
+    }
+
+    #[rstest]
+    #[case(1, 1, "Beta\nbreach one\nGamma\n--\nEpsilon\nbreach two\n")]
+    #[case(0, 2, "breach one\nGamma\nDelta\n--\nbreach two\n")]
+    #[case(2, 0, "Alpha\nBeta\nbreach one\n--\nDelta\nEpsilon\nbreach two\n")]
+    // Overlapping context forms one group without separator
+    #[case(3, 3, "Alpha\nBeta\nbreach one\nGamma\nDelta\nEpsilon\nbreach two\n")]
+    #[case(0, 0, "breach one\n--\nbreach two\n")]
+    fn test_process_emits_context_records(
+        #[case] before: usize,
+        #[case] after: usize,
+        #[case] expected: &str,
+    ) {
+        let input = "Alpha\nBeta\nbreach one\nGamma\nDelta\nEpsilon\nbreach two";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::Regex(RegexFilter::new("breach".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_context(before, after);
+
+        processor.process().unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_process_context_starts_without_separator() {
+        let input = "breach one\nAlpha\nBeta\nGamma\nbreach two";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::Regex(RegexFilter::new("breach".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_context(1, 1);
+
+        processor.process().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "breach one\nAlpha\n--\nGamma\nbreach two\n"
+        );
+    }
+
+    #[test]
+    fn test_process_counts_matched_records() {
+        let input = "Defiant launched\n{\"ship\": \"Defiant\"}\nRio Grande launched";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::Regex(RegexFilter::new("defiant".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        assert_eq!(processor.matched_records(), 2);
+    }
+
+    #[rstest]
+    #[case(0, "Defiant launched\n{\"ship\":\"Defiant\"}\n")]
+    #[case(1, "Defiant launched\n{\"ship\":\"Defiant\"}\nRio Grande launched\n")]
+    fn test_process_stops_after_max_count(#[case] after: usize, #[case] expected: &str) {
+        let input = "Defiant launched\n{\"ship\": \"Defiant\"}\nRio Grande launched\n\
+                     Defiant docked\n";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::Regex(RegexFilter::new("defiant".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_max_count(2)
+                .with_context(0, after);
+
+        processor.process().unwrap();
+
+        assert_eq!(processor.matched_records(), 2);
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(RecordRange { skip: 1, ..Default::default() }, "B\nC\nD\nE\n")]
+    #[case(RecordRange { head: Some(2), ..Default::default() }, "A\nB\n")]
+    #[case(RecordRange { tail: Some(2), ..Default::default() }, "D\nE\n")]
+    #[case(RecordRange { tail: Some(0), ..Default::default() }, "")]
+    #[case(RecordRange { skip: 1, head: Some(3), tail: Some(2) }, "C\nD\n")]
+    fn test_process_outputs_record_range(#[case] range: RecordRange, #[case] expected: &str) {
+        let input = "A\nB\nSisko\nC\nD\nE\n";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::Invert(InvertFilter::new(OutputFilter::Regex(
+            RegexFilter::new("sisko".to_string(), false).unwrap(),
+        )));
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_record_range(range);
+
+        processor.process().unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_process_reports_records_over_rate_limit() {
+        let input = "Defiant docked\nRio Grande departed\nMekong docked\n\
+                     Yangtzee Kiang departed\nOrinoco docked\n";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let limit = RateLimit {
+            records: 2,
+            period: Duration::from_secs(3600),
+        };
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_rate_limit(limit, false);
+
+        processor.process().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Defiant docked\nRio Grande departed\n\
+             --- suppressed 3 records over the rate limit ---\n"
+        );
+    }
+
+    #[test]
+    fn test_throttle_starts_new_period() {
+        let start = Instant::now();
+        let mut throttle = Throttle {
+            limit: RateLimit {
+                records: 1,
+                period: Duration::from_secs(1),
+            },
+            colored: false,
+            window_start: None,
+            emitted: 0,
+            suppressed: 0,
+        };
+
+        assert_eq!(throttle.admit(start), (true, 0));
+        assert_eq!(
+            throttle.admit(start + Duration::from_millis(300)),
+            (false, 0)
+        );
+        assert_eq!(
+            throttle.admit(start + Duration::from_millis(600)),
+            (false, 0)
+        );
+        assert_eq!(throttle.admit(start + Duration::from_secs(1)), (true, 2));
+        assert_eq!(throttle.admit(start + Duration::from_secs(5)), (true, 0));
+    }
+
+    #[test]
+    fn test_process_collects_stats() {
+        let input = "Defiant launched\n{\"ship\": \"Defiant\"}\n{\"ship\": \"Rio Grande\"}\n{ \"ship\": \ntruncated";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(2);
+        let filter = OutputFilter::Regex(RegexFilter::new("defiant".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let stats = Arc::new(Stats::new());
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_stats(Arc::clone(&stats));
+
+        processor.process().unwrap();
+
+        assert_eq!(stats.lines(), 5);
+        assert_eq!(stats.bytes(), input.len() as u64 + 1);
+        assert_eq!(stats.json_records(), 2);
+        assert_eq!(stats.text_lines(), 3);
+        assert_eq!(stats.filtered_out(), 3);
+        assert_eq!(stats.parse_failures(), 1);
+    }
+
+    #[rstest]
+    #[case(TextWrapping::Text, "{\"text\":\"Garak tailors a suit\"}")]
+    #[case(
+        TextWrapping::Message,
+        "{\"message\":\"Garak tailors a suit\",\"jlif_parsed\":false}"
+    )]
+    fn test_process_wraps_text_lines(#[case] wrapping: TextWrapping, #[case] wrapped: &str) {
+        let input = "Garak tailors a suit\n{\"customer\": \"Bashir\"}";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_wrapped_text(wrapping);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}\n{{\"customer\":\"Bashir\"}}\n", wrapped)
+        );
+    }
+
+    #[test]
+    fn test_malformed_lines_merge_keeps_earlier_examples() {
+        let mut malformed = MalformedLines::default();
+        for line in 0..8 {
+            malformed.record(&format!("{{\"deck\": {}", line));
+        }
+        let mut other = MalformedLines::default();
+        for line in 8..12 {
+            other.record(&format!("{{\"deck\": {}", line));
+        }
+
+        malformed.merge(other);
+
+        assert_eq!(malformed.count, 12);
+        assert_eq!(malformed.examples.len(), MALFORMED_EXAMPLES);
+        assert_eq!(malformed.examples[9], "{\"deck\": 9");
+    }
+
+    #[test]
+    fn test_process_counts_malformed_lines_in_strict_mode() {
+        let input = "{\"ship\": \"Defiant\"}\n\
+                     {\"ship\": \"Rio Grande\",\n\
+                     Quark's bar opens\n\
+                     [1, 2\n\
+                     {\"ship\": \"Orinoco\"}";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(2);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_strict();
+
+        processor.process().unwrap();
+
+        assert_eq!(
+            processor.malformed_lines(),
+            Some(&MalformedLines {
+                count: 2,
+                examples: vec![
+                    "{\"ship\": \"Rio Grande\",".to_string(),
+                    "[1, 2".to_string()
+                ],
+            })
+        );
+        drop(processor);
+        // Malformed lines are still output as text
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"ship\":\"Defiant\"}\n{\"ship\": \"Rio Grande\",\nQuark's bar opens\n[1, 2\n\
+             {\"ship\":\"Orinoco\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_groups_records_by_id() {
+        let input = "{\"req\": \"a\", \"ts\": 1714564800, \"msg\": \"Docking request\"}\n\
+                     {\"req\": \"b\", \"ts\": 1714564801, \"msg\": \"Cargo manifest\"}\n\
+                     Quark's bar opens\n\
+                     {\"req\": \"a\", \"ts\": 1714564802.5, \"msg\": \"Docked\"}";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let groups = RecordGroups::new(FieldPath::parse("req"), vec![FieldPath::parse("ts")]);
+        let projection = ProjectTransform::new(&["msg".to_string()]);
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_grouping(groups, false)
+                .with_transform(RecordTransform::Project(projection));
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Quark's bar opens\n\
+             --- req a: 2 records in 2.5s ---\n\
+             {\"msg\":\"Docking request\"}\n\
+             {\"msg\":\"Docked\"}\n\
+             --- req b: 1 record in 0s ---\n\
+             {\"msg\":\"Cargo manifest\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_filters_stack_traces_as_a_whole() {
+        let input = "{\"msg\": \"Docked\"}\n\
+                     java.lang.IllegalStateException: Warp core breach\n\
+                     \tat com.ds9.Engineering.eject(Engineering.java:42)\n\
+                     {\"msg\": \"Undocked\"}\n\
+                     java.lang.IllegalStateException: Plasma leak\n\
+                     \tat com.ds9.Engineering.vent(Engineering.java:7)";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::from_args(
+            Some(Pattern::Regex("eject".to_string())),
+            false,
+            None,
+            vec![],
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_trace_grouping()
+                .with_wrapped_text(TextWrapping::Text);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"text\":\"java.lang.IllegalStateException: Warp core breach\\n\\tat \
+             com.ds9.Engineering.eject(Engineering.java:42)\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_interrupt_drains_buffer() {
+        let input = "{ \"log\": \"Captain's log,\n  \"stardate\": 47457.1 }\nNever read";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let interrupt = Arc::new(AtomicBool::new(true));
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_interrupt(interrupt);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        // The incomplete record still appears as text
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{ \"log\": \"Captain's log,\n"
+        );
+    }
+
+    #[rstest]
+    #[case(AnsiStripping::All, "Docking at pylon 3")]
+    #[case(AnsiStripping::Json, "\x1b[2mDocking at pylon 3\x1b[0m")]
+    fn test_process_strips_ansi(#[case] stripping: AnsiStripping, #[case] text: &str) {
+        let input = "\x1b[32m{\"level\": \"info\",\x1b[0m\n\x1b[32m \"msg\": \"Docked\"}\x1b[0m\n\
+                     \x1b[2mDocking at pylon 3\x1b[0m\n";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_ansi_stripping(stripping);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{{\"level\":\"info\",\"msg\":\"Docked\"}}\n{}\n", text)
+        );
+    }
+
+    #[test]
+    fn test_process_parses_logfmt_lines() {
+        let input = "level=info msg=\"Replicator online\" deck=3\n\
+                     Quark's bar opens\n\
+                     {\"level\": \"warn\", \"msg\": \"Shields low\"}\n\
+                     level=error msg=\"Warp core breach\"";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::Regex(RegexFilter::new("level".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_input_format(InputFormat::Logfmt);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"level\":\"info\",\"msg\":\"Replicator online\",\"deck\":\"3\"}\n\
+             {\"level\":\"warn\",\"msg\":\"Shields low\"}\n\
+             {\"level\":\"error\",\"msg\":\"Warp core breach\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_unwraps_docker_entries() {
+        let input = r#"{"log":"{\"level\":\"info\",\n","stream":"stdout","time":"2024-05-01T12:00:03Z"}
+{"log":" \"msg\":\"Docked\"}\n","stream":"stdout","time":"2024-05-01T12:00:04Z"}
+{"log":"\u001b[31mShields failing\u001b[0m\n","stream":"stderr","time":"2024-05-01T12:00:05Z"}
+{"log":"{\"msg\":","stream":"stdout","time":"2024-05-01T12:00:06Z"}
+{"log":"\"Undocked\"}\n","stream":"stdout","time":"2024-05-01T12:00:06Z"}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_envelope(LogEnvelope::Docker(DockerUnwrapper::new(true)))
+                .with_ansi_stripping(AnsiStripping::All);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        // Multi-line records take the metadata of their first line
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"level\":\"info\",\"msg\":\"Docked\",\"docker\":{\"stream\":\"stdout\",\"time\":\"2024-05-01T12:00:03Z\"}}\n\
+             Shields failing\n\
+             {\"msg\":\"Undocked\",\"docker\":{\"stream\":\"stdout\",\"time\":\"2024-05-01T12:00:06Z\"}}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_strips_line_prefixes() {
+        let input = r#"ops-1        | {"msg": "Replicator online"}
+promenade-1  | Quark's bar opens
+[pod/promenade] Quark's bar closes
+ops-1        | {"msg": "Shields up",
+ops-1        |  "deck": 3}
+promenade-1  | {"msg": "Docking", "source": "pylon"}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let prefix =
+            LinePrefix::new(crate::prefix::DEFAULT_PREFIX_PATTERN, "source".to_string()).unwrap();
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_line_prefix(prefix);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"msg\":\"Replicator online\",\"source\":\"ops-1\"}\n\
+             promenade-1  | Quark's bar opens\n\
+             [pod/promenade] Quark's bar closes\n\
+             {\"msg\":\"Shields up\",\"deck\":3,\"source\":\"ops-1\"}\n\
+             {\"msg\":\"Docking\",\"source\":\"pylon\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_process_strips_prefixes_of_parsed_lines() {
+        let input = "[10.0.0.7:514] <12>May  1 12:00:03 ds9 api: Shields low\n\
+                     [10.0.0.7:514] Quark's bar opens";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let prefix =
+            LinePrefix::new(crate::prefix::DEFAULT_PREFIX_PATTERN, "source".to_string()).unwrap();
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_line_prefix(prefix)
+                .with_input_format(InputFormat::Syslog);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"timestamp\":\"May  1 12:00:03\",\"host\":\"ds9\",\"app\":\"api\",\"facility\":\"user\",\"level\":\"warning\",\"msg\":\"Shields low\",\"source\":\"10.0.0.7:514\"}\n\
+             [10.0.0.7:514] Quark's bar opens\n"
+        );
+    }
+
+    #[test]
+    fn test_process_explodes_arrays() {
+        let input = r#"[{"ship": "Defiant", "status": "docked"}, {"ship": "Rio Grande", "status": "away"}]
+[]
+{"ship": "Enterprise", "status": "away"}
+[
+  ["Sisko", "Kira"],
+  "Odo"
+]"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter =
+            OutputFilter::Regex(RegexFilter::new("away|Kira|Odo".to_string(), false).unwrap());
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_exploded_arrays();
+
+        processor.process().unwrap();
+
+        drop(processor);
+        // Only the top level is exploded, nested arrays stay records
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"ship\":\"Rio Grande\",\"status\":\"away\"}\n\
+             {\"ship\":\"Enterprise\",\"status\":\"away\"}\n\
+             [\"Sisko\",\"Kira\"]\n\
+             \"Odo\"\n"
+        );
+    }
+
+    #[test]
+    fn test_process_slurps_records_into_array() {
+        let input = r#"{"ship": "Defiant", "class": "Defiant"}
+Docking at upper pylon 1
+{"ship": "Rio Grande",
+ "class": "Danube"}"#;
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let projection = ProjectTransform::new(&["ship".to_string()]);
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_transform(RecordTransform::Project(projection))
+                .with_slurp(SlurpedRecords::new());
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[{\"ship\":\"Defiant\"},{\"ship\":\"Rio Grande\"}]\n"
+        );
+    }
+
+    #[test]
+    fn test_process_slurps_records_beyond_memory_limit_from_disk() {
+        let input = "{\"ship\": \"Defiant\"}\n{\"ship\": \"Rio Grande\"}\n{\"ship\": \"Orinoco\"}";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_slurp(SlurpedRecords::new().with_memory_limit(100));
+
+        processor.process().unwrap();
+
+        drop(processor);
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "[\n  {\"ship\":\"Defiant\"},\n  {\"ship\":\"Rio Grande\"},\n  {\"ship\":\"Orinoco\"}\n]\n"
+        );
+        let array: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(array.as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_process_reads_json_text_sequences() {
+        // A truncated record is cut off by the next RS instead of swallowing it
+        let input = "\u{1e}{\"ship\": \"Defiant\",\n \"warp\": 9.5}\n\
+                     \u{1e}{\"ship\": \u{1e}{\"ship\": \"Rio Grande\"}\n\
+                     Quark's bar\n";
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"ship\":\"Defiant\",\"warp\":9.5}\n\
+             {\"ship\": \n\
+             {\"ship\":\"Rio Grande\"}\n\
+             Quark's bar\n"
+        );
+    }
+
+    #[test]
+    fn test_process_writes_text_separately() {
+        let input = r#"Starting replicator
+{"item": "raktajino",
+ "temperature": "hot"}
+Replicator offline"#;
+        let dir = tempfile::tempdir().unwrap();
+        let text_path = dir.path().join("text.log");
+
+        let mut output = Vec::new();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let text_output = std::fs::File::create(&text_path).unwrap();
+        let mut processor =
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_text_output(Box::new(text_output));
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"item\":\"raktajino\",\"temperature\":\"hot\"}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&text_path).unwrap(),
+            "Starting replicator\nReplicator offline\n"
+        );
+    }
+
+    /// Output remembering what had been flushed, like a buffered stdout
+    #[derive(Default)]
+    struct FlushRecorder {
+        pending: Vec<u8>,
+        flushed: Vec<String>,
+    }
+
+    impl Write for &mut FlushRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let pending = std::mem::take(&mut self.pending);
+            self.flushed.push(String::from_utf8(pending).unwrap());
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    #[case(false, vec!["Nog enlists\nJake writes\n"])]
+    #[case(true, vec!["Nog enlists\n", "Jake writes\n", ""])]
+    fn test_process_flushes_output(#[case] unbuffered: bool, #[case] expected: Vec<&str>) {
+        let mut output = FlushRecorder::default();
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor = StreamProcessor::new(
+            Cursor::new("Nog enlists\nJake writes\n"),
+            &mut output,
             buffer,
             filter,
-            json_formatter,
+            formatter,
+        );
+        if unbuffered {
+            processor = processor.with_unbuffered_output();
         }
+
+        processor.process().unwrap();
+
+        drop(processor);
+        assert_eq!(output.flushed, expected);
     }
 
-    /// Process the stream line by line until EOF, then drain remaining buffer
-    pub fn process(&mut self) -> Result<()> {
-        let mut line = String::new();
+    /// Output whose reader goes away after `remaining` writes, like `head` does
+    struct ClosingWriter {
+        remaining: usize,
+    }
 
-        // Read lines until EOF
-        while self.reader.read_line(&mut line)? > 0 {
-            // Remove trailing newline if present
-            if line.ends_with('\n') {
-                line.pop();
-                if line.ends_with('\r') {
-                    line.pop();
-                }
+    impl Write for ClosingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(ErrorKind::BrokenPipe.into());
             }
+            self.remaining -= 1;
+            Ok(buf.len())
+        }
 
-            // Process line through buffer
-            let results = self.buffer.add_line(line.clone());
-            self.handle_results(results)?;
-
-            line.clear();
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
         }
+    }
 
-        // Drain remaining buffered content at EOF
-        let drain_results = self.buffer.drain();
-        self.handle_results(drain_results)?;
+    #[test]
+    fn test_process_stops_quietly_on_broken_pipe() {
+        let input = "Worf on duty\n".repeat(100);
 
-        Ok(())
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let stats = Arc::new(Stats::new());
+        let mut processor = StreamProcessor::new(
+            Cursor::new(input),
+            ClosingWriter { remaining: 5 },
+            buffer,
+            filter,
+            formatter,
+        )
+        .with_stats(Arc::clone(&stats));
+
+        processor.process().unwrap();
+
+        // Reading stops with the first failed write
+        assert!(stats.lines() < 100);
     }
 
-    fn handle_results(&mut self, results: Vec<BufferResult>) -> Result<()> {
-        for result in results {
-            // Try to convert BufferResult to FilterInput
-            // Incomplete results are automatically filtered out by the conversion
-            if let Ok(filter_input) = FilterInput::try_from(&result) {
-                // Apply filter to determine if content should be output
-                if self.filter.matches(&filter_input) {
-                    match result {
-                        BufferResult::Json(json_value) => {
-                            // Output JSON using the configured formatter
-                            let json_string = self.json_formatter.format_json(&json_value)?;
-                            writeln!(self.writer, "{}", json_string)?;
-                        }
-                        BufferResult::Text(text) => {
-                            // Output text as-is
-                            writeln!(self.writer, "{}", text)?;
-                        }
-                        BufferResult::Incomplete(_) => {
-                            // This should never happen due to FilterInput::try_from filtering,
-                            // but we handle it defensively
-                        }
-                    }
-                }
-                // If filter doesn't match, content is suppressed (no output)
+    #[test]
+    fn test_process_reports_other_write_errors() {
+        struct FullDisk;
+
+        impl Write for FullDisk {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("no space left on device"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
             }
-            // If conversion fails (Incomplete), content is not output
         }
-        Ok(())
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::filter::{NoFilter, OutputFilter};
-    use std::io::Cursor;
+        let buffer = LineBuffer::new(10);
+        let filter = OutputFilter::None(NoFilter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor = StreamProcessor::new(
+            Cursor::new("Worf on duty\n"),
+            FullDisk,
+            buffer,
+            filter,
+            formatter,
+        );
+
+        assert!(processor.process().is_err());
+    }
 
     #[test]
-    fn test_process_mixed_content() {
-        let input = r#"Regular text line
-{"valid": "json", "number": 42}
-Another text line
-{
-  "multiline": "json"
-}
-Final text line"#;
+    fn test_process_quiet_stops_at_first_match() {
+        let input = "Odo on patrol\nQuark arrested\nQuark released\nRom promoted";
 
         let mut output = Vec::new();
         let buffer = LineBuffer::new(10);
-        let filter = OutputFilter::None(NoFilter);
+        let filter = OutputFilter::Regex(RegexFilter::new("quark".to_string(), false).unwrap());
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
         let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_quiet();
 
         processor.process().unwrap();
 
-        let output_str = String::from_utf8(output).unwrap();
-        let lines: Vec<&str> = output_str.trim().split('\n').collect();
-
-        assert_eq!(
-            lines,
-            vec![
-                "Regular text line",
-                r#"{"valid":"json","number":42}"#, // Original key order preserved
-                "Another text line",
-                r#"{"multiline":"json"}"#,
-                "Final text line"
-            ]
-        );
+        assert_eq!(processor.matched_records(), 1);
+        drop(processor);
+        assert!(output.is_empty());
     }
 
     #[test]
-    fn test_process_incomplete_json_at_eof() {
-        let input = r#"Complete line
-{
-  "incomplete": "json without closing"#;
+    fn test_process_redraws_aggregation_periodically() {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        let feeder = std::thread::spawn(move || {
+            writeln!(writer, r#"{{"level": "info", "msg": "Docking"}}"#).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            writeln!(writer, r#"{{"level": "warn", "msg": "Undocking"}}"#).unwrap();
+        });
 
         let mut output = Vec::new();
         let buffer = LineBuffer::new(10);
         let filter = OutputFilter::None(NoFilter);
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
-        let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+        let mut processor = StreamProcessor::new(reader, &mut output, buffer, filter, formatter)
+            .with_aggregation(Aggregation::Summary(SummaryAggregator::new(
+                LevelMap::default(),
+            )))
+            .with_refresh(Duration::from_millis(20));
 
         processor.process().unwrap();
+        feeder.join().unwrap();
 
         let output_str = String::from_utf8(output).unwrap();
-        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+        let frames: Vec<&str> = output_str.split("\x1b[2J\x1b[H").skip(1).collect();
 
-        assert_eq!(
-            lines,
-            vec![
-                "Complete line",
-                "{",
-                r#"  "incomplete": "json without closing"#
-            ]
-        );
+        // At least one intermediate frame plus the final one at EOF
+        assert!(frames.len() >= 2);
+        assert!(frames.first().unwrap().starts_with("1 records in "));
+        assert!(frames.last().unwrap().starts_with("2 records in "));
+        assert!(!output_str.contains(r#"{"level""#));
     }
 
     #[test]
-    fn test_process_only_json() {
-        let input = r#"{"first": 1}
-{"second": 2}
-[1, 2, 3]
-"string value""#;
+    fn test_process_filters_by_original_size() {
+        // The pretty-printed record is 25 bytes in the input, but only 20 bytes compact
+        let input =
+            "{\n  \"vessel\": \"Defiant\"\n}\nRunabout Rio Grande\n{\"vessel\": \"Odyssey\"}";
 
         let mut output = Vec::new();
         let buffer = LineBuffer::new(10);
         let filter = OutputFilter::None(NoFilter);
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
         let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_size_limits(SizeLimits {
+                    min: Some(22),
+                    max: Some(30),
+                });
 
         processor.process().unwrap();
 
         let output_str = String::from_utf8(output).unwrap();
-        let lines: Vec<&str> = output_str.trim().split('\n').collect();
-
-        assert_eq!(
-            lines,
-            vec![
-                r#"{"first":1}"#,
-                r#"{"second":2}"#,
-                r#"[1,2,3]"#,
-                r#""string value""#
-            ]
-        );
+        assert_eq!(output_str, "{\"vessel\":\"Defiant\"}\n");
     }
 
     #[test]
-    fn test_process_empty_input() {
-        let input = "";
+    fn test_process_annotates_deltas() {
+        let input = "{\"ts\": \"2024-05-01T12:00:03Z\"}\n\
+                     Runabout Rio Grande\n\
+                     {\"ts\": \"2024-05-01T12:00:05.300Z\"}\n\
+                     {\"ts\": 1714564805}";
+        let clock = DeltaClock::Fields(vec![FieldPath::parse("ts")], TimeFormats::default());
 
         let mut output = Vec::new();
         let buffer = LineBuffer::new(10);
         let filter = OutputFilter::None(NoFilter);
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
         let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_delta_annotations(clock, false);
 
         processor.process().unwrap();
 
         let output_str = String::from_utf8(output).unwrap();
-        assert_eq!(output_str, "");
+        assert_eq!(
+            output_str,
+            "[+0s] {\"ts\":\"2024-05-01T12:00:03Z\"}\n\
+             Runabout Rio Grande\n\
+             [+2.3s] {\"ts\":\"2024-05-01T12:00:05.300Z\"}\n\
+             [-0.3s] {\"ts\":1714564805}\n"
+        );
     }
 
     #[test]
-    fn test_process_with_overflow() {
-        let input = r#"{invalid json start
-more invalid
-yet more invalid
-{"valid": "json"}
-final text"#;
+    fn test_process_annotates_sizes() {
+        let input = "{\n  \"vessel\": \"Defiant\"\n}\nRunabout Rio Grande";
 
         let mut output = Vec::new();
-        let buffer = LineBuffer::new(3); // Small buffer to trigger overflow
+        let buffer = LineBuffer::new(10);
         let filter = OutputFilter::None(NoFilter);
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
         let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_size_annotations(false);
 
         processor.process().unwrap();
 
         let output_str = String::from_utf8(output).unwrap();
-        let lines: Vec<&str> = output_str.trim().split('\n').collect();
-
         assert_eq!(
-            lines,
-            vec![
-                "{invalid json start",
-                "more invalid",
-                "yet more invalid",
-                r#"{"valid":"json"}"#,
-                "final text"
-            ]
+            output_str,
+            "[25 B] {\"vessel\":\"Defiant\"}\n[19 B] Runabout Rio Grande\n"
         );
     }
 
+    #[rstest]
+    #[case(0, "0 B")]
+    #[case(1023, "1023 B")]
+    #[case(1024, "1.0 KiB")]
+    #[case(1536, "1.5 KiB")]
+    #[case(5 * 1024 * 1024, "5.0 MiB")]
+    fn test_format_size(#[case] bytes: u64, #[case] expected: &str) {
+        assert_eq!(format_size(bytes), expected);
+    }
+
     #[test]
-    fn test_process_with_regex_filter() {
-        let input = r#"Regular text line
-{"status": "error", "message": "failed"}
-Info: everything ok
-{"status": "ok", "message": "success"}
-ERROR: critical system failure"#;
+    fn test_process_splits_array_larger_than_buffer() {
+        let input = r#"[
+  {
+    "name": "Sisko",
+    "rank": "Captain"
+  },
+  {
+    "name": "Kira",
+    "rank": "Colonel"
+  }
+]
+Transmission ends"#;
 
         let mut output = Vec::new();
-        let buffer = LineBuffer::new(10);
-        let filter = OutputFilter::from_args(Some("error".to_string()), false, false, false).unwrap();
+        let buffer = LineBuffer::new(2); // Far smaller than the array
+        let filter = OutputFilter::None(NoFilter);
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
         let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_array_splitting();
 
         processor.process().unwrap();
 
         let output_str = String::from_utf8(output).unwrap();
-        let lines: Vec<&str> = output_str.trim().split('\n').collect();
-
-        // Should only output lines containing "error" (case-insensitive)
         assert_eq!(
-            lines,
-            vec![
-                r#"{"status":"error","message":"failed"}"#,
-                "ERROR: critical system failure"
-            ]
+            output_str,
+            "{\"name\":\"Sisko\",\"rank\":\"Captain\"}\n{\"name\":\"Kira\",\"rank\":\"Colonel\"}\nTransmission ends\n"
         );
     }
 
     #[test]
-    fn test_process_with_case_sensitive_filter() {
-        let input = r#"error: lowercase
-ERROR: uppercase
-Error: mixed case
-info: no match"#;
+    fn test_process_passes_bracketed_text_through_when_splitting_arrays() {
+        let input = "[INFO] Server started\n{\"ship\": \"Defiant\"}";
 
         let mut output = Vec::new();
-        let buffer = LineBuffer::new(10);
-        let filter = OutputFilter::from_args(Some("ERROR".to_string()), true, false, false).unwrap();
+        let buffer = LineBuffer::new(2);
+        let filter = OutputFilter::None(NoFilter);
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
         let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter)
+                .with_array_splitting();
 
         processor.process().unwrap();
 
-        let output_str = String::from_utf8(output).unwrap();
-        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+        drop(processor);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[INFO] Server started\n{\"ship\":\"Defiant\"}\n"
+        );
+    }
 
-        // Should only output lines with exact case match
-        assert_eq!(lines, vec!["ERROR: uppercase"]);
+    #[test]
+    fn test_process_suppresses_records_seen_in_previous_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("state.db");
+
+        let run = |input: &str| -> String {
+            let mut output = Vec::new();
+            let buffer = LineBuffer::new(10);
+            let filter = OutputFilter::None(NoFilter);
+            let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+            let mut processor = StreamProcessor::new(
+                Cursor::new(input.to_string()),
+                &mut output,
+                buffer,
+                filter,
+                formatter,
+            )
+            .with_dedup(DedupState::open(&state_path).unwrap());
+            processor.process().unwrap();
+            drop(processor);
+            String::from_utf8(output).unwrap()
+        };
+
+        let first = run("{\"shift\": \"alpha\"}\nDocking request\n{\"shift\": \"alpha\"}");
+        assert_eq!(first, "{\"shift\":\"alpha\"}\nDocking request\n");
+
+        let second = run("{\"shift\": \"alpha\"}\nDocking request\n{\"shift\": \"beta\"}");
+        assert_eq!(second, "{\"shift\":\"beta\"}\n");
     }
 
     #[test]
-    fn test_process_filter_json_structure() {
-        let input = r#"{"status": "error", "code": 500}
-{"status": "ok", "code": 200}
-{"error": "not matching"}
-Plain text with status error"#;
+    fn test_process_drains_buffer_between_inputs() {
+        // The unterminated object of the first input must not swallow the second
+        let first = Cursor::new("{\"log\": \"captain\"}\n{\n  \"incomplete\": true");
+        let second = Cursor::new("}\n{\"log\": \"first officer\"}");
 
         let mut output = Vec::new();
         let buffer = LineBuffer::new(10);
-        // Filter for JSON objects with status: error pattern
-        let filter =
-            OutputFilter::from_args(Some(r#""status"\s*:\s*"error""#.to_string()), false, false, false)
-                .unwrap();
+        let filter = OutputFilter::None(NoFilter);
         let formatter = JsonFormatter::from_args(true, true); // compact, no_color
-        let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+        let mut processor = StreamProcessor::new(first, &mut output, buffer, filter, formatter)
+            .with_inputs(vec![second]);
 
         processor.process().unwrap();
 
         let output_str = String::from_utf8(output).unwrap();
-        let lines: Vec<&str> = output_str.trim().split('\n').collect();
-
-        // Should only match the first JSON object
-        assert_eq!(lines, vec![r#"{"status":"error","code":500}"#]);
+        assert_eq!(
+            output_str,
+            "{\"log\":\"captain\"}\n{\n  \"incomplete\": true\n}\n{\"log\":\"first officer\"}\n"
+        );
     }
 
     #[test]
-    fn test_process_pretty_printed_output() {
-        let input = r#"{"name": "Deep Space Nine", "location": "Bajoran system"}
-Text line
-{"crew": {"captain": "Sisko", "science": "Dax"}}"#;
+    fn test_process_labels_records_with_their_input() {
+        let first = Cursor::new("{\n  \"msg\": \"Docked\"\n}\nQuark's bar opens");
+        let second = Cursor::new("{\"msg\": \"Undocked\", \"source\": \"runabout\"}");
+        let labels = InputLabels::new(
+            vec!["ops.log".to_string(), "bar.log".to_string()],
+            "source".to_string(),
+        );
 
         let mut output = Vec::new();
         let buffer = LineBuffer::new(10);
         let filter = OutputFilter::None(NoFilter);
-        let formatter = JsonFormatter::from_args(false, true); // pretty, no_color
-        let mut processor =
-            StreamProcessor::new(Cursor::new(input), &mut output, buffer, filter, formatter);
+        let formatter = JsonFormatter::from_args(true, true); // compact, no_color
+        let mut processor = StreamProcessor::new(first, &mut output, buffer, filter, formatter)
+            .with_inputs(vec![second])
+            .with_input_labels(labels);
 
         processor.process().unwrap();
 
         let output_str = String::from_utf8(output).unwrap();
-
-        // Should contain pretty-printed JSON with indentation
-        assert!(output_str.contains("{\n  \"name\": \"Deep Space Nine\""));
-        assert!(output_str.contains("  \"location\": \"Bajoran system\"\n}"));
-        assert!(output_str.contains("{\n  \"crew\": {\n    \"captain\": \"Sisko\""));
-        assert!(output_str.contains("Text line"));
+        assert_eq!(
+            output_str,
+            "{\"msg\":\"Docked\",\"source\":\"ops.log\"}\n\
+             [ops.log] Quark's bar opens\n\
+             {\"msg\":\"Undocked\",\"source\":\"runabout\"}\n"
+        );
     }
 }
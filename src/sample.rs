@@ -0,0 +1,98 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::buffer::BufferResult;
+
+/// How records are thinned out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// Keep each record with this probability between 0 and 1
+    Random(f64),
+    /// Keep every Nth record, starting with the first
+    Every(u64),
+}
+
+/// Decides which records to keep of a stream too chatty to read in full
+pub struct Sampler {
+    sampling: Sampling,
+    rng: fastrand::Rng,
+    seen: u64,
+    keep_text: bool,
+}
+
+impl Sampler {
+    pub fn new(sampling: Sampling) -> Self {
+        Self {
+            sampling,
+            rng: fastrand::Rng::new(),
+            seen: 0,
+            keep_text: false,
+        }
+    }
+
+    /// Makes random sampling reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Keeps all text lines, sampling only JSON records
+    pub fn keeping_text(mut self) -> Self {
+        self.keep_text = true;
+        self
+    }
+
+    /// Whether to keep the next record
+    pub fn keep(&mut self, result: &BufferResult) -> bool {
+        if self.keep_text && matches!(result, BufferResult::Text(_)) {
+            return true;
+        }
+        let index = self.seen;
+        self.seen += 1;
+        match self.sampling {
+            Sampling::Random(rate) => self.rng.f64() < rate,
+            Sampling::Every(every) => index.is_multiple_of(every),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record() -> BufferResult {
+        BufferResult::Json(json!({"msg": "Docked"}), 15)
+    }
+
+    #[test]
+    fn test_every_keeps_first_of_each_group() {
+        let mut sampler = Sampler::new(Sampling::Every(3));
+
+        let kept: Vec<bool> = (0..7).map(|_| sampler.keep(&record())).collect();
+
+        assert_eq!(kept, [true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_random_keeps_about_the_rate() {
+        let mut sampler = Sampler::new(Sampling::Random(0.1)).with_seed(47);
+
+        let kept = (0..10_000).filter(|_| sampler.keep(&record())).count();
+
+        assert!((900..1100).contains(&kept), "kept {}", kept);
+    }
+
+    #[test]
+    fn test_keeping_text_samples_json_only() {
+        let mut sampler = Sampler::new(Sampling::Every(2)).keeping_text();
+        let text = BufferResult::Text("Quark complains".to_string());
+
+        assert!(sampler.keep(&record()));
+        assert!(sampler.keep(&text));
+        assert!(!sampler.keep(&record()));
+        assert!(sampler.keep(&text));
+        assert!(sampler.keep(&record()));
+    }
+}
@@ -0,0 +1,248 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::aggregate::Aggregator;
+use serde_json::{Map, Value, json};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+// Distinct values are counted up to this many per field
+const MAX_CARDINALITY: usize = 1000;
+// Number of different example values kept per field
+const MAX_EXAMPLES: usize = 3;
+
+/// Everything seen at one place in the records, like the field `http.status`
+#[derive(Debug, Default)]
+struct Shape {
+    // How often a value was seen here
+    seen: u64,
+    // JSON Schema type names in the order they were first seen
+    types: Vec<&'static str>,
+    // How many of the values were objects, for telling required fields apart
+    objects: u64,
+    properties: Vec<(String, Shape)>,
+    index: HashMap<String, usize>,
+    // Elements of all arrays seen here
+    items: Option<Box<Shape>>,
+    examples: Vec<Value>,
+    distinct: HashSet<String>,
+}
+
+impl Shape {
+    fn observe(&mut self, value: &Value) {
+        self.seen += 1;
+        let kind = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(number) if number.is_f64() => "number",
+            Value::Number(_) => "integer",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        if !self.types.contains(&kind) {
+            self.types.push(kind);
+        }
+
+        match value {
+            Value::Object(fields) => {
+                self.objects += 1;
+                for (name, field_value) in fields {
+                    self.property(name).observe(field_value);
+                }
+            }
+            Value::Array(elements) => {
+                let items = self.items.get_or_insert_default();
+                for element in elements {
+                    items.observe(element);
+                }
+            }
+            scalar => {
+                if self.distinct.len() < MAX_CARDINALITY {
+                    self.distinct.insert(scalar.to_string());
+                }
+                if self.examples.len() < MAX_EXAMPLES && !self.examples.contains(scalar) {
+                    self.examples.push(scalar.clone());
+                }
+            }
+        }
+    }
+
+    fn property(&mut self, name: &str) -> &mut Shape {
+        let position = match self.index.get(name) {
+            Some(&position) => position,
+            None => {
+                self.index.insert(name.to_string(), self.properties.len());
+                self.properties.push((name.to_string(), Shape::default()));
+                self.properties.len() - 1
+            }
+        };
+        &mut self.properties[position].1
+    }
+
+    fn to_schema(&self, schema: &mut Map<String, Value>) {
+        // Every integer is a number as well
+        let types: Vec<&str> = self
+            .types
+            .iter()
+            .copied()
+            .filter(|kind| *kind != "integer" || !self.types.contains(&"number"))
+            .collect();
+        match types.as_slice() {
+            [] => {}
+            [kind] => {
+                schema.insert("type".to_string(), json!(kind));
+            }
+            kinds => {
+                schema.insert("type".to_string(), json!(kinds));
+            }
+        }
+
+        if !self.properties.is_empty() {
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            for (name, shape) in &self.properties {
+                let mut property = Map::new();
+                shape.to_schema(&mut property);
+                properties.insert(name.clone(), Value::Object(property));
+                if shape.seen == self.objects {
+                    required.push(json!(name));
+                }
+            }
+            schema.insert("properties".to_string(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".to_string(), Value::Array(required));
+            }
+        }
+
+        if let Some(items) = &self.items {
+            let mut schema_items = Map::new();
+            items.to_schema(&mut schema_items);
+            schema.insert("items".to_string(), Value::Object(schema_items));
+        }
+
+        if !self.examples.is_empty() {
+            schema.insert("examples".to_string(), json!(self.examples));
+        }
+        if !self.distinct.is_empty() {
+            schema.insert("x-cardinality".to_string(), json!(self.distinct.len()));
+        }
+    }
+}
+
+/// JSON Schema describing the records, inferred from all records seen.
+///
+/// Fields present in every object around them are `required`, values of
+/// different types list them all, and the elements of arrays are described
+/// together as their `items`. Besides up to three `examples`, every field that
+/// held plain values has its number of distinct values as `x-cardinality`,
+/// counted up to 1000.
+#[derive(Debug, Default)]
+pub struct SchemaAggregator {
+    root: Shape,
+}
+
+impl SchemaAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Aggregator for SchemaAggregator {
+    fn observe(&mut self, value: &Value) {
+        self.root.observe(value);
+    }
+
+    fn render(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let mut schema = Map::new();
+        schema.insert(
+            "$schema".to_string(),
+            json!("https://json-schema.org/draft/2020-12/schema"),
+        );
+        self.root.to_schema(&mut schema);
+
+        serde_json::to_writer_pretty(&mut *writer, &schema)?;
+        writeln!(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(records: &[Value]) -> Value {
+        let mut aggregator = SchemaAggregator::new();
+        for record in records {
+            aggregator.observe(record);
+        }
+        let mut output = Vec::new();
+        aggregator.render(&mut output).unwrap();
+        serde_json::from_slice(&output).unwrap()
+    }
+
+    #[test]
+    fn test_infers_types_and_required_fields() {
+        let schema = infer(&[
+            json!({"name": "Kira", "rank": "Major", "age": 32}),
+            json!({"name": "Odo", "age": 31.5}),
+        ]);
+
+        assert_eq!(schema["type"], json!("object"));
+        assert_eq!(schema["required"], json!(["name", "age"]));
+        assert_eq!(schema["properties"]["name"]["type"], json!("string"));
+        assert_eq!(schema["properties"]["age"]["type"], json!("number"));
+        assert_eq!(schema["properties"]["rank"]["examples"], json!(["Major"]));
+    }
+
+    #[test]
+    fn test_lists_mixed_types() {
+        let schema = infer(&[
+            json!({"code": 404}),
+            json!({"code": "timeout"}),
+            json!({"code": null}),
+        ]);
+
+        assert_eq!(
+            schema["properties"]["code"]["type"],
+            json!(["integer", "string", "null"])
+        );
+    }
+
+    #[test]
+    fn test_describes_nested_objects_and_arrays() {
+        let schema = infer(&[
+            json!({"ship": {"name": "Defiant", "crew": [{"name": "Worf"}, {"name": "Dax", "rank": "Lt"}]}}),
+            json!({"ship": {"name": "Rio Grande"}}),
+        ]);
+
+        let ship = &schema["properties"]["ship"];
+        assert_eq!(ship["required"], json!(["name"]));
+        assert_eq!(ship["properties"]["crew"]["type"], json!("array"));
+        let crew = &ship["properties"]["crew"]["items"];
+        assert_eq!(crew["required"], json!(["name"]));
+        assert_eq!(crew["properties"]["name"]["x-cardinality"], json!(2));
+    }
+
+    #[test]
+    fn test_counts_distinct_values_and_keeps_few_examples() {
+        let records: Vec<Value> = ["Sisko", "Kira", "Sisko", "Bashir", "O'Brien", "Kira"]
+            .iter()
+            .map(|name| json!({"name": name}))
+            .collect();
+
+        let schema = infer(&records);
+
+        let name = &schema["properties"]["name"];
+        assert_eq!(name["x-cardinality"], json!(4));
+        assert_eq!(name["examples"], json!(["Sisko", "Kira", "Bashir"]));
+    }
+
+    #[test]
+    fn test_renders_empty_schema_without_records() {
+        assert_eq!(
+            infer(&[]),
+            json!({"$schema": "https://json-schema.org/draft/2020-12/schema"})
+        );
+    }
+}
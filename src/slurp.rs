@@ -0,0 +1,144 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, Write};
+
+// Estimated bookkeeping per collected record on top of its size
+const RECORD_OVERHEAD: usize = 64;
+
+/// JSON records collected to be output as a single array at the end.
+///
+/// Once the records take up more than the memory limit, they are moved to a
+/// temporary file along with all later ones, so the array is written record
+/// by record at the end instead of being held as a whole.
+#[derive(Default)]
+pub struct SlurpedRecords {
+    records: Vec<Value>,
+    memory: usize,
+    memory_limit: Option<usize>,
+    spilled: Option<BufWriter<File>>,
+}
+
+/// The collected records, as they are held at the end
+pub enum Slurped {
+    Memory(Vec<Value>),
+    Spilled(SpilledRecords),
+}
+
+impl SlurpedRecords {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the records to a temporary file once they take up more than
+    /// this many bytes
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Adds a record of the given size in bytes
+    pub fn add(&mut self, record: Value, size: usize) -> io::Result<()> {
+        if let Some(spilled) = &mut self.spilled {
+            return write_record(spilled, &record);
+        }
+
+        self.records.push(record);
+        self.memory += size + RECORD_OVERHEAD;
+        if self.memory_limit.is_some_and(|limit| self.memory > limit) {
+            let mut spilled = BufWriter::new(tempfile::tempfile()?);
+            for record in self.records.drain(..) {
+                write_record(&mut spilled, &record)?;
+            }
+            self.spilled = Some(spilled);
+            self.memory = 0;
+        }
+        Ok(())
+    }
+
+    pub fn is_spilled(&self) -> bool {
+        self.spilled.is_some()
+    }
+
+    pub fn finish(self) -> io::Result<Slurped> {
+        match self.spilled {
+            None => Ok(Slurped::Memory(self.records)),
+            Some(spilled) => {
+                let mut file = spilled.into_inner().map_err(|error| error.into_error())?;
+                file.rewind()?;
+                Ok(Slurped::Spilled(SpilledRecords {
+                    lines: BufReader::new(file).lines(),
+                }))
+            }
+        }
+    }
+}
+
+fn write_record(writer: &mut impl Write, record: &Value) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")
+}
+
+/// Iterator reading back the records moved to a temporary file
+pub struct SpilledRecords {
+    lines: io::Lines<BufReader<File>>,
+}
+
+impl Iterator for SpilledRecords {
+    type Item = io::Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.lines.next()? {
+            Ok(line) => line,
+            Err(error) => return Some(Err(error)),
+        };
+        Some(serde_json::from_str(&line).map_err(io::Error::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn crew() -> Vec<Value> {
+        ["Sisko", "Kira", "Odo", "Dax"]
+            .iter()
+            .map(|name| json!({"name": name}))
+            .collect()
+    }
+
+    #[test]
+    fn test_keeps_records_in_memory_without_limit() {
+        let mut slurped = SlurpedRecords::new();
+        for record in crew() {
+            slurped.add(record, 100).unwrap();
+        }
+
+        assert!(!slurped.is_spilled());
+        match slurped.finish().unwrap() {
+            Slurped::Memory(records) => assert_eq!(records, crew()),
+            Slurped::Spilled(_) => panic!("records were moved to disk"),
+        }
+    }
+
+    #[test]
+    fn test_moves_records_to_disk_beyond_limit() {
+        let mut slurped = SlurpedRecords::new().with_memory_limit(2 * (100 + RECORD_OVERHEAD));
+        for record in crew() {
+            slurped.add(record, 100).unwrap();
+        }
+
+        assert!(slurped.is_spilled());
+        match slurped.finish().unwrap() {
+            Slurped::Spilled(records) => {
+                let records: Vec<Value> = records.map(Result::unwrap).collect();
+                assert_eq!(records, crew());
+            }
+            Slurped::Memory(_) => panic!("records were kept in memory"),
+        }
+    }
+}
@@ -0,0 +1,407 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::buffer::BufferResult;
+use crate::field_path::FieldPath;
+use crate::timestamp::TimeFormats;
+use serde_json::{Value, json};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Seek, Write};
+use std::vec;
+
+/// Default memory held by buffered records before they are sorted to disk
+pub const DEFAULT_SORT_MEMORY: usize = 256 * 1024 * 1024;
+
+// Estimated bookkeeping per buffered record on top of its size
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Value records are ordered by.
+///
+/// Numbers compare numerically and strings holding a timestamp by the point
+/// in time, so offsets like `+02:00` are taken into account. Other values
+/// compare as text.
+#[derive(Debug, Clone)]
+enum SortKey {
+    Number(f64),
+    Time(i128),
+    Text(String),
+}
+
+impl SortKey {
+    fn of(value: &Value, formats: &TimeFormats) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::Number(number) => number.as_f64().map(SortKey::Number),
+            Value::String(text) => Some(match formats.parse_str(text) {
+                Some(time) => SortKey::Time(time.as_nanosecond()),
+                None => SortKey::Text(text.clone()),
+            }),
+            other => Some(SortKey::Text(other.to_string())),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortKey::Number(_) => 0,
+            SortKey::Time(_) => 1,
+            SortKey::Text(_) => 2,
+        }
+    }
+
+    fn encode(&self) -> Value {
+        match self {
+            SortKey::Number(number) => json!([0, number]),
+            SortKey::Time(nanos) => json!([1, nanos.to_string()]),
+            SortKey::Text(text) => json!([2, text]),
+        }
+    }
+
+    fn decode(value: &Value) -> Option<Self> {
+        match (value.get(0)?.as_u64()?, value.get(1)?) {
+            (0, number) => number.as_f64().map(SortKey::Number),
+            (1, Value::String(nanos)) => nanos.parse().ok().map(SortKey::Time),
+            (2, Value::String(text)) => Some(SortKey::Text(text.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+            (SortKey::Time(a), SortKey::Time(b)) => a.cmp(b),
+            (SortKey::Text(a), SortKey::Text(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey {}
+
+// Position of a record in the output: its sort key, then its input order
+type Order = (Option<SortKey>, u64);
+
+/// A buffered record with its place in the output
+struct Entry {
+    key: Option<SortKey>,
+    // Input order, keeping the sort stable
+    seq: u64,
+    result: BufferResult,
+}
+
+impl Entry {
+    fn order(&self) -> Order {
+        (self.key.clone(), self.seq)
+    }
+
+    fn encode(&self) -> Value {
+        let key = self.key.as_ref().map_or(Value::Null, SortKey::encode);
+        match &self.result {
+            BufferResult::Json(record, size) => json!([key, self.seq, size, record]),
+            BufferResult::Text(text) => json!([key, self.seq, null, text]),
+            BufferResult::Incomplete(_) => Value::Null,
+        }
+    }
+
+    fn decode(line: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(ErrorKind::InvalidData, "corrupt sort run");
+        let Value::Array(mut fields) = serde_json::from_str(line)? else {
+            return Err(invalid());
+        };
+        if fields.len() != 4 {
+            return Err(invalid());
+        }
+        let value = fields.pop().unwrap_or_default();
+        let result = match (fields[2].as_u64(), value) {
+            (Some(size), record) => BufferResult::Json(record, size as usize),
+            (None, Value::String(text)) => BufferResult::Text(text),
+            _ => return Err(invalid()),
+        };
+        Ok(Entry {
+            key: SortKey::decode(&fields[0]),
+            seq: fields[1].as_u64().ok_or_else(invalid)?,
+            result,
+        })
+    }
+}
+
+/// Buffers records to output them ordered by a field, typically a timestamp.
+///
+/// Text lines and records without the field keep their place after the
+/// record before them, and records with equal values keep their input order.
+/// Once the buffered records exceed the memory limit, they are sorted and
+/// written to a temporary file, and all files are merged at the end, so
+/// inputs larger than the memory can be sorted as well.
+pub struct RecordSorter {
+    field: FieldPath,
+    formats: TimeFormats,
+    memory_limit: usize,
+    entries: Vec<Entry>,
+    memory: usize,
+    runs: Vec<File>,
+    seq: u64,
+    last_key: Option<SortKey>,
+}
+
+impl RecordSorter {
+    pub fn new(field: FieldPath) -> Self {
+        Self {
+            field,
+            formats: TimeFormats::default(),
+            memory_limit: DEFAULT_SORT_MEMORY,
+            entries: Vec::new(),
+            memory: 0,
+            runs: Vec::new(),
+            seq: 0,
+            last_key: None,
+        }
+    }
+
+    /// Also compares strings in these formats as timestamps
+    pub fn with_time_formats(mut self, formats: TimeFormats) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Sorts buffered records to disk once they take up more than this many
+    /// bytes, instead of 256 MiB
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = bytes;
+        self
+    }
+
+    pub fn add(&mut self, result: BufferResult) -> io::Result<()> {
+        let key = match &result {
+            BufferResult::Json(record, _) => self
+                .field
+                .get(record)
+                .and_then(|value| SortKey::of(value, &self.formats)),
+            _ => None,
+        };
+        let key = match key {
+            Some(key) => {
+                self.last_key = Some(key.clone());
+                Some(key)
+            }
+            None => self.last_key.clone(),
+        };
+
+        self.memory += result.size() + ENTRY_OVERHEAD;
+        self.entries.push(Entry {
+            key,
+            seq: self.seq,
+            result,
+        });
+        self.seq += 1;
+
+        if self.memory > self.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered records to a temporary file, sorted
+    fn spill(&mut self) -> io::Result<()> {
+        self.sort_entries();
+        let mut writer = BufWriter::new(tempfile::tempfile()?);
+        for entry in self.entries.drain(..) {
+            serde_json::to_writer(&mut writer, &entry.encode())?;
+            writer.write_all(b"\n")?;
+        }
+        let mut file = writer.into_inner().map_err(|error| error.into_error())?;
+        file.rewind()?;
+        self.runs.push(file);
+        self.memory = 0;
+        Ok(())
+    }
+
+    fn sort_entries(&mut self) {
+        self.entries
+            .sort_unstable_by(|a, b| (&a.key, a.seq).cmp(&(&b.key, b.seq)));
+    }
+
+    /// Number of temporary files written so far
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Returns all records in order
+    pub fn into_sorted(mut self) -> io::Result<SortedRecords> {
+        self.sort_entries();
+        let mut runs: Vec<Run> = self
+            .runs
+            .into_iter()
+            .map(|file| Run::File(BufReader::new(file).lines()))
+            .collect();
+        runs.push(Run::Memory(self.entries.into_iter()));
+
+        let mut sorted = SortedRecords {
+            heads: Vec::with_capacity(runs.len()),
+            heap: BinaryHeap::with_capacity(runs.len()),
+            runs,
+        };
+        for index in 0..sorted.runs.len() {
+            let head = sorted.runs[index].next_entry()?;
+            sorted.push_head(index, head);
+        }
+        Ok(sorted)
+    }
+}
+
+/// Records sorted in memory or on disk, each in order
+enum Run {
+    Memory(vec::IntoIter<Entry>),
+    File(io::Lines<BufReader<File>>),
+}
+
+impl Run {
+    fn next_entry(&mut self) -> io::Result<Option<Entry>> {
+        match self {
+            Run::Memory(entries) => Ok(entries.next()),
+            Run::File(lines) => lines.next().map(|line| Entry::decode(&line?)).transpose(),
+        }
+    }
+}
+
+/// Iterator merging the sorted runs of a [`RecordSorter`]
+pub struct SortedRecords {
+    runs: Vec<Run>,
+    // Next entry of every run
+    heads: Vec<Option<Entry>>,
+    heap: BinaryHeap<Reverse<(Order, usize)>>,
+}
+
+impl SortedRecords {
+    fn push_head(&mut self, index: usize, head: Option<Entry>) {
+        if let Some(entry) = &head {
+            self.heap.push(Reverse((entry.order(), index)));
+        }
+        if index < self.heads.len() {
+            self.heads[index] = head;
+        } else {
+            self.heads.push(head);
+        }
+    }
+}
+
+impl Iterator for SortedRecords {
+    type Item = io::Result<BufferResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, index)) = self.heap.pop()?;
+        let entry = self.heads[index].take()?;
+        match self.runs[index].next_entry() {
+            Ok(head) => self.push_head(index, head),
+            Err(error) => return Some(Err(error)),
+        }
+        Some(Ok(entry.result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort(sorter: RecordSorter, results: Vec<BufferResult>) -> Vec<BufferResult> {
+        let mut sorter = sorter;
+        for result in results {
+            sorter.add(result).unwrap();
+        }
+        sorter
+            .into_sorted()
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    fn record(value: Value) -> BufferResult {
+        BufferResult::Json(value, 20)
+    }
+
+    #[test]
+    fn test_sorts_by_timestamp_keeping_text_in_place() {
+        let results = vec![
+            BufferResult::Text("Station log".to_string()),
+            record(json!({"ts": "2024-05-01T12:00:03Z", "msg": "Undocked"})),
+            record(json!({"ts": "2024-05-01T14:00:01+02:00", "msg": "Docked"})),
+            BufferResult::Text("Traceback (most recent call last):".to_string()),
+            record(json!({"msg": "No time"})),
+            record(json!({"ts": "2024-05-01T12:00:02Z", "msg": "Cargo"})),
+        ];
+
+        assert_eq!(
+            sort(RecordSorter::new(FieldPath::parse("ts")), results),
+            vec![
+                BufferResult::Text("Station log".to_string()),
+                record(json!({"ts": "2024-05-01T14:00:01+02:00", "msg": "Docked"})),
+                BufferResult::Text("Traceback (most recent call last):".to_string()),
+                record(json!({"msg": "No time"})),
+                record(json!({"ts": "2024-05-01T12:00:02Z", "msg": "Cargo"})),
+                record(json!({"ts": "2024-05-01T12:00:03Z", "msg": "Undocked"})),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sorts_numbers_numerically_and_keeps_equal_order() {
+        let results: Vec<BufferResult> = [10, 9, 100, 9]
+            .iter()
+            .enumerate()
+            .map(|(index, ms)| record(json!({"ms": ms, "n": index})))
+            .collect();
+
+        let sorted = sort(RecordSorter::new(FieldPath::parse("ms")), results);
+
+        let order: Vec<&Value> = sorted
+            .iter()
+            .map(|result| match result {
+                BufferResult::Json(record, _) => &record["n"],
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(order, [&json!(1), &json!(3), &json!(0), &json!(2)]);
+    }
+
+    #[test]
+    fn test_merges_runs_sorted_to_disk() {
+        let stardates: Vec<u64> = (0..50).map(|n| (n * 37) % 50).collect();
+        let mut results: Vec<BufferResult> = stardates
+            .iter()
+            .map(|stardate| record(json!({"stardate": stardate})))
+            .collect();
+        results.insert(10, BufferResult::Text("Captain's log".to_string()));
+
+        let mut sorter = RecordSorter::new(FieldPath::parse("stardate")).with_memory_limit(500);
+        for result in results {
+            sorter.add(result).unwrap();
+        }
+        assert!(sorter.spilled_runs() > 1);
+        let sorted: Vec<BufferResult> = sorter
+            .into_sorted()
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        let mut expected: Vec<BufferResult> =
+            (0..50).map(|n| record(json!({"stardate": n}))).collect();
+        // The text line stays after the record before it, stardate 33
+        expected.insert(34, BufferResult::Text("Captain's log".to_string()));
+        assert_eq!(sorted, expected);
+    }
+}
@@ -0,0 +1,400 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+/// Starts every record of a JSON text sequence (RFC 7464, `application/json-seq`)
+pub const RECORD_SEPARATOR: char = '\u{1e}';
+
+// Upper bound of lines read ahead by the background reader thread.
+// Keeps memory bounded and provides backpressure when output is slow.
+const READ_AHEAD_LINES: usize = 1024;
+
+// How often a channel reader waiting for lines checks for an interrupt
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What ends each input line
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LineSeparator {
+    /// `\n`, a preceding `\r` being stripped as well
+    #[default]
+    Newline,
+    /// Any non-empty byte sequence, e.g. `\0` for `find -print0` style streams
+    Custom(Vec<u8>),
+}
+
+impl LineSeparator {
+    /// Reads the next line without its separator, `None` at EOF
+    fn read_line(&self, reader: &mut impl BufRead) -> io::Result<Option<String>> {
+        let separator = match self {
+            LineSeparator::Newline => {
+                let mut line = String::new();
+                return Ok(match reader.read_line(&mut line)? {
+                    0 => None,
+                    _ => Some(strip_line_ending(line)),
+                });
+            }
+            LineSeparator::Custom(separator) => separator,
+        };
+
+        let Some(&last) = separator.last() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line separator is empty",
+            ));
+        };
+        let mut bytes = Vec::new();
+        // Multi-byte separators end with their last byte, which is searched for
+        while reader.read_until(last, &mut bytes)? > 0 {
+            if bytes.ends_with(separator) {
+                bytes.truncate(bytes.len() - separator.len());
+                return into_line(bytes).map(Some);
+            }
+        }
+        if bytes.is_empty() {
+            Ok(None)
+        } else {
+            into_line(bytes).map(Some)
+        }
+    }
+}
+
+/// Result of waiting for the next input line
+#[derive(Debug, PartialEq)]
+pub enum NextLine {
+    Line(String),
+    Timeout,    // No input arrived within the requested timeout
+    EndOfInput, // One input ended and the next one starts
+    Eof,
+}
+
+/// Line-oriented input over one or more readers, consumed one after another,
+/// with optional timeout support.
+///
+/// The `Direct` variant reads on the calling thread and is used whenever no
+/// timers are configured, keeping the hot path free of channel overhead.
+/// The `Threaded` variant moves the blocking reads to a background thread
+/// (see ADR 10) so the processor can wake up when input stalls.
+pub enum LineSource<R: Read> {
+    Direct {
+        reader: Option<BufReader<R>>,
+        pending: VecDeque<R>,
+        separator: LineSeparator,
+    },
+    Threaded(Receiver<io::Result<NextLine>>),
+}
+
+impl<R: Read + Send + 'static> LineSource<R> {
+    pub fn direct(inputs: Vec<R>, separator: LineSeparator) -> Self {
+        let mut pending = VecDeque::from(inputs);
+        LineSource::Direct {
+            reader: pending.pop_front().map(BufReader::new),
+            pending,
+            separator,
+        }
+    }
+
+    pub fn threaded(inputs: Vec<R>, separator: LineSeparator) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD_LINES);
+
+        thread::spawn(move || {
+            let count = inputs.len();
+            for (index, input) in inputs.into_iter().enumerate() {
+                let mut reader = BufReader::new(input);
+                loop {
+                    let line = match separator.read_line(&mut reader) {
+                        Ok(None) => break,
+                        Ok(Some(line)) => Ok(NextLine::Line(line)),
+                        Err(e) => Err(e),
+                    };
+                    let is_err = line.is_err();
+                    // A closed receiver means the processor is gone, stop reading
+                    if sender.send(line).is_err() || is_err {
+                        return;
+                    }
+                }
+
+                if index + 1 < count && sender.send(Ok(NextLine::EndOfInput)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        LineSource::Threaded(receiver)
+    }
+
+    /// Waits for the next line.
+    ///
+    /// The timeout is only honored by threaded sources, direct sources block
+    /// until a line or EOF arrives.
+    pub fn next_line(&mut self, timeout: Option<Duration>) -> io::Result<NextLine> {
+        match self {
+            LineSource::Direct {
+                reader,
+                pending,
+                separator,
+            } => {
+                let Some(current) = reader else {
+                    return Ok(NextLine::Eof);
+                };
+
+                match separator.read_line(current)? {
+                    Some(line) => Ok(NextLine::Line(line)),
+                    None => {
+                        *reader = pending.pop_front().map(BufReader::new);
+                        Ok(match reader {
+                            Some(_) => NextLine::EndOfInput,
+                            None => NextLine::Eof,
+                        })
+                    }
+                }
+            }
+            LineSource::Threaded(receiver) => {
+                let received = match timeout {
+                    Some(timeout) => receiver.recv_timeout(timeout),
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match received {
+                    Ok(next) => next,
+                    Err(RecvTimeoutError::Timeout) => Ok(NextLine::Timeout),
+                    Err(RecvTimeoutError::Disconnected) => Ok(NextLine::Eof),
+                }
+            }
+        }
+    }
+}
+
+/// Removes a trailing `\n` or `\r\n`
+fn strip_line_ending(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    line
+}
+
+/// Decodes a line, rejecting invalid UTF-8 like `BufRead::read_line` does
+fn into_line(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Reader over lines handed on by other threads, e.g. one per network
+/// client, as a single input.
+///
+/// Every line arrives complete, ending with `\n`, so lines of different
+/// threads never interleave. Ends once all senders are gone or an interrupt
+/// is requested.
+pub struct ChannelReader {
+    receiver: Receiver<Vec<u8>>,
+    line: Vec<u8>,
+    position: usize,
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+impl ChannelReader {
+    /// Creates the reader along with the sender to clone for every thread
+    pub fn new() -> (SyncSender<Vec<u8>>, Self) {
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD_LINES);
+        let reader = Self {
+            receiver,
+            line: Vec::new(),
+            position: 0,
+            interrupt: None,
+        };
+        (sender, reader)
+    }
+
+    /// Reports EOF instead of waiting for more lines once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    fn is_interrupted(&self) -> bool {
+        self.interrupt
+            .as_ref()
+            .is_some_and(|interrupt| interrupt.load(Ordering::Relaxed))
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.line.len() {
+            match self.receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => {
+                    self.line = line;
+                    self.position = 0;
+                }
+                Err(RecvTimeoutError::Timeout) if self.is_interrupted() => return Ok(0),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+
+        let remaining = &self.line[self.position..];
+        let count = remaining.len().min(buf.len());
+        buf[..count].copy_from_slice(&remaining[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// Hands on the lines of a stream to a [`ChannelReader`], each starting with
+/// `prefix`, until the stream ends
+pub fn forward_lines(stream: impl Read, prefix: Option<String>, sender: SyncSender<Vec<u8>>) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = prefix.clone().unwrap_or_default().into_bytes();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        // The last line of a stream may lack its line ending
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        // A closed receiver means the processor is gone
+        if sender.send(line).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn test_direct_source_strips_line_endings() {
+        let mut source = LineSource::direct(
+            vec![Cursor::new("Kira\r\nOdo\nQuark")],
+            LineSeparator::Newline,
+        );
+
+        assert_eq!(
+            source.next_line(None).unwrap(),
+            NextLine::Line("Kira".into())
+        );
+        assert_eq!(
+            source.next_line(None).unwrap(),
+            NextLine::Line("Odo".into())
+        );
+        assert_eq!(
+            source.next_line(None).unwrap(),
+            NextLine::Line("Quark".into())
+        );
+        assert_eq!(source.next_line(None).unwrap(), NextLine::Eof);
+    }
+
+    #[test]
+    fn test_threaded_source_reports_timeout_while_input_stalls() {
+        let (reader, mut writer) = io::pipe().unwrap();
+        let mut source = LineSource::threaded(vec![reader], LineSeparator::Newline);
+        let timeout = Some(Duration::from_millis(20));
+
+        assert_eq!(source.next_line(timeout).unwrap(), NextLine::Timeout);
+
+        writeln!(writer, "Jadzia").unwrap();
+        drop(writer);
+
+        assert_eq!(
+            source.next_line(Some(Duration::from_secs(5))).unwrap(),
+            NextLine::Line("Jadzia".into())
+        );
+        assert_eq!(
+            source.next_line(Some(Duration::from_secs(5))).unwrap(),
+            NextLine::Eof
+        );
+    }
+
+    #[test]
+    fn test_sources_separate_consecutive_inputs() {
+        let inputs = || {
+            vec![
+                Cursor::new("Sisko\nKira"),
+                Cursor::new(""),
+                Cursor::new("Odo\n"),
+            ]
+        };
+        let expected = vec![
+            NextLine::Line("Sisko".into()),
+            NextLine::Line("Kira".into()),
+            NextLine::EndOfInput,
+            NextLine::EndOfInput,
+            NextLine::Line("Odo".into()),
+            NextLine::Eof,
+        ];
+
+        for mut source in [
+            LineSource::direct(inputs(), LineSeparator::Newline),
+            LineSource::threaded(inputs(), LineSeparator::Newline),
+        ] {
+            let received: Vec<NextLine> = expected
+                .iter()
+                .map(|_| source.next_line(Some(Duration::from_secs(5))).unwrap())
+                .collect();
+            assert_eq!(received, expected);
+        }
+    }
+
+    #[rstest]
+    #[case(b"\0", "Kira\0Odo\nQuark\0", vec!["Kira", "Odo\nQuark"])]
+    #[case(b"--", "Kira-Nerys--Odo---Quark", vec!["Kira-Nerys", "Odo", "-Quark"])]
+    #[case(b"\r\n\r\n", "Kira\r\nOdo\r\n\r\nQuark\r\n", vec!["Kira\r\nOdo", "Quark\r\n"])]
+    fn test_sources_split_at_custom_separators(
+        #[case] separator: &[u8],
+        #[case] input: &'static str,
+        #[case] lines: Vec<&str>,
+    ) {
+        let separator = LineSeparator::Custom(separator.to_vec());
+        let mut expected: Vec<NextLine> = lines
+            .into_iter()
+            .map(|line| NextLine::Line(line.into()))
+            .collect();
+        expected.push(NextLine::Eof);
+
+        for mut source in [
+            LineSource::direct(vec![Cursor::new(input)], separator.clone()),
+            LineSource::threaded(vec![Cursor::new(input)], separator.clone()),
+        ] {
+            let received: Vec<NextLine> = expected
+                .iter()
+                .map(|_| source.next_line(Some(Duration::from_secs(5))).unwrap())
+                .collect();
+            assert_eq!(received, expected);
+        }
+    }
+
+    #[test]
+    fn test_custom_separator_rejects_invalid_utf8() {
+        let input = Cursor::new(b"Garak\0\xff\0".to_vec());
+        let mut source = LineSource::direct(vec![input], LineSeparator::Custom(vec![0]));
+
+        assert_eq!(
+            source.next_line(None).unwrap(),
+            NextLine::Line("Garak".into())
+        );
+        assert_eq!(
+            source.next_line(None).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_source_without_inputs_is_at_eof() {
+        let mut source = LineSource::<Cursor<&str>>::direct(vec![], LineSeparator::Newline);
+
+        assert_eq!(source.next_line(None).unwrap(), NextLine::Eof);
+    }
+}
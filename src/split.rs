@@ -0,0 +1,106 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::level::{Level, LevelMap};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+// File for records without a detectable level
+const UNKNOWN_LEVEL_FILE: &str = "unknown.log";
+
+/// Writes records into one file per log level, e.g. `error.log` and `warn.log`.
+///
+/// Files are created in the directory on the first record of their level, so
+/// only levels that actually occur leave a file behind. Existing files are
+/// replaced.
+#[derive(Debug)]
+pub struct LevelSplit {
+    directory: PathBuf,
+    level_map: LevelMap,
+    files: HashMap<Option<Level>, BufWriter<File>>,
+}
+
+impl LevelSplit {
+    pub fn new(directory: PathBuf, level_map: LevelMap) -> Self {
+        Self {
+            directory,
+            level_map,
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn level_map(&self) -> &LevelMap {
+        &self.level_map
+    }
+
+    /// Appends a rendered record to the file of its level
+    pub fn write(&mut self, level: Option<Level>, record: &str) -> io::Result<()> {
+        let file = match self.files.entry(level) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let name = level.map_or(UNKNOWN_LEVEL_FILE.to_string(), |level| {
+                    format!("{}.log", level)
+                });
+                let path = self.directory.join(name);
+                let file = File::create(&path).map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("Cannot create split file '{}': {}", path.display(), e),
+                    )
+                })?;
+                entry.insert(BufWriter::new(file))
+            }
+        };
+        writeln!(file, "{}", record)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.files.values_mut().try_for_each(|file| file.flush())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writes_records_by_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut split = LevelSplit::new(dir.path().to_path_buf(), LevelMap::default());
+
+        split.write(Some(Level::Error), "Warp core breach").unwrap();
+        split
+            .write(Some(Level::Info), "Docking at pylon 3")
+            .unwrap();
+        split.write(None, "Quark's bar opens").unwrap();
+        split.write(Some(Level::Error), "Shields failing").unwrap();
+        split.flush().unwrap();
+
+        let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("error.log"), "Warp core breach\nShields failing\n");
+        assert_eq!(read("info.log"), "Docking at pylon 3\n");
+        assert_eq!(read("unknown.log"), "Quark's bar opens\n");
+        assert!(!dir.path().join("warn.log").exists());
+    }
+
+    #[test]
+    fn test_replaces_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("warn.log"), "Stale warning\n").unwrap();
+        let mut split = LevelSplit::new(dir.path().to_path_buf(), LevelMap::default());
+
+        split
+            .write(Some(Level::Warn), "Cloaked ship nearby")
+            .unwrap();
+        split.flush().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("warn.log")).unwrap(),
+            "Cloaked ship nearby\n"
+        );
+    }
+}
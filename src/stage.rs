@@ -0,0 +1,182 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::ValueEnum;
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum StageError {
+    #[error("Stage '{0}' is listed more than once")]
+    Duplicate(Stage),
+    #[error("Stage '{0}' always runs {1}, it can't be moved")]
+    Anchored(Stage, &'static str),
+}
+
+/// A step records pass through between reading and writing, as named by `--pipeline`
+///
+/// Most stages are the transforms enabled by their own options, like `redact`
+/// for `--redact`, and do nothing unless enabled.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    /// Remove ANSI escape sequences from input lines (`--strip-ansi`)
+    StripAnsi,
+    /// Detect JSON and other structured records in the input
+    Parse,
+    /// Select the records to output (`-f`, `--level`, `--where`, ...)
+    Filter,
+    /// Parse stringified JSON in string values (`--unwrap-nested`)
+    Unwrap,
+    /// Replace the values of sensitive keys (`--redact`)
+    Redact,
+    /// Rewrite timestamps (`--time`)
+    Time,
+    /// Keep only the selected fields (`--fields`)
+    Fields,
+    /// Remove fields (`--drop`)
+    Drop,
+    /// Set static fields (`--set`)
+    Set,
+    /// Flatten nested objects (`--flatten`)
+    Flatten,
+    /// Shorten long strings and arrays (`--max-string-len`, `--max-array-items`)
+    Truncate,
+    /// Collapse deeply nested values (`--max-depth`)
+    Depth,
+    /// Render records in the output format
+    Format,
+}
+
+/// Stages in the order they run unless `--pipeline` says otherwise
+const DEFAULT_ORDER: [Stage; 13] = [
+    Stage::StripAnsi,
+    Stage::Parse,
+    Stage::Filter,
+    Stage::Unwrap,
+    Stage::Redact,
+    Stage::Time,
+    Stage::Fields,
+    Stage::Drop,
+    Stage::Set,
+    Stage::Flatten,
+    Stage::Truncate,
+    Stage::Depth,
+    Stage::Format,
+];
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
+/// Returns all stages in the order they run, with the `listed` ones first.
+///
+/// Listed stages run in the given order, followed by the others in their
+/// default order. Reading and rendering can't be moved: `strip-ansi` and
+/// `parse` always run first and `format` last, listing them in those places
+/// is fine, so a whole pipeline can be spelled out.
+pub fn order(listed: &[Stage]) -> Result<Vec<Stage>, StageError> {
+    for (index, stage) in listed.iter().enumerate() {
+        let earlier = &listed[..index];
+        if earlier.contains(stage) {
+            return Err(StageError::Duplicate(*stage));
+        }
+        match stage {
+            Stage::StripAnsi if !earlier.is_empty() => {
+                return Err(StageError::Anchored(*stage, "first"));
+            }
+            Stage::Parse if earlier.iter().any(|stage| *stage != Stage::StripAnsi) => {
+                return Err(StageError::Anchored(*stage, "right after 'strip-ansi'"));
+            }
+            Stage::Format if index + 1 != listed.len() => {
+                return Err(StageError::Anchored(*stage, "last"));
+            }
+            _ => {}
+        }
+    }
+
+    let movable =
+        |stage: &&Stage| !matches!(stage, Stage::StripAnsi | Stage::Parse | Stage::Format);
+    let mut stages = vec![Stage::StripAnsi, Stage::Parse];
+    stages.extend(listed.iter().filter(movable));
+    stages.extend(
+        DEFAULT_ORDER
+            .iter()
+            .filter(movable)
+            .filter(|stage| !listed.contains(stage)),
+    );
+    stages.push(Stage::Format);
+    Ok(stages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_default_order() {
+        assert_eq!(order(&[]).unwrap(), DEFAULT_ORDER);
+    }
+
+    #[test]
+    fn test_listed_stages_run_first() {
+        assert_eq!(
+            order(&[Stage::Redact, Stage::Flatten, Stage::Filter]).unwrap(),
+            [
+                Stage::StripAnsi,
+                Stage::Parse,
+                Stage::Redact,
+                Stage::Flatten,
+                Stage::Filter,
+                Stage::Unwrap,
+                Stage::Time,
+                Stage::Fields,
+                Stage::Drop,
+                Stage::Set,
+                Stage::Truncate,
+                Stage::Depth,
+                Stage::Format,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fixed_stages_may_be_spelled_out() {
+        let listed = [
+            Stage::StripAnsi,
+            Stage::Parse,
+            Stage::Redact,
+            Stage::Flatten,
+            Stage::Filter,
+            Stage::Format,
+        ];
+        assert_eq!(
+            order(&listed).unwrap(),
+            order(&[Stage::Redact, Stage::Flatten]).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(&[Stage::Redact, Stage::Redact], StageError::Duplicate(Stage::Redact))]
+    #[case(&[Stage::Parse, Stage::StripAnsi], StageError::Anchored(Stage::StripAnsi, "first"))]
+    #[case(&[Stage::Redact, Stage::Parse], StageError::Anchored(Stage::Parse, "right after 'strip-ansi'"))]
+    #[case(&[Stage::Format, Stage::Filter], StageError::Anchored(Stage::Format, "last"))]
+    fn test_invalid_order(#[case] listed: &[Stage], #[case] expected: StageError) {
+        assert_eq!(order(listed).unwrap_err(), expected);
+    }
+
+    #[test]
+    fn test_error_names_stage() {
+        let error = order(&[Stage::Format, Stage::Filter]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Stage 'format' always runs last, it can't be moved"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::processor::format_size;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counters collected while processing a stream.
+///
+/// Atomic, so a summary can be rendered from another thread, e.g. a signal
+/// handler, while the processor keeps counting.
+#[derive(Debug)]
+pub struct Stats {
+    started: Instant,
+    lines: AtomicU64,
+    json_records: AtomicU64,
+    text_lines: AtomicU64,
+    filtered_out: AtomicU64,
+    parse_failures: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            lines: AtomicU64::new(0),
+            json_records: AtomicU64::new(0),
+            text_lines: AtomicU64::new(0),
+            filtered_out: AtomicU64::new(0),
+            parse_failures: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts an input line of `bytes` length, including its line ending
+    pub fn record_line(&self, bytes: usize) {
+        self.lines.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_json(&self) {
+        self.json_records.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a text line, which is a parse failure if it looked like JSON
+    pub fn record_text(&self, text: &str) {
+        self.text_lines.fetch_add(1, Ordering::Relaxed);
+        if text.trim_start().starts_with(['{', '[']) {
+            self.parse_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts a record that was not output because of a filter, size limit or dedup
+    pub fn record_filtered_out(&self) {
+        self.filtered_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn lines(&self) -> u64 {
+        self.lines.load(Ordering::Relaxed)
+    }
+
+    pub fn json_records(&self) -> u64 {
+        self.json_records.load(Ordering::Relaxed)
+    }
+
+    pub fn text_lines(&self) -> u64 {
+        self.text_lines.load(Ordering::Relaxed)
+    }
+
+    pub fn filtered_out(&self) -> u64 {
+        self.filtered_out.load(Ordering::Relaxed)
+    }
+
+    pub fn parse_failures(&self) -> u64 {
+        self.parse_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Writes the summary, measuring the rate against the time since creation
+    pub fn render(&self, writer: &mut impl Write) -> io::Result<()> {
+        self.render_with_elapsed(writer, self.started.elapsed())
+    }
+
+    fn render_with_elapsed(&self, writer: &mut impl Write, elapsed: Duration) -> io::Result<()> {
+        let records = self.json_records() + self.text_lines();
+        let seconds = elapsed.as_secs_f64();
+        let rate = if seconds > 0.0 {
+            records as f64 / seconds
+        } else {
+            0.0
+        };
+
+        writeln!(writer, "lines:          {}", self.lines())?;
+        writeln!(writer, "json records:   {}", self.json_records())?;
+        writeln!(writer, "text lines:     {}", self.text_lines())?;
+        writeln!(writer, "filtered out:   {}", self.filtered_out())?;
+        writeln!(writer, "parse failures: {}", self.parse_failures())?;
+        writeln!(writer, "bytes:          {}", format_size(self.bytes()))?;
+        writeln!(writer, "records/sec:    {:.1}", rate)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Quark closes the bar", 0)]
+    #[case("{ \"station\": \"Deep Space 9\"", 1)]
+    #[case("  [\"Kira\", \"Odo\"", 1)]
+    fn test_text_counts_parse_failures(#[case] text: &str, #[case] failures: u64) {
+        let stats = Stats::new();
+        stats.record_text(text);
+
+        assert_eq!(stats.text_lines(), 1);
+        assert_eq!(stats.parse_failures(), failures);
+    }
+
+    #[test]
+    fn test_render_summary() {
+        let stats = Stats::new();
+        for _ in 0..3 {
+            stats.record_line(700);
+            stats.record_json();
+        }
+        stats.record_line(20);
+        stats.record_text("Odo investigates a crime");
+        stats.record_filtered_out();
+
+        let mut output = Vec::new();
+        stats
+            .render_with_elapsed(&mut output, Duration::from_secs(2))
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "lines:          4\n\
+             json records:   3\n\
+             text lines:     1\n\
+             filtered out:   1\n\
+             parse failures: 0\n\
+             bytes:          2.1 KiB\n\
+             records/sec:    2.0\n"
+        );
+    }
+}
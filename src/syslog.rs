@@ -0,0 +1,253 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::sync::LazyLock;
+
+// RFC 5424: `<PRI>VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID SD [MSG]`
+static RFC5424: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^<(\d{1,3})>\d{1,2} (\S+) (\S+) (\S+) (\S+) (\S+) (.*)$").unwrap()
+});
+
+// RFC 3164 and the files written from it: `[<PRI>]TIMESTAMP HOSTNAME TAG[PID]: MSG`,
+// with either the classic `Mmm dd hh:mm:ss` or an RFC 3339 timestamp
+static RFC3164: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:<(\d{1,3})>)?([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}|\d{4}-\d{2}-\d{2}T\S+) (\S+) ([^\s:\[]+)(?:\[([^\]]*)\])?: ?(.*)$",
+    )
+    .unwrap()
+});
+
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// Parses a syslog line in the RFC 5424 or RFC 3164 format into an object.
+///
+/// The record holds the timestamp, host, app, pid and message, plus facility
+/// and severity as `level` where the line starts with a priority. A message
+/// that is a JSON object is merged into the record, its own fields winning.
+pub fn parse(line: &str) -> Option<Map<String, Value>> {
+    let line = line.trim_end();
+    if let Some(captures) = RFC5424.captures(line) {
+        let mut record = Map::new();
+        insert_field(&mut record, "timestamp", &captures[2]);
+        insert_field(&mut record, "host", &captures[3]);
+        insert_field(&mut record, "app", &captures[4]);
+        insert_field(&mut record, "pid", &captures[5]);
+        insert_field(&mut record, "msgid", &captures[6]);
+        insert_priority(&mut record, &captures[1])?;
+
+        let rest = &captures[7];
+        let message = if let Some(message) = rest.strip_prefix('-') {
+            message
+        } else {
+            let (structured_data, message) = parse_structured_data(rest)?;
+            record.insert(
+                "structured_data".to_string(),
+                Value::Object(structured_data),
+            );
+            message
+        };
+        if !message.is_empty() && !message.starts_with(' ') {
+            return None;
+        }
+        let message = message
+            .trim_start_matches(' ')
+            .trim_start_matches('\u{feff}');
+        return Some(with_message(record, message));
+    }
+
+    let captures = RFC3164.captures(line)?;
+    let mut record = Map::new();
+    insert_field(&mut record, "timestamp", &captures[2]);
+    insert_field(&mut record, "host", &captures[3]);
+    insert_field(&mut record, "app", &captures[4]);
+    if let Some(pid) = captures.get(5) {
+        insert_field(&mut record, "pid", pid.as_str());
+    }
+    if let Some(priority) = captures.get(1) {
+        insert_priority(&mut record, priority.as_str())?;
+    }
+    Some(with_message(record, &captures[6]))
+}
+
+/// Inserts a header field unless it holds the nil value `-`
+fn insert_field(record: &mut Map<String, Value>, key: &str, value: &str) {
+    if value != "-" {
+        record.insert(key.to_string(), Value::String(value.to_string()));
+    }
+}
+
+/// Inserts facility and severity of a priority, `None` if it is out of range
+fn insert_priority(record: &mut Map<String, Value>, priority: &str) -> Option<()> {
+    let priority: usize = priority.parse().ok()?;
+    let facility = FACILITIES.get(priority / 8)?;
+    record.insert("facility".to_string(), Value::String(facility.to_string()));
+    record.insert(
+        "level".to_string(),
+        Value::String(SEVERITIES[priority % 8].to_string()),
+    );
+    Some(())
+}
+
+/// Adds the message, merging it into the record if it is a JSON object
+fn with_message(mut record: Map<String, Value>, message: &str) -> Map<String, Value> {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Object(payload)) => record.extend(payload),
+        _ => {
+            record.insert("msg".to_string(), Value::String(message.to_string()));
+        }
+    }
+    record
+}
+
+/// Parses RFC 5424 structured data elements like `[id key="value"]` into an
+/// object of their parameters by id, returning the rest of the line
+fn parse_structured_data(mut rest: &str) -> Option<(Map<String, Value>, &str)> {
+    let mut elements = Map::new();
+    while let Some(element) = rest.strip_prefix('[') {
+        let id_end = element.find([' ', ']'])?;
+        let id = &element[..id_end];
+        let mut params = Map::new();
+        rest = &element[id_end..];
+
+        while let Some(param) = rest.strip_prefix(' ') {
+            let (name, value) = param.split_once("=\"")?;
+            let mut chars = value.char_indices();
+            let mut parsed = String::new();
+            let end = loop {
+                match chars.next()? {
+                    (index, '"') => break index,
+                    (_, '\\') => parsed.push(chars.next()?.1),
+                    (_, c) => parsed.push(c),
+                }
+            };
+            params.insert(name.to_string(), Value::String(parsed));
+            rest = &value[end + 1..];
+        }
+
+        rest = rest.strip_prefix(']')?;
+        elements.insert(id.to_string(), Value::Object(params));
+    }
+    Some((elements, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(
+        "<34>1 2024-05-01T12:00:03.003Z ds9.ops sensors 1701 ALERT - Hull breach on deck 12",
+        json!({
+            "timestamp": "2024-05-01T12:00:03.003Z",
+            "host": "ds9.ops",
+            "app": "sensors",
+            "pid": "1701",
+            "msgid": "ALERT",
+            "facility": "auth",
+            "level": "crit",
+            "msg": "Hull breach on deck 12"
+        })
+    )]
+    #[case(
+        r#"<165>1 2024-05-01T12:00:04Z ds9 replicator - - [origin ip="10.0.0.7"][meta tag="q\"uark"] Replicator online"#,
+        json!({
+            "timestamp": "2024-05-01T12:00:04Z",
+            "host": "ds9",
+            "app": "replicator",
+            "facility": "local4",
+            "level": "notice",
+            "structured_data": {"origin": {"ip": "10.0.0.7"}, "meta": {"tag": "q\"uark"}},
+            "msg": "Replicator online"
+        })
+    )]
+    #[case(
+        "<14>1 2024-05-01T12:00:05Z ds9 api - - - {\"level\":\"warn\",\"msg\":\"Shields low\",\"deck\":3}",
+        json!({
+            "timestamp": "2024-05-01T12:00:05Z",
+            "host": "ds9",
+            "app": "api",
+            "facility": "user",
+            "level": "warn",
+            "msg": "Shields low",
+            "deck": 3
+        })
+    )]
+    #[case(
+        "<13>May  1 12:00:06 defiant cloak[42]: Cloaking device engaged",
+        json!({
+            "timestamp": "May  1 12:00:06",
+            "host": "defiant",
+            "app": "cloak",
+            "pid": "42",
+            "facility": "user",
+            "level": "notice",
+            "msg": "Cloaking device engaged"
+        })
+    )]
+    #[case(
+        "May 01 12:00:07 ds9 sshd[1234]: Accepted publickey for odo",
+        json!({
+            "timestamp": "May 01 12:00:07",
+            "host": "ds9",
+            "app": "sshd",
+            "pid": "1234",
+            "msg": "Accepted publickey for odo"
+        })
+    )]
+    #[case(
+        "2024-05-01T12:00:08.123456+02:00 ds9 kernel: {\"msg\":\"Docked\"}",
+        json!({
+            "timestamp": "2024-05-01T12:00:08.123456+02:00",
+            "host": "ds9",
+            "app": "kernel",
+            "msg": "Docked"
+        })
+    )]
+    fn test_parses_lines(#[case] line: &str, #[case] expected: Value) {
+        assert_eq!(parse(line).map(Value::Object), Some(expected));
+    }
+
+    #[rstest]
+    #[case("Quark's bar opens")]
+    #[case("level=info msg=Docked")]
+    #[case("<999>1 2024-05-01T12:00:03Z ds9 api - - - Docked")]
+    #[case("<14>1 2024-05-01T12:00:03Z ds9 api - - [unterminated Docked")]
+    #[case("2024-05-01 12:00:03 INFO Docked")]
+    fn test_rejects_other_lines(#[case] line: &str) {
+        assert_eq!(parse(line), None);
+    }
+}
@@ -0,0 +1,70 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// Reader copying the bytes it reads into a file, like `tee`.
+///
+/// Copies are written unbuffered with every read, so the file is complete no
+/// matter how the process ends. The file is shared, which lets several inputs
+/// read one after another end up in the same copy.
+pub struct TeeReader<R: Read> {
+    reader: R,
+    copy: Arc<File>,
+}
+
+impl<R: Read> TeeReader<R> {
+    pub fn new(reader: R, copy: Arc<File>) -> Self {
+        Self { reader, copy }
+    }
+}
+
+impl<R: Read> Read for TeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        (&*self.copy).write_all(&buf[..read])?;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Cursor};
+
+    #[test]
+    fn test_copies_original_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("raw.log");
+        let copy = Arc::new(File::create(&path).unwrap());
+        let input = "{\"station\": \"Deep Space 9\"}\r\nQuark's bar\n\u{1F378} no newline";
+
+        let mut reader = BufReader::new(TeeReader::new(Cursor::new(input), Arc::clone(&copy)));
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), input);
+    }
+
+    #[test]
+    fn test_inputs_share_one_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("raw.log");
+        let copy = Arc::new(File::create(&path).unwrap());
+
+        for input in ["Alpha shift\n", "Beta shift\n"] {
+            let mut reader = TeeReader::new(Cursor::new(input), Arc::clone(&copy));
+            io::copy(&mut reader, &mut io::sink()).unwrap();
+        }
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Alpha shift\nBeta shift\n"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::field_path::FieldPath;
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TemplateError {
+    #[error("Unclosed placeholder starting at position {0}")]
+    Unclosed(usize),
+    #[error("Empty placeholder at position {0}")]
+    EmptyPlaceholder(usize),
+    #[error("Unmatched '}}' at position {0}, write '}}}}' for a literal brace")]
+    UnmatchedBrace(usize),
+    #[error("Literal fallbacks must be fully quoted, as in '{{{0}}}'")]
+    InvalidLiteral(String),
+}
+
+/// Source of a placeholder's value, tried in order until one is present
+#[derive(Debug, PartialEq)]
+enum Alternative {
+    Field(FieldPath),
+    Literal(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(Vec<Alternative>),
+    // Fields not referenced by any placeholder
+    Remainder,
+}
+
+/// Line template rendering JSON records, e.g. `{time} {level} {msg} ({request_id|"-"})`.
+///
+/// Placeholders reference fields by dotted path. Alternatives separated by `|`
+/// are tried in order, a quoted alternative being a literal fallback; without
+/// one, missing fields render as nothing. Strings are inserted as they are,
+/// other values as compact JSON. `{...}` renders all fields no placeholder
+/// references as a compact JSON object, or nothing if none are left. `{{` and
+/// `}}` stand for literal braces.
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.char_indices().peekable();
+
+        while let Some((position, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, next)| next == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|&(_, next)| next == '}').is_some() => text.push('}'),
+                '}' => return Err(TemplateError::UnmatchedBrace(position)),
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut in_literal = false;
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => {
+                                in_literal = !in_literal;
+                                placeholder.push('"');
+                            }
+                            Some((_, '}')) if !in_literal => break,
+                            Some((_, c)) => placeholder.push(c),
+                            None => return Err(TemplateError::Unclosed(position)),
+                        }
+                    }
+
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(parse_placeholder(&placeholder, position)?);
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Renders a record, records other than objects as compact JSON
+    pub fn render(&self, value: &Value) -> String {
+        if !value.is_object() {
+            return value.to_string();
+        }
+
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Placeholder(alternatives) => {
+                    let resolved = alternatives
+                        .iter()
+                        .find_map(|alternative| match alternative {
+                            Alternative::Field(path) => path.get(value).map(display_value),
+                            Alternative::Literal(literal) => Some(literal.clone()),
+                        });
+                    output.push_str(&resolved.unwrap_or_default());
+                }
+                Segment::Remainder => output.push_str(&self.remainder(value)),
+            }
+        }
+        output
+    }
+
+    fn remainder(&self, value: &Value) -> String {
+        let mut remaining = value.clone();
+        for segment in &self.segments {
+            let Segment::Placeholder(alternatives) = segment else {
+                continue;
+            };
+            for alternative in alternatives {
+                if let Alternative::Field(path) = alternative {
+                    remove_with_empty_parents(path, &mut remaining);
+                }
+            }
+        }
+
+        match &remaining {
+            Value::Object(map) if map.is_empty() => String::new(),
+            remaining => remaining.to_string(),
+        }
+    }
+}
+
+fn parse_placeholder(placeholder: &str, position: usize) -> Result<Segment, TemplateError> {
+    let placeholder = placeholder.trim();
+    if placeholder == "..." {
+        return Ok(Segment::Remainder);
+    }
+
+    let mut alternatives = Vec::new();
+    for alternative in split_alternatives(placeholder) {
+        let alternative = alternative.trim();
+        if alternative.is_empty() {
+            return Err(TemplateError::EmptyPlaceholder(position));
+        }
+        if let Some(literal) = alternative.strip_prefix('"') {
+            let literal = literal
+                .strip_suffix('"')
+                .ok_or_else(|| TemplateError::InvalidLiteral(placeholder.to_string()))?;
+            alternatives.push(Alternative::Literal(literal.to_string()));
+        } else {
+            alternatives.push(Alternative::Field(FieldPath::parse(alternative)));
+        }
+    }
+    Ok(Segment::Placeholder(alternatives))
+}
+
+/// Splits at `|` outside of quoted literals
+fn split_alternatives(placeholder: &str) -> Vec<&str> {
+    let mut alternatives = Vec::new();
+    let mut start = 0;
+    let mut in_literal = false;
+    for (index, c) in placeholder.char_indices() {
+        match c {
+            '"' => in_literal = !in_literal,
+            '|' if !in_literal => {
+                alternatives.push(&placeholder[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    alternatives.push(&placeholder[start..]);
+    alternatives
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Removes the field and any objects left empty by its removal
+fn remove_with_empty_parents(path: &FieldPath, target: &mut Value) {
+    if path.remove(target).is_none() {
+        return;
+    }
+    let mut current = path.split_last().0;
+    while !current.is_empty() {
+        match current.get(target) {
+            Some(Value::Object(map)) if map.is_empty() => {
+                current.remove(target);
+                current = current.split_last().0;
+            }
+            _ => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn render(template: &str, value: Value) -> String {
+        Template::parse(template).unwrap().render(&value)
+    }
+
+    #[rstest]
+    #[case("{level} {msg}", "warn Shields at 30%")]
+    #[case("[{ship.name}] {msg}", "[Defiant] Shields at 30%")]
+    #[case("{message|msg}", "Shields at 30%")]
+    #[case("{request_id}|{msg}", "|Shields at 30%")]
+    #[case("{request_id|\"-\"} {msg}", "- Shields at 30%")]
+    #[case("{ship.crew} {ship.warp}", "[\"Sisko\",\"Dax\"] 9.5")]
+    #[case("{{{level}}}", "{warn}")]
+    #[case(
+        "{level} {...}",
+        "warn {\"msg\":\"Shields at 30%\",\"ship\":{\"name\":\"Defiant\",\"crew\":[\"Sisko\",\"Dax\"],\"warp\":9.5}}"
+    )]
+    #[case(
+        "{msg} {...}",
+        "Shields at 30% {\"level\":\"warn\",\"ship\":{\"name\":\"Defiant\",\"crew\":[\"Sisko\",\"Dax\"],\"warp\":9.5}}"
+    )]
+    fn test_render(#[case] template: &str, #[case] expected: &str) {
+        let record = json!({
+            "level": "warn",
+            "msg": "Shields at 30%",
+            "ship": {"name": "Defiant", "crew": ["Sisko", "Dax"], "warp": 9.5}
+        });
+
+        assert_eq!(render(template, record), expected);
+    }
+
+    #[test]
+    fn test_remainder_drops_emptied_objects() {
+        let template = "{level} {http.status} {...}";
+
+        assert_eq!(
+            render(template, json!({"level": "info", "http": {"status": 200}})),
+            "info 200 "
+        );
+        assert_eq!(
+            render(
+                template,
+                json!({"level": "info", "http": {"status": 200, "path": "/replicator"}})
+            ),
+            "info 200 {\"http\":{\"path\":\"/replicator\"}}"
+        );
+    }
+
+    #[test]
+    fn test_literal_may_contain_separators() {
+        assert_eq!(
+            render("{user|\"n/a | none\"}", json!({"level": "info"})),
+            "n/a | none"
+        );
+    }
+
+    #[test]
+    fn test_non_objects_render_as_json() {
+        assert_eq!(
+            render("{msg}", json!(["Kira", "Odo"])),
+            "[\"Kira\",\"Odo\"]"
+        );
+    }
+
+    #[rstest]
+    #[case("{msg", TemplateError::Unclosed(0))]
+    #[case("{level} {}", TemplateError::EmptyPlaceholder(8))]
+    #[case("{msg|}", TemplateError::EmptyPlaceholder(0))]
+    #[case("{msg} }", TemplateError::UnmatchedBrace(6))]
+    #[case("{msg|\"-}", TemplateError::Unclosed(0))]
+    #[case("{msg|\"-\"x}", TemplateError::InvalidLiteral("msg|\"-\"x".to_string()))]
+    fn test_parse_errors(#[case] template: &str, #[case] expected: TemplateError) {
+        assert_eq!(Template::parse(template).unwrap_err(), expected);
+    }
+}
@@ -0,0 +1,311 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::formatter::ThemeError;
+use colored_json::{Color, Style, Styler};
+use std::path::Path;
+
+/// Names of the color themes bundled for the builtin highlighting engine
+pub const BUILTIN_THEMES: [&str; 5] = ["default", "solarized", "monokai", "dracula", "mono"];
+
+/// Loads a bundled color theme by name, or a user-defined theme from a `.toml` file.
+///
+/// A theme file assigns styles to the parts of a JSON document, each a space
+/// separated list of at most one color (a name like `blue` or `bright-black`,
+/// `#rrggbb`, or a 256-color index) and any of `bold`, `dim`, `italic` and
+/// `underline`. Parts not mentioned keep the style of the `base` theme:
+///
+/// ```toml
+/// base = "dracula"
+/// key = "#ff79c6 bold"
+/// string = "green"
+/// number = "magenta"
+/// bool = "yellow italic"
+/// null = "bright-black"
+/// brackets = "bold"
+/// colon = ""
+/// ```
+pub fn load_theme(theme: &str) -> Result<Styler, ThemeError> {
+    if theme.ends_with(".toml") {
+        let path = Path::new(theme);
+        let invalid = |message: String| ThemeError::InvalidThemeConfig {
+            path: theme.to_string(),
+            message,
+        };
+
+        let contents = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        return parse_theme_file(&contents).map_err(invalid);
+    }
+
+    builtin_theme(theme).ok_or_else(|| ThemeError::UnknownTheme {
+        name: theme.to_string(),
+        available: BUILTIN_THEMES.join(", "),
+    })
+}
+
+fn builtin_theme(name: &str) -> Option<Styler> {
+    match name {
+        "default" => Some(Styler::default()),
+        "solarized" => Some(palette(
+            Color::Rgb(0x83, 0x94, 0x96),
+            Color::Rgb(0x26, 0x8b, 0xd2),
+            Color::Rgb(0x2a, 0xa1, 0x98),
+            Color::Rgb(0xd3, 0x36, 0x82),
+            Color::Rgb(0xb5, 0x89, 0x00),
+            Color::Rgb(0x58, 0x6e, 0x75),
+        )),
+        "monokai" => Some(palette(
+            Color::Rgb(0xf8, 0xf8, 0xf2),
+            Color::Rgb(0xf9, 0x26, 0x72),
+            Color::Rgb(0xe6, 0xdb, 0x74),
+            Color::Rgb(0xae, 0x81, 0xff),
+            Color::Rgb(0x66, 0xd9, 0xef),
+            Color::Rgb(0x75, 0x71, 0x5e),
+        )),
+        "dracula" => Some(palette(
+            Color::Rgb(0xf8, 0xf8, 0xf2),
+            Color::Rgb(0x8b, 0xe9, 0xfd),
+            Color::Rgb(0xf1, 0xfa, 0x8c),
+            Color::Rgb(0xbd, 0x93, 0xf9),
+            Color::Rgb(0xff, 0x79, 0xc6),
+            Color::Rgb(0x62, 0x72, 0xa4),
+        )),
+        // Attributes only, for terminals and recordings without colors
+        "mono" => Some(Styler {
+            object_brackets: Style::new().bold(),
+            object_colon: Style::new(),
+            array_brackets: Style::new().bold(),
+            key: Style::new().bold(),
+            string_value: Style::new(),
+            integer_value: Style::new(),
+            float_value: Style::new(),
+            bool_value: Style::new().italic(),
+            nil_value: Style::new().dim(),
+            string_include_quotation: true,
+        }),
+        _ => None,
+    }
+}
+
+/// Builds a theme from a color per kind of token
+fn palette(
+    punctuation: Color,
+    key: Color,
+    string: Color,
+    number: Color,
+    constant: Color,
+    null: Color,
+) -> Styler {
+    Styler {
+        object_brackets: punctuation.foreground(),
+        object_colon: punctuation.foreground(),
+        array_brackets: punctuation.foreground(),
+        key: key.bold(),
+        string_value: string.foreground(),
+        integer_value: number.foreground(),
+        float_value: number.foreground(),
+        bool_value: constant.foreground(),
+        nil_value: null.foreground(),
+        string_include_quotation: true,
+    }
+}
+
+fn parse_theme_file(contents: &str) -> Result<Styler, String> {
+    let table: toml::Table = toml::from_str(contents).map_err(|e| e.message().to_string())?;
+
+    let base = match table.get("base") {
+        Some(toml::Value::String(name)) => builtin_theme(name).ok_or_else(|| {
+            format!(
+                "unknown base theme '{}', available themes: {}",
+                name,
+                BUILTIN_THEMES.join(", ")
+            )
+        })?,
+        Some(_) => return Err("'base' must be a theme name".to_string()),
+        None => Styler::default(),
+    };
+
+    table.iter().try_fold(base, |mut styler, (part, spec)| {
+        if part == "base" {
+            return Ok(styler);
+        }
+        let toml::Value::String(spec) = spec else {
+            return Err(format!("style of '{}' must be a string", part));
+        };
+        let style = parse_style(spec).map_err(|e| format!("{} in style of '{}'", e, part))?;
+
+        match part.as_str() {
+            "key" => styler.key = style,
+            "string" => styler.string_value = style,
+            "number" => {
+                styler.integer_value = style;
+                styler.float_value = style;
+            }
+            "bool" => styler.bool_value = style,
+            "null" => styler.nil_value = style,
+            "brackets" => {
+                styler.object_brackets = style;
+                styler.array_brackets = style;
+            }
+            "colon" => styler.object_colon = style,
+            _ => {
+                return Err(format!(
+                    "unknown part '{}', expected key, string, number, bool, null, brackets or colon",
+                    part
+                ));
+            }
+        }
+        Ok(styler)
+    })
+}
+
+/// Parses a style like `#ff79c6 bold` or `bright-black italic`
+fn parse_style(spec: &str) -> Result<Style, String> {
+    let mut style = Style::new();
+    let mut colored = false;
+
+    for token in spec.split_whitespace() {
+        style = match token {
+            "bold" => style.bold(),
+            "dim" => style.dim(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            _ if colored => return Err(format!("more than one color in '{}'", spec)),
+            _ => {
+                colored = true;
+                style.fg(parse_color(token)?)
+            }
+        };
+    }
+
+    Ok(style)
+}
+
+fn parse_color(token: &str) -> Result<Color, String> {
+    if let Some(hex) = token.strip_prefix('#') {
+        let channel = |range| {
+            hex.get(range)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        return match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(format!("'{}' is not a #rrggbb color", token)),
+        };
+    }
+    if let Ok(index) = token.parse::<u8>() {
+        return Ok(Color::Fixed(index));
+    }
+
+    match token {
+        "black" => Ok(Color::Black),
+        "red" => Ok(Color::Red),
+        "green" => Ok(Color::Green),
+        "yellow" => Ok(Color::Yellow),
+        "blue" => Ok(Color::Blue),
+        "magenta" => Ok(Color::Magenta),
+        "cyan" => Ok(Color::Cyan),
+        "white" => Ok(Color::White),
+        "bright-black" => Ok(Color::BrightBlack),
+        "bright-red" => Ok(Color::BrightRed),
+        "bright-green" => Ok(Color::BrightGreen),
+        "bright-yellow" => Ok(Color::BrightYellow),
+        "bright-blue" => Ok(Color::BrightBlue),
+        "bright-magenta" => Ok(Color::BrightMagenta),
+        "bright-cyan" => Ok(Color::BrightCyan),
+        "bright-white" => Ok(Color::BrightWhite),
+        _ => Err(format!("unknown color or attribute '{}'", token)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("default")]
+    #[case("solarized")]
+    #[case("monokai")]
+    #[case("dracula")]
+    #[case("mono")]
+    fn test_loads_builtin_themes(#[case] name: &str) {
+        assert!(load_theme(name).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_theme_lists_available_themes() {
+        let error = load_theme("cardassian").err().unwrap();
+
+        assert!(matches!(error, ThemeError::UnknownTheme { .. }));
+        assert!(error.to_string().contains("dracula"));
+    }
+
+    #[rstest]
+    #[case("bold", Style::new().bold())]
+    #[case("#ff79c6 bold", Style::new().fg(Color::Rgb(0xff, 0x79, 0xc6)).bold())]
+    #[case("bright-black italic", Style::new().fg(Color::BrightBlack).italic())]
+    #[case("208 underline dim", Style::new().fg(Color::Fixed(208)).underline().dim())]
+    #[case("", Style::new())]
+    fn test_parse_style(#[case] spec: &str, #[case] expected: Style) {
+        assert_eq!(parse_style(spec).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("red blue")]
+    #[case("#ff79c")]
+    #[case("#gg0000")]
+    #[case("latinum")]
+    fn test_parse_style_rejects_invalid(#[case] spec: &str) {
+        assert!(parse_style(spec).is_err());
+    }
+
+    #[test]
+    fn test_theme_file_overrides_base_theme() {
+        let styler = parse_theme_file(
+            r##"
+            base = "mono"
+            key = "#8be9fd bold"
+            number = "magenta"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            styler.key,
+            Style::new().fg(Color::Rgb(0x8b, 0xe9, 0xfd)).bold()
+        );
+        assert_eq!(styler.integer_value, Color::Magenta.foreground());
+        assert_eq!(styler.float_value, Color::Magenta.foreground());
+        // Inherited from the base theme
+        assert_eq!(styler.nil_value, Style::new().dim());
+    }
+
+    #[rstest]
+    #[case(r#"keys = "blue""#)]
+    #[case(r#"base = "lcars""#)]
+    #[case("key = 42")]
+    #[case(r#"key = "blue red""#)]
+    #[case("key = ")]
+    fn test_theme_file_rejects_invalid(#[case] contents: &str) {
+        assert!(parse_theme_file(contents).is_err());
+    }
+
+    #[test]
+    fn test_loads_theme_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bajoran.toml");
+        std::fs::write(&path, "string = \"yellow\"\n").unwrap();
+
+        let styler = load_theme(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(styler.string_value, Color::Yellow.foreground());
+    }
+
+    #[test]
+    fn test_missing_theme_file_fails() {
+        let error = load_theme("/nonexistent/ferengi.toml").err().unwrap();
+
+        assert!(matches!(error, ThemeError::InvalidThemeConfig { .. }));
+    }
+}
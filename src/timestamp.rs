@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use jiff::civil::DateTime;
+use jiff::fmt::strtime;
+use jiff::tz::TimeZone;
+use jiff::{SignedDuration, Timestamp};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Reads the point in time a record field holds.
+///
+/// Strings are RFC 3339 / ISO 8601 timestamps, taken as UTC if they have no
+/// offset, and numbers are Unix epoch values in seconds, milliseconds,
+/// microseconds or nanoseconds, told apart by their magnitude.
+pub fn parse(value: &Value) -> Option<Timestamp> {
+    match value {
+        Value::String(text) => parse_str(text),
+        Value::Number(number) => from_epoch(number.as_f64()?),
+        _ => None,
+    }
+}
+
+/// Parses a timestamp string, see [`parse`]
+pub fn parse_str(text: &str) -> Option<Timestamp> {
+    let text = text.trim();
+    if let Ok(timestamp) = text.parse::<Timestamp>() {
+        return Some(timestamp);
+    }
+    let datetime: DateTime = text.parse().ok()?;
+    datetime
+        .to_zoned(TimeZone::UTC)
+        .ok()
+        .map(|zoned| zoned.timestamp())
+}
+
+/// `strptime` patterns timestamp strings are also tried with, for formats
+/// like `02/May/2024:10:00:00 +0000` that aren't RFC 3339.
+///
+/// Patterns are tried in order after RFC 3339, the first one that matches the
+/// whole string wins. Times without an offset are taken as UTC.
+#[derive(Debug, Clone, Default)]
+pub struct TimeFormats {
+    patterns: Vec<String>,
+}
+
+impl TimeFormats {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+
+    /// Reads the point in time a record field holds, see [`parse`]
+    pub fn parse(&self, value: &Value) -> Option<Timestamp> {
+        match value {
+            Value::String(text) => self.parse_str(text),
+            other => parse(other),
+        }
+    }
+
+    /// Parses a timestamp string, see [`parse`]
+    pub fn parse_str(&self, text: &str) -> Option<Timestamp> {
+        parse_str(text).or_else(|| {
+            self.patterns
+                .iter()
+                .find_map(|pattern| parse_pattern(pattern, text.trim()))
+        })
+    }
+}
+
+fn parse_pattern(pattern: &str, text: &str) -> Option<Timestamp> {
+    let time = strtime::parse(pattern, text).ok()?;
+    if let Ok(timestamp) = time.to_timestamp() {
+        return Some(timestamp);
+    }
+    time.to_datetime()
+        .ok()?
+        .to_zoned(TimeZone::UTC)
+        .ok()
+        .map(|zoned| zoned.timestamp())
+}
+
+/// Parses a bound of a time range, either a timestamp like
+/// `2024-05-01T12:00:00Z` or a duration before `now` like `15m ago` or `2h`
+pub fn parse_bound(text: &str, now: Timestamp) -> Result<Timestamp, String> {
+    if let Some(timestamp) = parse_str(text) {
+        return Ok(timestamp);
+    }
+    let duration = text.trim();
+    let duration = duration.strip_suffix("ago").unwrap_or(duration).trim_end();
+    let duration = humantime::parse_duration(duration)
+        .map_err(|_| format!("'{}' is neither a timestamp nor a duration", text))?;
+    SignedDuration::try_from(duration)
+        .ok()
+        .and_then(|duration| now.checked_sub(duration).ok())
+        .ok_or_else(|| format!("'{}' is too long ago", text))
+}
+
+/// Formats an offset between two times like `+2.3s`, to the millisecond
+pub fn format_offset(offset: SignedDuration) -> String {
+    let sign = if offset.is_negative() { '-' } else { '+' };
+    format!("{}{}", sign, format_elapsed(offset.unsigned_abs()))
+}
+
+/// Formats a duration like `2.3s`, to the millisecond and without a sign
+pub fn format_elapsed(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    let seconds = format!("{}.{:03}", millis / 1000, millis % 1000);
+    format!("{}s", seconds.trim_end_matches('0').trim_end_matches('.'))
+}
+
+fn from_epoch(value: f64) -> Option<Timestamp> {
+    let magnitude = value.abs();
+    let seconds = if magnitude < 1e11 {
+        value
+    } else if magnitude < 1e14 {
+        value / 1e3
+    } else if magnitude < 1e17 {
+        value / 1e6
+    } else {
+        value / 1e9
+    };
+    let duration = SignedDuration::try_from_secs_f64(seconds).ok()?;
+    Timestamp::from_duration(duration).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    const DOCKED: &str = "2024-05-01T12:00:03Z";
+
+    #[rstest]
+    #[case(json!("2024-05-01T12:00:03Z"))]
+    #[case(json!("2024-05-01T14:00:03+02:00"))]
+    #[case(json!("2024-05-01 12:00:03Z"))]
+    #[case(json!("2024-05-01T12:00:03"))]
+    #[case(json!("2024-05-01 12:00:03"))]
+    #[case(json!(1714564803))]
+    #[case(json!(1714564803.0))]
+    #[case(json!(1714564803000_u64))]
+    #[case(json!(1714564803000000_u64))]
+    #[case(json!(1714564803000000000_u64))]
+    fn test_parses_timestamps(#[case] value: Value) {
+        assert_eq!(parse(&value), Some(DOCKED.parse().unwrap()));
+    }
+
+    #[test]
+    fn test_keeps_fractional_seconds() {
+        assert_eq!(
+            parse(&json!("2024-05-01T12:00:03.250Z")),
+            parse(&json!(1714564803.25))
+        );
+    }
+
+    #[rstest]
+    #[case("2024-05-01T12:00:03Z", "2024-05-01T12:00:03Z")]
+    #[case("2024-05-01 12:00", "2024-05-01T12:00:00Z")]
+    #[case("2024-05-01", "2024-05-01T00:00:00Z")]
+    #[case("15m ago", "2024-05-01T11:45:03Z")]
+    #[case("1h 30s", "2024-05-01T10:59:33Z")]
+    fn test_parses_bounds(#[case] text: &str, #[case] expected: &str) {
+        assert_eq!(
+            parse_bound(text, DOCKED.parse().unwrap()),
+            Ok(expected.parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_bounds() {
+        assert_eq!(
+            parse_bound("stardate 47634", DOCKED.parse().unwrap()),
+            Err("'stardate 47634' is neither a timestamp nor a duration".to_string())
+        );
+    }
+
+    #[rstest]
+    #[case("%d/%b/%Y:%H:%M:%S %z", "01/May/2024:14:00:03 +0200")]
+    #[case("%d/%b/%Y:%H:%M:%S", "01/May/2024:12:00:03")]
+    #[case("%s", "1714564803")]
+    fn test_parses_custom_formats(#[case] pattern: &str, #[case] text: &str) {
+        let formats = TimeFormats::new(vec!["%Y%m%d".to_string(), pattern.to_string()]);
+
+        assert_eq!(formats.parse_str(text), Some(DOCKED.parse().unwrap()));
+        assert_eq!(formats.parse(&json!(DOCKED)), Some(DOCKED.parse().unwrap()));
+        assert_eq!(parse_str(text), None);
+    }
+
+    #[test]
+    fn test_custom_formats_must_match_whole_string() {
+        let formats = TimeFormats::new(vec!["%d/%b/%Y".to_string()]);
+
+        assert_eq!(formats.parse_str("01/May/2024 docked"), None);
+    }
+
+    #[rstest]
+    #[case(json!("Quark's bar opens"))]
+    #[case(json!("May  1 12:00:06"))]
+    #[case(json!(true))]
+    #[case(json!(null))]
+    #[case(json!({"stardate": 47634.4}))]
+    fn test_rejects_other_values(#[case] value: Value) {
+        assert_eq!(parse(&value), None);
+    }
+}
@@ -0,0 +1,198 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::buffer::BufferResult;
+
+// Longer traces are split into blocks of this many lines, bounding memory
+const MAX_TRACE_LINES: usize = 1000;
+
+/// Joins the lines of stack traces into a single text block, so a traceback
+/// between JSON records is filtered and output as a whole.
+///
+/// A text line continues the block before it if it is indented, like the
+/// frames of Java and Python traces, or starts with `at `, `Caused by:`,
+/// `Suppressed:` or `... N more`. The unindented exception line ending a
+/// Python `Traceback (most recent call last):` belongs to the block as well.
+/// Any other text line and every JSON record end the block.
+#[derive(Debug, Default)]
+pub struct TraceGrouper {
+    lines: Vec<String>,
+    // Whether the block is a Python traceback still listing its frames
+    python_frames: bool,
+}
+
+impl TraceGrouper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds results in input order, returning those complete for output.
+    ///
+    /// The last text line is held back, as the next line may continue it.
+    pub fn group(&mut self, results: Vec<BufferResult>) -> Vec<BufferResult> {
+        let mut grouped = Vec::with_capacity(results.len());
+        for result in results {
+            match result {
+                BufferResult::Text(line) if self.continues(&line) => {
+                    if self.python_frames && !starts_indented(&line) {
+                        self.python_frames = false;
+                    }
+                    self.lines.push(line);
+                    if self.lines.len() >= MAX_TRACE_LINES {
+                        grouped.extend(self.flush());
+                    }
+                }
+                BufferResult::Text(line) => {
+                    grouped.extend(self.flush());
+                    self.python_frames = line.starts_with("Traceback (most recent call last):");
+                    self.lines.push(line);
+                }
+                result => {
+                    grouped.extend(self.flush());
+                    grouped.push(result);
+                }
+            }
+        }
+        grouped
+    }
+
+    /// Returns the held back block, e.g. at the end of the input
+    pub fn flush(&mut self) -> Option<BufferResult> {
+        if self.lines.is_empty() {
+            return None;
+        }
+        self.python_frames = false;
+        let block = self.lines.join("\n");
+        self.lines.clear();
+        Some(BufferResult::Text(block))
+    }
+
+    /// Whether a text block is held back
+    pub fn is_pending(&self) -> bool {
+        !self.lines.is_empty()
+    }
+
+    fn continues(&self, line: &str) -> bool {
+        if self.lines.is_empty() {
+            return false;
+        }
+        let trimmed = line.trim_start();
+        (starts_indented(line) && !trimmed.is_empty())
+            || trimmed.starts_with("at ")
+            || line.starts_with("Caused by:")
+            || line.starts_with("Suppressed:")
+            || (trimmed.starts_with("...") && trimmed.ends_with(" more"))
+            // The exception line after the frames of a Python traceback
+            || (self.python_frames && !trimmed.is_empty())
+    }
+}
+
+fn starts_indented(line: &str) -> bool {
+    line.starts_with([' ', '\t'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(lines: &[&str]) -> Vec<BufferResult> {
+        lines
+            .iter()
+            .map(|line| BufferResult::Text(line.to_string()))
+            .collect()
+    }
+
+    fn group(results: Vec<BufferResult>) -> Vec<BufferResult> {
+        let mut grouper = TraceGrouper::new();
+        let mut grouped = grouper.group(results);
+        grouped.extend(grouper.flush());
+        grouped
+    }
+
+    #[test]
+    fn test_groups_java_stack_trace() {
+        let trace = [
+            "java.lang.IllegalStateException: Warp core breach",
+            "\tat com.ds9.Engineering.eject(Engineering.java:42)",
+            "\tat com.ds9.Ops.main(Ops.java:7)",
+            "Caused by: java.io.IOException: Plasma leak",
+            "\t... 2 more",
+        ];
+        let mut results = text(&trace);
+        results.push(BufferResult::Json(json!({"msg": "Docked"}), 15));
+
+        assert_eq!(
+            group(results),
+            vec![
+                BufferResult::Text(trace.join("\n")),
+                BufferResult::Json(json!({"msg": "Docked"}), 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_groups_python_traceback_with_exception_line() {
+        let traceback = [
+            "Traceback (most recent call last):",
+            "  File \"ops.py\", line 3, in <module>",
+            "    eject_core()",
+            "ValueError: core already ejected",
+        ];
+        let mut lines = traceback.to_vec();
+        lines.push("Station stable");
+
+        assert_eq!(
+            group(text(&lines)),
+            vec![
+                BufferResult::Text(traceback.join("\n")),
+                BufferResult::Text("Station stable".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keeps_unrelated_text_lines_apart() {
+        let lines = ["Quark's bar opens", "Odo shapeshifts", ""];
+
+        assert_eq!(group(text(&lines)), text(&lines));
+    }
+
+    #[test]
+    fn test_indented_line_after_record_starts_block() {
+        let results = vec![
+            BufferResult::Json(json!({"msg": "Hull breach"}), 20),
+            BufferResult::Text("\tat com.ds9.Hull.seal(Hull.java:9)".to_string()),
+            BufferResult::Text("\tat com.ds9.Ops.main(Ops.java:7)".to_string()),
+        ];
+
+        assert_eq!(
+            group(results),
+            vec![
+                BufferResult::Json(json!({"msg": "Hull breach"}), 20),
+                BufferResult::Text(
+                    "\tat com.ds9.Hull.seal(Hull.java:9)\n\tat com.ds9.Ops.main(Ops.java:7)"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_holds_back_block_until_it_ends() {
+        let mut grouper = TraceGrouper::new();
+
+        assert_eq!(grouper.group(text(&["Exception: boom", "\tat a"])), vec![]);
+        assert!(grouper.is_pending());
+        assert_eq!(
+            grouper.group(text(&["\tat b", "Recovered"])),
+            text(&["Exception: boom\n\tat a\n\tat b"])
+        );
+        assert_eq!(
+            grouper.flush(),
+            Some(BufferResult::Text("Recovered".into()))
+        );
+        assert!(!grouper.is_pending());
+    }
+}
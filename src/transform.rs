@@ -0,0 +1,751 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::field_path::FieldPath;
+use crate::timestamp::{self, TimeFormats};
+use enum_dispatch::enum_dispatch;
+use jiff::Timestamp;
+use jiff::tz::{Offset, TimeZone};
+use serde_json::{Map, Value};
+use std::cell::OnceCell;
+use std::collections::HashSet;
+
+// Placeholder replacing the values of redacted keys
+const REDACTED: &str = "[REDACTED]";
+
+// Levels of stringified JSON unwrapped by default, guarding against pathological input
+const DEFAULT_UNWRAP_DEPTH: usize = 8;
+
+/// Trait for reshaping JSON records between filtering and formatting
+#[enum_dispatch]
+pub trait Transform {
+    /// Returns the transformed record
+    fn apply(&self, value: Value) -> Value;
+}
+
+/// Reduces objects to a selected set of fields.
+///
+/// Fields are output in the order they were selected, with dotted paths keeping
+/// their nesting, so `msg,http.status` turns a large access log record into
+/// `{"msg": ..., "http": {"status": ...}}`. Missing fields are skipped, values
+/// other than objects pass through unchanged.
+#[derive(Debug)]
+pub struct ProjectTransform {
+    fields: Vec<FieldPath>,
+}
+
+impl ProjectTransform {
+    pub fn new(fields: &[String]) -> Self {
+        Self {
+            fields: fields.iter().map(|field| FieldPath::parse(field)).collect(),
+        }
+    }
+}
+
+impl Transform for ProjectTransform {
+    fn apply(&self, value: Value) -> Value {
+        if !value.is_object() {
+            return value;
+        }
+
+        let mut projected = Value::Object(Map::new());
+        for field in &self.fields {
+            if let Some(selected) = field.get(&value) {
+                field.insert(&mut projected, selected.clone());
+            }
+        }
+        projected
+    }
+}
+
+/// Removes fields, e.g. noisy `kubernetes` or `metadata.labels` subtrees.
+///
+/// Fields a record doesn't have are ignored.
+#[derive(Debug)]
+pub struct DropTransform {
+    fields: Vec<FieldPath>,
+}
+
+impl DropTransform {
+    pub fn new(fields: &[String]) -> Self {
+        Self {
+            fields: fields.iter().map(|field| FieldPath::parse(field)).collect(),
+        }
+    }
+}
+
+impl Transform for DropTransform {
+    fn apply(&self, mut value: Value) -> Value {
+        for field in &self.fields {
+            field.remove(&mut value);
+        }
+        value
+    }
+}
+
+/// Sets fields to static string values, e.g. `env=staging`, overwriting what
+/// a record had.
+///
+/// Dotted paths create the objects they lead through. Values other than
+/// objects pass through unchanged.
+#[derive(Debug)]
+pub struct SetTransform {
+    fields: Vec<(FieldPath, Value)>,
+}
+
+impl SetTransform {
+    pub fn new(fields: &[(String, String)]) -> Self {
+        Self {
+            fields: fields
+                .iter()
+                .map(|(path, value)| (FieldPath::parse(path), Value::String(value.clone())))
+                .collect(),
+        }
+    }
+}
+
+impl Transform for SetTransform {
+    fn apply(&self, mut value: Value) -> Value {
+        if !value.is_object() {
+            return value;
+        }
+        for (field, static_value) in &self.fields {
+            field.insert(&mut value, static_value.clone());
+        }
+        value
+    }
+}
+
+/// Replaces strings holding JSON objects or arrays with the parsed structure.
+///
+/// Services often embed payloads as escaped JSON (`"payload": "{\"a\":1}"`).
+/// Unwrapped values are searched again, as the payload may itself contain
+/// stringified JSON, up to `max_depth` levels of nesting. Strings containing
+/// other JSON values, like `"42"` or `"true"`, are left alone.
+#[derive(Debug)]
+pub struct UnwrapTransform {
+    max_depth: usize,
+}
+
+impl UnwrapTransform {
+    pub fn new(max_depth: usize) -> Self {
+        Self { max_depth }
+    }
+
+    fn unwrap(&self, value: &mut Value, depth: usize) {
+        match value {
+            Value::String(text) if depth < self.max_depth => {
+                let trimmed = text.trim_start();
+                if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+                    return;
+                }
+                if let Ok(parsed) = serde_json::from_str::<Value>(text) {
+                    *value = parsed;
+                    self.unwrap(value, depth + 1);
+                }
+            }
+            Value::Object(map) => map.values_mut().for_each(|v| self.unwrap(v, depth)),
+            Value::Array(items) => items.iter_mut().for_each(|v| self.unwrap(v, depth)),
+            _ => {}
+        }
+    }
+}
+
+impl Default for UnwrapTransform {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNWRAP_DEPTH)
+    }
+}
+
+impl Transform for UnwrapTransform {
+    fn apply(&self, mut value: Value) -> Value {
+        self.unwrap(&mut value, 0);
+        value
+    }
+}
+
+/// Hides the values of sensitive keys like `password` or `authorization`.
+///
+/// Keys are matched case-insensitively at any depth, and their values, however
+/// deeply structured, are replaced by a `"[REDACTED]"` placeholder.
+#[derive(Debug)]
+pub struct RedactTransform {
+    keys: HashSet<String>,
+}
+
+impl RedactTransform {
+    pub fn new(keys: &[String]) -> Self {
+        Self {
+            keys: keys.iter().map(|key| key.trim().to_lowercase()).collect(),
+        }
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if self.keys.contains(&key.to_lowercase()) {
+                        *value = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            _ => {}
+        }
+    }
+}
+
+impl Transform for RedactTransform {
+    fn apply(&self, mut value: Value) -> Value {
+        self.redact(&mut value);
+        value
+    }
+}
+
+/// Rewrites timestamp fields, whether RFC 3339 strings or epoch numbers, into
+/// one readable form.
+///
+/// Times are shown as RFC 3339 in a time zone, or relative to the first
+/// timestamp seen like `+2.3s`, which makes the gaps between records stand
+/// out. Values that aren't timestamps are left alone.
+#[derive(Debug)]
+pub struct TimeTransform {
+    fields: Vec<FieldPath>,
+    formats: TimeFormats,
+    // `None` shows times relative to the first one
+    time_zone: Option<TimeZone>,
+    start: OnceCell<Timestamp>,
+}
+
+impl TimeTransform {
+    /// Shows the times of `fields` in a time zone
+    pub fn zoned(fields: &[String], time_zone: TimeZone) -> Self {
+        Self {
+            fields: fields.iter().map(|field| FieldPath::parse(field)).collect(),
+            formats: TimeFormats::default(),
+            time_zone: Some(time_zone),
+            start: OnceCell::new(),
+        }
+    }
+
+    /// Shows the times of `fields` as offsets from the first time seen
+    pub fn relative(fields: &[String]) -> Self {
+        Self {
+            time_zone: None,
+            ..Self::zoned(fields, TimeZone::UTC)
+        }
+    }
+
+    /// Also rewrites timestamp strings in these formats
+    pub fn with_time_formats(mut self, formats: TimeFormats) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    fn format(&self, time: Timestamp) -> String {
+        match &self.time_zone {
+            Some(time_zone) => match time_zone.to_offset(time) {
+                Offset::UTC => time.to_string(),
+                offset => time.display_with_offset(offset).to_string(),
+            },
+            None => timestamp::format_offset(time.duration_since(*self.start.get_or_init(|| time))),
+        }
+    }
+}
+
+impl Transform for TimeTransform {
+    fn apply(&self, mut value: Value) -> Value {
+        for field in &self.fields {
+            if let Some(current) = field.get_mut(&mut value)
+                && let Some(time) = self.formats.parse(current)
+            {
+                *current = Value::String(self.format(time));
+            }
+        }
+        value
+    }
+}
+
+/// Shortens huge values, like base64 blobs or arrays of thousands of items,
+/// so the rest of the record stays readable.
+///
+/// Longer strings keep their first `max_string_len` characters, followed by
+/// `… (N chars)` with their original length. Longer arrays keep their first
+/// `max_array_items` items, followed by a `"… (N items)"` marker item.
+#[derive(Debug)]
+pub struct TruncateTransform {
+    max_string_len: Option<usize>,
+    max_array_items: Option<usize>,
+}
+
+impl TruncateTransform {
+    pub fn new(max_string_len: Option<usize>, max_array_items: Option<usize>) -> Self {
+        Self {
+            max_string_len,
+            max_array_items,
+        }
+    }
+
+    fn truncate(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => {
+                if let Some(max) = self.max_string_len
+                    && let Some((end, _)) = text.char_indices().nth(max)
+                {
+                    let length = text.chars().count();
+                    text.truncate(end);
+                    text.push_str(&format!("… ({} chars)", length));
+                }
+            }
+            Value::Object(map) => map.values_mut().for_each(|v| self.truncate(v)),
+            Value::Array(items) => {
+                if let Some(max) = self.max_array_items
+                    && items.len() > max
+                {
+                    let length = items.len();
+                    items.truncate(max);
+                    items.iter_mut().for_each(|v| self.truncate(v));
+                    items.push(Value::String(format!("… ({} items)", length)));
+                } else {
+                    items.iter_mut().for_each(|v| self.truncate(v));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Transform for TruncateTransform {
+    fn apply(&self, mut value: Value) -> Value {
+        self.truncate(&mut value);
+        value
+    }
+}
+
+/// Collapses objects and arrays nested deeper than `max_depth` levels into
+/// summaries like `"{…5 keys}"` or `"[…12 items]"`.
+///
+/// The fields of a record are at depth 1, so `max_depth` 1 keeps its fields
+/// and collapses any object or array among their values. Empty objects and
+/// arrays are kept, as there is nothing to hide.
+#[derive(Debug)]
+pub struct DepthTransform {
+    max_depth: usize,
+}
+
+impl DepthTransform {
+    pub fn new(max_depth: usize) -> Self {
+        Self { max_depth }
+    }
+
+    fn collapse(&self, value: &mut Value, depth: usize) {
+        let summary = match value {
+            Value::Object(map) if depth >= self.max_depth && !map.is_empty() => {
+                format!("{{…{}}}", count(map.len(), "key"))
+            }
+            Value::Array(items) if depth >= self.max_depth && !items.is_empty() => {
+                format!("[…{}]", count(items.len(), "item"))
+            }
+            Value::Object(map) => {
+                map.values_mut().for_each(|v| self.collapse(v, depth + 1));
+                return;
+            }
+            Value::Array(items) => {
+                items.iter_mut().for_each(|v| self.collapse(v, depth + 1));
+                return;
+            }
+            _ => return,
+        };
+        *value = Value::String(summary);
+    }
+}
+
+/// Formats a count with its noun, like `1 key` or `5 keys`
+fn count(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+impl Transform for DepthTransform {
+    fn apply(&self, mut value: Value) -> Value {
+        self.collapse(&mut value, 0);
+        value
+    }
+}
+
+/// Turns nested objects into a single level of keys joined by a separator,
+/// like `{"http": {"status": 200}}` into `{"http.status": 200}`.
+///
+/// Arrays stay values of their key unless `arrays` is set, which flattens
+/// them as well, with the index of each item as its key segment. Empty
+/// objects and arrays are kept, values other than objects pass through.
+#[derive(Debug)]
+pub struct FlattenTransform {
+    separator: String,
+    arrays: bool,
+}
+
+impl FlattenTransform {
+    pub fn new(separator: String, arrays: bool) -> Self {
+        Self { separator, arrays }
+    }
+
+    fn flatten(&self, prefix: String, value: Value, flat: &mut Map<String, Value>) {
+        let key = |segment: &str| format!("{}{}{}", prefix, self.separator, segment);
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                for (segment, value) in map {
+                    self.flatten(key(&segment), value, flat);
+                }
+            }
+            Value::Array(items) if self.arrays && !items.is_empty() => {
+                for (index, value) in items.into_iter().enumerate() {
+                    self.flatten(key(&index.to_string()), value, flat);
+                }
+            }
+            value => {
+                flat.insert(prefix, value);
+            }
+        }
+    }
+}
+
+impl Transform for FlattenTransform {
+    fn apply(&self, value: Value) -> Value {
+        let Value::Object(map) = value else {
+            return value;
+        };
+        let mut flat = Map::new();
+        for (key, value) in map {
+            self.flatten(key, value, &mut flat);
+        }
+        Value::Object(flat)
+    }
+}
+
+/// Enum dispatch for the transform implementations
+#[enum_dispatch(Transform)]
+#[derive(Debug)]
+pub enum RecordTransform {
+    Project(ProjectTransform),
+    Drop(DropTransform),
+    Unwrap(UnwrapTransform),
+    Redact(RedactTransform),
+    Time(TimeTransform),
+    Truncate(TruncateTransform),
+    Depth(DepthTransform),
+    Flatten(FlattenTransform),
+    Set(SetTransform),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn project(fields: &[&str], value: Value) -> Value {
+        let fields: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+        RecordTransform::Project(ProjectTransform::new(&fields)).apply(value)
+    }
+
+    fn drop(fields: &[&str], value: Value) -> Value {
+        let fields: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+        RecordTransform::Drop(DropTransform::new(&fields)).apply(value)
+    }
+
+    #[test]
+    fn test_project_keeps_selected_fields_in_order() {
+        let record = json!({
+            "timestamp": "2375-03-12T08:00:00Z",
+            "host": "ops-7",
+            "msg": "Docking request granted",
+            "level": "info",
+        });
+
+        let projected = project(&["timestamp", "level", "msg"], record);
+
+        assert_eq!(
+            serde_json::to_string(&projected).unwrap(),
+            r#"{"timestamp":"2375-03-12T08:00:00Z","level":"info","msg":"Docking request granted"}"#
+        );
+    }
+
+    #[test]
+    fn test_project_keeps_nesting_of_dotted_paths() {
+        let record = json!({
+            "http": {"method": "GET", "status": 200, "path": "/promenade"},
+            "msg": "Served",
+        });
+
+        assert_eq!(
+            project(&["http.status", "http.method", "user.id"], record),
+            json!({"http": {"status": 200, "method": "GET"}})
+        );
+    }
+
+    #[test]
+    fn test_project_passes_non_objects_through() {
+        assert_eq!(
+            project(&["name"], json!(["Sisko", "Kira"])),
+            json!(["Sisko", "Kira"])
+        );
+        assert_eq!(project(&["name"], json!(47)), json!(47));
+    }
+
+    #[test]
+    fn test_drop_removes_subtrees() {
+        let record = json!({
+            "msg": "Runabout departed",
+            "kubernetes": {"pod": "ops-7", "namespace": "bajor"},
+            "metadata": {"labels": {"tier": "ops"}, "version": 3},
+        });
+
+        assert_eq!(
+            drop(&["kubernetes", "metadata.labels", "trace.id"], record),
+            json!({"msg": "Runabout departed", "metadata": {"version": 3}})
+        );
+    }
+
+    #[test]
+    fn test_drop_after_project() {
+        let record = json!({"level": "warn", "http": {"status": 503, "headers": {}}});
+        let projected = project(&["level", "http"], record);
+
+        assert_eq!(
+            drop(&["http.headers"], projected),
+            json!({"level": "warn", "http": {"status": 503}})
+        );
+    }
+
+    #[test]
+    fn test_set_adds_static_fields() {
+        let fields = [
+            ("env".to_string(), "staging".to_string()),
+            ("station.sector".to_string(), "Bajor".to_string()),
+            ("level".to_string(), "warn".to_string()),
+        ];
+        let record = json!({"level": "info", "msg": "Docked"});
+
+        assert_eq!(
+            SetTransform::new(&fields).apply(record),
+            json!({"level": "warn", "msg": "Docked", "env": "staging", "station": {"sector": "Bajor"}})
+        );
+        assert_eq!(
+            SetTransform::new(&fields).apply(json!(["Sisko"])),
+            json!(["Sisko"])
+        );
+    }
+
+    #[test]
+    fn test_unwrap_parses_stringified_json_recursively() {
+        let record = json!({
+            "msg": "Transmission received",
+            "payload": r#"{"sender": "Garak", "body": "{\"cipher\": [1, 2]}"}"#,
+            "items": ["[\"self-sealing\", \"stem bolts\"]", "plain"],
+        });
+
+        assert_eq!(
+            UnwrapTransform::default().apply(record),
+            json!({
+                "msg": "Transmission received",
+                "payload": {"sender": "Garak", "body": {"cipher": [1, 2]}},
+                "items": [["self-sealing", "stem bolts"], "plain"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_unwrap_leaves_scalars_and_invalid_json() {
+        let record = json!({"count": "42", "flag": "true", "broken": "{not json", "empty": ""});
+
+        assert_eq!(UnwrapTransform::default().apply(record.clone()), record);
+    }
+
+    #[test]
+    fn test_unwrap_stops_at_depth_limit() {
+        let record = json!({"outer": r#"{"inner": "{\"core\": 1}"}"#});
+
+        assert_eq!(
+            UnwrapTransform::new(1).apply(record),
+            json!({"outer": {"inner": r#"{"core": 1}"#}})
+        );
+    }
+
+    #[test]
+    fn test_redact_replaces_values_at_any_depth() {
+        let keys = ["password".to_string(), "Authorization".to_string()];
+        let record = json!({
+            "user": "Garak",
+            "Password": "plain simple tailor",
+            "request": {
+                "headers": [{"authorization": "Bearer obsidian-order"}],
+                "body": {"password": {"hash": "abc", "salt": "def"}},
+            },
+        });
+
+        assert_eq!(
+            RedactTransform::new(&keys).apply(record),
+            json!({
+                "user": "Garak",
+                "Password": "[REDACTED]",
+                "request": {
+                    "headers": [{"authorization": "[REDACTED]"}],
+                    "body": {"password": "[REDACTED]"},
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_truncate_shortens_strings_and_arrays_at_any_depth() {
+        let record = json!({
+            "msg": "Transmission received",
+            "payload": {"image": "aGVsbG8gd29ybGQ=", "crew": ["Sisko", "Kira", "Odo", "Dax"]},
+            "short": "Quark",
+            "tags": [["self-sealing stem bolts"], "yamok"],
+        });
+
+        assert_eq!(
+            TruncateTransform::new(Some(5), Some(3)).apply(record),
+            json!({
+                "msg": "Trans… (21 chars)",
+                "payload": {"image": "aGVsb… (16 chars)", "crew": ["Sisko", "Kira", "Odo", "… (4 items)"]},
+                "short": "Quark",
+                "tags": [["self-… (23 chars)"], "yamok"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_truncate_counts_characters() {
+        let record = json!({"name": "Bārēīl Antos", "list": [1, 2]});
+
+        assert_eq!(
+            TruncateTransform::new(Some(4), None).apply(record),
+            json!({"name": "Bārē… (12 chars)", "list": [1, 2]})
+        );
+        assert_eq!(
+            TruncateTransform::new(None, Some(1)).apply(json!([1, 2])),
+            json!([1, "… (2 items)"])
+        );
+    }
+
+    #[test]
+    fn test_depth_collapses_deeply_nested_values() {
+        let record = json!({
+            "msg": "Docked",
+            "ship": {"name": "Defiant", "crew": ["Sisko", "Dax"], "specs": {"warp": 9.5}},
+            "cargo": [],
+            "tags": [{"id": 1}],
+        });
+
+        assert_eq!(
+            DepthTransform::new(2).apply(record.clone()),
+            json!({
+                "msg": "Docked",
+                "ship": {"name": "Defiant", "crew": "[…2 items]", "specs": "{…1 key}"},
+                "cargo": [],
+                "tags": ["{…1 key}"],
+            })
+        );
+        assert_eq!(
+            DepthTransform::new(1).apply(record),
+            json!({"msg": "Docked", "ship": "{…3 keys}", "cargo": [], "tags": "[…1 item]"})
+        );
+    }
+
+    #[test]
+    fn test_flatten_joins_nested_keys() {
+        let record = json!({
+            "msg": "Docked",
+            "http": {"status": 200, "headers": {"host": "ds9"}, "empty": {}},
+            "crew": ["Sisko", {"name": "Kira"}],
+        });
+
+        assert_eq!(
+            serde_json::to_string(
+                &FlattenTransform::new(".".to_string(), false).apply(record.clone())
+            )
+            .unwrap(),
+            r#"{"msg":"Docked","http.status":200,"http.headers.host":"ds9","http.empty":{},"crew":["Sisko",{"name":"Kira"}]}"#
+        );
+        assert_eq!(
+            FlattenTransform::new("_".to_string(), true).apply(record),
+            json!({
+                "msg": "Docked",
+                "http_status": 200,
+                "http_headers_host": "ds9",
+                "http_empty": {},
+                "crew_0": "Sisko",
+                "crew_1_name": "Kira",
+            })
+        );
+    }
+
+    #[test]
+    fn test_flatten_passes_non_objects_through() {
+        let flatten = FlattenTransform::new(".".to_string(), true);
+
+        assert_eq!(flatten.apply(json!([{"a": 1}])), json!([{"a": 1}]));
+        assert_eq!(flatten.apply(json!({})), json!({}));
+    }
+
+    #[test]
+    fn test_time_shows_times_in_time_zone() {
+        let fields = ["timestamp".to_string(), "meta.ts".to_string()];
+        let record = json!({
+            "timestamp": "2375-03-12T08:00:00.25Z",
+            "meta": {"ts": 1714564803},
+            "msg": "Docking request granted",
+        });
+        let bajor = TimeZone::fixed(jiff::tz::offset(2));
+
+        assert_eq!(
+            TimeTransform::zoned(&fields, TimeZone::UTC).apply(record.clone()),
+            json!({
+                "timestamp": "2375-03-12T08:00:00.25Z",
+                "meta": {"ts": "2024-05-01T12:00:03Z"},
+                "msg": "Docking request granted",
+            })
+        );
+        assert_eq!(
+            TimeTransform::zoned(&fields, bajor).apply(record),
+            json!({
+                "timestamp": "2375-03-12T10:00:00.25+02:00",
+                "meta": {"ts": "2024-05-01T14:00:03+02:00"},
+                "msg": "Docking request granted",
+            })
+        );
+    }
+
+    #[test]
+    fn test_time_shows_offsets_from_first_time() {
+        let transform = TimeTransform::relative(&["ts".to_string()]);
+
+        let times: Vec<Value> = [
+            json!({"ts": "2024-05-01T12:00:03Z"}),
+            json!({"ts": "2024-05-01T12:00:05.300Z"}),
+            json!({"ts": 1714564802}),
+            json!({"ts": "stardate 47634.4"}),
+        ]
+        .into_iter()
+        .map(|record| transform.apply(record)["ts"].clone())
+        .collect();
+
+        assert_eq!(times, vec!["+0s", "+2.3s", "-1s", "stardate 47634.4"]);
+    }
+
+    #[test]
+    fn test_redact_leaves_matching_values_alone() {
+        let keys = ["token".to_string()];
+        let record = json!({"msg": "token", "tokens": 3});
+
+        assert_eq!(RedactTransform::new(&keys).apply(record.clone()), record);
+    }
+}